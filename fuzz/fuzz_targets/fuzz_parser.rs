@@ -0,0 +1,134 @@
+#![no_main]
+
+use arbitrary::Arbitrary;
+use class_scanner::lexer::Tokenizer;
+use class_scanner::parser::Parser;
+use libfuzzer_sys::fuzz_target;
+
+/// Config-shaped input, so most cases get past the tokenizer and exercise
+/// the parser. `Raw` text is spliced in as is to reach malformed input.
+#[derive(Arbitrary, Debug)]
+enum Item {
+    Property { name: Name, value: Value },
+    Array { name: Name, operator: ArrayOperator, values: Vec<Value> },
+    Class { name: Name, parent: Option<Name>, body: Option<Vec<Item>> },
+    Delete(Name),
+    Enum(Vec<(Name, Option<i64>)>),
+    Raw(String),
+}
+
+#[derive(Arbitrary, Debug)]
+enum Value {
+    Number(f64),
+    Hex(u32),
+    String(String),
+    Word(Name),
+    Nested(Vec<Value>),
+}
+
+#[derive(Arbitrary, Debug)]
+enum ArrayOperator {
+    Assign,
+    Append,
+    Remove,
+}
+
+#[derive(Arbitrary, Debug)]
+struct Name(u8);
+
+impl Name {
+    fn render(&self, out: &mut String) {
+        out.push_str(["A", "b", "Base", "class_2", "_x", "delete", "class", "enum"][self.0 as usize % 8]);
+    }
+}
+
+impl Value {
+    fn render(&self, out: &mut String) {
+        match self {
+            Value::Number(n) => out.push_str(&n.to_string()),
+            Value::Hex(n) => out.push_str(&format!("0x{:X}", n)),
+            Value::String(s) => {
+                out.push('"');
+                out.push_str(&s.replace('"', "\"\""));
+                out.push('"');
+            }
+            Value::Word(name) => name.render(out),
+            Value::Nested(values) => render_list(values, out),
+        }
+    }
+}
+
+fn render_list(values: &[Value], out: &mut String) {
+    out.push('{');
+    for (i, value) in values.iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        value.render(out);
+    }
+    out.push('}');
+}
+
+impl Item {
+    fn render(&self, out: &mut String) {
+        match self {
+            Item::Property { name, value } => {
+                name.render(out);
+                out.push_str(" = ");
+                value.render(out);
+                out.push_str(";\n");
+            }
+            Item::Array { name, operator, values } => {
+                name.render(out);
+                out.push_str(match operator {
+                    ArrayOperator::Assign => "[] = ",
+                    ArrayOperator::Append => "[] += ",
+                    ArrayOperator::Remove => "[] -= ",
+                });
+                render_list(values, out);
+                out.push_str(";\n");
+            }
+            Item::Class { name, parent, body } => {
+                out.push_str("class ");
+                name.render(out);
+                if let Some(parent) = parent {
+                    out.push_str(": ");
+                    parent.render(out);
+                }
+                if let Some(body) = body {
+                    out.push_str(" {\n");
+                    body.iter().for_each(|item| item.render(out));
+                    out.push('}');
+                }
+                out.push_str(";\n");
+            }
+            Item::Delete(name) => {
+                out.push_str("delete ");
+                name.render(out);
+                out.push_str(";\n");
+            }
+            Item::Enum(entries) => {
+                out.push_str("enum {");
+                for (name, value) in entries {
+                    name.render(out);
+                    if let Some(value) = value {
+                        out.push_str(&format!(" = {}", value));
+                    }
+                    out.push(',');
+                }
+                out.push_str("};\n");
+            }
+            Item::Raw(text) => out.push_str(text),
+        }
+    }
+}
+
+// Tokenizing and parsing must return `Ok` or `Err` for any input; only
+// malformed input may fail, and never by panicking.
+fuzz_target!(|items: Vec<Item>| {
+    let mut input = String::new();
+    items.iter().for_each(|item| item.render(&mut input));
+    if let Ok(tokens) = Tokenizer::new(&input).tokenize() {
+        let _ = Parser::new(tokens).parse();
+    }
+});
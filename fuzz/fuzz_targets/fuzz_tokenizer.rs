@@ -0,0 +1,9 @@
+#![no_main]
+
+use class_scanner::lexer::Tokenizer;
+use libfuzzer_sys::fuzz_target;
+
+// Any UTF-8 input must tokenize to `Ok` or `Err`; a panic is a bug.
+fuzz_target!(|input: &str| {
+    let _ = Tokenizer::new(input).tokenize();
+});
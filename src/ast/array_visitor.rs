@@ -1,14 +1,21 @@
 use super::{ClassNode, PropertyNode, AstVisitor};
 use crate::error::Error;
-use crate::operations::arrays::{ArrayOperation, ArrayProcessor};
+use crate::operations::arrays::{ArrayOperation, ArrayOptions, ArrayProcessor};
 
 pub struct ArrayVisitor {
-    // Implementation can be extended with state if needed
+    options: ArrayOptions,
 }
 
 impl ArrayVisitor {
     pub fn new() -> Self {
-        Self {}
+        Self {
+            options: ArrayOptions::default(),
+        }
+    }
+
+    pub fn with_options(mut self, options: ArrayOptions) -> Self {
+        self.options = options;
+        self
     }
 }
 
@@ -40,8 +47,8 @@ impl AstVisitor for ArrayVisitor {
                 // Replace operation keeps duplicates
                 // The array already contains the values it should have
             },
-            ArrayOperation::Append => {
-                // For append, we need to ensure no duplicates within the array
+            ArrayOperation::Append if self.options.dedup_on_append => {
+                // Appended values only need to be unique when asked for
                 let mut unique: Vec<String> = Vec::new();
                 for item in array.iter() {
                     if !unique.contains(item) {
@@ -50,9 +57,10 @@ impl AstVisitor for ArrayVisitor {
                 }
                 *array = unique;
             },
-            ArrayOperation::Remove => {
-                // Remove operation doesn't need duplicate handling
-                // The array already contains just the items to remove
+            ArrayOperation::Append | ArrayOperation::Remove | ArrayOperation::Intersect => {
+                // Remove and intersect don't need duplicate handling
+                // The array already contains just the items to match against
+                // Appended duplicates are kept, as the game does
             }
         }
 
@@ -66,6 +74,8 @@ mod tests {
     use crate::ast::PropertyType;
     use std::collections::HashMap;
 
+    const DEDUP: ArrayOptions = ArrayOptions { dedup_on_append: true };
+
     // Helper function to create a property node with array values
     fn create_array_property(
         name: &str, 
@@ -138,13 +148,14 @@ mod tests {
         // For completeness, we should manually include both arrays like inheritance would
         // Since we don't test inheritance here, we'll manually verify the result
         let parent_values = &parent_class.properties["items"].array_values;
-        let result = ArrayProcessor::process(
+        let result = ArrayProcessor::process_with_options(
             parent_values,
             &["child1".to_string(), "common".to_string()],
-            ArrayOperation::Append
+            ArrayOperation::Append,
+            DEDUP,
         );
         
-        // Result should include all unique elements from both arrays
+        // With deduplication the result has all unique elements from both arrays
         assert_eq!(result, vec!["parent1", "parent2", "common", "child1"]);
     }
 
@@ -247,14 +258,27 @@ mod tests {
         visitor.visit_array(&mut append_with_dupes.array_values, append_with_dupes.operation).unwrap();
         
         // Manually check what would happen during inheritance resolution
-        let result = ArrayProcessor::process(
+        let result = ArrayProcessor::process_with_options(
             &base_array, 
             &append_with_dupes.array_values, 
-            ArrayOperation::Append
+            ArrayOperation::Append,
+            DEDUP,
         );
         
-        // Duplicates should be removed during append
+        // Duplicates are removed during append when asked for
         assert_eq!(result, vec!["base1", "base2", "new1"]);
+
+        // By default they are kept, as in the game
+        let mut visitor = ArrayVisitor::new();
+        let mut magazines = create_array_property("magazines", vec!["mag", "mag"], Some(ArrayOperation::Append));
+        visitor.visit_array(&mut magazines.array_values, magazines.operation).unwrap();
+        assert_eq!(magazines.array_values, vec!["mag", "mag"]);
+        let result = ArrayProcessor::process(&["mag".to_string()], &magazines.array_values, ArrayOperation::Append);
+        assert_eq!(result, vec!["mag", "mag", "mag"]);
+
+        let mut visitor = ArrayVisitor::new().with_options(DEDUP);
+        visitor.visit_array(&mut magazines.array_values, magazines.operation).unwrap();
+        assert_eq!(magazines.array_values, vec!["mag"]);
     }
 
     #[test]
@@ -338,10 +362,11 @@ mod tests {
         
         // Manually combine base with first child result
         let base_strings: Vec<String> = base_values.iter().map(|s| s.to_string()).collect();
-        let first_result = ArrayProcessor::process(
+        let first_result = ArrayProcessor::process_with_options(
             &base_strings,
             &append_op.array_values,
-            ArrayOperation::Append
+            ArrayOperation::Append,
+            DEDUP,
         );
         
         // First result should have unique combined items
@@ -0,0 +1,155 @@
+use super::{AccessModifier, ClassNode, PropertyNode, PropertyType};
+use crate::error::{Error, SourceLocation};
+use crate::models::property_value::PropertyValue;
+
+/// Builds a [`ClassNode`] without inserting each property by hand:
+///
+/// ```
+/// use class_scanner::ast::ClassNodeBuilder;
+/// use class_scanner::{PropertyType, PropertyValue};
+///
+/// let class = ClassNodeBuilder::new("Car")
+///     .parent("Vehicle")
+///     .property("maxSpeed", PropertyValue::Number(120.0))
+///     .property_raw("displayName", PropertyType::String, "Car")
+///     .array_property("wheels", vec!["front", "back"])
+///     .nested_class(ClassNodeBuilder::new("Turrets"))
+///     .build()
+///     .unwrap();
+/// assert_eq!(class.properties["maxSpeed"].raw_value, "120");
+/// assert_eq!(class.nested_classes[0].name, "Turrets");
+/// ```
+#[derive(Debug, Clone)]
+pub struct ClassNodeBuilder {
+    class: ClassNode,
+    /// Properties in the order added, duplicates included, checked by `build`.
+    properties: Vec<PropertyNode>,
+    nested_classes: Vec<ClassNodeBuilder>,
+}
+
+impl ClassNodeBuilder {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            class: ClassNode::new(name.into()),
+            properties: Vec::new(),
+            nested_classes: Vec::new(),
+        }
+    }
+
+    pub fn parent(mut self, parent: impl Into<String>) -> Self {
+        self.class.parent = Some(parent.into());
+        self
+    }
+
+    pub fn access(mut self, access: AccessModifier) -> Self {
+        self.class.access = access;
+        self
+    }
+
+    /// A property holding `value`; see [`PropertyNode::from_value`].
+    pub fn property(self, name: impl Into<String>, value: PropertyValue) -> Self {
+        self.property_node(PropertyNode::from_value(name, value))
+    }
+
+    /// A property with `raw_value` as written in a config.
+    pub fn property_raw(self, name: impl Into<String>, value_type: PropertyType, raw_value: impl Into<String>) -> Self {
+        self.property_node(PropertyNode::new(name, value_type, raw_value))
+    }
+
+    /// An array property assigned with `=`.
+    pub fn array_property<I, S>(self, name: impl Into<String>, values: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let values: Vec<String> = values.into_iter().map(Into::into).collect();
+        let raw_value = format!("{{{}}}", values.join(","));
+        self.property_node(PropertyNode::new(name, PropertyType::Array, raw_value).with_array_values(values))
+    }
+
+    /// A property built separately, e.g. with an array operation or
+    /// [`PropertyNode::deleted`].
+    pub fn property_node(mut self, property: PropertyNode) -> Self {
+        self.properties.push(property);
+        self
+    }
+
+    pub fn nested_class(mut self, nested: ClassNodeBuilder) -> Self {
+        self.nested_classes.push(nested);
+        self
+    }
+
+    /// The class, or a [`ParseError`](Error::ParseError) if it or a nested
+    /// class was given two properties of the same name.
+    pub fn build(self) -> Result<ClassNode, Error> {
+        let mut class = self.class;
+        for property in self.properties {
+            if class.properties.contains_key(&property.name) {
+                return Err(Error::ParseError {
+                    message: format!("Property '{}' of class '{}' is already defined", property.name, class.name),
+                    location: SourceLocation::unknown(),
+                });
+            }
+            class.properties.insert(property.name.clone(), property);
+        }
+        for nested in self.nested_classes {
+            class.nested_classes.push(nested.build()?);
+        }
+        Ok(class)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::operations::arrays::ArrayOperation;
+
+    #[test]
+    fn test_build() {
+        let class = ClassNodeBuilder::new("Child")
+            .parent("Base")
+            .access(AccessModifier::Private)
+            .property("name", PropertyValue::String("child".to_string()))
+            .property("mass", PropertyValue::Number(42.0))
+            .property("enabled", PropertyValue::Bool(true))
+            .property("items", PropertyValue::Array(vec![
+                PropertyValue::String("a".to_string()),
+                PropertyValue::Array(vec![PropertyValue::Number(1.0)]),
+            ]))
+            .property_raw("scale", PropertyType::Number, "0.5")
+            .array_property("wheels", vec!["front", "back"])
+            .property_node(PropertyNode::new("extra", PropertyType::Array, "{c}")
+                .with_array_values(vec!["c".to_string()])
+                .with_array_op(ArrayOperation::Append))
+            .nested_class(ClassNodeBuilder::new("Turret").property("gunner", PropertyValue::Bool(false)))
+            .build()
+            .unwrap();
+
+        assert_eq!(class.parent.as_deref(), Some("Base"));
+        assert_eq!(class.access, AccessModifier::Private);
+        let names: Vec<&str> = class.properties.keys().map(String::as_str).collect();
+        assert_eq!(names, vec!["name", "mass", "enabled", "items", "scale", "wheels", "extra"]);
+        assert_eq!(class.properties["mass"].raw_value, "42");
+        assert_eq!(class.properties["enabled"].value_type, PropertyType::Boolean);
+        assert_eq!(class.properties["items"].raw_value, "{a,{1}}");
+        assert_eq!(class.properties["items"].array_values, vec!["a", "{1}"]);
+        assert_eq!(class.properties["wheels"].raw_value, "{front,back}");
+        assert_eq!(class.properties["wheels"].array_values, vec!["front", "back"]);
+        assert_eq!(PropertyValue::from(class.properties["mass"].clone()), PropertyValue::Number(42.0));
+        assert_eq!(class.properties["extra"].operation, Some(ArrayOperation::Append));
+        assert_eq!(class.nested_classes[0].properties["gunner"].raw_value, "false");
+    }
+
+    #[test]
+    fn test_duplicate_property() {
+        let result = ClassNodeBuilder::new("A")
+            .property("x", PropertyValue::Number(1.0))
+            .property_raw("x", PropertyType::Number, "2")
+            .build();
+        assert!(matches!(result, Err(Error::ParseError { message, .. }) if message.contains("'x'")));
+
+        // Also in a nested class
+        let nested = ClassNodeBuilder::new("Inner").array_property("x", ["a"]).array_property("x", ["b"]);
+        assert!(ClassNodeBuilder::new("A").nested_class(nested).build().is_err());
+    }
+}
@@ -0,0 +1,248 @@
+use super::{ArrayVisitor, AstVisitor, ClassNode, EnumNode, InheritanceVisitor, PropertyNode, SchemaVisitor};
+use crate::error::Error;
+use crate::operations::arrays::ArrayOperation;
+use crate::types::ClassSchema;
+
+/// Runs several visitors in one [`ClassNode::accept`] pass. Each node is
+/// handed to every visitor in the order they were added; the first error
+/// stops the pass.
+///
+/// Visitors are owned, or lent as `&mut visitor` to read their results
+/// once the pass is done.
+#[derive(Default)]
+pub struct CompositeVisitor<'a> {
+    visitors: Vec<Box<dyn AstVisitor + 'a>>,
+}
+
+impl<'a> CompositeVisitor<'a> {
+    pub fn new() -> Self {
+        Self { visitors: Vec::new() }
+    }
+
+    pub fn push(&mut self, visitor: impl AstVisitor + 'a) -> &mut Self {
+        self.visitors.push(Box::new(visitor));
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.visitors.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.visitors.is_empty()
+    }
+}
+
+impl AstVisitor for CompositeVisitor<'_> {
+    fn visit_class(&mut self, class: &mut ClassNode) -> Result<(), Error> {
+        self.visitors.iter_mut().try_for_each(|visitor| visitor.visit_class(class))
+    }
+
+    fn visit_property(&mut self, property: &mut PropertyNode) -> Result<(), Error> {
+        self.visitors.iter_mut().try_for_each(|visitor| visitor.visit_property(property))
+    }
+
+    fn visit_array(&mut self, array: &mut Vec<String>, operation: Option<ArrayOperation>) -> Result<(), Error> {
+        self.visitors.iter_mut().try_for_each(|visitor| visitor.visit_array(array, operation))
+    }
+
+    fn visit_enum(&mut self, node: &mut EnumNode) -> Result<(), Error> {
+        self.visitors.iter_mut().try_for_each(|visitor| visitor.visit_enum(node))
+    }
+}
+
+/// Builds a [`CompositeVisitor`] from the usual processing steps:
+///
+/// ```
+/// use class_scanner::ast::VisitorBuilder;
+///
+/// let mut root = class_scanner::ClassScanner::new()
+///     .parse_string("class Base { x = 1; }; class Child : Base {};")
+///     .unwrap()
+///     .remove(0);
+/// root.accept(&mut VisitorBuilder::new().with_inheritance().with_arrays().build()).unwrap();
+/// assert_eq!(root.nested_classes[1].properties["x"].raw_value, "1");
+/// ```
+#[derive(Default)]
+pub struct VisitorBuilder<'a> {
+    composite: CompositeVisitor<'a>,
+}
+
+impl<'a> VisitorBuilder<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Resolve inheritance of the visited tree's top-level classes in place,
+    /// with default [`InheritanceVisitor`] settings.
+    pub fn with_inheritance(self) -> Self {
+        self.with_inheritance_visitor(InheritanceVisitor::new())
+    }
+
+    /// [`with_inheritance`](Self::with_inheritance) using `resolver` and its
+    /// settings, e.g. a missing-parent policy.
+    pub fn with_inheritance_visitor(self, resolver: InheritanceVisitor) -> Self {
+        self.with(ResolveTree { resolver, done: false })
+    }
+
+    pub fn with_arrays(self) -> Self {
+        self.with(ArrayVisitor::new())
+    }
+
+    /// Fail with a [`TypeError`](Error::TypeError) listing the violations if a
+    /// class named `class_name` does not match `schema`.
+    pub fn with_type_check(self, class_name: &str, schema: ClassSchema) -> Self {
+        self.with(TypeCheck { schema: SchemaVisitor::new().with_schema(class_name, schema) })
+    }
+
+    pub fn with(mut self, visitor: impl AstVisitor + 'a) -> Self {
+        self.composite.push(visitor);
+        self
+    }
+
+    pub fn build(self) -> CompositeVisitor<'a> {
+        self.composite
+    }
+}
+
+/// Resolves the whole tree on the first class it visits, which `accept`
+/// passes before any of its members.
+struct ResolveTree {
+    resolver: InheritanceVisitor,
+    done: bool,
+}
+
+impl AstVisitor for ResolveTree {
+    fn visit_class(&mut self, class: &mut ClassNode) -> Result<(), Error> {
+        if !std::mem::replace(&mut self.done, true) {
+            self.resolver.resolve_tree(class)?;
+        }
+        Ok(())
+    }
+
+    fn visit_property(&mut self, _property: &mut PropertyNode) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn visit_array(&mut self, _array: &mut Vec<String>, _operation: Option<ArrayOperation>) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+/// Validates each class as it is visited, failing on the first that has
+/// violations.
+struct TypeCheck {
+    schema: SchemaVisitor,
+}
+
+impl AstVisitor for TypeCheck {
+    fn visit_class(&mut self, class: &mut ClassNode) -> Result<(), Error> {
+        let before = self.schema.errors().len();
+        self.schema.visit_class(class)?;
+        match &self.schema.errors()[before..] {
+            [] => Ok(()),
+            errors => Err(Error::TypeError(errors.iter().map(ToString::to_string).collect::<Vec<_>>().join("; "))),
+        }
+    }
+
+    fn visit_property(&mut self, _property: &mut PropertyNode) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn visit_array(&mut self, _array: &mut Vec<String>, _operation: Option<ArrayOperation>) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{PropertySchema, TypeDefinition};
+    use crate::ClassScanner;
+
+    /// Records what it is shown, and fails on the class named `fail_on`.
+    struct Recorder {
+        seen: Vec<String>,
+        fail_on: Option<&'static str>,
+    }
+
+    impl AstVisitor for Recorder {
+        fn visit_class(&mut self, class: &mut ClassNode) -> Result<(), Error> {
+            self.seen.push(format!("class {}", class.name));
+            match self.fail_on {
+                Some(name) if name == class.name => Err(Error::TypeError(format!("failed on {}", name))),
+                _ => Ok(()),
+            }
+        }
+
+        fn visit_property(&mut self, property: &mut PropertyNode) -> Result<(), Error> {
+            self.seen.push(format!("property {}", property.name));
+            Ok(())
+        }
+
+        fn visit_array(&mut self, array: &mut Vec<String>, _operation: Option<ArrayOperation>) -> Result<(), Error> {
+            self.seen.push(format!("array {}", array.join(",")));
+            Ok(())
+        }
+    }
+
+    fn parse(input: &str) -> ClassNode {
+        ClassScanner::new().parse_string(input).unwrap().remove(0)
+    }
+
+    #[test]
+    fn test_composite_delegates_in_order() {
+        let mut root = parse("class A { x = 1; items[] = {a}; class B {}; };");
+        let mut first = Recorder { seen: Vec::new(), fail_on: None };
+        let mut second = Recorder { seen: Vec::new(), fail_on: None };
+        let mut composite = CompositeVisitor::new();
+        composite.push(&mut first).push(&mut second);
+        assert_eq!(composite.len(), 2);
+        root.accept(&mut composite).unwrap();
+        drop(composite);
+
+        assert_eq!(first.seen, vec!["class ", "class A", "property x", "property items", "array a", "class B"]);
+        assert_eq!(first.seen, second.seen);
+    }
+
+    #[test]
+    fn test_composite_stops_on_error() {
+        let mut root = parse("class A {}; class B {}; class C {};");
+        let mut first = Recorder { seen: Vec::new(), fail_on: Some("B") };
+        let mut second = Recorder { seen: Vec::new(), fail_on: None };
+        let mut composite = CompositeVisitor::new();
+        composite.push(&mut first).push(&mut second);
+        assert!(matches!(root.accept(&mut composite), Err(Error::TypeError(message)) if message == "failed on B"));
+        drop(composite);
+
+        assert_eq!(first.seen, vec!["class ", "class A", "class B"]);
+        assert_eq!(second.seen, vec!["class ", "class A"]);
+    }
+
+    #[test]
+    fn test_builder_pipeline() {
+        let input = r#"
+            class Base { mass = 10; items[] = {"a"}; };
+            class Child : Base { items[] += {"b"}; };
+        "#;
+        let mut root = parse(input);
+        root.accept(&mut VisitorBuilder::new().with_inheritance().with_arrays().build()).unwrap();
+        let child = &root.nested_classes[1];
+        assert_eq!(child.properties["mass"].raw_value, "10");
+        assert_eq!(child.properties["items"].array_values, vec!["a", "b"]);
+
+        // The inherited `mass` satisfies the schema, the missing `scope` does not
+        let schema = ClassSchema::new()
+            .with_property("mass", PropertySchema::new(TypeDefinition::Number).required())
+            .with_property("scope", PropertySchema::new(TypeDefinition::Number).required());
+        let mut root = parse(input);
+        let mut visitor = VisitorBuilder::new().with_inheritance().with_type_check("Child", schema).build();
+        match root.accept(&mut visitor) {
+            Err(Error::TypeError(message)) => assert!(message.starts_with("Child.scope") && !message.contains("mass"), "{}", message),
+            other => panic!("expected a type error, got {:?}", other),
+        }
+
+        let mut root = parse("class Child : Missing {};");
+        assert!(root.accept(&mut VisitorBuilder::new().with_inheritance().build()).is_err());
+    }
+}
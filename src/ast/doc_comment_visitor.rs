@@ -0,0 +1,141 @@
+use std::collections::HashMap;
+use super::{ClassNode, PropertyNode, AstVisitor};
+use crate::error::Error;
+use crate::operations::arrays::ArrayOperation;
+
+/// Collects doc comments keyed by path: `CfgWeapons/Rifle` for a class and
+/// `CfgWeapons/Rifle.displayName` for a property. Paths start at the class
+/// [`ClassNode::accept`] is called on.
+pub struct DocCommentVisitor {
+    comments: HashMap<String, String>,
+    /// Path of each enclosing class with the number of its nested classes
+    /// not visited yet; the last is the class being visited.
+    scopes: Vec<(String, usize)>,
+}
+
+impl DocCommentVisitor {
+    pub fn new() -> Self {
+        Self {
+            comments: HashMap::new(),
+            scopes: Vec::new(),
+        }
+    }
+
+    pub fn comments(&self) -> &HashMap<String, String> {
+        &self.comments
+    }
+
+    pub fn into_comments(self) -> HashMap<String, String> {
+        self.comments
+    }
+}
+
+impl Default for DocCommentVisitor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AstVisitor for DocCommentVisitor {
+    fn visit_class(&mut self, class: &mut ClassNode) -> Result<(), Error> {
+        // `accept` visits classes depth first, so the enclosing class is the
+        // innermost one with nested classes left to visit
+        while self.scopes.last().is_some_and(|(_, remaining)| *remaining == 0) {
+            self.scopes.pop();
+        }
+        let scope = match self.scopes.last_mut() {
+            Some((path, remaining)) => {
+                *remaining -= 1;
+                path.as_str()
+            }
+            None => "",
+        };
+        // The parser's unnamed root does not add a path segment
+        let path = match (scope.is_empty(), class.name.is_empty()) {
+            (_, true) => scope.to_string(),
+            (true, false) => class.name.clone(),
+            (false, false) => format!("{}/{}", scope, class.name),
+        };
+
+        if let Some(comment) = &class.doc_comment {
+            self.comments.insert(path.clone(), comment.clone());
+        }
+        self.scopes.push((path, class.nested_classes.len()));
+        Ok(())
+    }
+
+    fn visit_property(&mut self, property: &mut PropertyNode) -> Result<(), Error> {
+        // Properties are visited after their class and before its nested classes
+        if let (Some(comment), Some((path, _))) = (&property.doc_comment, self.scopes.last()) {
+            self.comments.insert(format!("{}.{}", path, property.name), comment.clone());
+        }
+        Ok(())
+    }
+
+    fn visit_array(&mut self, _array: &mut Vec<String>, _operation: Option<ArrayOperation>) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Tokenizer;
+    use crate::parser::Parser;
+
+    fn parse(input: &str, attach: bool) -> ClassNode {
+        let tokens = Tokenizer::new(input).with_comments(true).tokenize().unwrap();
+        Parser::new(tokens).with_comments(attach).parse().unwrap()
+    }
+
+    #[test]
+    fn test_collect_doc_comments() {
+        let input = r#"
+            // Weapon definitions
+            class CfgWeapons {
+                /* Base rifle
+                 * used by all variants */
+                class Rifle {
+                    // Shown in the arsenal
+                    displayName = "Rifle"; // trails the property
+                    mass = 10;
+                    /// Inline ammo list
+                    magazines[] = {"30Rnd"};
+                };
+            };
+        "#;
+
+        let mut root = parse(input, true);
+        let mut visitor = DocCommentVisitor::new();
+        root.accept(&mut visitor).unwrap();
+        let comments = visitor.into_comments();
+
+        assert_eq!(comments["CfgWeapons"], "Weapon definitions");
+        assert_eq!(comments["CfgWeapons/Rifle"], "Base rifle\nused by all variants");
+        assert_eq!(comments["CfgWeapons/Rifle.displayName"], "Shown in the arsenal\ntrails the property");
+        assert_eq!(comments["CfgWeapons/Rifle.magazines"], "Inline ammo list");
+        assert!(!comments.contains_key("CfgWeapons/Rifle.mass"));
+        assert_eq!(comments.len(), 4);
+
+        // Paths follow the nesting back out to siblings of enclosing classes
+        let input = "class A {\n class B { class C {}; };\n class E {\n // e\n x = 1;\n };\n};\n// d\nclass D {};";
+        let mut root = parse(input, true);
+        let mut visitor = DocCommentVisitor::new();
+        root.accept(&mut visitor).unwrap();
+        let mut paths: Vec<&String> = visitor.comments().keys().collect();
+        paths.sort();
+        assert_eq!(paths, ["A/E.x", "D"]);
+    }
+
+    #[test]
+    fn test_comments_ignored_unless_enabled() {
+        let input = "// Doc\nclass A {\n    // Doc\n    x = 1;\n};\n";
+
+        let mut root = parse(input, false);
+        assert_eq!(root.nested_classes[0].properties["x"].raw_value, "1");
+
+        let mut visitor = DocCommentVisitor::new();
+        root.accept(&mut visitor).unwrap();
+        assert!(visitor.comments().is_empty());
+    }
+}
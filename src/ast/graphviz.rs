@@ -0,0 +1,226 @@
+use std::collections::HashSet;
+use std::fmt::Write;
+use super::ClassNode;
+
+/// Controls what [`to_dot_with_options`] renders.
+#[derive(Debug, Clone, Default)]
+pub struct DotOptions {
+    /// List each class's own properties in its node label.
+    pub include_properties: bool,
+    /// How many levels of nested classes to render; `Some(0)` renders only
+    /// top-level classes.
+    pub max_depth: Option<usize>,
+    /// Class names (ignoring case) to fill in a highlight colour.
+    pub highlight_classes: Vec<String>,
+    /// Show how many properties each class declares in its node label.
+    pub property_counts: bool,
+}
+
+impl DotOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_properties(mut self, include: bool) -> Self {
+        self.include_properties = include;
+        self
+    }
+
+    pub fn with_max_depth(mut self, max_depth: Option<usize>) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    pub fn with_property_counts(mut self, show: bool) -> Self {
+        self.property_counts = show;
+        self
+    }
+
+    pub fn with_highlight<I, S>(mut self, classes: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.highlight_classes = classes.into_iter().map(Into::into).collect();
+        self
+    }
+}
+
+/// Render the inheritance graph of `classes` as a Graphviz digraph with
+/// default options.
+pub fn to_dot(classes: &[ClassNode]) -> String {
+    to_dot_with_options(classes, &DotOptions::default())
+}
+
+/// Render the inheritance graph of `classes` as a Graphviz digraph.
+///
+/// Every class is a node with an edge to its parent; root classes are boxes.
+/// Nested classes are grouped in a `cluster_<Container>` subgraph and their
+/// node ids are slash-separated paths (`CfgVehicles/Car`), so same-named
+/// classes in different containers stay apart. Parents are looked up the way
+/// the game does, in the enclosing class first and then further out; a parent
+/// that is not found gets a dashed node of its own.
+///
+/// A `parse_string`/`parse_file` result may be passed as is: when the first
+/// class is the unnamed parse root, only its classes are rendered.
+pub fn to_dot_with_options(classes: &[ClassNode], options: &DotOptions) -> String {
+    let classes = match classes.first() {
+        Some(root) if root.name.is_empty() => &root.nested_classes[..],
+        _ => classes,
+    };
+
+    let mut graph = DotGraph {
+        options,
+        highlight: options.highlight_classes.iter().map(|name| name.to_lowercase()).collect(),
+        out: String::from("digraph classes {\n    node [shape=ellipse];\n"),
+        edges: Vec::new(),
+        missing: Vec::new(),
+    };
+    let scopes = vec![classes];
+    graph.write_level(classes, &scopes, "", 0, 1);
+
+    for parent in std::mem::take(&mut graph.missing) {
+        let _ = writeln!(graph.out, "    \"{}\" [label=\"{}\", style=dashed];", escape(&parent), escape(&parent));
+    }
+    for (child, parent) in &graph.edges {
+        let _ = writeln!(graph.out, "    \"{}\" -> \"{}\";", escape(child), escape(parent));
+    }
+    graph.out.push_str("}\n");
+    graph.out
+}
+
+struct DotGraph<'a> {
+    options: &'a DotOptions,
+    highlight: HashSet<String>,
+    out: String,
+    edges: Vec<(String, String)>,
+    missing: Vec<String>,
+}
+
+impl<'a> DotGraph<'a> {
+    /// Write the nodes for `classes`, whose ids start with `prefix`.
+    /// `scopes` lists the sibling sets from the outermost level inwards,
+    /// with `classes` last, for parent lookup.
+    fn write_level<'c>(&mut self, classes: &'c [ClassNode], scopes: &[&'c [ClassNode]], prefix: &str, depth: usize, indent: usize) {
+        let pad = "    ".repeat(indent);
+        for class in classes {
+            let id = format!("{}{}", prefix, class.name);
+
+            let mut label = escape(&class.name);
+            if self.options.property_counts {
+                let count = class.properties.len();
+                let _ = write!(label, "\\n({} {})", count, if count == 1 { "property" } else { "properties" });
+            }
+            if self.options.include_properties {
+                label.push_str("\\n");
+                for property in class.properties.values() {
+                    let _ = write!(label, "{} = {}\\l", escape(&property.name), escape(&property.raw_value));
+                }
+            }
+            let mut attributes = format!("label=\"{}\"", label);
+            if class.parent.is_none() {
+                attributes.push_str(", shape=box");
+            }
+            if self.highlight.contains(&class.name.to_lowercase()) {
+                attributes.push_str(", style=filled, fillcolor=yellow");
+            }
+            let _ = writeln!(self.out, "{}\"{}\" [{}];", pad, escape(&id), attributes);
+
+            if let Some(parent) = &class.parent {
+                let target = self.resolve_parent(parent, scopes, prefix);
+                self.edges.push((id.clone(), target));
+            }
+
+            let descend = self.options.max_depth.is_none_or(|max| depth < max);
+            if descend && !class.nested_classes.is_empty() {
+                let _ = writeln!(self.out, "{}subgraph \"cluster_{}\" {{", pad, escape(&id));
+                let _ = writeln!(self.out, "{}    label=\"{}\";", pad, escape(&class.name));
+                let mut inner = scopes.to_vec();
+                inner.push(&class.nested_classes);
+                self.write_level(&class.nested_classes, &inner, &format!("{}/", id), depth + 1, indent + 1);
+                let _ = writeln!(self.out, "{}}}", pad);
+            }
+        }
+    }
+
+    /// Node id of `parent` as seen from a class whose id starts with `prefix`.
+    fn resolve_parent(&mut self, parent: &str, scopes: &[&[ClassNode]], prefix: &str) -> String {
+        // Each scope's id prefix is the prefix of the level inside it minus
+        // its last segment
+        let mut scope_prefix = prefix.to_string();
+        for scope in scopes.iter().rev() {
+            if let Some(found) = scope.iter().find(|class| class.name.eq_ignore_ascii_case(parent)) {
+                return format!("{}{}", scope_prefix, found.name);
+            }
+            let trimmed = scope_prefix.trim_end_matches('/');
+            scope_prefix = match trimmed.rfind('/') {
+                Some(index) => trimmed[..=index].to_string(),
+                None => String::new(),
+            };
+        }
+        if !self.missing.iter().any(|name| name == parent) {
+            self.missing.push(parent.to_string());
+        }
+        parent.to_string()
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ClassScanner;
+
+    #[test]
+    fn test_to_dot() {
+        let classes = ClassScanner::new().parse_string(r#"
+            class Vehicle { crew = 1; };
+            class Car: Vehicle { wheels = 4; };
+            class CfgVehicles {
+                class Tank: Vehicle {};
+                class Truck: Car {
+                    class Turrets { class MainTurret: NewTurret {}; };
+                };
+                class Car {};
+            };
+        "#).unwrap();
+
+        let dot = to_dot(&classes);
+        assert!(dot.starts_with("digraph classes {\n"));
+        assert!(dot.ends_with("}\n"));
+        assert!(dot.contains("    \"Vehicle\" [label=\"Vehicle\", shape=box];\n"));
+        assert!(dot.contains("    \"Car\" [label=\"Car\"];\n"));
+        assert!(dot.contains("    subgraph \"cluster_CfgVehicles\" {\n        label=\"CfgVehicles\";\n"));
+        assert!(dot.contains("        \"CfgVehicles/Tank\" [label=\"Tank\"];\n"));
+        assert!(dot.contains("            \"CfgVehicles/Truck/Turrets/MainTurret\" [label=\"MainTurret\"];\n"));
+
+        assert!(dot.contains("    \"Car\" -> \"Vehicle\";\n"));
+        assert!(dot.contains("    \"CfgVehicles/Tank\" -> \"Vehicle\";\n"));
+        // The closest Car is the sibling inside CfgVehicles
+        assert!(dot.contains("    \"CfgVehicles/Truck\" -> \"CfgVehicles/Car\";\n"));
+        assert!(dot.contains("    \"NewTurret\" [label=\"NewTurret\", style=dashed];\n"));
+        assert!(dot.contains("    \"CfgVehicles/Truck/Turrets/MainTurret\" -> \"NewTurret\";\n"));
+        assert_eq!(dot.matches('{').count(), dot.matches('}').count());
+    }
+
+    #[test]
+    fn test_dot_options() {
+        let classes = ClassScanner::new().parse_string(r#"
+            class Outer { class Inner { class Deep {}; }; };
+            class Rifle { model = "\x\rifle\m4.p3d"; };
+        "#).unwrap();
+
+        let options = DotOptions::new()
+            .with_properties(true)
+            .with_max_depth(Some(1))
+            .with_highlight(["rifle"]);
+        let dot = ClassScanner::new().to_dot(&classes, options);
+
+        assert!(dot.contains("\"Outer/Inner\""));
+        assert!(!dot.contains("Deep"));
+        assert!(dot.contains(r#""Rifle" [label="Rifle\nmodel = \\x\\rifle\\m4.p3d\l", shape=box, style=filled, fillcolor=yellow];"#));
+    }
+}
@@ -1,78 +1,380 @@
 use std::collections::{HashMap, HashSet};
 use super::{ClassNode, PropertyNode, AstVisitor};
-use crate::error::Error;
-use crate::operations::arrays::ArrayOperation;
+use crate::error::{Error, Warning};
+use crate::operations::arrays::{ArrayOperation, ArrayOptions};
+use crate::operations::inheritance::MissingParent;
+use tracing::{debug, warn};
 
+/// Resolves class inheritance the way the game does: every class is
+/// registered under its full path (e.g. `CfgWeapons/Uniform_Base/ItemInfo`)
+/// and parents are looked up from the child's scope outwards, including
+/// members inherited by the enclosing classes, before the global scope.
 pub struct InheritanceVisitor {
     class_map: HashMap<String, ClassNode>,
-    processed: Vec<String>,
+    /// Path keys by simple class name, in registration order.
+    paths_by_name: HashMap<String, Vec<String>>,
+    resolved: HashMap<String, ClassNode>,
+    case_insensitive: bool,
+    missing_parent: MissingParent,
+    strict_cycles: bool,
+    array_options: ArrayOptions,
+    unresolved_parents: Vec<String>,
+    warnings: Vec<Warning>,
 }
 
 impl InheritanceVisitor {
     pub fn new() -> Self {
         Self {
             class_map: HashMap::new(),
-            processed: Vec::new(),
+            paths_by_name: HashMap::new(),
+            resolved: HashMap::new(),
+            case_insensitive: true,
+            missing_parent: MissingParent::Error,
+            strict_cycles: false,
+            array_options: ArrayOptions::default(),
+            unresolved_parents: Vec::new(),
+            warnings: Vec::new(),
         }
     }
 
+    /// Match class names ignoring case, as the game does. On by default.
+    pub fn with_case_insensitive(mut self, enabled: bool) -> Self {
+        self.case_insensitive = enabled;
+        self
+    }
+
+    pub fn with_missing_parent(mut self, policy: MissingParent) -> Self {
+        self.missing_parent = policy;
+        self
+    }
+
+    /// Fail on an inheritance cycle with an error naming it, instead of
+    /// leaving the parent that closes the cycle unmerged and raising a
+    /// [`Warning::CircularInheritanceResolved`]. Off by default.
+    pub fn with_strict_cycles(mut self, enabled: bool) -> Self {
+        self.strict_cycles = enabled;
+        self
+    }
+
+    /// How inherited arrays are combined with `+=`, `-=` and `&=`.
+    pub fn with_array_options(mut self, options: ArrayOptions) -> Self {
+        self.array_options = options;
+        self
+    }
+
+    /// Parent names that were not found, in the order they were first met.
+    /// Only filled with [`MissingParent::Collect`].
+    pub fn unresolved_parents(&self) -> &[String] {
+        &self.unresolved_parents
+    }
+
+    /// Take the warnings collected while processing, such as broken cycles
+    /// and parents missing under [`MissingParent::Collect`].
+    pub fn take_warnings(&mut self) -> Vec<Warning> {
+        std::mem::take(&mut self.warnings)
+    }
+
+    /// Register a top-level class and, recursively, its nested classes. A
+    /// class with an empty name (the parser's root) only registers its
+    /// children.
     pub fn register_class(&mut self, class: ClassNode) {
-        // Save both the class name and its full path if available
-        let class_name = class.name.clone();
-        self.class_map.insert(class_name, class);
+        if class.name.is_empty() {
+            for nested in class.nested_classes {
+                self.register_at(nested, &[]);
+            }
+        } else {
+            self.register_at(class, &[]);
+        }
     }
 
+    fn register_at(&mut self, class: ClassNode, scope: &[String]) {
+        let mut path = scope.to_vec();
+        path.push(self.key(&class.name));
+        let key = path.join("/");
+
+        for nested in &class.nested_classes {
+            self.register_at(nested.clone(), &path);
+        }
+
+        if let Some(existing) = self.class_map.get(&key) {
+            if existing.name != class.name {
+                warn!(existing = %existing.name, replacement = %class.name, "Class names differ only by case; keeping the last one");
+            }
+        } else {
+            self.paths_by_name.entry(self.key(&class.name)).or_default().push(key.clone());
+        }
+        self.resolved.remove(&key);
+        self.class_map.insert(key, class);
+    }
+
+    /// Resolve a class given either its simple name or a slash-separated
+    /// path. A simple name prefers a top-level class, then the most recently
+    /// registered nested class of that name.
     pub fn process(&mut self, class_name: &str) -> Result<ClassNode, Error> {
-        // Use a separate set to track recursion paths during a single processing call
-        self.process_with_cycle_detection(class_name, &mut HashSet::new())
+        let key = self.find_class(class_name)
+            .ok_or_else(|| Error::InheritanceError(format!("Class {} not found", class_name)))?;
+        // Use a separate stack to track recursion paths during a single processing call
+        self.process_with_cycle_detection(&key, &mut Vec::new())
+    }
+
+    /// Register `root` and replace each of its top-level classes with its
+    /// resolved form. `root` may be the parser's unnamed root. Nested
+    /// classes are merged as [`process`](Self::process) merges them.
+    pub fn resolve_tree(&mut self, root: &mut ClassNode) -> Result<(), Error> {
+        self.register_class(root.clone());
+        let classes = if root.name.is_empty() {
+            root.nested_classes.iter_mut().collect()
+        } else {
+            vec![root]
+        };
+        for class in classes {
+            let key = self.key(&class.name);
+            *class = self.process_with_cycle_detection(&key, &mut Vec::new())?;
+        }
+        Ok(())
+    }
+
+    fn find_class(&self, class_name: &str) -> Option<String> {
+        let key = class_name.split('/')
+            .map(|segment| self.key(segment))
+            .collect::<Vec<_>>()
+            .join("/");
+        if self.class_map.contains_key(&key) || key.contains('/') {
+            return self.class_map.contains_key(&key).then_some(key);
+        }
+
+        let candidates = self.paths_by_name.get(&key)?;
+        if candidates.len() > 1 {
+            debug!(class_name, candidates = ?candidates, "Ambiguous class name, using the last registration");
+        }
+        candidates.last().cloned()
     }
     
-    fn process_with_cycle_detection(&mut self, class_name: &str, processing_stack: &mut HashSet<String>) 
+    fn process_with_cycle_detection(&mut self, key: &str, processing_stack: &mut Vec<String>) 
         -> Result<ClassNode, Error> {
         // Return already processed classes directly
-        if self.processed.contains(&class_name.to_string()) {
-            return Ok(self.class_map.get(class_name).unwrap().clone());
+        if let Some(resolved) = self.resolved.get(key) {
+            return Ok(resolved.clone());
         }
         
         // Check for circular inheritance
-        if processing_stack.contains(&class_name.to_string()) {
-            return Err(Error::InheritanceError(format!("Circular inheritance detected involving class {}", class_name)));
+        if let Some(start) = processing_stack.iter().position(|k| k == key) {
+            let chain: Vec<String> = processing_stack[start..].iter()
+                .chain([&key.to_string()])
+                .map(|k| self.display_path(k))
+                .collect();
+            if self.strict_cycles {
+                return Err(Error::InheritanceError(format!("Circular inheritance detected: {}", chain.join(" -> "))));
+            }
+            self.warnings.push(Warning::CircularInheritanceResolved { chain });
+            return Err(Error::InheritanceError(format!("Circular inheritance detected involving class {}", key)));
         }
         
-        let class = self.class_map.get(class_name)
-            .ok_or_else(|| Error::InheritanceError(format!("Class {} not found", class_name)))?
+        let class = self.class_map.get(key)
+            .ok_or_else(|| Error::InheritanceError(format!("Class {} not found", key)))?
             .clone();
 
         let mut result = class.clone();
 
         // Mark this class as being processed to detect cycles
-        processing_stack.insert(class_name.to_string());
+        processing_stack.push(key.to_string());
         
         if let Some(parent_name) = &class.parent {
-            // Try to process the parent class, which might detect a cycle
-            match self.process_with_cycle_detection(parent_name, processing_stack) {
-                Ok(parent) => self.merge_properties(&mut result, &parent),
-                Err(Error::InheritanceError(msg)) if msg.contains("Circular inheritance") => {
-                    // If it's a circular reference, we can still use what we have
-                    // Just continue with the current class without merging parent properties
-                    // Optionally log the circular reference
+            let path: Vec<String> = key.split('/').map(str::to_string).collect();
+            match self.lookup_parent(&path, parent_name, &mut HashSet::new()) {
+                // Try to process the parent class, which might detect a cycle
+                Some(parent_key) => match self.process_with_cycle_detection(&parent_key, processing_stack) {
+                    Ok(parent) => self.merge_properties(&mut result, &parent),
+                    Err(Error::InheritanceError(msg)) if !self.strict_cycles && msg.contains("Circular inheritance") => {
+                        // If it's a circular reference, we can still use what we have
+                        // Just continue with the current class without merging parent properties
+                    },
+                    Err(e) => return Err(e),  // Propagate other errors
                 },
-                Err(e) => return Err(e),  // Propagate other errors
+                None => self.missing_parent(&class.name, parent_name)?,
             }
         }
 
         // Remove this class from the processing stack since we're done with it
-        processing_stack.remove(&class_name.to_string());
+        processing_stack.pop();
         
         // Mark as fully processed for future reference
-        self.processed.push(class_name.to_string());
+        self.resolved.insert(key.to_string(), result.clone());
         
         Ok(result)
     }
 
+    /// Apply the missing-parent policy to `class`'s unregistered `parent`.
+    fn missing_parent(&mut self, class: &str, parent: &str) -> Result<(), Error> {
+        match self.missing_parent {
+            MissingParent::Error => Err(Error::InheritanceError(format!("Class {} not found", parent))),
+            MissingParent::Ignore => Ok(()),
+            MissingParent::Collect => {
+                let key = self.key(parent);
+                if !self.unresolved_parents.iter().any(|name| self.key(name) == key) {
+                    self.unresolved_parents.push(parent.to_string());
+                }
+                self.warnings.push(Warning::MissingParent { class: class.to_string(), parent: parent.to_string() });
+                Ok(())
+            }
+        }
+    }
+
+    /// Ancestors of a class (simple name or slash-separated path), nearest
+    /// first. Each is given as its path, e.g. `CfgWeapons/ItemCore`. A parent
+    /// that is not registered ends the chain under the name the class gives
+    /// it. An inheritance cycle is an error naming the cycle.
+    pub fn ancestors(&self, class_name: &str) -> Result<Vec<String>, Error> {
+        let key = self.find_class(class_name)
+            .ok_or_else(|| Error::InheritanceError(format!("Class {} not found", class_name)))?;
+        let (chain, cycle) = self.walk_ancestors(key);
+        match cycle {
+            Some(cycle) => Err(Error::InheritanceError(format!("Circular inheritance detected: {}", cycle.join(" -> ")))),
+            None => Ok(chain),
+        }
+    }
+
+    /// Whether a class named `ancestor` is among the ancestors of
+    /// `class_name`. A cycle ends the walk rather than failing it.
+    pub fn derives_from(&self, class_name: &str, ancestor: &str) -> bool {
+        let Some(key) = self.find_class(class_name) else {
+            return false;
+        };
+        let ancestor = self.key(ancestor);
+        self.walk_ancestors(key).0.iter()
+            .any(|path| path.rsplit('/').next().is_some_and(|name| self.key(name) == ancestor))
+    }
+
+    /// Paths of every class that inherits from `class_name`, directly or
+    /// not, nearest first.
+    pub fn descendants(&self, class_name: &str) -> Vec<String> {
+        let Some(root) = self.find_class(class_name) else {
+            return Vec::new();
+        };
+
+        let mut keys: Vec<&String> = self.class_map.keys().collect();
+        keys.sort();
+        let mut children: HashMap<String, Vec<String>> = HashMap::new();
+        for key in keys {
+            if let Some(parent) = &self.class_map[key].parent {
+                if let Some(parent_key) = self.find_parent(key, parent) {
+                    children.entry(parent_key).or_default().push(key.clone());
+                }
+            }
+        }
+
+        let mut descendants = Vec::new();
+        let mut seen = HashSet::from([root.clone()]);
+        let mut queue = std::collections::VecDeque::from([root]);
+        while let Some(key) = queue.pop_front() {
+            for child in children.get(&key).into_iter().flatten() {
+                if seen.insert(child.clone()) {
+                    descendants.push(self.display_path(child));
+                    queue.push_back(child.clone());
+                }
+            }
+        }
+        descendants
+    }
+
+    /// The ancestor paths of the class at `key` and, if the walk ran into a
+    /// cycle, the cycle's paths.
+    fn walk_ancestors(&self, mut key: String) -> (Vec<String>, Option<Vec<String>>) {
+        let mut chain = Vec::new();
+        let mut seen = vec![key.clone()];
+        while let Some(parent) = self.class_map.get(&key).and_then(|class| class.parent.as_ref()) {
+            let Some(parent_key) = self.find_parent(&key, parent) else {
+                chain.push(parent.clone());
+                break;
+            };
+            if let Some(start) = seen.iter().position(|k| *k == parent_key) {
+                let mut cycle: Vec<String> = seen[start..].iter().map(|k| self.display_path(k)).collect();
+                cycle.push(self.display_path(&parent_key));
+                return (chain, Some(cycle));
+            }
+            chain.push(self.display_path(&parent_key));
+            seen.push(parent_key.clone());
+            key = parent_key;
+        }
+        (chain, None)
+    }
+
+    /// A path key with each segment in the case the class was declared with.
+    fn display_path(&self, key: &str) -> String {
+        let mut prefix = String::new();
+        let mut names = Vec::new();
+        for segment in key.split('/') {
+            if !prefix.is_empty() {
+                prefix.push('/');
+            }
+            prefix.push_str(segment);
+            names.push(self.class_map.get(&prefix).map_or(segment, |class| class.name.as_str()).to_string());
+        }
+        names.join("/")
+    }
+
+    /// Path key of the class that `parent` names when declared as the parent
+    /// of the class at the slash-separated `class_path`, scoped the same way
+    /// as [`process`](Self::process).
+    pub fn find_parent(&self, class_path: &str, parent: &str) -> Option<String> {
+        let path: Vec<String> = class_path.split('/').map(|segment| self.key(segment)).collect();
+        self.lookup_parent(&path, parent, &mut HashSet::new())
+    }
+
+    /// Find `parent` as seen from the class at `path`, walking outwards
+    /// through the enclosing scopes. The class itself is never its own
+    /// parent, so `class Turrets: Turrets` finds an inherited `Turrets`.
+    fn lookup_parent(&self, path: &[String], parent: &str, visited: &mut HashSet<String>) -> Option<String> {
+        let own_key = path.join("/");
+        let name = self.key(parent);
+        (0..path.len()).rev()
+            .find_map(|depth| self.find_in_scope(&path[..depth], &name, &own_key, visited))
+    }
+
+    fn find_in_scope(&self, scope: &[String], name: &str, exclude: &str, visited: &mut HashSet<String>) -> Option<String> {
+        let mut candidate = scope.to_vec();
+        candidate.push(name.to_string());
+        let candidate = candidate.join("/");
+        if candidate != exclude && self.class_map.contains_key(&candidate) {
+            return Some(candidate);
+        }
+
+        // Members inherited by the enclosing class are in scope too
+        let scope_key = scope.join("/");
+        if scope.is_empty() || !visited.insert(scope_key.clone()) {
+            return None;
+        }
+        let scope_parent = self.class_map.get(&scope_key)?.parent.as_ref()?;
+        let scope_parent_key = self.lookup_parent(scope, scope_parent, visited)?;
+        let scope_parent_path: Vec<String> = scope_parent_key.split('/').map(str::to_string).collect();
+        self.find_in_scope(&scope_parent_path, name, exclude, visited)
+    }
+
+    fn key(&self, name: &str) -> String {
+        if self.case_insensitive {
+            name.to_lowercase()
+        } else {
+            name.to_string()
+        }
+    }
+
     fn merge_properties(&self, child: &mut ClassNode, parent: &ClassNode) {
+        // `delete name;` in the child drops the inherited member entirely
+        let deleted: HashSet<String> = child.properties.values()
+            .filter(|prop| prop.is_deleted())
+            .map(|prop| &prop.name)
+            .chain(&child.deleted_classes)
+            .map(|name| self.key(name))
+            .collect();
+        child.properties.retain(|_, prop| !prop.is_deleted());
+        child.deleted_classes.clear();
+        child.nested_classes.retain(|nested| !deleted.contains(&self.key(&nested.name)));
+
         // Copy properties from parent that aren't in child
         for (name, parent_prop) in &parent.properties {
+            if deleted.contains(&self.key(name)) {
+                continue;
+            }
             if !child.properties.contains_key(name) {
                 child.properties.insert(name.clone(), parent_prop.clone());
             } else if let Some(child_prop) = child.properties.get_mut(name) {
@@ -81,22 +383,20 @@ impl InheritanceVisitor {
                    parent_prop.value_type == crate::ast::PropertyType::Array && 
                    child_prop.operation.is_some() {
                     // Apply the array operation
-                    use crate::operations::arrays::ArrayProcessor;
                     let op = child_prop.operation.unwrap();
-                    child_prop.array_values = ArrayProcessor::process(
-                        &parent_prop.array_values,
-                        &child_prop.array_values,
-                        op
-                    );
+                    child_prop.apply_array_operation(parent_prop, op, self.array_options);
                 }
             }
         }
 
         // Merge nested classes recursively
         for parent_nested in &parent.nested_classes {
+            if deleted.contains(&self.key(&parent_nested.name)) {
+                continue;
+            }
             let mut found = false;
             for child_nested in &mut child.nested_classes {
-                if child_nested.name == parent_nested.name {
+                if self.key(&child_nested.name) == self.key(&parent_nested.name) {
                     self.merge_properties(child_nested, parent_nested);
                     found = true;
                     break;
@@ -111,11 +411,8 @@ impl InheritanceVisitor {
 
 impl AstVisitor for InheritanceVisitor {
     fn visit_class(&mut self, class: &mut ClassNode) -> Result<(), Error> {
-        // Register this class and any nested classes
+        // Registers this class and, with their full paths, any nested classes
         self.register_class(class.clone());
-        for nested in &mut class.nested_classes {
-            self.visit_class(nested)?;
-        }
         Ok(())
     }
 
@@ -133,26 +430,18 @@ impl AstVisitor for InheritanceVisitor {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::ast::{PropertyType, AccessModifier};
+    use crate::ast::{PropertyType, AccessModifier, ClassNodeBuilder};
     
     // Helper function to create test classes
     fn create_test_class(name: &str, parent: Option<&str>, properties: Vec<(&str, &str)>) -> ClassNode {
-        let mut class = ClassNode::new(name.to_string());
-        
+        let mut builder = ClassNodeBuilder::new(name);
         if let Some(parent_name) = parent {
-            class = class.with_parent(parent_name);
-        }
-        
-        for (prop_name, prop_value) in properties {
-            let property = PropertyNode::new(
-                prop_name, 
-                PropertyType::String, 
-                prop_value
-            );
-            class.properties.insert(prop_name.to_string(), property);
+            builder = builder.parent(parent_name);
         }
-        
-        class
+        properties.into_iter()
+            .fold(builder, |builder, (prop_name, prop_value)| builder.property_raw(prop_name, PropertyType::String, prop_value))
+            .build()
+            .unwrap()
     }
     
     #[test]
@@ -181,6 +470,71 @@ mod tests {
         assert_eq!(processed.properties["prop3"].raw_value, "child_val3"); // Child's own
     }
     
+    #[test]
+    fn test_case_insensitive_lookup() {
+        let parent = create_test_class("Parent", None, vec![("prop1", "parent_val1")]);
+        let child = create_test_class("child", Some("PARENT"), vec![("prop2", "child_val2")]);
+
+        let mut visitor = InheritanceVisitor::new();
+        visitor.register_class(parent.clone());
+        visitor.register_class(child.clone());
+
+        let processed = visitor.process("CHILD").unwrap();
+        assert_eq!(processed.name, "child");
+        assert_eq!(processed.properties["prop1"].raw_value, "parent_val1");
+
+        let mut strict = InheritanceVisitor::new().with_case_insensitive(false);
+        strict.register_class(parent);
+        strict.register_class(child);
+        assert!(strict.process("child").is_err());
+
+        // Names differing only by case collide; the last registration wins
+        let mut visitor = InheritanceVisitor::new();
+        visitor.register_class(create_test_class("Item", None, vec![("v", "first")]));
+        visitor.register_class(create_test_class("ITEM", None, vec![("v", "second")]));
+        let processed = visitor.process("item").unwrap();
+        assert_eq!(processed.name, "ITEM");
+        assert_eq!(processed.properties["v"].raw_value, "second");
+    }
+
+    #[test]
+    fn test_scoped_parent_lookup() {
+        let input = r#"
+            class CfgWeapons {
+                class InventoryItem_Base_F { mass = 1; };
+                class Uniform_Base {
+                    class ItemInfo: InventoryItem_Base_F { type = 801; };
+                };
+                class MyUniform: Uniform_Base {
+                    class ItemInfo: ItemInfo { mass = 40; };
+                };
+            };
+            class CfgVehicles {
+                class ItemInfo { wrong = 1; };
+                class InventoryItem_Base_F { alsoWrong = 1; };
+            };
+        "#;
+        let tokens = crate::lexer::Tokenizer::new(input).tokenize().unwrap();
+        let mut root = crate::parser::Parser::new(tokens).parse().unwrap();
+
+        let mut visitor = InheritanceVisitor::new();
+        visitor.visit_class(&mut root).unwrap();
+
+        // `ItemInfo: ItemInfo` is found through MyUniform's parent, and
+        // `InventoryItem_Base_F` in the enclosing CfgWeapons scope
+        let processed = visitor.process("CfgWeapons/MyUniform/ItemInfo").unwrap();
+        assert_eq!(processed.properties["mass"].raw_value, "40");
+        assert_eq!(processed.properties["type"].raw_value, "801");
+        assert!(!processed.properties.contains_key("wrong"));
+        assert!(!processed.properties.contains_key("alsoWrong"));
+
+        let base_item = visitor.process("cfgweapons/uniform_base/iteminfo").unwrap();
+        assert_eq!(base_item.properties["mass"].raw_value, "1");
+
+        assert!(visitor.process("CfgWeapons/Missing").is_err());
+        assert_eq!(visitor.process("CfgVehicles").unwrap().name, "CfgVehicles");
+    }
+
     #[test]
     fn test_multilevel_inheritance() {
         let grandparent = create_test_class(
@@ -236,6 +590,30 @@ mod tests {
         assert_eq!(processed.nested_classes[0].properties["nested_prop"].raw_value, "nested_val");
     }
     
+    #[test]
+    fn test_deleted_nested_class() {
+        let mut parent = create_test_class("Parent", None, vec![]);
+        parent.nested_classes.push(create_test_class("Turrets", None, vec![("gunner", "A")]));
+        parent.nested_classes.push(create_test_class("HitPoints", None, vec![]));
+
+        // A grandchild must not get the class back through its parent either
+        let mut child = create_test_class("Child", Some("Parent"), vec![]);
+        child.deleted_classes.push("turrets".to_string());
+        let grandchild = create_test_class("GrandChild", Some("Child"), vec![]);
+
+        let mut visitor = InheritanceVisitor::new();
+        visitor.register_class(parent);
+        visitor.register_class(child);
+        visitor.register_class(grandchild);
+
+        for name in ["Child", "GrandChild"] {
+            let processed = visitor.process(name).unwrap();
+            let nested: Vec<&str> = processed.nested_classes.iter().map(|c| c.name.as_str()).collect();
+            assert_eq!(nested, vec!["HitPoints"]);
+            assert!(processed.deleted_classes.is_empty());
+        }
+    }
+
     #[test]
     fn test_nested_class_override() {
         // Parent with nested class
@@ -295,10 +673,15 @@ mod tests {
         let class_c = create_test_class("ClassC", Some("ClassB"), vec![("propC", "valC")]);
         
         let mut visitor = InheritanceVisitor::new();
-        visitor.register_class(class_a);
-        visitor.register_class(class_b);
-        visitor.register_class(class_c);
+        visitor.register_class(class_a.clone());
+        visitor.register_class(class_b.clone());
+        visitor.register_class(class_c.clone());
         
+        let mut strict = InheritanceVisitor::new().with_strict_cycles(true);
+        for class in [&class_a, &class_b, &class_c] {
+            strict.register_class(class.clone());
+        }
+
         // Process should not hang and should correctly resolve properties
         let processed = visitor.process("ClassA").unwrap();
         
@@ -306,18 +689,33 @@ mod tests {
         assert!(processed.properties.contains_key("propA"));
         assert!(processed.properties.contains_key("propB"));
         assert!(processed.properties.contains_key("propC"));
+        assert_eq!(visitor.take_warnings(), vec![Warning::CircularInheritanceResolved {
+            chain: vec!["ClassA".to_string(), "ClassC".to_string(), "ClassB".to_string(), "ClassA".to_string()],
+        }]);
+
+        match strict.process("ClassA") {
+            Err(Error::InheritanceError(message)) => {
+                assert_eq!(message, "Circular inheritance detected: ClassA -> ClassC -> ClassB -> ClassA");
+            }
+            other => panic!("expected a cycle error, got {:?}", other),
+        }
+        assert!(strict.take_warnings().is_empty());
     }
     
     #[test]
     fn test_access_modifier_inheritance() {
         // Parent with public access
-        let parent = ClassNode::new("Parent".to_string())
-            .with_access(AccessModifier::Public);
+        let parent = ClassNodeBuilder::new("Parent")
+            .access(AccessModifier::Public)
+            .build()
+            .unwrap();
             
         // Child with private access
-        let child = ClassNode::new("Child".to_string())
-            .with_parent("Parent")
-            .with_access(AccessModifier::Private);
+        let child = ClassNodeBuilder::new("Child")
+            .parent("Parent")
+            .access(AccessModifier::Private)
+            .build()
+            .unwrap();
             
         let mut visitor = InheritanceVisitor::new();
         visitor.register_class(parent);
@@ -332,19 +730,13 @@ mod tests {
     #[test]
     fn test_ast_visitor_implementation() {
         // Create a hierarchy with nested classes
-        let mut root = ClassNode::new("Root".to_string());
-        
-        let mut parent = ClassNode::new("Parent".to_string());
-        parent.properties.insert("parentProp".to_string(), 
-            PropertyNode::new("parentProp", PropertyType::String, "parentVal"));
-            
-        let mut child = ClassNode::new("Child".to_string())
-            .with_parent("Parent");
-        child.properties.insert("childProp".to_string(),
-            PropertyNode::new("childProp", PropertyType::String, "childVal"));
-            
-        parent.nested_classes.push(child);
-        root.nested_classes.push(parent);
+        let child = ClassNodeBuilder::new("Child")
+            .parent("Parent")
+            .property_raw("childProp", PropertyType::String, "childVal");
+        let parent = ClassNodeBuilder::new("Parent")
+            .property_raw("parentProp", PropertyType::String, "parentVal")
+            .nested_class(child);
+        let mut root = ClassNodeBuilder::new("Root").nested_class(parent).build().unwrap();
         
         // Use the visitor pattern to register all classes
         let mut visitor = InheritanceVisitor::new();
@@ -357,4 +749,72 @@ mod tests {
         assert_eq!(processed.properties["parentProp"].raw_value, "parentVal");
         assert_eq!(processed.properties["childProp"].raw_value, "childVal");
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_ancestors_and_descendants() {
+        let classes = crate::ClassScanner::new().parse_string(r#"
+            class CfgWeapons {
+                class ItemCore;
+                class Vest_Camo_Base: ItemCore { class ItemInfo; };
+                class pca_vest: Vest_Camo_Base { class ItemInfo: ItemInfo {}; };
+                class pca_vest_kevlar: pca_vest {};
+                class Loop_A: Loop_B {};
+                class Loop_B: Loop_A {};
+                class Orphan: Missing_Base {};
+            };
+        "#).unwrap();
+        let mut visitor = InheritanceVisitor::new();
+        visitor.register_class(classes[0].clone());
+
+        assert_eq!(visitor.ancestors("pca_vest_kevlar").unwrap(), vec![
+            "CfgWeapons/pca_vest", "CfgWeapons/Vest_Camo_Base", "CfgWeapons/ItemCore",
+        ]);
+        assert_eq!(visitor.ancestors("CfgWeapons/pca_vest/ItemInfo").unwrap(), vec!["CfgWeapons/Vest_Camo_Base/ItemInfo"]);
+        assert_eq!(visitor.ancestors("Orphan").unwrap(), vec!["Missing_Base"]);
+        match visitor.ancestors("Loop_A") {
+            Err(Error::InheritanceError(message)) => assert!(message.ends_with("CfgWeapons/Loop_A -> CfgWeapons/Loop_B -> CfgWeapons/Loop_A")),
+            other => panic!("expected a cycle error, got {:?}", other),
+        }
+        assert!(visitor.ancestors("Unknown").is_err());
+
+        assert!(visitor.derives_from("pca_vest_kevlar", "itemcore"));
+        assert!(visitor.derives_from("Orphan", "Missing_Base"));
+        assert!(!visitor.derives_from("Loop_A", "ItemCore"));
+        assert!(visitor.derives_from("Loop_A", "Loop_B"));
+
+        assert_eq!(visitor.descendants("ItemCore"), vec![
+            "CfgWeapons/Vest_Camo_Base", "CfgWeapons/pca_vest", "CfgWeapons/pca_vest_kevlar",
+        ]);
+        assert_eq!(visitor.descendants("Loop_A"), vec!["CfgWeapons/Loop_B"]);
+    }
+
+    #[test]
+    fn test_missing_parent_policy() {
+        let classes = crate::ClassScanner::new().parse_string(r#"
+            class CfgWeapons {
+                class Vest_Base: Vest_Camo_Base { armor = 10; };
+                class Vest: Vest_Base { mass = 5; };
+            };
+        "#).unwrap();
+        let visitor = |policy| {
+            let mut visitor = InheritanceVisitor::new().with_missing_parent(policy);
+            visitor.register_class(classes[0].clone());
+            visitor
+        };
+
+        assert!(matches!(visitor(MissingParent::Error).process("Vest"),
+            Err(Error::InheritanceError(message)) if message == "Class Vest_Camo_Base not found"));
+
+        let mut ignoring = visitor(MissingParent::Ignore);
+        assert_eq!(ignoring.process("Vest").unwrap().properties["armor"].raw_value, "10");
+        assert!(ignoring.unresolved_parents().is_empty());
+
+        let mut collecting = visitor(MissingParent::Collect);
+        let vest = collecting.process("CfgWeapons/Vest").unwrap();
+        assert_eq!(vest.properties["armor"].raw_value, "10");
+        assert_eq!(vest.properties["mass"].raw_value, "5");
+        assert_eq!(collecting.unresolved_parents(), ["Vest_Camo_Base"]);
+        assert!(matches!(collecting.take_warnings().as_slice(),
+            [Warning::MissingParent { class, parent }] if class == "Vest_Base" && parent == "Vest_Camo_Base"));
+    }
+}
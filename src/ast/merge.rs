@@ -0,0 +1,165 @@
+use super::{ClassNode, PropertyNode, PropertyType};
+use crate::error::Error;
+use crate::operations::arrays::ArrayOperation;
+
+/// How [`ClassNode::merge_from`] resolves a member defined differently on
+/// both sides.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MergeStrategy {
+    /// The merged-in class overrides, as a later file would in game.
+    #[default]
+    LastWins,
+    FirstWins,
+    /// Fail with `Error::MergeError` on the first conflict.
+    Error,
+}
+
+impl ClassNode {
+    /// Merge another definition of the same class into this one.
+    ///
+    /// Nested classes with matching names (ignoring case) merge recursively
+    /// and `+=` arrays in `other` extend the existing values. A forward
+    /// declaration (`class Name;`) contributes nothing; merging a
+    /// definition into one replaces it, source location included.
+    pub fn merge_from(&mut self, other: ClassNode, strategy: MergeStrategy) -> Result<(), Error> {
+        if other.is_external {
+            return Ok(());
+        }
+        if self.is_external {
+            *self = other;
+            return Ok(());
+        }
+
+        if other.parent.is_some() && other.parent != self.parent
+            && (self.parent.is_none() || resolve_conflict(strategy, || format!("parent of class {}", self.name))?)
+        {
+            self.parent = other.parent;
+        }
+
+        for (name, property) in other.properties {
+            match self.properties.get_mut(&name) {
+                None => {
+                    self.properties.insert(name, property);
+                }
+                Some(existing) if property.operation == Some(ArrayOperation::Append)
+                    && existing.value_type == PropertyType::Array =>
+                {
+                    existing.array_values.extend(property.array_values);
+                    existing.raw_value = format!("{{{}}}", existing.array_values.join(","));
+                }
+                Some(existing) if same_value(existing, &property) => {}
+                Some(existing) => {
+                    let what = || format!("property {}.{}", self.name, name);
+                    if resolve_conflict(strategy, what)? {
+                        *existing = property;
+                    }
+                }
+            }
+        }
+
+        for nested in other.nested_classes {
+            match self.nested_classes.iter_mut().find(|c| c.name.eq_ignore_ascii_case(&nested.name)) {
+                Some(existing) => existing.merge_from(nested, strategy)?,
+                None => self.nested_classes.push(nested),
+            }
+        }
+
+        self.enums.extend(other.enums);
+        for name in other.deleted_classes {
+            if !self.deleted_classes.contains(&name) {
+                self.deleted_classes.push(name);
+            }
+        }
+        if self.doc_comment.is_none() {
+            self.doc_comment = other.doc_comment;
+        }
+        Ok(())
+    }
+}
+
+/// Whether the incoming side of a conflict should replace the existing one.
+fn resolve_conflict(strategy: MergeStrategy, what: impl FnOnce() -> String) -> Result<bool, Error> {
+    match strategy {
+        MergeStrategy::LastWins => Ok(true),
+        MergeStrategy::FirstWins => Ok(false),
+        MergeStrategy::Error => Err(Error::MergeError(format!("Conflicting definitions of {}", what()))),
+    }
+}
+
+fn same_value(a: &PropertyNode, b: &PropertyNode) -> bool {
+    a.value_type == b.value_type
+        && a.raw_value == b.raw_value
+        && a.operation == b.operation
+        && a.array_values == b.array_values
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ClassScanner;
+
+    fn parse(input: &str) -> ClassNode {
+        ClassScanner::new().parse_string(input).unwrap().remove(1)
+    }
+
+    #[test]
+    fn test_merge_trees() {
+        let mut weapons = parse(r#"
+            class CfgWeapons {
+                class Rifle_Base;
+                class Rifle: Rifle_Base {
+                    mass = 10;
+                    magazines[] = {"30Rnd"};
+                };
+            };
+        "#);
+        let other = parse(r#"
+            class CfgWeapons {
+                class Rifle_Base { scope = 0; };
+                class rifle {
+                    mass = 12;
+                    magazines[] += {"20Rnd"};
+                    class ItemInfo { weight = 1; };
+                };
+                class Pistol {};
+            };
+        "#);
+
+        weapons.merge_from(other, MergeStrategy::LastWins).unwrap();
+
+        let names: Vec<&str> = weapons.nested_classes.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["Rifle_Base", "Rifle", "Pistol"]);
+        assert!(!weapons.nested_classes[0].is_external);
+        assert_eq!(weapons.nested_classes[0].properties["scope"].raw_value, "0");
+
+        let rifle = &weapons.nested_classes[1];
+        assert_eq!(rifle.parent.as_deref(), Some("Rifle_Base"));
+        assert_eq!(rifle.properties["mass"].raw_value, "12");
+        assert_eq!(rifle.properties["magazines"].array_values, vec!["30Rnd", "20Rnd"]);
+        assert_eq!(rifle.nested_classes[0].name, "ItemInfo");
+    }
+
+    #[test]
+    fn test_merge_strategies() {
+        let first = parse("class A: Base { x = 1; y = 2; };");
+        let second = parse("class A: Other { x = 3; y = 2; };");
+
+        let mut merged = first.clone();
+        merged.merge_from(second.clone(), MergeStrategy::FirstWins).unwrap();
+        assert_eq!(merged.properties["x"].raw_value, "1");
+        assert_eq!(merged.parent.as_deref(), Some("Base"));
+
+        let mut merged = first.clone();
+        merged.merge_from(second.clone(), MergeStrategy::LastWins).unwrap();
+        assert_eq!(merged.properties["x"].raw_value, "3");
+        assert_eq!(merged.parent.as_deref(), Some("Other"));
+
+        let mut merged = first.clone();
+        assert!(matches!(merged.merge_from(second, MergeStrategy::Error), Err(Error::MergeError(_))));
+
+        // Identical values are not a conflict
+        let mut merged = first.clone();
+        merged.merge_from(first.clone(), MergeStrategy::Error).unwrap();
+        assert_eq!(merged.properties.len(), 2);
+    }
+}
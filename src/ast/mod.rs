@@ -1,45 +1,141 @@
 pub mod array_visitor;
+pub mod builder;
+pub mod composite;
+pub mod doc_comment_visitor;
+pub mod graphviz;
 pub mod inheritance_visitor;
+pub mod merge;
+pub mod pattern_visitor;
+pub mod pretty_printer;
+pub mod schema_visitor;
+pub mod walkers;
 
 pub use array_visitor::ArrayVisitor;
+pub use builder::ClassNodeBuilder;
+pub use composite::{CompositeVisitor, VisitorBuilder};
+pub use crate::operations::diff::{diff, apply_diff, ClassDiff, DiffKind, NestedClassDiff};
+pub use doc_comment_visitor::DocCommentVisitor;
+pub use graphviz::{to_dot, to_dot_with_options, DotOptions};
 pub use inheritance_visitor::InheritanceVisitor;
+pub use merge::MergeStrategy;
+pub use pattern_visitor::PatternValidationVisitor;
+pub use pretty_printer::{PrettyPrinter, PrintOptions};
+pub use schema_visitor::SchemaVisitor;
+pub use walkers::{ClassCountWalker, PropertyCollectorWalker, SchemaValidationWalker};
 
 use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
 use crate::models::property_value::PropertyValue;
-use crate::operations::arrays::ArrayOperation;
-use crate::error::Error;
+use crate::operations::inheritance::InheritanceResolver;
+use crate::operations::arrays::{ArrayElement, ArrayOperation, ArrayOptions, ArrayProcessor};
+use crate::error::{Error, SourceLocation};
+use crate::lexer::Token;
+use crate::utils::{InternedStr, StringInterner};
 use std::path::PathBuf;
 
-#[derive(Debug, Clone)]
+/// Compared and hashed by content: name, parent, access, properties (in any
+/// order), nested classes and deleted classes (both in any order). Where a class came from, its
+/// spans and comments do not count.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ClassNode {
     pub name: String,
     pub parent: Option<String>,
-    pub properties: HashMap<String, PropertyNode>,
+    /// Properties in declaration order.
+    pub properties: IndexMap<String, PropertyNode>,
     pub nested_classes: Vec<ClassNode>,
     pub access: AccessModifier,
     pub raw_block: String,
+    #[serde(with = "crate::utils::optional_path")]
     pub file_path: Option<PathBuf>,
+    /// Where the class was declared, if it came from parsed source.
+    pub location: Option<SourceLocation>,
+    /// Enumerations declared at this level (only populated on the parse root).
+    pub enums: Vec<EnumNode>,
+    /// From the `class` keyword to the closing brace (or `;` of a declaration).
+    pub span: Option<Span>,
+    pub name_span: Option<Span>,
+    /// The `{ ... }` body, absent for `class Name;` declarations.
+    pub block_span: Option<Span>,
+    /// Comment directly preceding the class, see `Parser::with_comments`.
+    pub doc_comment: Option<String>,
+    /// Inherited nested classes to drop, for classes built in code. Parsed
+    /// `delete X;` statements are recorded only as `PropertyType::Deleted`
+    /// properties, since the parser cannot tell classes from properties.
+    pub deleted_classes: Vec<String>,
+    /// `class Name;` forward declaration of a class defined elsewhere
+    /// (typically another addon), as opposed to an empty `class Name {};`.
+    pub is_external: bool,
 }
 
-#[derive(Debug, Clone)]
+/// Byte range and start position of a node in the preprocessed source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Span {
+    /// Span from the start of `first` to the end of `last`.
+    pub fn between(first: &Token, last: &Token) -> Self {
+        Self {
+            start: first.start,
+            end: last.end,
+            line: first.line,
+            column: first.column,
+        }
+    }
+
+    /// The spanned text, given the source the node was parsed from.
+    pub fn text<'a>(&self, source: &'a str) -> &'a str {
+        &source[self.start..self.end]
+    }
+}
+
+/// An `enum { ... };` block. Values keep their declaration order.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EnumNode {
+    pub name: String,
+    pub values: IndexMap<String, i64>,
+    pub location: Option<SourceLocation>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct PropertyNode {
     pub name: String,
     pub value_type: PropertyType,
     pub raw_value: String,
     pub operation: Option<ArrayOperation>,
+    /// Array elements as text, strings unquoted and nested arrays as `{a,b}`.
     pub array_values: Vec<String>,
+    /// Array elements keeping numbers, booleans and nested arrays apart from
+    /// strings. `array_values` is the text of these.
+    #[serde(default)]
+    pub typed_values: Vec<ArrayElement>,
+    /// Where the property was declared, if it came from parsed source.
+    pub location: Option<SourceLocation>,
+    /// The full `name = value;` statement.
+    pub span: Option<Span>,
+    /// Comment directly preceding the property, see `Parser::with_comments`.
+    pub doc_comment: Option<String>,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum PropertyType {
     String,
     Number,
     Boolean,
     Array,
     Object,
+    /// `delete name;` removes an inherited property or nested class.
+    Deleted,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum AccessModifier {
     Public,
     Private,
@@ -49,6 +145,57 @@ pub trait AstVisitor {
     fn visit_class(&mut self, class: &mut ClassNode) -> Result<(), Error>;
     fn visit_property(&mut self, property: &mut PropertyNode) -> Result<(), Error>;
     fn visit_array(&mut self, array: &mut Vec<String>, operation: Option<ArrayOperation>) -> Result<(), Error>;
+
+    fn visit_enum(&mut self, node: &mut EnumNode) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+/// Lets a visitor be lent to a [`CompositeVisitor`] and inspected afterwards.
+impl<V: AstVisitor + ?Sized> AstVisitor for &mut V {
+    fn visit_class(&mut self, class: &mut ClassNode) -> Result<(), Error> {
+        (**self).visit_class(class)
+    }
+
+    fn visit_property(&mut self, property: &mut PropertyNode) -> Result<(), Error> {
+        (**self).visit_property(property)
+    }
+
+    fn visit_array(&mut self, array: &mut Vec<String>, operation: Option<ArrayOperation>) -> Result<(), Error> {
+        (**self).visit_array(array, operation)
+    }
+
+    fn visit_enum(&mut self, node: &mut EnumNode) -> Result<(), Error> {
+        (**self).visit_enum(node)
+    }
+}
+
+/// Read-only counterpart of [`AstVisitor`], driven by [`ClassNode::walk`].
+/// Each method sees a single node; `walk` takes care of the recursion.
+pub trait AstWalker {
+    fn walk_class(&mut self, class: &ClassNode) -> Result<(), Error>;
+    fn walk_property(&mut self, property: &PropertyNode) -> Result<(), Error>;
+
+    fn walk_array(&mut self, array: &[String], operation: Option<ArrayOperation>) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn walk_enum(&mut self, node: &EnumNode) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Walk every class in `classes`. Note that `parse_string` and
+    /// `parse_file` results list top-level classes twice, under the root and
+    /// after it; pass `&classes[1..]` to see each once.
+    fn walk_all(&mut self, classes: &[ClassNode]) -> Result<(), Error>
+    where
+        Self: Sized,
+    {
+        for class in classes {
+            class.walk(self)?;
+        }
+        Ok(())
+    }
 }
 
 impl ClassNode {
@@ -56,11 +203,19 @@ impl ClassNode {
         Self {
             name,
             parent: None,
-            properties: HashMap::new(),
+            properties: IndexMap::new(),
             nested_classes: Vec::new(),
             access: AccessModifier::Public,
             raw_block: String::new(),
             file_path: None,
+            location: None,
+            enums: Vec::new(),
+            span: None,
+            name_span: None,
+            block_span: None,
+            doc_comment: None,
+            deleted_classes: Vec::new(),
+            is_external: false,
         }
     }
 
@@ -74,8 +229,31 @@ impl ClassNode {
         self
     }
 
+    pub fn with_location(mut self, location: SourceLocation) -> Self {
+        self.location = Some(location);
+        self
+    }
+
+    /// This class as config text, laid out by [`PrettyPrinter`] with the
+    /// default [`PrintOptions`].
+    pub fn to_config_string(&self) -> String {
+        PrettyPrinter::default().print(self)
+    }
+
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string(self)
+    }
+
+    pub fn from_json(s: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(s)
+    }
+
     pub fn accept<V: AstVisitor>(&mut self, visitor: &mut V) -> Result<(), Error> {
         visitor.visit_class(self)?;
+
+        for node in &mut self.enums {
+            visitor.visit_enum(node)?;
+        }
         
         for property in self.properties.values_mut() {
             visitor.visit_property(property)?;
@@ -91,6 +269,28 @@ impl ClassNode {
         Ok(())
     }
 
+    /// Like [`accept`](Self::accept), with shared borrows.
+    pub fn walk<W: AstWalker>(&self, walker: &mut W) -> Result<(), Error> {
+        walker.walk_class(self)?;
+
+        for node in &self.enums {
+            walker.walk_enum(node)?;
+        }
+
+        for property in self.properties.values() {
+            walker.walk_property(property)?;
+            if property.value_type == PropertyType::Array {
+                walker.walk_array(&property.array_values, property.operation)?;
+            }
+        }
+
+        for nested in &self.nested_classes {
+            nested.walk(walker)?;
+        }
+
+        Ok(())
+    }
+
     pub fn get_array(&self, name: &str) -> Option<&Vec<String>> {
         self.properties.get(name).and_then(|prop| {
             if prop.value_type == PropertyType::Array {
@@ -100,6 +300,230 @@ impl ClassNode {
             }
         })
     }
+
+    /// All descendant classes, depth-first in declaration order. The class
+    /// itself is not included.
+    pub fn classes(&self) -> Classes<'_> {
+        Classes {
+            stack: vec![self.nested_classes.iter()],
+        }
+    }
+
+    /// First descendant named `name`, ignoring case as the game does.
+    pub fn find_class(&self, name: &str) -> Option<&ClassNode> {
+        self.classes().find(|class| class.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Like [`find_class`](Self::find_class), but the name must match exactly.
+    pub fn find_class_exact(&self, name: &str) -> Option<&ClassNode> {
+        self.classes().find(|class| class.name == name)
+    }
+
+    pub fn find_class_mut(&mut self, name: &str) -> Option<&mut ClassNode> {
+        for nested in &mut self.nested_classes {
+            if nested.name.eq_ignore_ascii_case(name) {
+                return Some(nested);
+            }
+            if let Some(found) = nested.find_class_mut(name) {
+                return Some(found);
+            }
+        }
+        None
+    }
+
+    /// Class at a slash-separated path below this one, e.g.
+    /// `CfgWeapons/pca_vest_invisible/ItemInfo` from the parse root.
+    pub fn get_path(&self, path: &str) -> Option<&ClassNode> {
+        path.split('/')
+            .filter(|segment| !segment.is_empty())
+            .try_fold(self, |class, segment| {
+                class.nested_classes.iter().find(|nested| nested.name.eq_ignore_ascii_case(segment))
+            })
+    }
+
+    /// Every property of this class as a [`PropertyValue`], keyed by name.
+    /// `delete` markers are left out. Inherited properties are only included
+    /// once inheritance has been resolved, see
+    /// [`to_flat_map_resolved`](Self::to_flat_map_resolved).
+    pub fn to_flat_map(&self) -> HashMap<String, PropertyValue> {
+        self.properties.values()
+            .filter(|property| !property.is_deleted())
+            .map(|property| (property.name.clone(), PropertyValue::from(property.clone())))
+            .collect()
+    }
+
+    /// Like [`to_flat_map`](Self::to_flat_map), after resolving this class
+    /// (looked up by name) through `resolver`.
+    pub fn to_flat_map_resolved(&self, resolver: &mut InheritanceResolver) -> Result<HashMap<String, PropertyValue>, Error> {
+        Ok(resolver.resolve_class(&self.name)?.to_flat_map())
+    }
+
+    /// Like [`to_flat_map`](Self::to_flat_map), plus the properties of direct
+    /// nested classes under `Nested.property` keys.
+    pub fn to_flat_map_nested(&self) -> HashMap<String, PropertyValue> {
+        let mut map = self.to_flat_map();
+        for nested in &self.nested_classes {
+            for (name, value) in nested.to_flat_map() {
+                map.insert(format!("{}.{}", nested.name, name), value);
+            }
+        }
+        map
+    }
+
+    /// Class at a dot-separated path below this one, e.g.
+    /// `CfgWeapons.rhs_weap_m4.Components.MuzzleSlot`. Unlike
+    /// [`get_path`](Self::get_path), segments must match the class names
+    /// exactly.
+    pub fn find_at_path(&self, path: &str) -> Option<&ClassNode> {
+        path.split('.')
+            .filter(|segment| !segment.is_empty())
+            .try_fold(self, |class, segment| {
+                class.nested_classes.iter().find(|nested| nested.name == segment)
+            })
+    }
+
+    pub fn find_at_path_mut(&mut self, path: &str) -> Option<&mut ClassNode> {
+        path.split('.')
+            .filter(|segment| !segment.is_empty())
+            .try_fold(self, |class, segment| {
+                class.nested_classes.iter_mut().find(|nested| nested.name == segment)
+            })
+    }
+
+    /// Hash of the class's content, see [`ClassNode`]'s `Hash` impl. Stable
+    /// for a given build, so it can tell whether a re-parsed class changed.
+    pub fn content_hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Properties sorted by name.
+    fn sorted_properties(&self) -> Vec<(&String, &PropertyNode)> {
+        let mut properties: Vec<_> = self.properties.iter().collect();
+        properties.sort_by(|a, b| a.0.cmp(b.0));
+        properties
+    }
+
+    /// Names in `deleted_classes`, sorted.
+    fn sorted_deleted(&self) -> Vec<&String> {
+        let mut deleted: Vec<_> = self.deleted_classes.iter().collect();
+        deleted.sort();
+        deleted
+    }
+
+    /// Nested classes sorted by name, keeping declaration order among equal
+    /// names.
+    fn sorted_nested(&self) -> Vec<&ClassNode> {
+        let mut nested: Vec<_> = self.nested_classes.iter().collect();
+        nested.sort_by(|a, b| a.name.cmp(&b.name));
+        nested
+    }
+
+    /// Property at a dot-separated path, where the last segment names the
+    /// property, e.g. `CfgWeapons.Rifle.ItemInfo.mass`. Matches exactly,
+    /// like [`find_at_path`](Self::find_at_path).
+    pub fn get_property_at_path(&self, path: &str) -> Option<&PropertyNode> {
+        let (class, name) = match path.rsplit_once('.') {
+            Some((classes, name)) => (self.find_at_path(classes)?, name),
+            None => (self, path),
+        };
+        class.properties.get(name)
+    }
+
+    /// The properties of this class keyed by their interned name, in
+    /// declaration order. Names not yet in `interner` are added.
+    pub fn interned_properties<'a>(&'a self, interner: &StringInterner) -> IndexMap<InternedStr, &'a PropertyNode> {
+        self.properties.iter()
+            .map(|(name, property)| (interner.intern(name), property))
+            .collect()
+    }
+}
+
+impl PartialEq for ClassNode {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+            && self.parent == other.parent
+            && self.access == other.access
+            && self.is_external == other.is_external
+            && self.enums.len() == other.enums.len()
+            && self.enums.iter().zip(&other.enums).all(|(a, b)| a.name == b.name && a.values == b.values)
+            && self.properties.len() == other.properties.len()
+            && self.properties.iter().all(|(name, property)| {
+                other.properties.get(name).is_some_and(|other| property.same_content(other))
+            })
+            && self.sorted_nested() == other.sorted_nested()
+            && self.sorted_deleted() == other.sorted_deleted()
+    }
+}
+
+impl Eq for ClassNode {}
+
+impl Hash for ClassNode {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.name.hash(state);
+        self.parent.hash(state);
+        self.access.hash(state);
+        self.is_external.hash(state);
+        self.enums.len().hash(state);
+        for node in &self.enums {
+            node.name.hash(state);
+            // Value maps compare without regard to order
+            let mut values: Vec<_> = node.values.iter().collect();
+            values.sort();
+            values.hash(state);
+        }
+        let properties = self.sorted_properties();
+        properties.len().hash(state);
+        for (name, property) in properties {
+            name.hash(state);
+            property.hash_content(state);
+        }
+        let nested = self.sorted_nested();
+        nested.len().hash(state);
+        for class in nested {
+            class.hash(state);
+        }
+        self.sorted_deleted().hash(state);
+    }
+}
+
+/// Depth-first iterator returned by [`ClassNode::classes`].
+pub struct Classes<'a> {
+    stack: Vec<std::slice::Iter<'a, ClassNode>>,
+}
+
+impl<'a> Iterator for Classes<'a> {
+    type Item = &'a ClassNode;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(level) = self.stack.last_mut() {
+            match level.next() {
+                Some(class) => {
+                    self.stack.push(class.nested_classes.iter());
+                    return Some(class);
+                }
+                None => {
+                    self.stack.pop();
+                }
+            }
+        }
+        None
+    }
+}
+
+impl EnumNode {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            values: IndexMap::new(),
+            location: None,
+        }
+    }
+
+    pub fn get(&self, entry: &str) -> Option<i64> {
+        self.values.get(entry).copied()
+    }
 }
 
 impl PropertyNode {
@@ -110,6 +534,10 @@ impl PropertyNode {
             raw_value: raw_value.into(),
             operation: None,
             array_values: Vec::new(),
+            typed_values: Vec::new(),
+            location: None,
+            span: None,
+            doc_comment: None,
         }
     }
 
@@ -118,14 +546,92 @@ impl PropertyNode {
         self
     }
 
+    /// Set the elements from their text; see [`ArrayElement::parse`].
     pub fn with_array_values(mut self, values: Vec<String>) -> Self {
+        self.typed_values = values.iter().map(|value| ArrayElement::parse(value)).collect();
         self.array_values = values;
         self
     }
 
+    pub fn with_typed_values(mut self, values: Vec<ArrayElement>) -> Self {
+        self.array_values = values.iter().map(ToString::to_string).collect();
+        self.typed_values = values;
+        self
+    }
+
+    /// Apply `operation` to `base`'s elements and this property's, as when
+    /// inheriting `name[] += {...}`. Elements are matched by their typed
+    /// form and keep their text.
+    pub fn apply_array_operation(&mut self, base: &PropertyNode, operation: ArrayOperation, options: ArrayOptions) {
+        let pairs = |property: &PropertyNode| -> Vec<(String, ArrayElement)> {
+            property.array_values.iter().cloned().zip(property.elements()).collect()
+        };
+        let result = ArrayProcessor::process_by(&pairs(base), &pairs(self), operation, options, |(_, element)| element.key());
+        (self.array_values, self.typed_values) = result.into_iter().unzip();
+    }
+
+    /// The typed elements, parsed from `array_values` when only the text
+    /// was filled in.
+    pub(crate) fn elements(&self) -> Vec<ArrayElement> {
+        if self.typed_values.len() == self.array_values.len() {
+            self.typed_values.clone()
+        } else {
+            self.array_values.iter().map(|value| ArrayElement::parse(value)).collect()
+        }
+    }
+
+    pub fn with_location(mut self, location: SourceLocation) -> Self {
+        self.location = Some(location);
+        self
+    }
+
+    pub fn deleted(name: impl Into<String>) -> Self {
+        Self::new(name, PropertyType::Deleted, "")
+    }
+
+    /// A property holding `value`, with its type and raw text derived from
+    /// it. Objects inside an array, which no config array can hold, are left
+    /// out.
+    pub fn from_value(name: impl Into<String>, value: PropertyValue) -> Self {
+        match value {
+            PropertyValue::String(s) => Self::new(name, PropertyType::String, s),
+            PropertyValue::Number(n) => Self::new(name, PropertyType::Number, n.to_string()),
+            PropertyValue::Bool(b) => Self::new(name, PropertyType::Boolean, b.to_string()),
+            PropertyValue::Array(values) => {
+                let elements: Vec<ArrayElement> = values.iter().filter_map(|value| ArrayElement::try_from(value).ok()).collect();
+                let raw_value = ArrayElement::Array(elements.clone()).to_string();
+                Self::new(name, PropertyType::Array, raw_value).with_typed_values(elements)
+            }
+            PropertyValue::Object(_) => Self::new(name, PropertyType::Object, ""),
+        }
+    }
+
+    pub fn is_deleted(&self) -> bool {
+        self.value_type == PropertyType::Deleted
+    }
+
     pub fn as_str(&self) -> Option<&str> {
         Some(&self.raw_value)
     }
+
+    /// Equality of everything but location, span and doc comment.
+    fn same_content(&self, other: &PropertyNode) -> bool {
+        self.name == other.name
+            && self.value_type == other.value_type
+            && self.raw_value == other.raw_value
+            && self.operation == other.operation
+            && self.array_values == other.array_values
+            && self.typed_values == other.typed_values
+    }
+
+    fn hash_content<H: Hasher>(&self, state: &mut H) {
+        self.name.hash(state);
+        self.value_type.hash(state);
+        self.raw_value.hash(state);
+        self.operation.hash(state);
+        self.array_values.hash(state);
+        self.typed_values.hash(state);
+    }
 }
 
 #[cfg(test)]
@@ -268,6 +774,58 @@ mod tests {
         assert_eq!(array.unwrap(), &values);
     }
 
+    #[test]
+    fn test_visit_enum() {
+        struct EnumCollector(Vec<String>);
+
+        impl AstVisitor for EnumCollector {
+            fn visit_class(&mut self, _: &mut ClassNode) -> Result<(), Error> {
+                Ok(())
+            }
+            fn visit_property(&mut self, _: &mut PropertyNode) -> Result<(), Error> {
+                Ok(())
+            }
+            fn visit_array(&mut self, _: &mut Vec<String>, _: Option<ArrayOperation>) -> Result<(), Error> {
+                Ok(())
+            }
+            fn visit_enum(&mut self, node: &mut EnumNode) -> Result<(), Error> {
+                self.0.extend(node.values.keys().cloned());
+                Ok(())
+            }
+        }
+
+        let mut root = ClassNode::new(String::new());
+        let mut node = EnumNode::new("Visibility");
+        node.values.insert("private".to_string(), 0);
+        node.values.insert("public".to_string(), 2);
+        root.enums.push(node);
+
+        let mut collector = EnumCollector(Vec::new());
+        root.accept(&mut collector).unwrap();
+        assert_eq!(collector.0, vec!["private", "public"]);
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let mut class = ClassNode::new("Child".to_string())
+            .with_parent("Base")
+            .with_access(AccessModifier::Private)
+            .with_location(SourceLocation::new(Some(PathBuf::from("addons/config.cpp")), 3, 0));
+        class.file_path = Some(PathBuf::from("addons/config.cpp"));
+        class.properties.insert("weapons".to_string(),
+            PropertyNode::new("weapons", PropertyType::Array, "{a,b}")
+                .with_array_op(ArrayOperation::Remove)
+                .with_array_values(vec!["a".to_string(), "b".to_string()]));
+        class.properties.insert("model".to_string(), PropertyNode::deleted("model"));
+        let mut nested = ClassNode::new("Turrets".to_string());
+        nested.properties.insert("speed".to_string(), PropertyNode::new("speed", PropertyType::Number, "1.5e2"));
+        class.nested_classes.push(nested);
+
+        let json = class.to_json().unwrap();
+        assert_eq!(ClassNode::from_json(&json).unwrap(), class);
+        assert!(ClassNode::from_json("{}").is_err());
+    }
+
     #[test]
     fn test_nested_class_operations() {
         let mut parent = ClassNode::new("Parent".to_string());
@@ -338,4 +896,213 @@ mod tests {
         assert_eq!(visitor1.visit_order, vec!["V1-Root", "V1-Child"]);
         assert_eq!(visitor2.visit_order, vec!["V2-Root", "V2-Child"]);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_class_queries() {
+        let tree = |name: &str, children: Vec<ClassNode>| {
+            let mut class = ClassNode::new(name.to_string());
+            class.nested_classes = children;
+            class
+        };
+        let mut root = tree("", vec![
+            tree("CfgWeapons", vec![
+                tree("Vest", vec![tree("ItemInfo", vec![])]),
+                tree("Rifle", vec![]),
+            ]),
+            tree("CfgVehicles", vec![tree("Tank_A", vec![tree("ItemInfo", vec![])])]),
+        ]);
+
+        let names: Vec<&str> = root.classes().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["CfgWeapons", "Vest", "ItemInfo", "Rifle", "CfgVehicles", "Tank_A", "ItemInfo"]);
+
+        assert_eq!(root.find_class("tank_a").unwrap().name, "Tank_A");
+        assert!(root.find_class_exact("tank_a").is_none());
+        assert!(root.find_class("Missing").is_none());
+
+        assert_eq!(root.get_path("CfgVehicles/Tank_A").unwrap().name, "Tank_A");
+        assert_eq!(root.get_path("cfgweapons/vest/iteminfo").unwrap().name, "ItemInfo");
+        assert!(root.get_path("CfgWeapons/ItemInfo").is_none());
+
+        root.find_class_mut("rifle").unwrap().parent = Some("Base".to_string());
+        assert_eq!(root.get_path("CfgWeapons/Rifle").unwrap().parent.as_deref(), Some("Base"));
+    }
+
+    #[test]
+    fn test_to_flat_map() {
+        let classes = crate::ClassScanner::new().parse_string(r#"
+            class Vehicle { crew = 1; armor = 10; name = "vehicle"; };
+            class Tank: Vehicle {
+                delete armor;
+                tracked = true;
+                class Turret { weapons[] = {"Cannon"}; elevation = 20; };
+            };
+        "#).unwrap();
+        let tank = &classes[2];
+
+        let own = tank.to_flat_map();
+        assert_eq!(own.len(), 1);
+        assert_eq!(own["tracked"], PropertyValue::Bool(true));
+
+        let mut resolver = InheritanceResolver::new();
+        for class in classes.iter().skip(1) {
+            resolver.add_class(class.clone());
+        }
+        let resolved = tank.to_flat_map_resolved(&mut resolver).unwrap();
+        let mut keys: Vec<&String> = resolved.keys().collect();
+        keys.sort();
+        assert_eq!(keys, vec!["crew", "name", "tracked"]);
+        assert_eq!(resolved["crew"], PropertyValue::Number(1.0));
+        assert_eq!(resolved["name"], PropertyValue::String("vehicle".to_string()));
+
+        let nested = tank.to_flat_map_nested();
+        assert_eq!(nested.len(), 3);
+        assert_eq!(nested["Turret.weapons"], PropertyValue::Array(vec![PropertyValue::String("Cannon".to_string())]));
+        assert_eq!(nested["Turret.elevation"], PropertyValue::Number(20.0));
+
+        assert!(matches!(
+            ClassNode::new("Missing".to_string()).to_flat_map_resolved(&mut resolver),
+            Err(Error::InheritanceError(_))
+        ));
+    }
+
+    #[test]
+    fn test_find_at_path() {
+        let tree = |name: &str, properties: &[(&str, &str)], children: Vec<ClassNode>| {
+            let mut class = ClassNode::new(name.to_string());
+            for (key, value) in properties {
+                class.properties.insert(key.to_string(), PropertyNode::new(*key, PropertyType::Number, *value));
+            }
+            class.nested_classes = children;
+            class
+        };
+        let mut weapons = tree("CfgWeapons", &[], vec![
+            tree("rhs_weap_m4", &[("mass", "70")], vec![
+                tree("Components", &[], vec![
+                    tree("MuzzleSlot", &[("iconScale", "0.2")], vec![]),
+                    tree("CowsSlot", &[("iconScale", "0.5")], vec![]),
+                ]),
+            ]),
+            tree("rhs_weap_m16", &[], vec![tree("Components", &[], vec![])]),
+        ]);
+        let mut root = tree("", &[], vec![weapons.clone()]);
+
+        let muzzle = root.find_at_path("CfgWeapons.rhs_weap_m4.Components.MuzzleSlot").unwrap();
+        assert_eq!(muzzle.name, "MuzzleSlot");
+        assert_eq!(root.find_at_path("CfgWeapons.rhs_weap_m4.Components.CowsSlot").unwrap().name, "CowsSlot");
+        assert!(root.find_at_path("cfgweapons.RHS_WEAP_M4.components.cowsslot").is_none());
+        assert_eq!(root.get_path("cfgweapons/RHS_WEAP_M4/components/cowsslot").unwrap().name, "CowsSlot");
+        assert_eq!(weapons.find_at_path("rhs_weap_m16.Components").unwrap().nested_classes.len(), 0);
+        assert_eq!(weapons.find_at_path("").unwrap().name, "CfgWeapons");
+        assert!(root.find_at_path("CfgWeapons.rhs_weap_m16.Components.MuzzleSlot").is_none());
+        assert!(root.find_at_path("CfgWeapons.Components").is_none());
+        assert!(root.find_at_path("CfgWeapons/rhs_weap_m4").is_none());
+
+        let property = root.get_property_at_path("CfgWeapons.rhs_weap_m4.Components.MuzzleSlot.iconScale").unwrap();
+        assert_eq!(property.raw_value, "0.2");
+        assert!(root.get_property_at_path("CfgWeapons.rhs_weap_m4.MASS").is_none());
+        assert_eq!(weapons.find_at_path("rhs_weap_m4").unwrap().get_property_at_path("mass").unwrap().raw_value, "70");
+        assert!(root.get_property_at_path("CfgWeapons.rhs_weap_m4.Components").is_none());
+        assert!(root.get_property_at_path("CfgWeapons.Missing.mass").is_none());
+
+        root.find_at_path_mut("CfgWeapons.rhs_weap_m4.Components.MuzzleSlot").unwrap()
+            .properties.insert("mass".to_string(), PropertyNode::new("mass", PropertyType::Number, "5"));
+        assert_eq!(root.get_property_at_path("CfgWeapons.rhs_weap_m4.Components.MuzzleSlot.mass").unwrap().raw_value, "5");
+        weapons.find_at_path_mut("rhs_weap_m16").unwrap().parent = Some("rhs_weap_m4".to_string());
+        assert_eq!(weapons.find_at_path("rhs_weap_m16").unwrap().parent.as_deref(), Some("rhs_weap_m4"));
+        assert!(weapons.find_at_path_mut("rhs_weap_m16.Missing").is_none());
+    }
+
+    /// Lookup cost of the ordered `IndexMap` properties against a
+    /// `HashMap`, for class sizes seen in real configs. Run with
+    /// `cargo test --release -- --ignored --nocapture bench_property_lookup`.
+    #[test]
+    #[ignore]
+    fn bench_property_lookup() {
+        for size in [10, 50, 200] {
+            let names: Vec<String> = (0..size).map(|i| format!("property_{}", i)).collect();
+            let ordered: IndexMap<String, PropertyNode> = names.iter()
+                .map(|name| (name.clone(), PropertyNode::new(name.clone(), PropertyType::Number, "1")))
+                .collect();
+            let hashed: HashMap<String, PropertyNode> = ordered.clone().into_iter().collect();
+
+            let rounds = 2_000_000 / size;
+            let start = std::time::Instant::now();
+            for _ in 0..rounds {
+                for name in &names {
+                    std::hint::black_box(ordered.get(std::hint::black_box(name)));
+                }
+            }
+            let ordered_time = start.elapsed();
+            let start = std::time::Instant::now();
+            for _ in 0..rounds {
+                for name in &names {
+                    std::hint::black_box(hashed.get(std::hint::black_box(name)));
+                }
+            }
+            let hashed_time = start.elapsed();
+            println!("{} properties: IndexMap {:?}, HashMap {:?} per 2M lookups", size, ordered_time, hashed_time);
+        }
+    }
+
+    #[test]
+    fn test_content_equality_and_hash() {
+        let scanner = crate::ClassScanner::new();
+        let first = scanner.parse_string("class A: Base { scope = 2; model = \"a.p3d\"; class X {}; class Y { v = 1; }; };").unwrap();
+        let second = scanner.parse_string(r#"
+            // Same content, different layout and order
+            class A: Base {
+                class Y { v = 1; };
+                model = "a.p3d";
+                class X {};
+                scope = 2;
+            };
+        "#).unwrap();
+        let (a, b) = (&first[1], &second[1]);
+
+        assert_ne!(a.location, b.location);
+        assert_eq!(a, b);
+        assert_eq!(a.content_hash(), b.content_hash());
+        let unique: std::collections::HashSet<&ClassNode> = [a, b].into_iter().collect();
+        assert_eq!(unique.len(), 1);
+
+        let mut changed = b.clone();
+        changed.nested_classes[0].properties["v"].raw_value = "2".to_string();
+        assert_ne!(a, &changed);
+        assert_ne!(a.content_hash(), changed.content_hash());
+
+        let mut private = b.clone();
+        private.access = AccessModifier::Private;
+        assert_ne!(a, &private);
+        assert_ne!(a, &b.clone().with_parent("Other"));
+
+        let mut deleting = b.clone();
+        deleting.deleted_classes = vec!["X".to_string(), "Y".to_string()];
+        assert_ne!(a, &deleting);
+        assert_ne!(a.content_hash(), deleting.content_hash());
+        let mut reordered = deleting.clone();
+        reordered.deleted_classes.reverse();
+        assert_eq!(deleting, reordered);
+        assert_eq!(deleting.content_hash(), reordered.content_hash());
+
+        // Typed elements count even where their text is the same
+        let numbers = scanner.parse_string("class A { v[] = {1, 2}; };").unwrap();
+        let mut strings = numbers[1].clone();
+        strings.properties["v"].typed_values = vec![ArrayElement::String("1".to_string()), ArrayElement::String("2".to_string())];
+        assert_eq!(strings.properties["v"].array_values, numbers[1].properties["v"].array_values);
+        assert_ne!(numbers[1], strings);
+        assert_ne!(numbers[1].content_hash(), strings.content_hash());
+
+        // A forward declaration is not an empty class
+        let declared = scanner.parse_string("class A;").unwrap();
+        let defined = scanner.parse_string("class A {};").unwrap();
+        assert_ne!(declared[1], defined[1]);
+        assert_ne!(declared[1].content_hash(), defined[1].content_hash());
+
+        // Enums count, their location does not
+        let with_enum = scanner.parse_string("enum { a = 1, b = 2 };\nclass A {};").unwrap();
+        let moved = scanner.parse_string("\n\nenum { a = 1, b = 2 }; class A {};").unwrap();
+        assert_ne!(with_enum[0], defined[0]);
+        assert_eq!(with_enum[0], moved[0]);
+        assert_eq!(with_enum[0].content_hash(), moved[0].content_hash());
+    }
+}
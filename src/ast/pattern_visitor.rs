@@ -0,0 +1,113 @@
+use std::collections::HashMap;
+use regex::Regex;
+use super::{ClassNode, PropertyNode, PropertyType, AstVisitor};
+use crate::error::Error;
+use crate::operations::arrays::ArrayOperation;
+use crate::types::{ValidationError, ValidationErrorKind};
+
+/// Checks string values against a pattern per property name (ignoring
+/// case), collecting a [`ValidationErrorKind::PatternMismatch`] for every
+/// value that does not match. Array properties are checked element by
+/// element; numbers and booleans are not checked.
+pub struct PatternValidationVisitor {
+    patterns: HashMap<String, Regex>,
+    /// Name of the class whose properties are being visited.
+    class_name: String,
+    errors: Vec<ValidationError>,
+}
+
+impl PatternValidationVisitor {
+    pub fn new(patterns: HashMap<String, Regex>) -> Self {
+        Self {
+            patterns: patterns.into_iter().map(|(name, pattern)| (name.to_lowercase(), pattern)).collect(),
+            class_name: String::new(),
+            errors: Vec::new(),
+        }
+    }
+
+    pub fn errors(&self) -> &[ValidationError] {
+        &self.errors
+    }
+
+    pub fn into_errors(self) -> Vec<ValidationError> {
+        self.errors
+    }
+}
+
+impl AstVisitor for PatternValidationVisitor {
+    fn visit_class(&mut self, class: &mut ClassNode) -> Result<(), Error> {
+        // `accept` visits a class's own properties before its nested classes
+        self.class_name = class.name.clone();
+        Ok(())
+    }
+
+    fn visit_property(&mut self, property: &mut PropertyNode) -> Result<(), Error> {
+        let Some(pattern) = self.patterns.get(&property.name.to_lowercase()) else {
+            return Ok(());
+        };
+        let values = match property.value_type {
+            PropertyType::String => std::slice::from_ref(&property.raw_value),
+            PropertyType::Array => &property.array_values[..],
+            _ => return Ok(()),
+        };
+        for value in values {
+            if !pattern.is_match(value) {
+                self.errors.push(ValidationError {
+                    class_name: self.class_name.clone(),
+                    property: property.name.clone(),
+                    kind: ValidationErrorKind::PatternMismatch,
+                    message: format!("'{}' does not match /{}/", value, pattern.as_str()),
+                    location: property.location.clone(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn visit_array(&mut self, _array: &mut Vec<String>, _operation: Option<ArrayOperation>) -> Result<(), Error> {
+        // Checked with the property, which knows its name
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ClassScanner;
+
+    #[test]
+    fn test_pattern_validation_collects_all_mismatches() {
+        let mut root = ClassScanner::new().parse_string(r#"
+            class CfgVehicles {
+                class Car {
+                    model = "\a3\car.p3d";
+                    hiddenSelectionsTextures[] = {"\a3\car_co.paa", "\a3\car_co.jpg", "\a3\glass.PNG"};
+                    class Wheel {
+                        Model = "\a3\wheel.obj";
+                    };
+                };
+                class Truck {
+                    model = 5;
+                };
+            };
+        "#).unwrap().remove(0);
+
+        let patterns = HashMap::from([
+            ("model".to_string(), Regex::new(r"\.p3d$").unwrap()),
+            ("hiddenSelectionsTextures".to_string(), Regex::new(r"\.paa$").unwrap()),
+        ]);
+        let mut visitor = PatternValidationVisitor::new(patterns);
+        root.accept(&mut visitor).unwrap();
+
+        let mismatches: Vec<(&str, &str, &str)> = visitor.errors().iter()
+            .map(|e| (e.class_name.as_str(), e.property.as_str(), e.message.split('\'').nth(1).unwrap()))
+            .collect();
+        assert_eq!(mismatches, vec![
+            ("Car", "hiddenSelectionsTextures", "\\a3\\car_co.jpg"),
+            ("Car", "hiddenSelectionsTextures", "\\a3\\glass.PNG"),
+            ("Wheel", "Model", "\\a3\\wheel.obj"),
+        ]);
+        assert!(visitor.errors().iter().all(|e| e.kind == ValidationErrorKind::PatternMismatch));
+        assert_eq!(visitor.errors()[2].location.as_ref().unwrap().line, 7);
+    }
+}
@@ -0,0 +1,131 @@
+use std::fmt;
+use super::ClassNode;
+use crate::operations::writer::ConfigWriter;
+
+/// Layout of the text written by [`PrettyPrinter`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrintOptions {
+    /// Spaces per nesting level.
+    pub indent: usize,
+    /// Write properties ordered by name rather than in declaration order.
+    pub sort_properties: bool,
+    /// End the output with a newline.
+    pub trailing_newline: bool,
+}
+
+impl Default for PrintOptions {
+    fn default() -> Self {
+        Self {
+            indent: 4,
+            sort_properties: false,
+            trailing_newline: true,
+        }
+    }
+}
+
+/// Writes a [`ClassNode`], e.g. one resolved by
+/// [`InheritanceResolver`](crate::operations::InheritanceResolver), back as
+/// config text that parses to the same tree, apart from source positions.
+///
+/// Strings are quoted, numbers and booleans are not, arrays are written as
+/// `name[] = {"a", "b"};` with every string element quoted, even one that
+/// looks like a number, and nested classes are indented below their
+/// parent. A class with an empty name (the parse root) has its contents
+/// written at the top level.
+#[derive(Debug, Clone, Default)]
+pub struct PrettyPrinter {
+    options: PrintOptions,
+}
+
+impl PrettyPrinter {
+    pub fn new(options: PrintOptions) -> Self {
+        Self { options }
+    }
+
+    pub fn print(&self, class: &ClassNode) -> String {
+        let mut output = ConfigWriter::new()
+            .with_indent(self.options.indent)
+            .with_sorted_properties(self.options.sort_properties)
+            .write_class(class);
+        if !self.options.trailing_newline {
+            output.truncate(output.trim_end_matches('\n').len());
+        }
+        output
+    }
+
+    /// `class` formatted with these options, for use with `write!`.
+    pub fn display<'a>(&'a self, class: &'a ClassNode) -> impl fmt::Display + 'a {
+        Printed { printer: self, class }
+    }
+}
+
+struct Printed<'a> {
+    printer: &'a PrettyPrinter,
+    class: &'a ClassNode,
+}
+
+impl fmt::Display for Printed<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.printer.print(self.class))
+    }
+}
+
+/// Config text with the default [`PrintOptions`].
+impl fmt::Display for ClassNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        PrettyPrinter::default().display(self).fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ClassScanner;
+
+    #[test]
+    fn test_pretty_printer() {
+        let classes = ClassScanner::new().parse_string(r#"
+            class Vehicle: Base {
+                side = 1; displayName = "Car"; enabled = true;
+                wheels[] = {"front", "back"};
+                class Turret { speed = 1.5; };
+            };
+        "#).unwrap();
+        let vehicle = &classes[1];
+
+        assert_eq!(vehicle.to_string(), concat!(
+            "class Vehicle: Base {\n",
+            "    side = 1;\n",
+            "    displayName = \"Car\";\n",
+            "    enabled = true;\n",
+            "    wheels[] = {\"front\", \"back\"};\n",
+            "    class Turret {\n",
+            "        speed = 1.5;\n",
+            "    };\n",
+            "};\n",
+        ));
+        assert_eq!(vehicle.to_config_string(), vehicle.to_string());
+
+        let printer = PrettyPrinter::new(PrintOptions { indent: 2, sort_properties: true, trailing_newline: false });
+        let printed = format!("{}", printer.display(vehicle));
+        assert!(printed.starts_with("class Vehicle: Base {\n  displayName = \"Car\";\n  enabled = true;\n  side = 1;\n"), "{}", printed);
+        assert!(printed.ends_with("    speed = 1.5;\n  };\n};"));
+
+        let reparsed = ClassScanner::new().parse_string(&printed).unwrap();
+        assert_eq!(&reparsed[1], vehicle);
+    }
+
+    #[test]
+    fn test_round_trip_quoted_array_strings() {
+        let classes = ClassScanner::new().parse_string(r#"
+            class Codes { arr[] = {"007", "{x}", "1e5", 5, {"-1", 2}}; };
+        "#).unwrap();
+        let codes = &classes[1];
+
+        let printed = codes.to_string();
+        assert!(printed.contains(r#"arr[] = {"007", "{x}", "1e5", 5, {"-1", 2}};"#), "{}", printed);
+        let reparsed = ClassScanner::new().parse_string(&printed).unwrap();
+        assert_eq!(&reparsed[1], codes);
+        assert_eq!(reparsed[1].properties["arr"].typed_values, codes.properties["arr"].typed_values);
+    }
+}
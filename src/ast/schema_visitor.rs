@@ -0,0 +1,102 @@
+use super::{ClassNode, PropertyNode, AstVisitor, AstWalker, SchemaValidationWalker};
+use crate::error::Error;
+use crate::operations::arrays::ArrayOperation;
+use crate::types::{ClassSchema, ValidationError};
+
+/// Validates every class in a tree that has a registered schema, collecting
+/// the violations instead of failing on them. The [`AstVisitor`] form of
+/// [`SchemaValidationWalker`], for use in [`ClassNode::accept`] passes.
+pub struct SchemaVisitor {
+    walker: SchemaValidationWalker,
+}
+
+impl SchemaVisitor {
+    pub fn new() -> Self {
+        Self {
+            walker: SchemaValidationWalker::new(),
+        }
+    }
+
+    /// Apply `schema` to every class named `class_name` (ignoring case).
+    pub fn with_schema(mut self, class_name: &str, schema: ClassSchema) -> Self {
+        self.walker = self.walker.with_schema(class_name, schema);
+        self
+    }
+
+    pub fn errors(&self) -> &[ValidationError] {
+        self.walker.errors()
+    }
+
+    pub fn into_errors(self) -> Vec<ValidationError> {
+        self.walker.into_errors()
+    }
+}
+
+impl Default for SchemaVisitor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AstVisitor for SchemaVisitor {
+    fn visit_class(&mut self, class: &mut ClassNode) -> Result<(), Error> {
+        self.walker.walk_class(class)
+    }
+
+    fn visit_property(&mut self, _property: &mut PropertyNode) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn visit_array(&mut self, _array: &mut Vec<String>, _operation: Option<ArrayOperation>) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{PropertySchema, TypeDefinition, ValidationErrorKind};
+    use crate::ClassScanner;
+
+    #[test]
+    fn test_validate_tree() {
+        let input = r#"
+            class CfgPatches {
+                class my_addon {
+                    units[] = {};
+                };
+                class other_addon {
+                    requiredVersion = 0.1;
+                };
+            };
+            class CfgVehicles {
+                class Car {
+                    scope = 2;
+                };
+                class Truck {
+                    scope = 5;
+                };
+            };
+        "#;
+        let mut root = ClassScanner::new().parse_string(input).unwrap().remove(0);
+
+        let mut visitor = SchemaVisitor::new()
+            .with_schema("my_addon", ClassSchema::new()
+                .with_property("requiredVersion", PropertySchema::new(TypeDefinition::Number).required()))
+            .with_schema("other_addon", ClassSchema::new()
+                .with_property("requiredVersion", PropertySchema::new(TypeDefinition::Number).required()))
+            .with_schema("car", ClassSchema::new()
+                .with_property("scope", PropertySchema::new(TypeDefinition::Number).with_range(Some(0.0), Some(2.0))))
+            .with_schema("Truck", ClassSchema::new()
+                .with_property("scope", PropertySchema::new(TypeDefinition::Number).with_range(Some(0.0), Some(2.0))));
+        root.accept(&mut visitor).unwrap();
+
+        let errors: Vec<(&str, &ValidationErrorKind)> = visitor.errors().iter()
+            .map(|e| (e.class_name.as_str(), &e.kind))
+            .collect();
+        assert_eq!(errors, vec![
+            ("my_addon", &ValidationErrorKind::MissingProperty),
+            ("Truck", &ValidationErrorKind::OutOfRange),
+        ]);
+    }
+}
@@ -0,0 +1,159 @@
+use std::collections::HashMap;
+use super::{AstWalker, ClassNode, PropertyNode};
+use crate::error::Error;
+use crate::types::{ClassSchema, TypeValidator, ValidationError};
+
+/// Counts the classes and properties in a tree. The unnamed parse root is
+/// not counted as a class.
+#[derive(Debug, Default)]
+pub struct ClassCountWalker {
+    pub classes: usize,
+    pub properties: usize,
+}
+
+impl ClassCountWalker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl AstWalker for ClassCountWalker {
+    fn walk_class(&mut self, class: &ClassNode) -> Result<(), Error> {
+        if !class.name.is_empty() {
+            self.classes += 1;
+        }
+        Ok(())
+    }
+
+    fn walk_property(&mut self, _property: &PropertyNode) -> Result<(), Error> {
+        self.properties += 1;
+        Ok(())
+    }
+}
+
+/// Collects properties in walk order, optionally only those with a given
+/// name (ignoring case). Each entry records the name of the owning class.
+#[derive(Debug, Default)]
+pub struct PropertyCollectorWalker {
+    name: Option<String>,
+    current_class: String,
+    properties: Vec<(String, PropertyNode)>,
+}
+
+impl PropertyCollectorWalker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// `(class name, property)` pairs.
+    pub fn properties(&self) -> &[(String, PropertyNode)] {
+        &self.properties
+    }
+
+    pub fn into_properties(self) -> Vec<(String, PropertyNode)> {
+        self.properties
+    }
+}
+
+impl AstWalker for PropertyCollectorWalker {
+    fn walk_class(&mut self, class: &ClassNode) -> Result<(), Error> {
+        // `walk` visits a class's properties before its nested classes
+        self.current_class = class.name.clone();
+        Ok(())
+    }
+
+    fn walk_property(&mut self, property: &PropertyNode) -> Result<(), Error> {
+        if self.name.as_ref().is_none_or(|name| property.name.eq_ignore_ascii_case(name)) {
+            self.properties.push((self.current_class.clone(), property.clone()));
+        }
+        Ok(())
+    }
+}
+
+/// Read-only version of [`SchemaVisitor`](super::SchemaVisitor).
+#[derive(Debug, Default)]
+pub struct SchemaValidationWalker {
+    schemas: HashMap<String, ClassSchema>,
+    errors: Vec<ValidationError>,
+}
+
+impl SchemaValidationWalker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Apply `schema` to every class named `class_name` (ignoring case).
+    pub fn with_schema(mut self, class_name: &str, schema: ClassSchema) -> Self {
+        self.schemas.insert(class_name.to_lowercase(), schema);
+        self
+    }
+
+    pub fn errors(&self) -> &[ValidationError] {
+        &self.errors
+    }
+
+    pub fn into_errors(self) -> Vec<ValidationError> {
+        self.errors
+    }
+}
+
+impl AstWalker for SchemaValidationWalker {
+    fn walk_class(&mut self, class: &ClassNode) -> Result<(), Error> {
+        if let Some(schema) = self.schemas.get(&class.name.to_lowercase()) {
+            self.errors.extend(TypeValidator::validate_class(class, schema));
+        }
+        Ok(())
+    }
+
+    fn walk_property(&mut self, _property: &PropertyNode) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use crate::types::{PropertySchema, TypeDefinition};
+    use crate::ClassScanner;
+
+    #[test]
+    fn test_walkers_on_shared_tree() {
+        let classes = ClassScanner::new().parse_string(r#"
+            class CfgVehicles {
+                class Car { scope = 2; maxSpeed = 120; };
+                class Tank { scope = 5; class Turret { maxSpeed = 0; }; };
+            };
+            class CfgWeapons { class Rifle { scope = 1; }; };
+        "#).unwrap();
+        let shared: Arc<Vec<ClassNode>> = Arc::new(classes);
+
+        let mut counter = ClassCountWalker::new();
+        counter.walk_all(&shared[1..]).unwrap();
+        assert_eq!((counter.classes, counter.properties), (6, 5));
+
+        let mut root_counter = ClassCountWalker::new();
+        shared[0].walk(&mut root_counter).unwrap();
+        assert_eq!(root_counter.classes, 6);
+
+        let mut collector = PropertyCollectorWalker::new().with_name("MAXSPEED");
+        collector.walk_all(&shared[1..]).unwrap();
+        let found: Vec<(&str, &str)> = collector.properties().iter()
+            .map(|(class, property)| (class.as_str(), property.raw_value.as_str()))
+            .collect();
+        assert_eq!(found, vec![("Car", "120"), ("Turret", "0")]);
+
+        let schema = ClassSchema::new()
+            .with_property("scope", PropertySchema::new(TypeDefinition::Number).with_range(Some(0.0), Some(2.0)));
+        let mut validator = SchemaValidationWalker::new().with_schema("tank", schema.clone()).with_schema("Car", schema);
+        validator.walk_all(&shared[1..]).unwrap();
+        let errors = validator.into_errors();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].class_name, "Tank");
+    }
+}
@@ -1,46 +1,282 @@
 use thiserror::Error;
-use std::{io, path::PathBuf};
+use serde::{Deserialize, Serialize};
+use std::{io, ops::Range, path::PathBuf};
 
 #[derive(Error, Debug)]
 pub enum Error {
-    #[error("IO error: {0}")]
+    #[error("[E0501] IO error: {0}")]
     Io(#[from] io::Error),
 
-    #[error("Lexer error at {location}: {message}")]
+    #[error("[E0101] Lexer error at {location}: {message}")]
     LexerError {
         message: String,
         location: SourceLocation,
     },
 
-    #[error("Parser error at {location}: {message}")]
+    #[error("[E0201] Parser error at {location}: {message}")]
     ParseError {
         message: String,
         location: SourceLocation,
     },
 
-    #[error("Type error: {0}")]
+    #[error("[E0401] Type error: {0}")]
     TypeError(String),
 
-    #[error("Inheritance error: {0}")]
+    #[error("[E0301] Inheritance error: {0}")]
     InheritanceError(String),
 
-    #[error("Include error: Could not include file '{0}' from '{1}'")]
-    IncludeError(String, String),
+    /// The include as written, where it was included from and the paths
+    /// tried for it.
+    #[error("[E0102] Include error: Could not include file '{0}' from '{1}'{tried}", tried = tried_paths(.2))]
+    IncludeError(String, String, Vec<PathBuf>),
 
-    #[error("Macro error: {0}")]
+    #[error("[E0103] Macro error: {0}")]
     MacroError(String),
+
+    #[error("[E0104] Preprocessor error: {0}")]
+    PreprocessorError(String),
+
+    #[error("[E0601] Diff error: {0}")]
+    DiffError(String),
+
+    #[error("[E0602] Merge error: {0}")]
+    MergeError(String),
+
+    #[error("[E0701] Timeout: parsing '{}' took longer than the scan timeout", .0.display())]
+    Timeout(PathBuf),
+
+    #[error("[E0702] Config error: {0}")]
+    ConfigError(String),
+
+    #[error("[E0703] Watch error: {0}")]
+    WatchError(String),
+
+    #[error("[E0704] Parser panicked while parsing '{}'", .0.display())]
+    ParserPanic(PathBuf),
+}
+
+/// Every error code with a one-line description, for help output.
+///
+/// Codes are grouped by stage: `E01xx` lexing and preprocessing, `E02xx`
+/// parsing, `E03xx` inheritance, `E04xx` types, `E05xx` IO, `E06xx`
+/// operations on class trees and `E07xx` directory scans. Codes are stable; retired codes are not reused.
+pub const ALL_ERROR_CODES: &[(&str, &str)] = &[
+    ("E0101", "Invalid token in source text"),
+    ("E0102", "Included file could not be found"),
+    ("E0103", "Invalid macro use or preprocessor expression"),
+    ("E0104", "Malformed preprocessor directive"),
+    ("E0201", "Unexpected token while parsing"),
+    ("E0301", "Inheritance could not be resolved"),
+    ("E0401", "Value does not match the expected type"),
+    ("E0501", "File could not be read"),
+    ("E0601", "Diff could not be applied"),
+    ("E0602", "Conflicting definitions while merging"),
+    ("E0701", "File took longer to parse than the scan timeout"),
+    ("E0702", "Scanner config file is invalid"),
+    ("E0703", "Files could not be watched for changes"),
+    ("E0704", "Parser crashed on a file"),
+];
+
+impl Error {
+    /// The stable code for this kind of error, see [`ALL_ERROR_CODES`].
+    pub fn code(&self) -> &'static str {
+        match self {
+            Error::LexerError { .. } => "E0101",
+            Error::IncludeError(..) => "E0102",
+            Error::MacroError(_) => "E0103",
+            Error::PreprocessorError(_) => "E0104",
+            Error::ParseError { .. } => "E0201",
+            Error::InheritanceError(_) => "E0301",
+            Error::TypeError(_) => "E0401",
+            Error::Io(_) => "E0501",
+            Error::DiffError(_) => "E0601",
+            Error::MergeError(_) => "E0602",
+            Error::Timeout(_) => "E0701",
+            Error::ConfigError(_) => "E0702",
+            Error::WatchError(_) => "E0703",
+            Error::ParserPanic(_) => "E0704",
+        }
+    }
+
+    /// Where in the source the error happened, for errors that record it.
+    pub fn location_mut(&mut self) -> Option<&mut SourceLocation> {
+        match self {
+            Error::LexerError { location, .. } | Error::ParseError { location, .. } => Some(location),
+            _ => None,
+        }
+    }
+}
+
+impl Warning {
+    /// Every place in the source the warning points at.
+    pub fn locations_mut(&mut self) -> Vec<&mut SourceLocation> {
+        match self {
+            Warning::DuplicateProperty { location, previous, .. } => vec![location, previous],
+            Warning::MacroRedefined { location, .. }
+            | Warning::UnknownPreprocessorDirective { location, .. }
+            | Warning::PropertyTypeMismatch { location, .. }
+            | Warning::MissingSemicolon { location, .. }
+            | Warning::StraySemicolon { location }
+            | Warning::UnsupportedBuiltin { location, .. }
+            | Warning::MissingInclude { location, .. } => vec![location],
+            Warning::CircularInheritanceResolved { .. }
+            | Warning::MissingParent { .. }
+            | Warning::SkippedFile { .. } => Vec::new(),
+        }
+    }
+}
+
+/// Extended documentation for an error code, e.g. for an `--explain` flag.
+/// Codes are matched case-insensitively.
+pub fn explain(code: &str) -> Option<&'static str> {
+    let text = match code.to_ascii_uppercase().as_str() {
+        "E0101" => "\
+The tokenizer found text that is not part of the config syntax, such as an
+unterminated string literal or a stray character. The location points at the
+offending character.",
+        "E0102" => "\
+An `#include` names a file that does not exist relative to the including file,
+the scanner's base path or any configured include path. Check the path and
+that P-drive style prefixes (`\\x\\addon\\...`) have a matching include path.",
+        "E0103" => "\
+A function-like macro was called with the wrong number of arguments, expanded
+recursively, or an `#if`/`#elif` expression could not be evaluated.",
+        "E0104" => "\
+Preprocessor conditionals are unbalanced (an `#else` or `#endif` without a
+matching `#if`, or a block left open at the end of a file), or a directive is
+missing its argument.",
+        "E0201" => "\
+The parser expected a different token, for example a missing `;` after a
+property or class, a missing `}` or a property without a value. With error
+recovery enabled parsing continues after the next `;` or `}`.",
+        "E0301" => "\
+A class names a parent that is not defined in any of the parsed sources, or
+the requested class does not exist. Parents must be declared (at least with
+`class Name;`) before they can be inherited from.",
+        "E0401" => "\
+A value does not have the type required by a schema or type definition, for
+example text where a number is expected.",
+        "E0501" => "\
+A source file could not be read. The underlying operating system error is
+included in the message.",
+        "E0601" => "\
+A diff could not be applied because the class or member it refers to is not
+present in the target tree.",
+        "E0602" => "\
+Two sources define the same property or parent differently and the merge was
+run with `MergeStrategy::Error`. Use `LastWins` or `FirstWins` to pick one.",
+        "E0701" => "\
+A file in a directory scan was still being parsed when `ScannerConfig::timeout`
+ran out. The file is skipped and the scan continues; raise the timeout or
+exclude the file if it is expected to be this large.",
+        "E0702" => "\
+A scanner config file could not be loaded: it is not a `.json` or `.toml`
+file, it does not parse, or it sets a field `ScannerConfig` does not have.
+TOML files need the crate's `toml` feature.",
+        "E0703" => "\
+`ClassScanner::watch` could not start watching a path, usually because it does
+not exist or the system's limit on watched files was reached.",
+        "E0704" => "\
+The parser panicked on a file in a directory scan. The file is skipped and
+the scan continues. This is a bug in the crate; please report it
+with the file that caused it.",
+        _ => return None,
+    };
+    Some(text)
+}
+
+/// A non-fatal problem noticed while processing configs. Processing carries
+/// on; the warnings are collected alongside the result.
+#[derive(Error, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Warning {
+    #[error("Macro '{name}' redefined at {location}")]
+    MacroRedefined {
+        name: String,
+        location: SourceLocation,
+    },
+
+    #[error("Circular inheritance {} resolved by ignoring the last parent", chain.join(" -> "))]
+    CircularInheritanceResolved {
+        chain: Vec<String>,
+    },
+
+    #[error("Class '{class}' inherits from '{parent}', which was not found")]
+    MissingParent {
+        class: String,
+        parent: String,
+    },
+
+    #[error("Unknown preprocessor directive '#{directive}' at {location}")]
+    UnknownPreprocessorDirective {
+        directive: String,
+        location: SourceLocation,
+    },
+
+    #[error("Property '{name}' at {location} expected {expected}, found {found}")]
+    PropertyTypeMismatch {
+        name: String,
+        expected: String,
+        found: String,
+        location: SourceLocation,
+    },
+
+    /// The later definition, at `location`, replaces the one at `previous`.
+    #[error("Property '{name}' of class '{class}' defined again at {location}, overriding the definition at {previous}")]
+    DuplicateProperty {
+        class: String,
+        name: String,
+        location: SourceLocation,
+        previous: SourceLocation,
+    },
+
+    #[error("Missing ';' after {after} at {location}")]
+    MissingSemicolon {
+        after: String,
+        location: SourceLocation,
+    },
+
+    #[error("Stray ';' at {location}")]
+    StraySemicolon {
+        location: SourceLocation,
+    },
+
+    /// A builtin such as `__EXEC` that the preprocessor removed rather than run.
+    #[error("Unsupported '{name}' at {location} removed")]
+    UnsupportedBuiltin {
+        name: String,
+        location: SourceLocation,
+    },
+
+    /// An `#include` that was skipped, with the paths tried for it.
+    #[error("Included file '{include}' at {location} not found{tried}", tried = tried_paths(candidates))]
+    MissingInclude {
+        include: String,
+        candidates: Vec<PathBuf>,
+        location: SourceLocation,
+    },
+
+    /// A file of a directory that failed to parse and was left out, with
+    /// the error it failed with.
+    #[error("Skipped {}: {message}", file.display())]
+    SkippedFile {
+        file: PathBuf,
+        message: String,
+    },
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct SourceLocation {
     pub file: Option<PathBuf>,
     pub line: usize,
     pub column: usize,
+    /// Byte range of the offending text in the tokenized source, when known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub span: Option<Range<usize>>,
 }
 
 impl SourceLocation {
     pub fn new(file: Option<PathBuf>, line: usize, column: usize) -> Self {
-        Self { file, line, column }
+        Self { file, line, column, span: None }
     }
 
     pub fn unknown() -> Self {
@@ -48,6 +284,7 @@ impl SourceLocation {
             file: None,
             line: 0,
             column: 0,
+            span: None,
         }
     }
 
@@ -56,6 +293,59 @@ impl SourceLocation {
         self
     }
 
+    pub fn with_span(mut self, span: Range<usize>) -> Self {
+        self.span = Some(span);
+        self
+    }
+
+    /// The line of `source` this location points at, with the span (or
+    /// the column, without one) underlined:
+    ///
+    /// ```text
+    /// 3 | value = "Café" oops;
+    ///   |                ^^^^
+    /// ```
+    ///
+    /// `source` must be the text that was tokenized. Empty if the location
+    /// does not fall inside it.
+    pub fn snippet(&self, source: &str) -> String {
+        let (line_start, start, end) = match &self.span {
+            Some(span) => {
+                let line_start = source.get(..span.start).map_or(0, |before| before.rfind('\n').map_or(0, |i| i + 1));
+                (line_start, span.start, span.end)
+            }
+            None => {
+                let Some(line_start) = line_offset(source, self.line) else {
+                    return String::new();
+                };
+                let start = source[line_start..].char_indices().nth(self.column).map_or(source.len(), |(i, _)| line_start + i);
+                (line_start, start, start)
+            }
+        };
+        if !source.is_char_boundary(start) || start > source.len() {
+            return String::new();
+        }
+
+        let line_end = source[line_start..].find('\n').map_or(source.len(), |i| line_start + i);
+        let end = end.clamp(start, line_end);
+        let end = (end..=line_end).find(|&i| source.is_char_boundary(i)).unwrap_or(line_end);
+        // Tabs are kept so the carets line up however wide they are shown
+        let indent: String = source[line_start..start].chars()
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        let width = source[start..end].chars().count().max(1);
+        let number = source[..line_start].matches('\n').count() + 1;
+        let gutter = " ".repeat(number.to_string().len());
+        format!(
+            "{} | {}\n{} | {}{}",
+            number,
+            source[line_start..line_end].trim_end_matches('\r'),
+            gutter,
+            indent,
+            "^".repeat(width),
+        )
+    }
+
     pub fn with_context(&self) -> String {
         match (&self.file, self.line) {
             (Some(path), line) if line > 0 => format!(" at {}:{}", path.display(), line),
@@ -65,6 +355,23 @@ impl SourceLocation {
     }
 }
 
+/// Byte offset of the start of 1-based `line` in `source`.
+fn tried_paths(paths: &[PathBuf]) -> String {
+    if paths.is_empty() {
+        return String::new();
+    }
+    let paths: Vec<String> = paths.iter().map(|path| format!("'{}'", path.display())).collect();
+    format!(", tried {}", paths.join(", "))
+}
+
+fn line_offset(source: &str, line: usize) -> Option<usize> {
+    match line {
+        0 => None,
+        1 => Some(0),
+        _ => source.match_indices('\n').nth(line - 2).map(|(index, _)| index + 1),
+    }
+}
+
 impl std::fmt::Display for SourceLocation {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match &self.file {
@@ -74,3 +381,37 @@ impl std::fmt::Display for SourceLocation {
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_codes() {
+        let error = Error::ParseError {
+            message: "Expected '}'".to_string(),
+            location: SourceLocation::new(None, 12, 5),
+        };
+        assert_eq!(error.code(), "E0201");
+        assert_eq!(error.to_string(), "[E0201] Parser error at line 12:5: Expected '}'");
+
+        for (code, _) in ALL_ERROR_CODES {
+            assert!(explain(code).is_some(), "{} has no explanation", code);
+        }
+        assert!(ALL_ERROR_CODES.iter().any(|(code, _)| *code == Error::MergeError(String::new()).code()));
+        assert_eq!(explain("e0201"), explain("E0201"));
+        assert!(explain("E9999").is_none());
+    }
+
+    #[test]
+    fn test_snippet() {
+        let source = "class A {\n\tname = \"Café\" oops;\n};";
+        let start = source.find("oops").unwrap();
+        let location = SourceLocation::new(None, 2, 15).with_span(start..start + 4);
+        assert_eq!(location.snippet(source), format!("2 | \tname = \"Café\" oops;\n  | \t{}^^^^", " ".repeat(14)));
+
+        // Without a span the column is underlined
+        assert_eq!(SourceLocation::new(None, 1, 6).snippet(source), "1 | class A {\n  |       ^");
+        assert_eq!(SourceLocation::new(None, 9, 0).snippet(source), "");
+        assert_eq!(SourceLocation::unknown().snippet(source), "");
+    }
+}
@@ -0,0 +1,368 @@
+use std::collections::HashMap;
+use std::iter::Peekable;
+use std::str::CharIndices;
+use crate::error::Error;
+use crate::operations::arrays::parse_number;
+
+#[derive(Debug, Clone, PartialEq)]
+enum ExprToken {
+    Number(f64),
+    Identifier(String),
+    Operator(&'static str),
+    LeftParen,
+    RightParen,
+}
+
+/// How an expression's numbers are read and combined.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum NumericMode {
+    /// `#if` and `#elif`: C integer literals (`010` is octal, suffixes such
+    /// as `1u` are allowed), integer division and the full set of logical,
+    /// comparison and arithmetic operators. Undefined names are `0`.
+    Integer,
+    /// `__EVAL` and property values: config number literals (decimal,
+    /// scientific, `0x` hex, `0o` octal) with floating point `+ - * /`.
+    /// Any name makes the expression non-constant.
+    Float,
+}
+
+impl NumericMode {
+    fn operators(self) -> &'static [&'static str] {
+        match self {
+            NumericMode::Integer => &["||", "&&", "==", "!=", "<=", ">=", "<", ">", "+", "-", "*", "/", "%", "!"],
+            NumericMode::Float => &["+", "-", "*", "/"],
+        }
+    }
+}
+
+/// Evaluates the expressions of `#if` and `#elif` directives, `__EVAL` and
+/// property values, reading numbers as `mode` says.
+///
+/// Identifiers are expanded through the current defines; anything left
+/// undefined evaluates to `0`, matching the C preprocessor.
+pub(crate) struct ExpressionEvaluator<'a> {
+    tokens: Vec<ExprToken>,
+    position: usize,
+    mode: NumericMode,
+    defines: &'a HashMap<String, String>,
+    depth: usize,
+}
+
+const MAX_EXPANSION_DEPTH: usize = 16;
+
+impl<'a> ExpressionEvaluator<'a> {
+    pub(crate) fn evaluate(expression: &str, mode: NumericMode, defines: &'a HashMap<String, String>) -> Result<f64, Error> {
+        Self::evaluate_with_depth(expression, mode, defines, 0)
+    }
+
+    fn evaluate_with_depth(expression: &str, mode: NumericMode, defines: &'a HashMap<String, String>, depth: usize) -> Result<f64, Error> {
+        if depth > MAX_EXPANSION_DEPTH {
+            return Err(Error::MacroError(format!("Macro expansion too deep in expression '{}'", expression)));
+        }
+
+        let mut evaluator = Self {
+            tokens: tokenize(expression, mode)?,
+            position: 0,
+            mode,
+            defines,
+            depth,
+        };
+
+        if evaluator.tokens.is_empty() {
+            return Err(Error::MacroError("Empty expression".to_string()));
+        }
+
+        let value = evaluator.parse_or()?;
+        if evaluator.position < evaluator.tokens.len() {
+            return Err(Error::MacroError(format!("Unexpected token in expression '{}'", expression)));
+        }
+        Ok(value)
+    }
+
+    fn parse_or(&mut self) -> Result<f64, Error> {
+        let mut value = self.parse_and()?;
+        while self.match_operator("||") {
+            let rhs = self.parse_and()?;
+            value = truth((value != 0.0) || (rhs != 0.0));
+        }
+        Ok(value)
+    }
+
+    fn parse_and(&mut self) -> Result<f64, Error> {
+        let mut value = self.parse_equality()?;
+        while self.match_operator("&&") {
+            let rhs = self.parse_equality()?;
+            value = truth((value != 0.0) && (rhs != 0.0));
+        }
+        Ok(value)
+    }
+
+    fn parse_equality(&mut self) -> Result<f64, Error> {
+        let mut value = self.parse_comparison()?;
+        loop {
+            if self.match_operator("==") {
+                value = truth(value == self.parse_comparison()?);
+            } else if self.match_operator("!=") {
+                value = truth(value != self.parse_comparison()?);
+            } else {
+                return Ok(value);
+            }
+        }
+    }
+
+    fn parse_comparison(&mut self) -> Result<f64, Error> {
+        let mut value = self.parse_additive()?;
+        loop {
+            if self.match_operator("<=") {
+                value = truth(value <= self.parse_additive()?);
+            } else if self.match_operator(">=") {
+                value = truth(value >= self.parse_additive()?);
+            } else if self.match_operator("<") {
+                value = truth(value < self.parse_additive()?);
+            } else if self.match_operator(">") {
+                value = truth(value > self.parse_additive()?);
+            } else {
+                return Ok(value);
+            }
+        }
+    }
+
+    fn parse_additive(&mut self) -> Result<f64, Error> {
+        let mut value = self.parse_multiplicative()?;
+        loop {
+            if self.match_operator("+") {
+                value += self.parse_multiplicative()?;
+            } else if self.match_operator("-") {
+                value -= self.parse_multiplicative()?;
+            } else {
+                return Ok(value);
+            }
+        }
+    }
+
+    fn parse_multiplicative(&mut self) -> Result<f64, Error> {
+        let mut value = self.parse_unary()?;
+        loop {
+            if self.match_operator("*") {
+                value *= self.parse_unary()?;
+            } else if self.match_operator("/") || self.match_operator("%") {
+                let is_division = self.tokens[self.position - 1] == ExprToken::Operator("/");
+                let rhs = self.parse_unary()?;
+                if rhs == 0.0 {
+                    return Err(Error::MacroError("Division by zero in expression".to_string()));
+                }
+                value = match (is_division, self.mode) {
+                    (true, NumericMode::Float) => value / rhs,
+                    // Integer division truncates toward zero, as in C
+                    (true, NumericMode::Integer) => (value / rhs).trunc(),
+                    (false, _) => value % rhs,
+                };
+            } else {
+                return Ok(value);
+            }
+        }
+    }
+
+    fn parse_unary(&mut self) -> Result<f64, Error> {
+        if self.match_operator("!") {
+            return Ok(truth(self.parse_unary()? == 0.0));
+        }
+        if self.match_operator("-") {
+            return Ok(-self.parse_unary()?);
+        }
+        if self.match_operator("+") {
+            return self.parse_unary();
+        }
+        self.parse_primary()
+    }
+
+    fn parse_primary(&mut self) -> Result<f64, Error> {
+        match self.next_token() {
+            Some(ExprToken::Number(n)) => Ok(n),
+            Some(ExprToken::LeftParen) => {
+                let value = self.parse_or()?;
+                match self.next_token() {
+                    Some(ExprToken::RightParen) => Ok(value),
+                    _ => Err(Error::MacroError("Expected ')' in expression".to_string())),
+                }
+            }
+            Some(ExprToken::Identifier(name)) if self.mode == NumericMode::Float => {
+                Err(Error::MacroError(format!("'{}' is not a constant", name)))
+            }
+            Some(ExprToken::Identifier(name)) if name == "defined" => self.parse_defined(),
+            Some(ExprToken::Identifier(name)) => match self.defines.get(&name) {
+                Some(value) if !value.trim().is_empty() => {
+                    Self::evaluate_with_depth(value, self.mode, self.defines, self.depth + 1)
+                }
+                _ => Ok(0.0),
+            },
+            Some(token) => Err(Error::MacroError(format!("Unexpected token {:?} in expression", token))),
+            None => Err(Error::MacroError("Unexpected end of expression".to_string())),
+        }
+    }
+
+    fn parse_defined(&mut self) -> Result<f64, Error> {
+        let parenthesized = self.peek_token() == Some(&ExprToken::LeftParen);
+        if parenthesized {
+            self.position += 1;
+        }
+
+        let name = match self.next_token() {
+            Some(ExprToken::Identifier(name)) => name,
+            _ => return Err(Error::MacroError("Expected identifier after 'defined'".to_string())),
+        };
+
+        if parenthesized && self.next_token() != Some(ExprToken::RightParen) {
+            return Err(Error::MacroError("Expected ')' after 'defined(' identifier".to_string()));
+        }
+
+        Ok(truth(self.defines.contains_key(&name)))
+    }
+
+    fn match_operator(&mut self, operator: &str) -> bool {
+        if let Some(ExprToken::Operator(op)) = self.peek_token() {
+            if *op == operator {
+                self.position += 1;
+                return true;
+            }
+        }
+        false
+    }
+
+    fn peek_token(&self) -> Option<&ExprToken> {
+        self.tokens.get(self.position)
+    }
+
+    fn next_token(&mut self) -> Option<ExprToken> {
+        let token = self.tokens.get(self.position).cloned();
+        if token.is_some() {
+            self.position += 1;
+        }
+        token
+    }
+}
+
+fn truth(value: bool) -> f64 {
+    if value { 1.0 } else { 0.0 }
+}
+
+fn tokenize(expression: &str, mode: NumericMode) -> Result<Vec<ExprToken>, Error> {
+    let mut tokens = Vec::new();
+    let mut chars: Peekable<CharIndices> = expression.char_indices().peekable();
+
+    while let Some(&(start, c)) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c.is_ascii_digit() || (c == '.' && mode == NumericMode::Float) {
+            let mut end = start;
+            let mut previous = ' ';
+            while let Some(&(i, d)) = chars.peek() {
+                // In config literals a sign only belongs to the number right
+                // after an exponent
+                let exponent_sign = mode == NumericMode::Float
+                    && matches!(d, '+' | '-') && matches!(previous, 'e' | 'E')
+                    && !expression[start..i].starts_with("0x");
+                if !(d.is_ascii_alphanumeric() || (d == '.' && mode == NumericMode::Float) || exponent_sign) {
+                    break;
+                }
+                previous = d;
+                end = i + d.len_utf8();
+                chars.next();
+            }
+            let literal = &expression[start..end];
+            let value = match mode {
+                NumericMode::Integer => parse_integer(literal)? as f64,
+                NumericMode::Float => parse_number(literal)
+                    .ok_or_else(|| Error::MacroError(format!("Invalid number literal '{}' in expression", literal)))?,
+            };
+            tokens.push(ExprToken::Number(value));
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let mut ident = String::new();
+            while let Some(&(_, d)) = chars.peek() {
+                if d.is_ascii_alphanumeric() || d == '_' {
+                    ident.push(d);
+                    chars.next();
+                } else {
+                    break;
+                }
+            }
+            tokens.push(ExprToken::Identifier(ident));
+        } else if c == '(' {
+            chars.next();
+            tokens.push(ExprToken::LeftParen);
+        } else if c == ')' {
+            chars.next();
+            tokens.push(ExprToken::RightParen);
+        } else {
+            let rest = &expression[start..];
+            let operator = mode.operators().iter()
+                .find(|op| rest.starts_with(**op))
+                .ok_or_else(|| Error::MacroError(format!("Unexpected character '{}' in expression", c)))?;
+            for _ in 0..operator.len() {
+                chars.next();
+            }
+            tokens.push(ExprToken::Operator(operator));
+        }
+    }
+
+    Ok(tokens)
+}
+
+fn parse_integer(literal: &str) -> Result<i64, Error> {
+    let trimmed = literal.trim_end_matches(['u', 'U', 'l', 'L']);
+    let parsed = if let Some(hex) = trimmed.strip_prefix("0x").or_else(|| trimmed.strip_prefix("0X")) {
+        i64::from_str_radix(hex, 16)
+    } else if trimmed.len() > 1 && trimmed.starts_with('0') {
+        i64::from_str_radix(&trimmed[1..], 8)
+    } else {
+        trimmed.parse::<i64>()
+    };
+    parsed.map_err(|_| Error::MacroError(format!("Invalid integer literal '{}' in conditional expression", literal)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(expression: &str, defines: &[(&str, &str)]) -> Result<i64, Error> {
+        let defines: HashMap<String, String> = defines.iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        ExpressionEvaluator::evaluate(expression, NumericMode::Integer, &defines).map(|value| value as i64)
+    }
+
+    #[test]
+    fn test_arithmetic_and_comparison() {
+        assert_eq!(eval("1 + 2 * 3", &[]).unwrap(), 7);
+        assert_eq!(eval("(1 + 2) * 3", &[]).unwrap(), 9);
+        assert_eq!(eval("10 / 3", &[]).unwrap(), 3);
+        assert_eq!(eval("10 % 3", &[]).unwrap(), 1);
+        assert_eq!(eval("-4 + 6", &[]).unwrap(), 2);
+        assert_eq!(eval("3 > 2", &[]).unwrap(), 1);
+        assert_eq!(eval("3 <= 2", &[]).unwrap(), 0);
+        assert_eq!(eval("2 == 2 && 1 != 0", &[]).unwrap(), 1);
+        assert_eq!(eval("0 || !1", &[]).unwrap(), 0);
+        assert_eq!(eval("0x10 == 16", &[]).unwrap(), 1);
+    }
+
+    #[test]
+    fn test_defines_and_defined() {
+        let defines = [("VERSION", "3"), ("_ARMA_", ""), ("ALIAS", "VERSION + 1")];
+        assert_eq!(eval("VERSION > 2", &defines).unwrap(), 1);
+        assert_eq!(eval("ALIAS", &defines).unwrap(), 4);
+        assert_eq!(eval("UNKNOWN", &defines).unwrap(), 0);
+        assert_eq!(eval("defined(_ARMA_)", &defines).unwrap(), 1);
+        assert_eq!(eval("defined UNKNOWN", &defines).unwrap(), 0);
+        assert_eq!(eval("!defined(UNKNOWN) && VERSION == 3", &defines).unwrap(), 1);
+    }
+
+    #[test]
+    fn test_invalid_expressions() {
+        assert!(eval("", &[]).is_err());
+        assert!(eval("(1 + 2", &[]).is_err());
+        assert!(eval("1 / 0", &[]).is_err());
+        assert!(eval("1 2", &[]).is_err());
+        assert!(eval("\"text\"", &[]).is_err());
+        assert!(eval("SELF", &[("SELF", "SELF")]).is_err());
+    }
+}
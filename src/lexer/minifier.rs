@@ -0,0 +1,329 @@
+use crate::error::Error;
+use super::Tokenizer;
+use super::tokens::{Token, TokenType};
+
+/// Strip comments and whitespace from config source.
+///
+/// ```
+/// use class_scanner::lexer::minify;
+///
+/// let minified = minify("class A: B {\n    value = 1; // one\n};").unwrap();
+/// assert_eq!(minified, "class A:B{value=1;};");
+/// ```
+///
+/// Tokens are copied as written, so numbers keep their notation. Spaces
+/// are only kept between words, such as `class` and the class name, and
+/// before a `-` that could otherwise join the token before it.
+///
+/// Preprocessor directives keep a line of their own, along with any `\`
+/// continuation lines. Conditionals such as `#ifdef` are copied as written;
+/// other directives only lose comments and repeated spaces, so
+/// `#define FOO (1)` is not turned into a function-like macro.
+#[derive(Debug, Clone, Default)]
+pub struct Minifier {
+    max_line_length: Option<usize>,
+}
+
+impl Minifier {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start a new line before a token that would end past this column.
+    /// A token longer than the limit still gets a line of its own. By
+    /// default the output is a single line.
+    pub fn with_max_line_length(mut self, columns: Option<usize>) -> Self {
+        self.max_line_length = columns;
+        self
+    }
+
+    pub fn minify(&self, input: &str) -> Result<String, Error> {
+        // Directives are blanked out for the tokenizer, keeping the offsets
+        // and lines of everything else, and written back between tokens
+        let directives = find_directives(input);
+        let mut code = input.as_bytes().to_vec();
+        for directive in &directives {
+            for byte in &mut code[directive.start..directive.end] {
+                if *byte != b'\n' {
+                    *byte = b' ';
+                }
+            }
+        }
+        let code = String::from_utf8(code).expect("only whole characters are blanked");
+        let tokens = Tokenizer::new(&code).with_comments(false).tokenize()?;
+
+        let mut output = String::with_capacity(input.len() / 2);
+        let mut line_start = 0;
+        let mut previous: Option<&Token> = None;
+        let mut directives = directives.iter().peekable();
+        for token in &tokens {
+            while let Some(directive) = directives.next_if(|directive| directive.start < token.start) {
+                write_directive(&mut output, directive);
+                line_start = output.len();
+                previous = None;
+            }
+            let text = &input[token.start..token.end];
+
+            let mut separator = match previous {
+                Some(previous) if is_word(previous) && is_word(token) => " ",
+                // Identifiers may contain `-`, so `a - 1` keeps its space
+                Some(previous) if text.starts_with('-') && (is_operator(previous) || matches!(previous.token_type, TokenType::Identifier(_))) => " ",
+                _ => "",
+            };
+            if self.max_line_length.is_some_and(|max| {
+                output.len() > line_start && output.len() - line_start + separator.len() + text.len() > max
+            }) {
+                separator = "\n";
+            }
+
+            output.push_str(separator);
+            if separator == "\n" {
+                line_start = output.len();
+            }
+            output.push_str(text);
+            previous = Some(token);
+        }
+        for directive in directives {
+            write_directive(&mut output, directive);
+        }
+        Ok(output)
+    }
+}
+
+fn write_directive(output: &mut String, directive: &Directive) {
+    if !output.is_empty() && !output.ends_with('\n') {
+        output.push('\n');
+    }
+    output.push_str(&directive.text);
+    output.push('\n');
+}
+
+/// [`Minifier::minify`] with no line length limit.
+pub fn minify(input: &str) -> Result<String, Error> {
+    Minifier::new().minify(input)
+}
+
+/// Tokens after which a `-` is kept apart, as the sign of a number.
+fn is_operator(token: &Token) -> bool {
+    matches!(
+        token.token_type,
+        TokenType::Equals | TokenType::Plus | TokenType::Minus | TokenType::Star | TokenType::Slash
+    )
+}
+
+/// Tokens that would run together without a space between them.
+fn is_word(token: &Token) -> bool {
+    matches!(
+        token.token_type,
+        TokenType::Identifier(_)
+            | TokenType::NumberLiteral(_)
+            | TokenType::BooleanLiteral(_)
+            | TokenType::Class
+            | TokenType::Enum
+            | TokenType::Delete
+            | TokenType::Public
+            | TokenType::Private
+            | TokenType::Include
+            | TokenType::Define
+    )
+}
+
+/// A preprocessor directive line of the input, with its `\` continuation
+/// lines.
+struct Directive {
+    /// Byte range of the directive, up to its last newline or a block
+    /// comment it leaves open, which the tokenizer then skips.
+    start: usize,
+    end: usize,
+    /// The directive as written to the output, without a newline.
+    text: String,
+}
+
+const CONDITIONALS: [&str; 6] = ["if", "ifdef", "ifndef", "elif", "else", "endif"];
+
+fn find_directives(input: &str) -> Vec<Directive> {
+    let mut directives = Vec::new();
+    let mut in_comment = false;
+    let mut lines = line_offsets(input).peekable();
+    while let Some((start, line)) = lines.next() {
+        let name = line.trim_start().strip_prefix('#').map(|rest| rest.trim_start());
+        let name = match name {
+            Some(name) if !in_comment && name.starts_with(|c: char| c.is_ascii_alphabetic()) => name,
+            _ => {
+                strip_comments(line, &mut in_comment);
+                continue;
+            }
+        };
+        let name: String = name.chars().take_while(char::is_ascii_alphanumeric).collect();
+
+        let mut end = start;
+        let mut parts = Vec::new();
+        let mut current = Some((start, line));
+        while let Some((offset, line)) = current.take() {
+            let (code, open_comment) = strip_comments(line, &mut in_comment);
+            end = offset + open_comment.unwrap_or(line.len());
+            parts.push(collapse_whitespace(&code));
+            if open_comment.is_none() && code.trim_end().ends_with('\\') {
+                current = lines.next();
+            }
+        }
+        let text = if CONDITIONALS.contains(&name.as_str()) {
+            input[start..end].trim().to_string()
+        } else {
+            parts.join("\n")
+        };
+        directives.push(Directive { start, end, text });
+    }
+    directives
+}
+
+/// Lines of `input` with their byte offsets, without their newline.
+fn line_offsets(input: &str) -> impl Iterator<Item = (usize, &str)> {
+    input.split_inclusive('\n').scan(0, |offset, line| {
+        let start = *offset;
+        *offset += line.len();
+        Some((start, line.trim_end_matches('\n')))
+    })
+}
+
+/// `line` without its comments, and where a block comment that does not
+/// end on the line starts. `in_comment` is whether the line starts inside
+/// a block comment, and is updated for the next line.
+fn strip_comments(line: &str, in_comment: &mut bool) -> (String, Option<usize>) {
+    let mut code = String::with_capacity(line.len());
+    let mut in_string = false;
+    let mut opened = in_comment.then_some(0);
+    let mut chars = line.char_indices().peekable();
+    while let Some((index, c)) = chars.next() {
+        if opened.is_some() {
+            if c == '*' && chars.next_if(|&(_, next)| next == '/').is_some() {
+                opened = None;
+                code.push(' ');
+            }
+            continue;
+        }
+        match c {
+            '"' => in_string = !in_string,
+            '/' if !in_string && chars.peek().is_some_and(|&(_, next)| next == '/') => break,
+            '/' if !in_string && chars.peek().is_some_and(|&(_, next)| next == '*') => {
+                chars.next();
+                opened = Some(index);
+                continue;
+            }
+            _ => {}
+        }
+        code.push(c);
+    }
+    *in_comment = opened.is_some();
+    (code, opened)
+}
+
+/// `text` trimmed, with each run of whitespace outside strings made one space.
+fn collapse_whitespace(text: &str) -> String {
+    let mut collapsed = String::with_capacity(text.len());
+    let mut in_string = false;
+    for c in text.trim().chars() {
+        if c == '"' {
+            in_string = !in_string;
+        }
+        if !in_string && c.is_whitespace() {
+            if !collapsed.ends_with(' ') {
+                collapsed.push(' ');
+            }
+        } else {
+            collapsed.push(c);
+        }
+    }
+    collapsed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ClassScanner;
+
+    const CONFIG: &str = r#"
+        /* Weapons */
+        class CfgWeapons {
+            class Rifle_Base_F; // external
+            class arifle_MX_F : Rifle_Base_F {
+                scope = 2;
+                color = 0xFF0000;
+                displayName = "MX 6.5 mm";
+                magazines[] = { "30Rnd_65x39_caseless_mag", "100Rnd_65x39_caseless_mag" };
+                recoil[] += { -0.5, 0.25, {1, 2} };
+                delete oldValue;
+            };
+        };
+    "#;
+
+    #[test]
+    fn test_minify() {
+        let minified = minify(CONFIG).unwrap();
+        assert_eq!(minified, concat!(
+            r#"class CfgWeapons{class Rifle_Base_F;class arifle_MX_F:Rifle_Base_F{scope=2;color=0xFF0000;"#,
+            r#"displayName="MX 6.5 mm";magazines[]={"30Rnd_65x39_caseless_mag","100Rnd_65x39_caseless_mag"};"#,
+            r#"recoil[]+={-0.5,0.25,{1,2}};delete oldValue;};};"#,
+        ));
+
+        let scanner = ClassScanner::new();
+        assert_eq!(scanner.parse_string(&minified).unwrap(), scanner.parse_string(CONFIG).unwrap());
+        assert_eq!(minify("// only a comment").unwrap(), "");
+    }
+
+    #[test]
+    fn test_minify_line_length() {
+        let minified = Minifier::new().with_max_line_length(Some(40)).minify(CONFIG).unwrap();
+        assert!(minified.lines().count() > 1);
+        for line in minified.lines() {
+            assert!(line.len() <= 40, "line too long: {}", line);
+        }
+
+        let scanner = ClassScanner::new();
+        assert_eq!(scanner.parse_string(&minified).unwrap(), scanner.parse_string(CONFIG).unwrap());
+    }
+
+    #[test]
+    fn test_minify_keeps_directives_on_their_own_line() {
+        let minified = minify("#define SCOPE 2\nclass A { scope = SCOPE; };\n#include \"b.hpp\"\n").unwrap();
+        assert_eq!(minified, "#define SCOPE 2\nclass A{scope=SCOPE;};\n#include \"b.hpp\"\n");
+    }
+
+    #[test]
+    fn test_minify_keeps_object_like_macros() {
+        let minified = minify("#define FOO (1)\n#define BAR(x)  ( x  * 2 ) // double\nclass A { a = FOO; };").unwrap();
+        assert_eq!(minified, "#define FOO (1)\n#define BAR(x) ( x * 2 )\nclass A{a=FOO;};");
+    }
+
+    #[test]
+    fn test_minify_keeps_continuation_lines() {
+        let input = "class B {};\n#define LIST a, \\\n    b\nclass A { list[] = {LIST}; };";
+        let minified = minify(input).unwrap();
+        assert_eq!(minified, "class B{};\n#define LIST a, \\\nb\nclass A{list[]={LIST};};");
+    }
+
+    #[test]
+    fn test_minify_passes_conditionals_through() {
+        let input = "#ifdef  FOO // set by the build\nclass A { a = 1; };\n#else\nclass A { a = 2; };\n  #endif\n";
+        let minified = minify(input).unwrap();
+        assert_eq!(minified, "#ifdef  FOO // set by the build\nclass A{a=1;};\n#else\nclass A{a=2;};\n#endif\n");
+
+        // A comment left open on a directive line is skipped as a comment
+        let minified = minify("#define A 1 /* one\n   two */\nclass B {};").unwrap();
+        assert_eq!(minified, "#define A 1\nclass B{};");
+    }
+
+    #[test]
+    fn test_minify_subtraction() {
+        let scanner = ClassScanner::new();
+        for input in ["class A { v = 1 - 2; w[] = {1 - 2, 3}; };", "class A { v = 1-2; w[] = {1 -2, 3}; };"] {
+            let minified = minify(input).unwrap();
+            assert_eq!(minified, "class A{v=1-2;w[]={1-2,3};};");
+            assert_eq!(scanner.parse_string(&minified).unwrap(), scanner.parse_string(input).unwrap());
+        }
+        let input = "class A { v = -1; w = 2 - -3; x = a - 1; };";
+        let minified = minify(input).unwrap();
+        assert_eq!(minified, "class A{v= -1;w=2- -3;x=a -1;};");
+        assert_eq!(scanner.parse_string(&minified).unwrap(), scanner.parse_string(input).unwrap());
+    }
+}
@@ -1,7 +1,12 @@
 mod tokenizer;
 mod preprocessor;
+pub(crate) mod expression;
+mod source_map;
+mod minifier;
 pub mod tokens;
 
 pub use tokenizer::Tokenizer;
 pub use tokens::Token;
-pub use preprocessor::Preprocessor;
\ No newline at end of file
+pub use preprocessor::{IncludePolicy, Preprocessor};
+pub use source_map::{SourceMap, SourceMapEntry};
+pub use minifier::{minify, Minifier};
\ No newline at end of file
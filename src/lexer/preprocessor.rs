@@ -1,49 +1,456 @@
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use std::fs;
-use crate::error::Error;
-use crate::utils::{INCLUDE_PATTERN, DEFINE_PATTERN, PathResolver};
+use crate::error::{Error, SourceLocation, Warning};
+use crate::utils::{
+    INCLUDE_PATTERN, DEFINE_PATTERN, FUNCTION_DEFINE_PATTERN, UNDEF_PATTERN, CONDITIONAL_PATTERN,
+    DEFINED_PATTERN,
+    PathResolver,
+};
+use super::expression::{ExpressionEvaluator, NumericMode};
+use crate::operations::eval::{evaluate, format_number};
+use super::source_map::{SourceMap, SourceMapEntry};
+
+/// Maximum nesting of macro expansions before a macro is considered recursive.
+const MAX_MACRO_DEPTH: usize = 16;
+
+/// Files at least this large are memory-mapped instead of read into a
+/// `String` (with the `mmap` feature).
+const DEFAULT_MMAP_THRESHOLD: usize = 1024 * 1024;
+const DEFAULT_MAX_INCLUDE_DEPTH: usize = 64;
+
+/// Name under which the trailing arguments of a variadic macro are bound.
+const VARIADIC_PARAMETER: &str = "__VA_ARGS__";
+
+/// What the preprocessor does with an `#include` whose file is not found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IncludePolicy {
+    /// Fail with an [`IncludeError`](Error::IncludeError) naming the
+    /// directive's location.
+    Error,
+    /// Skip the include and raise a [`Warning::MissingInclude`].
+    #[default]
+    Warn,
+    /// Skip the include silently.
+    Stub,
+}
+
+/// A function-like macro such as `#define GVAR(var) ADDON##_##var`.
+#[derive(Debug, Clone, PartialEq)]
+struct MacroDefinition {
+    parameters: Vec<String>,
+    variadic: bool,
+    body: String,
+}
+
+impl MacroDefinition {
+    fn new(parameter_list: &str, body: &str) -> Self {
+        let mut parameters: Vec<String> = parameter_list
+            .split(',')
+            .map(|p| p.trim().to_string())
+            .filter(|p| !p.is_empty())
+            .collect();
+
+        let variadic = parameters.last().is_some_and(|p| p == "...");
+        if variadic {
+            parameters.pop();
+            parameters.push(VARIADIC_PARAMETER.to_string());
+        }
+
+        Self {
+            parameters,
+            variadic,
+            body: body.to_string(),
+        }
+    }
+
+    /// Substitute already expanded `arguments` into the macro body, applying
+    /// `#` stringification and `##` token pasting.
+    fn substitute(&self, name: &str, arguments: &[String]) -> Result<String, Error> {
+        let arguments = self.bind_arguments(name, arguments)?;
+
+        let chars: Vec<char> = self.body.chars().collect();
+        let mut result = String::with_capacity(self.body.len());
+        let mut in_string = false;
+        let mut i = 0;
+
+        while i < chars.len() {
+            let c = chars[i];
+            if c == '"' {
+                in_string = !in_string;
+                result.push(c);
+                i += 1;
+            } else if in_string {
+                result.push(c);
+                i += 1;
+            } else if c == '#' && chars.get(i + 1) == Some(&'#') {
+                result.push_str("##");
+                i += 2;
+            } else if c == '#' {
+                // Stringification: #param -> "argument"
+                let mut j = i + 1;
+                while j < chars.len() && chars[j].is_whitespace() {
+                    j += 1;
+                }
+                let start = j;
+                while j < chars.len() && is_identifier_char(chars[j]) {
+                    j += 1;
+                }
+                let word: String = chars[start..j].iter().collect();
+                match self.parameter_index(&word) {
+                    Some(index) => {
+                        result.push('"');
+                        result.push_str(&arguments[index]);
+                        result.push('"');
+                        i = j;
+                    }
+                    None => {
+                        result.push(c);
+                        i += 1;
+                    }
+                }
+            } else if is_identifier_char(c) {
+                let start = i;
+                while i < chars.len() && is_identifier_char(chars[i]) {
+                    i += 1;
+                }
+                let word: String = chars[start..i].iter().collect();
+                match self.parameter_index(&word) {
+                    Some(index) => result.push_str(&arguments[index]),
+                    None => result.push_str(&word),
+                }
+            } else {
+                result.push(c);
+                i += 1;
+            }
+        }
+
+        Ok(paste_tokens(&result))
+    }
+
+    /// Match invocation arguments to parameters, folding any extra arguments
+    /// of a variadic macro into `__VA_ARGS__`.
+    fn bind_arguments(&self, name: &str, arguments: &[String]) -> Result<Vec<String>, Error> {
+        let fixed = if self.variadic { self.parameters.len() - 1 } else { self.parameters.len() };
+
+        // `MACRO()` passes a single empty argument, which means "no arguments"
+        // for a macro without fixed parameters
+        let arguments = match arguments {
+            [only] if only.is_empty() && fixed == 0 => &[][..],
+            _ => arguments,
+        };
+
+        let count_matches = if self.variadic { arguments.len() >= fixed } else { arguments.len() == fixed };
+        if !count_matches {
+            return Err(Error::MacroError(format!(
+                "Macro '{}' expects {}{} argument(s), got {}",
+                name,
+                if self.variadic { "at least " } else { "" },
+                fixed,
+                arguments.len()
+            )));
+        }
+
+        let mut bound = arguments[..fixed].to_vec();
+        if self.variadic {
+            bound.push(arguments[fixed..].join(", "));
+        }
+        Ok(bound)
+    }
+
+    fn parameter_index(&self, word: &str) -> Option<usize> {
+        self.parameters.iter().position(|p| p == word)
+    }
+}
+
+fn is_identifier_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+/// Collapse `##` operators together with the whitespace around them.
+fn paste_tokens(text: &str) -> String {
+    let mut pieces = text.split("##");
+    let mut result = pieces.next().unwrap_or("").trim_end().to_string();
+    for piece in pieces {
+        result = result.trim_end().to_string();
+        result.push_str(piece.trim_start());
+    }
+    result
+}
+
+/// `line` without its `__EXEC(...)` blocks, which run SQF at load time, and
+/// whether there were any.
+fn strip_exec(line: &str) -> (String, bool) {
+    if !line.contains("__EXEC") {
+        return (line.to_string(), false);
+    }
+    let chars: Vec<char> = line.chars().collect();
+    let mut result = String::with_capacity(line.len());
+    let mut stripped = false;
+    let mut in_string = false;
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c == '"' {
+            in_string = !in_string;
+        }
+        let at_word = !in_string && (i == 0 || !is_identifier_char(chars[i - 1]));
+        if at_word && chars[i..].starts_with(&['_', '_', 'E', 'X', 'E', 'C'])
+            && chars.get(i + 6).is_none_or(|&c| !is_identifier_char(c))
+        {
+            if let Some((_, end)) = parse_macro_arguments(&chars, i + 6) {
+                stripped = true;
+                i = end;
+                continue;
+            }
+        }
+        result.push(c);
+        i += 1;
+    }
+    (result, stripped)
+}
+
+/// Parse the parenthesised argument list of a macro invocation starting at
+/// `position`. Commas inside nested parentheses or string literals do not
+/// split arguments. Returns the raw arguments and the index just past the
+/// closing parenthesis, or `None` if no argument list follows.
+fn parse_macro_arguments(chars: &[char], position: usize) -> Option<(Vec<String>, usize)> {
+    let mut i = position;
+    while i < chars.len() && chars[i].is_whitespace() {
+        i += 1;
+    }
+    if chars.get(i) != Some(&'(') {
+        return None;
+    }
+    i += 1;
+
+    let mut arguments = Vec::new();
+    let mut current = String::new();
+    let mut depth = 0;
+    let mut in_string = false;
+
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            '"' => {
+                in_string = !in_string;
+                current.push(c);
+            }
+            _ if in_string => current.push(c),
+            '(' => {
+                depth += 1;
+                current.push(c);
+            }
+            ')' if depth == 0 => {
+                arguments.push(current);
+                return Some((arguments, i + 1));
+            }
+            ')' => {
+                depth -= 1;
+                current.push(c);
+            }
+            ',' if depth == 0 => {
+                arguments.push(std::mem::take(&mut current));
+            }
+            _ => current.push(c),
+        }
+        i += 1;
+    }
+
+    // Unterminated argument list
+    None
+}
+
+/// State of a single `#if`/`#ifdef`/`#ifndef` block on the condition stack.
+#[derive(Debug, Clone, Copy)]
+struct ConditionFrame {
+    /// Whether lines in the current branch are emitted.
+    active: bool,
+    /// Whether any branch of this block has already been taken.
+    branch_taken: bool,
+    /// Whether the enclosing block was active when this one was opened.
+    parent_active: bool,
+}
 
 pub struct Preprocessor {
     defines: HashMap<String, String>,
+    macros: HashMap<String, MacroDefinition>,
     path_resolver: PathResolver,
     processed_files: Vec<PathBuf>,
+    condition_stack: Vec<ConditionFrame>,
+    mmap_threshold: usize,
+    warnings: Vec<Warning>,
+    /// File being processed and the line `process_line` is at, innermost
+    /// include last, for `__FILE__` and `__LINE__`.
+    source_stack: Vec<(PathBuf, usize)>,
+    source_map: Vec<SourceMapEntry>,
+    /// Offset in the top-level output at which the content being processed
+    /// will be emitted.
+    output_offset: usize,
+    max_include_depth: usize,
+    include_policy: IncludePolicy,
+    warn_on_redefinition: bool,
+    /// Includes nested around the file being processed; 0 for the file
+    /// `process_file` was called with.
+    current_depth: usize,
+    /// Contents of files that are not on disk, see [`add_source`](Self::add_source).
+    sources: HashMap<PathBuf, String>,
 }
 
 impl Preprocessor {
     pub fn new<P: AsRef<Path>>(base_path: P) -> Self {
         Self {
             defines: HashMap::new(),
+            macros: HashMap::new(),
             path_resolver: PathResolver::new(base_path),
             processed_files: Vec::new(),
+            condition_stack: Vec::new(),
+            mmap_threshold: DEFAULT_MMAP_THRESHOLD,
+            warnings: Vec::new(),
+            source_stack: Vec::new(),
+            source_map: Vec::new(),
+            output_offset: 0,
+            max_include_depth: DEFAULT_MAX_INCLUDE_DEPTH,
+            include_policy: IncludePolicy::default(),
+            warn_on_redefinition: true,
+            current_depth: 0,
+            sources: HashMap::new(),
         }
     }
 
+    /// Memory-map source files of at least `bytes` bytes rather than reading
+    /// them into memory first. Only has an effect with the `mmap` feature.
+    pub fn with_mmap_threshold(mut self, bytes: usize) -> Self {
+        self.mmap_threshold = bytes;
+        self
+    }
+
+    /// Fail with an [`IncludeError`](Error::IncludeError) rather than follow
+    /// more than `depth` nested includes. Defaults to 64.
+    pub fn with_max_include_depth(mut self, depth: usize) -> Self {
+        self.max_include_depth = depth;
+        self
+    }
+
+    /// How an `#include` of a file that cannot be found is handled.
+    /// By default it is skipped with a warning.
+    pub fn with_include_policy(mut self, policy: IncludePolicy) -> Self {
+        self.include_policy = policy;
+        self
+    }
+
+    /// Whether defining a macro again with a different value raises a
+    /// [`Warning::MacroRedefined`]. On by default; either way the new
+    /// definition replaces the old one.
+    pub fn with_redefinition_warnings(mut self, enabled: bool) -> Self {
+        self.warn_on_redefinition = enabled;
+        self
+    }
+
+    /// Take the warnings collected so far, such as redefined macros and
+    /// unknown directives.
+    pub fn take_warnings(&mut self) -> Vec<Warning> {
+        std::mem::take(&mut self.warnings)
+    }
+
+    /// Take the map from the content returned by the last
+    /// [`process_file`](Self::process_file) back to the lines of its sources.
+    pub fn take_source_map(&mut self) -> SourceMap {
+        SourceMap::new(std::mem::take(&mut self.source_map))
+    }
+
+    /// Every file read so far: the one given to `process_file`, then the
+    /// files it included, each once.
+    pub fn processed_files(&self) -> &[PathBuf] {
+        &self.processed_files
+    }
+
     pub fn add_include_path<P: AsRef<Path>>(&mut self, path: P) {
         self.path_resolver.add_include_path(path);
     }
 
+    /// Resolve includes that start with an addon prefix (from `$PBOPREFIX$`)
+    /// into `dir`.
+    pub fn add_addon_prefix<P: AsRef<Path>>(&mut self, prefix: &str, dir: P) {
+        self.path_resolver.add_prefix(prefix, dir);
+    }
+
+    /// Serve `content` as the file at `path`, for it and for includes that
+    /// name it, without reading the disk; used for files packed in a PBO.
+    pub fn add_source<P: AsRef<Path>>(&mut self, path: P, content: String) {
+        self.path_resolver.add_in_memory_file(&path);
+        self.sources.insert(path.as_ref().to_path_buf(), content);
+    }
+
     pub fn process_file<P: AsRef<Path>>(&mut self, file_path: P) -> Result<String, Error> {
         let file_path = file_path.as_ref().to_path_buf();
-        
+        if self.current_depth > self.max_include_depth {
+            return Err(Error::IncludeError(
+                "Maximum include depth exceeded".to_string(),
+                file_path.display().to_string(),
+                Vec::new(),
+            ));
+        }
+
         // Check for circular includes
         if self.processed_files.contains(&file_path) {
             return Ok(String::new()); // Skip already processed files
         }
         self.processed_files.push(file_path.clone());
 
-        // Read the file as raw bytes first
-        let content = fs::read_to_string(&file_path)?;
-        self.process_content(&content, &file_path)
+        if self.source_stack.is_empty() {
+            self.source_map.clear();
+            self.output_offset = 0;
+        }
+        self.source_stack.push((file_path.clone(), 1));
+        self.current_depth += 1;
+        let result = self.read_and_process(&file_path);
+        self.current_depth -= 1;
+        self.source_stack.pop();
+        result
+    }
+
+    fn read_and_process(&mut self, file_path: &Path) -> Result<String, Error> {
+        if let Some(content) = self.sources.get(file_path).cloned() {
+            return self.process_content(&content, file_path);
+        }
+
+        #[cfg(feature = "mmap")]
+        if let Some(map) = self.map_large_file(file_path)? {
+            // Non-UTF-8 files fall through to `read_to_string` for its error
+            if let Ok(content) = std::str::from_utf8(&map) {
+                return self.process_content(content, file_path);
+            }
+        }
+
+        let content = fs::read_to_string(file_path)?;
+        self.process_content(&content, file_path)
+    }
+
+    #[cfg(feature = "mmap")]
+    fn map_large_file(&self, path: &Path) -> Result<Option<memmap2::Mmap>, Error> {
+        let file = fs::File::open(path)?;
+        if file.metadata()?.len() < self.mmap_threshold as u64 {
+            return Ok(None);
+        }
+        // Safety: the map is read-only and dropped before `process_file`
+        // returns; a file truncated by another process while mapped is not
+        // something config sources are expected to encounter.
+        let map = unsafe { memmap2::MmapOptions::new().map(&file)? };
+        Ok(Some(map))
     }
 
     fn process_content(&mut self, content: &str, source_file: &Path) -> Result<String, Error> {
+        let output_offset = self.output_offset;
+        let conditional_depth = self.condition_stack.len();
         let mut result = String::new();
         let mut current_line = String::new();
         let mut in_string = false;
         let mut in_comment = false;
         let mut in_multiline_comment = false;
         let mut escape_next = false;
+        let mut line_number = 1;
+        let mut line_start = 1;
 
         let mut chars = content.chars().peekable();
         while let Some(c) = chars.next() {
@@ -95,10 +502,14 @@ impl Preprocessor {
                         in_comment = false;
                     }
                     escape_next = false;
+                    line_number += 1;
                     
                     if !in_multiline_comment {
                         if !current_line.trim().is_empty() {
-                            if let Some(processed) = self.process_line(&current_line) {
+                            self.set_source_line(line_start);
+                            self.map_output(output_offset + result.len(), source_file, line_start);
+                            let location = SourceLocation::new(Some(source_file.to_path_buf()), line_start, 1);
+                            if let Some(processed) = self.process_line(&current_line, location)? {
                                 result.push_str(&processed);
                                 result.push('\n');
                             }
@@ -106,6 +517,7 @@ impl Preprocessor {
                             result.push('\n');
                         }
                         current_line.clear();
+                        line_start = line_number;
                     } else {
                         current_line.push('\n');
                     }
@@ -118,87 +530,292 @@ impl Preprocessor {
         }
 
         if !current_line.is_empty() {
-            if let Some(processed) = self.process_line(&current_line) {
+            self.set_source_line(line_start);
+            self.map_output(output_offset + result.len(), source_file, line_start);
+            let location = SourceLocation::new(Some(source_file.to_path_buf()), line_start, 1);
+            if let Some(processed) = self.process_line(&current_line, location)? {
                 result.push_str(&processed);
             }
         }
 
+        if self.condition_stack.len() > conditional_depth {
+            self.condition_stack.truncate(conditional_depth);
+            return Err(Error::PreprocessorError(format!(
+                "Unterminated conditional block in '{}'",
+                source_file.display()
+            )));
+        }
+
         Ok(result)
     }
 
-    fn process_line(&mut self, line: &str) -> Option<String> {
+    /// Record that output from here on comes from `line` of `file`. An
+    /// included file's content is emitted at the same offset, so it starts
+    /// from there too.
+    fn map_output(&mut self, offset: usize, file: &Path, line: usize) {
+        self.output_offset = offset;
+        self.source_map.push(SourceMapEntry {
+            processed_byte: offset,
+            original_file: file.to_path_buf(),
+            original_line: line,
+        });
+    }
+
+    fn set_source_line(&mut self, line: usize) {
+        if let Some((_, current)) = self.source_stack.last_mut() {
+            *current = line;
+        }
+    }
+
+    /// Value of the built-in `__FILE__` (the quoted path of the file being
+    /// processed) or `__LINE__` (the line the directive or text starts on).
+    fn builtin_macro(&self, word: &str) -> Option<String> {
+        let (file, line) = self.source_stack.last()?;
+        match word {
+            "__FILE__" => Some(format!("\"{}\"", file.display())),
+            "__LINE__" => Some(line.to_string()),
+            _ => None,
+        }
+    }
+
+    fn process_line(&mut self, line: &str, location: SourceLocation) -> Result<Option<String>, Error> {
         let trimmed = line.trim();
         
         if trimmed.is_empty() {
-            return None;
+            return Ok(None);
+        }
+
+        // Conditional directives are evaluated even inside inactive branches
+        // so that nested blocks stay balanced
+        if let Some(captures) = CONDITIONAL_PATTERN.captures(trimmed) {
+            let directive = captures.get(1).unwrap().as_str();
+            let argument = captures.get(2)
+                .map(|m| m.as_str().split("//").next().unwrap_or("").trim())
+                .unwrap_or("");
+            self.process_conditional(directive, argument)?;
+            return Ok(None);
         }
 
-        // Always preserve array properties without preprocessing them
-        if trimmed.contains("[]") {
-            return Some(line.to_string());
+        // Lines inside a false branch are dropped without further processing
+        if !self.is_active() {
+            return Ok(None);
         }
 
         if trimmed.starts_with('#') {
             if let Some(captures) = INCLUDE_PATTERN.captures(line) {
                 let include_path = captures.get(1).unwrap().as_str();
-                if let Ok(resolved_path) = self.path_resolver.resolve_include(include_path, Path::new("")) {
-                    if let Ok(included_content) = self.process_file(resolved_path) {
-                        return Some(included_content);
-                    }
+                let mapped = self.source_map.len();
+                // Relative includes start from the including file's directory
+                let source_file = self.source_stack.last().map(|(file, _)| file.clone()).unwrap_or_default();
+                match self.path_resolver.resolve_include(include_path, &source_file) {
+                    // Any failure inside the included file, from the depth
+                    // limit to a bad macro call, ends the whole chain
+                    Ok(resolved_path) => return self.process_file(resolved_path).map(Some),
+                    Err(Error::IncludeError(include, _, candidates)) => match self.include_policy {
+                        IncludePolicy::Error => return Err(Error::IncludeError(include, location.to_string(), candidates)),
+                        IncludePolicy::Warn => self.warnings.push(Warning::MissingInclude { include, candidates, location }),
+                        IncludePolicy::Stub => {}
+                    },
+                    Err(error) => return Err(error),
+                }
+                // Nothing of a failed include is emitted
+                self.source_map.truncate(mapped);
+                return Ok(None);
+            } else if let Some(captures) = FUNCTION_DEFINE_PATTERN.captures(line) {
+                let name = captures.get(1).unwrap().as_str();
+                let parameters = captures.get(2).unwrap().as_str();
+                let body = captures.get(3).map(|m| m.as_str().trim()).unwrap_or("");
+                let definition = MacroDefinition::new(parameters, body);
+                let unchanged = self.macros.get(name) == Some(&definition);
+                if self.warn_on_redefinition && self.is_defined(name) && !unchanged {
+                    self.warnings.push(Warning::MacroRedefined { name: name.to_string(), location });
                 }
-                return None;
+                self.defines.remove(name);
+                self.macros.insert(name.to_string(), definition);
+                return Ok(None);
             } else if let Some(captures) = DEFINE_PATTERN.captures(line) {
                 let name = captures.get(1).unwrap().as_str();
                 let value = captures.get(2).map(|m| m.as_str().trim()).unwrap_or("");
+                let unchanged = self.defines.get(name).is_some_and(|existing| existing == value);
+                if self.warn_on_redefinition && self.is_defined(name) && !unchanged {
+                    self.warnings.push(Warning::MacroRedefined { name: name.to_string(), location });
+                }
+                self.macros.remove(name);
                 self.defines.insert(name.to_string(), value.to_string());
-                return None;
+                return Ok(None);
+            } else if let Some(captures) = UNDEF_PATTERN.captures(line) {
+                let name = captures.get(1).unwrap().as_str();
+                self.defines.remove(name);
+                self.macros.remove(name);
+                return Ok(None);
             }
-            return None;
+
+            let directive = trimmed.trim_start_matches('#').trim_start()
+                .split(|c: char| !c.is_alphanumeric() && c != '_')
+                .next()
+                .unwrap_or("");
+            self.warnings.push(Warning::UnknownPreprocessorDirective {
+                directive: directive.to_string(),
+                location,
+            });
+            return Ok(None);
+        }
+
+        let (line, stripped) = strip_exec(line);
+        if stripped {
+            self.warnings.push(Warning::UnsupportedBuiltin { name: "__EXEC".to_string(), location });
+        }
+        Ok(Some(self.expand_macros(&line, 0)?))
+    }
+
+    /// Expand object-like and function-like macros in `text`.
+    ///
+    /// Macro arguments are fully expanded before they are substituted, which
+    /// mirrors the Arma preprocessor (and is what CBA style `GVAR`/`QUOTE`
+    /// chains rely on). Expansion results are rescanned until no macros remain
+    /// or `MAX_MACRO_DEPTH` is exceeded.
+    fn expand_macros(&self, text: &str, depth: usize) -> Result<String, Error> {
+        if depth > MAX_MACRO_DEPTH {
+            return Err(Error::MacroError(format!(
+                "Macro expansion exceeded maximum depth of {} while expanding '{}'",
+                MAX_MACRO_DEPTH,
+                text.trim()
+            )));
         }
 
-        // Process defines only when not in a string
-        let mut result = String::with_capacity(line.len());
+        let chars: Vec<char> = text.chars().collect();
+        let mut result = String::with_capacity(text.len());
         let mut in_string = false;
-        let mut escape_next = false;
-        
-        let chars: Vec<char> = line.chars().collect();
         let mut i = 0;
+
         while i < chars.len() {
-            match chars[i] {
-                '\\' if in_string => {
-                    escape_next = !escape_next;
-                    result.push(chars[i]);
-                }
-                '"' => {
-                    if !escape_next {
-                        in_string = !in_string;
-                    }
-                    result.push(chars[i]);
-                    escape_next = false;
-                }
-                c if !in_string => {
-                    let mut found_define = false;
-                    for (name, value) in &self.defines {
-                        if chars[i..].starts_with(&name.chars().collect::<Vec<_>>()) {
-                            result.push_str(value);
-                            i += name.len() - 1;
-                            found_define = true;
-                            break;
+            let c = chars[i];
+            if c == '"' {
+                in_string = !in_string;
+                result.push(c);
+                i += 1;
+                continue;
+            }
+            if in_string || !is_identifier_char(c) {
+                result.push(c);
+                i += 1;
+                continue;
+            }
+
+            let start = i;
+            while i < chars.len() && is_identifier_char(chars[i]) {
+                i += 1;
+            }
+            let word: String = chars[start..i].iter().collect();
+
+            // Words starting with a digit (numbers, "3DEN") are never macro names
+            if word.starts_with(|c: char| c.is_ascii_digit()) {
+                result.push_str(&word);
+                continue;
+            }
+
+            if let Some(value) = self.defines.get(&word) {
+                result.push_str(&self.expand_macros(value, depth + 1)?);
+            } else if let Some(definition) = self.macros.get(&word) {
+                match parse_macro_arguments(&chars, i) {
+                    Some((arguments, end)) => {
+                        let mut expanded_arguments = Vec::with_capacity(arguments.len());
+                        for argument in &arguments {
+                            expanded_arguments.push(self.expand_macros(argument.trim(), depth + 1)?);
                         }
+                        let body = definition.substitute(&word, &expanded_arguments)?;
+                        result.push_str(&self.expand_macros(&body, depth + 1)?);
+                        i = end;
                     }
-                    if !found_define {
-                        result.push(c);
-                    }
-                }
-                _ => {
-                    escape_next = false;
-                    result.push(chars[i]);
+                    // A function-like macro name without arguments is left alone
+                    None => result.push_str(&word),
                 }
+            } else if let Some((arguments, end)) = (word == "__EVAL").then(|| parse_macro_arguments(&chars, i)).flatten() {
+                let expression = self.expand_macros(&arguments.join(","), depth + 1)?;
+                let value = evaluate(&expression).ok_or_else(|| Error::MacroError(
+                    format!("Cannot evaluate __EVAL({})", expression.trim())
+                ))?;
+                result.push_str(&format_number(value));
+                i = end;
+            } else if let Some(value) = self.builtin_macro(&word) {
+                // After user macros, so `#define __FILE__ ...` overrides it
+                result.push_str(&value);
+            } else {
+                result.push_str(&word);
             }
-            i += 1;
         }
 
-        Some(result)
+        Ok(result)
+    }
+
+    /// Replace `defined(NAME)` and `defined NAME` with `1` or `0` so the rest
+    /// of an `#if` expression can be macro expanded safely.
+    fn resolve_defined_operators(&self, expression: &str) -> String {
+        DEFINED_PATTERN.replace_all(expression, |captures: &regex::Captures| {
+            let name = captures.get(1).or_else(|| captures.get(2)).unwrap().as_str();
+            if self.is_defined(name) { "1" } else { "0" }
+        }).into_owned()
+    }
+
+    fn is_defined(&self, name: &str) -> bool {
+        self.defines.contains_key(name) || self.macros.contains_key(name)
+    }
+
+    fn is_active(&self) -> bool {
+        self.condition_stack.last().is_none_or(|frame| frame.active)
+    }
+
+    fn process_conditional(&mut self, directive: &str, argument: &str) -> Result<(), Error> {
+        match directive {
+            "ifdef" | "ifndef" | "if" => {
+                let parent_active = self.is_active();
+                // Skip evaluation inside inactive branches; the expression may
+                // legitimately reference things that are not defined there
+                let condition = parent_active && self.evaluate_condition(directive, argument)?;
+                self.condition_stack.push(ConditionFrame {
+                    active: condition,
+                    branch_taken: condition,
+                    parent_active,
+                });
+            }
+            "elif" => {
+                let frame = *self.condition_stack.last()
+                    .ok_or_else(|| Error::PreprocessorError("#elif without matching #if".to_string()))?;
+                let condition = frame.parent_active
+                    && !frame.branch_taken
+                    && self.evaluate_condition("if", argument)?;
+                let frame = self.condition_stack.last_mut().unwrap();
+                frame.active = condition;
+                frame.branch_taken |= condition;
+            }
+            "else" => {
+                let frame = self.condition_stack.last_mut()
+                    .ok_or_else(|| Error::PreprocessorError("#else without matching #if".to_string()))?;
+                frame.active = frame.parent_active && !frame.branch_taken;
+                frame.branch_taken = true;
+            }
+            "endif" => {
+                self.condition_stack.pop()
+                    .ok_or_else(|| Error::PreprocessorError("#endif without matching #if".to_string()))?;
+            }
+            _ => unreachable!("CONDITIONAL_PATTERN only matches known directives"),
+        }
+        Ok(())
+    }
+
+    fn evaluate_condition(&self, directive: &str, argument: &str) -> Result<bool, Error> {
+        match directive {
+            "ifdef" | "ifndef" => {
+                let name = argument.split_whitespace().next().ok_or_else(|| {
+                    Error::PreprocessorError(format!("#{} requires a macro name", directive))
+                })?;
+                Ok(self.is_defined(name) == (directive == "ifdef"))
+            }
+            _ => {
+                let expression = self.expand_macros(&self.resolve_defined_operators(argument), 0)?;
+                Ok(ExpressionEvaluator::evaluate(&expression, NumericMode::Integer, &self.defines)? != 0.0)
+            }
+        }
     }
 }
 
@@ -258,6 +875,49 @@ mod tests {
         // Clean up is automatic when temp_dir is dropped
     }
 
+    #[test]
+    fn test_mmap_threshold() {
+        let (_temp_dir, base_path) = setup_test_files();
+        let main_file = base_path.join("main.txt");
+
+        let read = Preprocessor::new(&base_path).process_file(&main_file).unwrap();
+        let mapped = Preprocessor::new(&base_path)
+            .with_mmap_threshold(0)
+            .process_file(&main_file)
+            .unwrap();
+        assert_eq!(mapped, read);
+    }
+
+    /// Compares mapped and buffered reads of a synthetic 5 MB config. Run
+    /// with `cargo test --release --features mmap -- --ignored --nocapture`.
+    #[test]
+    #[ignore]
+    fn bench_large_file_mmap() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("config.cpp");
+        let mut content = String::from("class CfgWeapons {\n");
+        let mut i = 0;
+        while content.len() < 5 * 1024 * 1024 {
+            content.push_str(&format!("    class Weapon_{} {{ displayName = \"Weapon {}\"; mass = {}; }};\n", i, i, i % 100));
+            i += 1;
+        }
+        content.push_str("};\n");
+        fs::write(&path, &content).unwrap();
+
+        let time = |threshold: usize| {
+            let start = std::time::Instant::now();
+            let output = Preprocessor::new(temp_dir.path())
+                .with_mmap_threshold(threshold)
+                .process_file(&path)
+                .unwrap();
+            (start.elapsed(), output.len())
+        };
+        let (buffered, buffered_len) = time(usize::MAX);
+        let (mapped, mapped_len) = time(0);
+        assert_eq!(mapped_len, buffered_len);
+        println!("5 MB config: read_to_string {:?}, mmap {:?}", buffered, mapped);
+    }
+
     #[test]
     fn test_circular_include() {
         let temp_dir = TempDir::new().unwrap();
@@ -306,6 +966,113 @@ mod tests {
         assert!(result.contains("class Main"));
     }
 
+    #[test]
+    fn test_include_ignores_case_and_backslashes() {
+        let temp_dir = TempDir::new().unwrap();
+        let base_path = temp_dir.path().to_path_buf();
+        let addon_dir = base_path.join("addon");
+        fs::create_dir_all(addon_dir.join("ui_f/hpp")).unwrap();
+        File::create(addon_dir.join("ui_f/hpp/defineDIKCodes.inc")).unwrap()
+            .write_all(b"#define DIK_ESCAPE 1").unwrap();
+
+        // Resolved relative to the including file, not the base path
+        File::create(addon_dir.join("config.cpp")).unwrap()
+            .write_all(b"#include \"UI_F\\HPP\\DefineDikCodes.INC\"\nkey = DIK_ESCAPE;").unwrap();
+
+        let mut preprocessor = Preprocessor::new(&base_path);
+        let result = preprocessor.process_file(addon_dir.join("config.cpp")).unwrap();
+        assert!(result.contains("key = 1;"), "{}", result);
+    }
+
+    #[test]
+    fn test_max_include_depth() {
+        let temp_dir = TempDir::new().unwrap();
+        let base_path = temp_dir.path().to_path_buf();
+        // level0.hpp includes level1.hpp, ... up to 65 nested includes
+        for level in 0..=65 {
+            let content = if level < 65 {
+                format!("#include \"level{}.hpp\"\nclass Level{} {{}};", level + 1, level)
+            } else {
+                "class Deepest {};".to_string()
+            };
+            fs::write(base_path.join(format!("level{}.hpp", level)), content).unwrap();
+        }
+
+        let mut preprocessor = Preprocessor::new(&base_path);
+        match preprocessor.process_file(base_path.join("level0.hpp")) {
+            Err(Error::IncludeError(message, file, _)) => {
+                assert_eq!(message, "Maximum include depth exceeded");
+                assert!(file.ends_with("level65.hpp"), "{}", file);
+            }
+            other => panic!("expected an include error, got {:?}", other),
+        }
+        assert!(preprocessor.source_stack.is_empty());
+
+        // 64 nested includes are fine, and so is a higher limit
+        let mut preprocessor = Preprocessor::new(&base_path);
+        assert!(preprocessor.process_file(base_path.join("level1.hpp")).unwrap().contains("class Deepest"));
+        let mut preprocessor = Preprocessor::new(&base_path).with_max_include_depth(65);
+        assert!(preprocessor.process_file(base_path.join("level0.hpp")).unwrap().contains("class Deepest"));
+    }
+
+    #[test]
+    fn test_failing_include_is_an_error() {
+        let temp_dir = TempDir::new().unwrap();
+        let base_path = temp_dir.path().to_path_buf();
+        fs::write(base_path.join("h.hpp"), "F(1)\n").unwrap();
+        fs::write(
+            base_path.join("main.cpp"),
+            "#define F(a, b) a b\n#include \"h.hpp\"\nclass Main {};\n",
+        ).unwrap();
+
+        for policy in [IncludePolicy::Error, IncludePolicy::Warn, IncludePolicy::Stub] {
+            let mut preprocessor = Preprocessor::new(&base_path).with_include_policy(policy);
+            let result = preprocessor.process_file(base_path.join("main.cpp"));
+            assert!(matches!(result, Err(Error::MacroError(_))), "{:?}", result);
+        }
+    }
+
+    #[test]
+    fn test_include_policy() {
+        let temp_dir = TempDir::new().unwrap();
+        let base_path = temp_dir.path().to_path_buf();
+        let config = base_path.join("config.cpp");
+        fs::write(&config, "class A {};\n#include \"\\x\\missing.hpp\"\nclass B {};").unwrap();
+        let location = SourceLocation::new(Some(config.clone()), 2, 1);
+        let candidates = vec![base_path.join("x/missing.hpp")];
+
+        let mut preprocessor = Preprocessor::new(&base_path).with_include_policy(IncludePolicy::Stub);
+        let result = preprocessor.process_file(&config).unwrap();
+        assert!(result.contains("class A") && result.contains("class B"));
+        assert!(preprocessor.take_warnings().is_empty());
+
+        let mut preprocessor = Preprocessor::new(&base_path);
+        let result = preprocessor.process_file(&config).unwrap();
+        assert!(result.contains("class A") && result.contains("class B"));
+        assert_eq!(preprocessor.take_warnings(), vec![Warning::MissingInclude {
+            include: r"\x\missing.hpp".to_string(),
+            candidates: candidates.clone(),
+            location: location.clone(),
+        }]);
+
+        // Also when the missing include is nested in an included file
+        let outer = base_path.join("outer.cpp");
+        fs::write(&outer, "#include \"config.cpp\"").unwrap();
+        for file in [&config, &outer] {
+            let mut preprocessor = Preprocessor::new(&base_path).with_include_policy(IncludePolicy::Error);
+            match preprocessor.process_file(file) {
+                Err(error @ Error::IncludeError(..)) => {
+                    let Error::IncludeError(include, from, tried) = &error else { unreachable!() };
+                    assert_eq!(include, r"\x\missing.hpp");
+                    assert_eq!(from, &location.to_string());
+                    assert_eq!(tried, &candidates);
+                    assert!(error.to_string().contains("missing.hpp' from '"), "{}", error);
+                }
+                other => panic!("expected an include error, got {:?}", other),
+            }
+        }
+    }
+
     #[test]
     fn test_define_replacement() {
         let temp_dir = TempDir::new().unwrap();
@@ -361,4 +1128,420 @@ mod tests {
         assert!(!result.contains("_ARMA_"));
         assert!(result.contains("class Test"));
     }
-}
\ No newline at end of file
+
+    fn preprocess(content: &str) -> Result<String, Error> {
+        let temp_dir = TempDir::new().unwrap();
+        let base_path = temp_dir.path().to_path_buf();
+        File::create(base_path.join("test.txt")).unwrap()
+            .write_all(content.as_bytes()).unwrap();
+
+        let mut preprocessor = Preprocessor::new(&base_path);
+        preprocessor.process_file(base_path.join("test.txt"))
+    }
+
+    #[test]
+    fn test_ifdef_else_endif() {
+        let content = r#"
+            #define _ARMA_
+            #ifdef _ARMA_
+            class Defined {};
+            #else
+            class NotDefined {};
+            #endif
+            #ifndef _ARMA_
+            class Inverse {};
+            #else
+            class InverseElse {};
+            #endif
+        "#;
+
+        let result = preprocess(content).unwrap();
+        assert!(result.contains("class Defined"));
+        assert!(!result.contains("class NotDefined"));
+        assert!(!result.contains("class Inverse "));
+        assert!(result.contains("class InverseElse"));
+        assert!(!result.contains("#ifdef"));
+        assert!(!result.contains("#endif"));
+    }
+
+    #[test]
+    fn test_nested_conditionals() {
+        let content = r#"
+            #define OUTER
+            #ifdef MISSING
+                #ifdef OUTER
+                class HiddenByParent {};
+                #else
+                class AlsoHidden {};
+                #endif
+                #define FROM_DEAD_BRANCH 1
+            #else
+                #ifdef OUTER
+                class Visible {};
+                #endif
+            #endif
+            #ifdef FROM_DEAD_BRANCH
+            class ShouldNotExist {};
+            #endif
+        "#;
+
+        let result = preprocess(content).unwrap();
+        assert!(result.contains("class Visible"));
+        assert!(!result.contains("HiddenByParent"));
+        assert!(!result.contains("AlsoHidden"));
+        assert!(!result.contains("ShouldNotExist"));
+    }
+
+    #[test]
+    fn test_if_elif_expressions() {
+        let content = r#"
+            #define VERSION 3
+            #if VERSION > 4
+            class Future {};
+            #elif VERSION == 3
+            class Current {};
+            #elif VERSION > 1
+            class Older {};
+            #else
+            class Ancient {};
+            #endif
+            #if defined(VERSION) && !defined(DEBUG)
+            class Release {};
+            #endif
+            #if 0
+            items[] = {"hidden"};
+            #endif
+        "#;
+
+        let result = preprocess(content).unwrap();
+        assert!(result.contains("class Current"));
+        assert!(!result.contains("class Future"));
+        assert!(!result.contains("class Older"));
+        assert!(!result.contains("class Ancient"));
+        assert!(result.contains("class Release"));
+        assert!(!result.contains("hidden"));
+    }
+
+    #[test]
+    fn test_unbalanced_conditionals() {
+        assert!(matches!(preprocess("#endif\n"), Err(Error::PreprocessorError(_))));
+        assert!(matches!(preprocess("#else\n"), Err(Error::PreprocessorError(_))));
+        assert!(matches!(preprocess("#ifdef FOO\nclass A {};\n"), Err(Error::PreprocessorError(_))));
+        assert!(matches!(preprocess("#if (1\n#endif\n"), Err(Error::MacroError(_))));
+    }
+
+    #[test]
+    fn test_warnings() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("config.cpp");
+        File::create(&path).unwrap().write_all(b"#define A 1
+#define A 1
+/* a
+   comment */
+#define A 2
+#define F(x) x
+#pragma once
+#ifdef MISSING
+#bogus
+#endif
+value = A;
+").unwrap();
+
+        let mut preprocessor = Preprocessor::new(temp_dir.path());
+        let result = preprocessor.process_file(&path).unwrap();
+        assert!(result.contains("value = 2;"));
+
+        let location = |line| SourceLocation::new(Some(path.clone()), line, 1);
+        assert_eq!(preprocessor.take_warnings(), vec![
+            Warning::MacroRedefined { name: "A".to_string(), location: location(5) },
+            Warning::UnknownPreprocessorDirective { directive: "pragma".to_string(), location: location(7) },
+        ]);
+        assert!(preprocessor.take_warnings().is_empty());
+    }
+
+    #[test]
+    fn test_function_like_macros() {
+        let content = r#"
+            #define DOUBLES(var1,var2) var1##_##var2
+            #define TRIPLES(var1,var2,var3) var1##_##var2##_##var3
+            #define QUOTE(var1) #var1
+            #define ARR_2(a,b) {a, b}
+            value = TRIPLES(a, b, c);
+            name = QUOTE(some text);
+            pair[] = ARR_2(DOUBLES(x, y), (1 + 2));
+            path = "DOUBLES(x, y)";
+        "#;
+
+        let result = preprocess(content).unwrap();
+        assert!(result.contains("value = a_b_c;"));
+        assert!(result.contains(r#"name = "some text";"#));
+        assert!(result.contains("pair[] = {x_y, (1 + 2)};"));
+        // Macro names inside string literals are not expanded
+        assert!(result.contains(r#"path = "DOUBLES(x, y)";"#));
+    }
+
+    #[test]
+    fn test_cba_macro_chain() {
+        let content = r#"
+            #define PREFIX pca
+            #define COMPONENT main
+            #define DOUBLES(var1,var2) var1##_##var2
+            #define QUOTE(var1) #var1
+            #define ADDON DOUBLES(PREFIX,COMPONENT)
+            #define GVAR(var1) DOUBLES(ADDON,var1)
+            #define QGVAR(var1) QUOTE(GVAR(var1))
+            class CfgWeapons {
+                class GVAR(vest) {
+                    displayName = QGVAR(vest);
+                    items[] = {QUOTE(ADDON), GVAR(item)};
+                };
+            };
+        "#;
+
+        let result = preprocess(content).unwrap();
+        assert!(result.contains("class pca_main_vest"));
+        assert!(result.contains(r#"displayName = "pca_main_vest";"#));
+
+        let tokens = crate::lexer::Tokenizer::new(&result).tokenize().unwrap();
+        let root = crate::parser::Parser::new(tokens).parse().unwrap();
+        let vest = &root.nested_classes[0].nested_classes[0];
+        assert_eq!(vest.name, "pca_main_vest");
+        assert_eq!(vest.properties["displayName"].raw_value, "pca_main_vest");
+        assert_eq!(vest.properties["items"].array_values, vec!["pca_main", "pca_main_item"]);
+    }
+
+    #[test]
+    fn test_macro_recursion_limit() {
+        let content = r#"
+            #define LOOP(x) LOOP(x)
+            value = LOOP(1);
+        "#;
+        assert!(matches!(preprocess(content), Err(Error::MacroError(_))));
+
+        let content = r#"
+            #define PING PONG
+            #define PONG PING
+            value = PING;
+        "#;
+        assert!(matches!(preprocess(content), Err(Error::MacroError(_))));
+    }
+
+    #[test]
+    fn test_macro_argument_count_mismatch() {
+        let content = r#"
+            #define DOUBLES(var1,var2) var1##_##var2
+            value = DOUBLES(a);
+        "#;
+        assert!(matches!(preprocess(content), Err(Error::MacroError(_))));
+    }
+
+    #[test]
+    fn test_function_macro_in_conditional() {
+        let content = r#"
+            #define VERSION_AT_LEAST(major) (3 >= major)
+            #if VERSION_AT_LEAST(2) && defined(VERSION_AT_LEAST)
+            class Supported {};
+            #endif
+            #ifdef VERSION_AT_LEAST
+            class Defined {};
+            #endif
+        "#;
+
+        let result = preprocess(content).unwrap();
+        assert!(result.contains("class Supported"));
+        assert!(result.contains("class Defined"));
+    }
+
+    #[test]
+    fn test_zero_argument_macros() {
+        let content = r#"
+            #define EMPTY() nothing
+            #define COUNT() 3
+            value = EMPTY();
+            count = COUNT ( );
+        "#;
+
+        let result = preprocess(content).unwrap();
+        assert!(result.contains("value = nothing;"));
+        assert!(result.contains("count = 3;"));
+
+        let content = r#"
+            #define EMPTY() nothing
+            value = EMPTY(1);
+        "#;
+        assert!(matches!(preprocess(content), Err(Error::MacroError(_))));
+    }
+
+    #[test]
+    fn test_variadic_macros() {
+        let content = r#"
+            #define LIST(name, ...) name[] = {__VA_ARGS__}
+            #define WRAP(...) (__VA_ARGS__)
+            LIST(items, "a", "b", "c");
+            LIST(empty);
+            value = WRAP();
+        "#;
+
+        let result = preprocess(content).unwrap();
+        assert!(result.contains(r#"items[] = {"a", "b", "c"};"#));
+        assert!(result.contains("empty[] = {};"));
+        assert!(result.contains("value = ();"));
+    }
+
+    #[test]
+    fn test_macro_arguments_with_strings_and_parens() {
+        let content = r#"
+            #define EGVAR(module,var) module##_fnc_##var
+            #define PAIR(a,b) a | b
+            script = EGVAR(ace,medical);
+            first = PAIR("x, y", f(1, 2));
+            second = PAIR("(", ")");
+        "#;
+
+        let result = preprocess(content).unwrap();
+        assert!(result.contains("script = ace_fnc_medical;"));
+        assert!(result.contains(r#"first = "x, y" | f(1, 2);"#));
+        assert!(result.contains(r#"second = "(" | ")";"#));
+    }
+
+    #[test]
+    fn test_undef_and_redefine() {
+        let content = r#"
+            #define VERSION "1.0"
+            first = VERSION;
+            #undef VERSION
+            second = VERSION;
+            #define VERSION "2.0"
+            third = VERSION;
+            #define GVAR(var) old_##var
+            #undef GVAR
+            #define GVAR(var) new_##var
+            name = GVAR(thing);
+            #undef NEVER_DEFINED
+        "#;
+
+        let result = preprocess(content).unwrap();
+        assert!(result.contains(r#"first = "1.0";"#));
+        assert!(result.contains("second = VERSION;"));
+        assert!(result.contains(r#"third = "2.0";"#));
+        assert!(result.contains("name = new_thing;"));
+        assert!(!result.contains("#undef"));
+    }
+
+    #[test]
+    fn test_redefinition_warnings_optional() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("config.cpp");
+        fs::write(&path, "#define SIZE 5\n#define SIZE 6\n#define F(x) x\n#define F(x) (x)\nsize = SIZE;").unwrap();
+
+        let mut preprocessor = Preprocessor::new(temp_dir.path());
+        preprocessor.process_file(&path).unwrap();
+        assert_eq!(preprocessor.take_warnings().len(), 2);
+
+        let mut preprocessor = Preprocessor::new(temp_dir.path()).with_redefinition_warnings(false);
+        assert!(preprocessor.process_file(&path).unwrap().contains("size = 6;"));
+        assert!(preprocessor.take_warnings().is_empty());
+    }
+
+    #[test]
+    fn test_defines_replace_whole_identifiers() {
+        let content = r#"
+            #define SIZE 5
+            #define MAX(a, b) ((a) > (b))
+            size = SIZE;
+            maxSize = MAXSIZE + SIZE_2 + _SIZE + SIZE5;
+            sum = SIZE+SIZE*SIZE;
+            label = "SIZE";
+            test = MAX(SIZE, 1) + MAXIMUM(1, 2);
+            #undef SIZE
+            after = SIZE;
+        "#;
+
+        let result = preprocess(content).unwrap();
+        assert!(result.contains("size = 5;"));
+        assert!(result.contains("maxSize = MAXSIZE + SIZE_2 + _SIZE + SIZE5;"));
+        assert!(result.contains("sum = 5+5*5;"));
+        assert!(result.contains(r#"label = "SIZE";"#));
+        assert!(result.contains("test = ((5) > (1)) + MAXIMUM(1, 2);"), "{}", result);
+        assert!(result.contains("after = SIZE;"));
+    }
+
+    #[test]
+    fn test_undef_in_inactive_branch() {
+        let content = r#"
+            #define KEEP 1
+            #ifdef MISSING
+            #undef KEEP
+            #endif
+            #ifdef KEEP
+            class StillDefined {};
+            #endif
+        "#;
+
+        let result = preprocess(content).unwrap();
+        assert!(result.contains("class StillDefined"));
+    }
+
+    #[test]
+    fn test_file_and_line_builtins() {
+        let temp_dir = TempDir::new().unwrap();
+        let base_path = temp_dir.path().to_path_buf();
+        fs::write(base_path.join("trace.hpp"), "\ninc_file = __FILE__; inc_line = __LINE__;\n").unwrap();
+        fs::write(base_path.join("main.cpp"), [
+            "#define WHERE __FILE__ + \":\" + str __LINE__",
+            "main_file = __FILE__;",
+            "#include \"trace.hpp\"",
+            "after = WHERE;",
+            "#define __LINE__ 42",
+            "custom = __LINE__;",
+        ].join("\n")).unwrap();
+
+        let mut preprocessor = Preprocessor::new(&base_path);
+        let result = preprocessor.process_file(base_path.join("main.cpp")).unwrap();
+
+        let main = base_path.join("main.cpp").display().to_string();
+        let header = base_path.join("trace.hpp").display().to_string();
+        assert!(result.contains(&format!("main_file = \"{}\";", main)), "{}", result);
+        assert!(result.contains(&format!("inc_file = \"{}\"; inc_line = 2;", header)), "{}", result);
+        assert!(result.contains(&format!("after = \"{}\" + \":\" + str 4;", main)), "{}", result);
+        assert!(result.contains("custom = 42;"));
+        assert!(preprocessor.source_stack.is_empty());
+
+        // Outside of any file the names are left alone
+        assert_eq!(Preprocessor::new(&base_path).expand_macros("__FILE__", 0).unwrap(), "__FILE__");
+    }
+
+    #[test]
+    fn test_eval_and_exec_builtins() {
+        let content = [
+            "#define COUNT 4",
+            "#define DOUBLE(x) __EVAL(x * 2)",
+            "__EXEC(testVar = 1)",
+            "total = __EVAL(COUNT * 3 + 1);",
+            "items[] = {__EVAL(10 / 2), DOUBLE(COUNT), \"__EVAL(1)\"};",
+            "rest = 1; __EXEC(a = (1 + 2)) other = __EVAL(2 - (3 - 1));",
+        ].join("\n");
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("config.cpp");
+        fs::write(&path, content).unwrap();
+
+        let mut preprocessor = Preprocessor::new(temp_dir.path());
+        let result = preprocessor.process_file(&path).unwrap();
+        assert!(result.contains("total = 13;"), "{}", result);
+        assert!(result.contains("items[] = {5, 8, \"__EVAL(1)\"};"), "{}", result);
+        assert!(result.contains("rest = 1;  other = 0;"), "{}", result);
+        assert!(!result.contains("__EXEC"));
+        let lines: Vec<usize> = preprocessor.take_warnings().into_iter().map(|warning| match warning {
+            Warning::UnsupportedBuiltin { name, location } if name == "__EXEC" => location.line,
+            other => panic!("unexpected warning {:?}", other),
+        }).collect();
+        assert_eq!(lines, vec![3, 6]);
+
+        assert!(matches!(preprocess("x = __EVAL(1 +);"), Err(Error::MacroError(_))));
+
+        // Fractions are kept rather than truncated
+        assert_eq!(preprocess("x = __EVAL(2.5*2); y = __EVAL(1/3); z = __EVAL(0.1+0.2);").unwrap().trim(),
+            "x = 5; y = 0.333333333333; z = 0.3;");
+        assert!(matches!(preprocess("x = __EVAL(1/0);"), Err(Error::MacroError(_))));
+    }
+}
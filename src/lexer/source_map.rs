@@ -0,0 +1,105 @@
+use std::path::PathBuf;
+use crate::error::SourceLocation;
+
+/// Start of a stretch of preprocessed content and the source line it was
+/// produced from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceMapEntry {
+    /// Byte offset in the preprocessed content.
+    pub processed_byte: usize,
+    pub original_file: PathBuf,
+    pub original_line: usize,
+}
+
+/// Maps positions in preprocessed content back to the file and line they
+/// came from, so errors can point into included files. Built by
+/// [`Preprocessor`](super::Preprocessor) with one entry per emitted source
+/// line, sorted by `processed_byte`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SourceMap {
+    entries: Vec<SourceMapEntry>,
+}
+
+impl SourceMap {
+    pub fn new(mut entries: Vec<SourceMapEntry>) -> Self {
+        // Stable, so an included file's first line still follows the
+        // `#include` line emitted at the same offset
+        entries.sort_by_key(|entry| entry.processed_byte);
+        Self { entries }
+    }
+
+    pub fn entries(&self) -> &[SourceMapEntry] {
+        &self.entries
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// File and line of the last entry starting at or before `offset`. The
+    /// column is not tracked and left at 0; an offset before the first entry
+    /// gives [`SourceLocation::unknown`].
+    pub fn lookup(&self, offset: usize) -> SourceLocation {
+        let index = self.entries.partition_point(|entry| entry.processed_byte <= offset);
+        match index.checked_sub(1).map(|index| &self.entries[index]) {
+            Some(entry) => SourceLocation::new(Some(entry.original_file.clone()), entry.original_line, 0),
+            None => SourceLocation::unknown(),
+        }
+    }
+
+    /// Rewrite `location`, a line and column in `content` (the preprocessed
+    /// text this map was built for), to the original file and line. Lines
+    /// after the start of an entry count on from its line, which keeps
+    /// multi-line comments and strings accurate. The column is kept.
+    pub fn remap(&self, content: &str, location: &mut SourceLocation) {
+        let Some(offset) = line_offset(content, location.line) else {
+            return;
+        };
+        let index = self.entries.partition_point(|entry| entry.processed_byte <= offset);
+        let Some(entry) = index.checked_sub(1).map(|index| &self.entries[index]) else {
+            return;
+        };
+        let lines_after = content.get(entry.processed_byte..offset)
+            .map_or(0, |text| text.matches('\n').count());
+        location.file = Some(entry.original_file.clone());
+        location.line = entry.original_line + lines_after;
+    }
+}
+
+/// Byte offset of the start of 1-based `line` in `content`.
+fn line_offset(content: &str, line: usize) -> Option<usize> {
+    match line {
+        0 => None,
+        1 => Some(0),
+        _ => content.match_indices('\n').nth(line - 2).map(|(index, _)| index + 1),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_source_map_lookup() {
+        let entry = |processed_byte, file: &str, original_line| SourceMapEntry {
+            processed_byte,
+            original_file: PathBuf::from(file),
+            original_line,
+        };
+        let content = "a = 1;\n/* x\ny */ b = 2;\nc = 3;\n";
+        let map = SourceMap::new(vec![entry(24, "main.cpp", 3), entry(0, "main.cpp", 1), entry(7, "inc.hpp", 4)]);
+
+        assert_eq!(map.lookup(0).line, 1);
+        assert_eq!(map.lookup(9), SourceLocation::new(Some(PathBuf::from("inc.hpp")), 4, 0));
+        assert_eq!(map.lookup(100).line, 3);
+        assert_eq!(SourceMap::default().lookup(5), SourceLocation::unknown());
+
+        let mut location = SourceLocation::new(None, 3, 6);
+        map.remap(content, &mut location);
+        assert_eq!(location, SourceLocation::new(Some(PathBuf::from("inc.hpp")), 5, 6));
+
+        let mut past_end = SourceLocation::new(None, 40, 1);
+        map.remap(content, &mut past_end);
+        assert_eq!(past_end.file, None);
+    }
+}
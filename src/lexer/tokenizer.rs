@@ -8,8 +8,14 @@ pub struct Tokenizer<'a> {
     input: Peekable<Chars<'a>>,
     line: usize,
     column: usize,
+    offset: usize,
     file_path: Option<PathBuf>,
     preserve_comments: bool,
+    /// Whether the last token ends an operand, making a following `-` a
+    /// subtraction rather than the sign of a number.
+    after_operand: bool,
+    /// Set once the input is exhausted or an error was returned.
+    finished: bool,
 }
 
 impl<'a> Tokenizer<'a> {
@@ -18,8 +24,11 @@ impl<'a> Tokenizer<'a> {
             input: input.chars().peekable(),
             line: 1,
             column: 0,
+            offset: 0,
             file_path: None,
             preserve_comments: false,
+            after_operand: false,
+            finished: false,
         }
     }
 
@@ -28,8 +37,11 @@ impl<'a> Tokenizer<'a> {
             input: input.chars().peekable(),
             line: 1,
             column: 0,
+            offset: 0,
             file_path: Some(file_path.into()),
             preserve_comments: false,
+            after_operand: false,
+            finished: false,
         }
     }
 
@@ -38,80 +50,108 @@ impl<'a> Tokenizer<'a> {
         self
     }
 
+    /// All remaining tokens. Iterating the tokenizer yields the same tokens
+    /// one at a time without collecting them.
     pub fn tokenize(&mut self) -> Result<Vec<Token>, Error> {
-        let mut tokens = Vec::new();
-        
-        while let Some(token) = self.next_token()? {
-            tokens.push(token);
-        }
-        
-        Ok(tokens)
+        self.collect()
     }
 
     fn next_token(&mut self) -> Result<Option<Token>, Error> {
-        self.skip_whitespace();
-
-        match self.peek() {
-            None => Ok(None),
-            Some(c) => {
-                let column = self.column; // Store column before advancing
-                let token = match c {
-                    '{' => self.single_char_token(TokenType::LeftBrace),
-                    '}' => self.single_char_token(TokenType::RightBrace),
-                    '[' => {
-                        self.advance();
-                        if self.match_char(']') {
-                            Token::new(TokenType::ArrayMarker, self.line, column)
-                        } else {
-                            Token::new(TokenType::LeftBracket, self.line, column)
-                        }
-                    },
-                    ']' => self.single_char_token(TokenType::RightBracket),
-                    ';' => self.single_char_token(TokenType::Semicolon),
-                    ':' => self.single_char_token(TokenType::Colon),
-                    ',' => self.single_char_token(TokenType::Comma),
-                    '=' => self.handle_equals()?,
-                    '+' => self.handle_plus()?,
-                    '-' => {
-                        if matches!(self.peek_next(), Some('0'..='9')) {
-                            self.read_number()?
-                        } else {
-                            self.handle_minus()?
-                        }
-                    },
-                    '"' => self.read_string()?,
-                    '/' => {
-                        if self.peek_next() == Some('/') {
-                            self.read_line_comment()?
-                        } else if self.peek_next() == Some('*') {
-                            self.read_block_comment()?
-                        } else {
-                            return Err(self.error("Unexpected '/' character"));
-                        }
-                    },
-                    '#' => {
-                        if matches!(self.peek_next(), Some('(')) {
-                            self.read_argb_color()?
-                        } else {
-                            self.read_preprocessor_directive()?
-                        }
-                    },
-                    '\\' => self.read_identifier(), // Treat backslash as part of an identifier for texture paths
-                    c if c.is_ascii_digit() => {
-                        if self.is_part_of_identifier() {
-                            self.read_identifier()
-                        } else {
-                            self.read_number()?
-                        }
-                    },
-                    c if c.is_ascii_alphabetic() || c == '_' => self.read_identifier(),
-                    _ => return Err(self.error(&format!("Unexpected character: {}", c))),
-                };
-                Ok(Some(token))
+        // A skipped comment goes round again, so offsets start at the token
+        loop {
+            let before_whitespace = self.offset;
+            self.skip_whitespace();
+            let spaced = self.offset > before_whitespace;
+
+            match self.peek() {
+                None => return Ok(None),
+                Some(c) => {
+                    let column = self.column; // Store column before advancing
+                    let start = self.offset;
+                    let token = match c {
+                        '{' => self.single_char_token(TokenType::LeftBrace),
+                        '}' => self.single_char_token(TokenType::RightBrace),
+                        '[' => {
+                            self.advance();
+                            if self.match_char(']') {
+                                Token::new(TokenType::ArrayMarker, self.line, column)
+                            } else {
+                                Token::new(TokenType::LeftBracket, self.line, column)
+                            }
+                        },
+                        ']' => self.single_char_token(TokenType::RightBracket),
+                        ';' => self.single_char_token(TokenType::Semicolon),
+                        ':' => self.single_char_token(TokenType::Colon),
+                        ',' => self.single_char_token(TokenType::Comma),
+                        '=' => self.handle_equals()?,
+                        // `2-3` and `2 -3` subtract, while `{2, -3}` holds a signed number
+                        '+' | '-' if !self.after_operand && self.sign_starts_number() => self.read_number()?,
+                        '+' => self.handle_plus(),
+                        '&' => self.handle_ampersand()?,
+                        '*' => self.single_char_token(TokenType::Star),
+                        '(' => self.single_char_token(TokenType::LeftParen),
+                        ')' => self.single_char_token(TokenType::RightParen),
+                        '-' => self.handle_minus(),
+                        '.' if (!self.after_operand || spaced) && matches!(self.peek_next(), Some('0'..='9')) => self.read_number()?,
+                        '"' => self.read_string()?,
+                        '/' => {
+                            let comment = if self.peek_next() == Some('/') {
+                                self.read_line_comment()
+                            } else if self.peek_next() == Some('*') {
+                                self.read_block_comment()?
+                            } else {
+                                Some(self.single_char_token(TokenType::Slash))
+                            };
+                            match comment {
+                                Some(token) => token,
+                                None => continue,
+                            }
+                        },
+                        '#' => {
+                            if matches!(self.peek_next(), Some('(')) {
+                                self.read_argb_color()?
+                            } else {
+                                self.read_preprocessor_directive()?
+                            }
+                        },
+                        '\\' => self.read_identifier(), // Treat backslash as part of an identifier for texture paths
+                        c if c.is_ascii_digit() => {
+                            if self.is_radix_prefix() {
+                                self.read_number()?
+                            } else if self.is_part_of_identifier() {
+                                self.read_identifier()
+                            } else {
+                                self.read_number()?
+                            }
+                        },
+                        c if c.is_ascii_alphabetic() || c == '_' => self.read_identifier(),
+                        _ => return Err(self.error(&format!("Unexpected character: {}", c))),
+                    };
+                    self.after_operand = matches!(
+                        token.token_type,
+                        TokenType::NumberLiteral(_) | TokenType::Identifier(_) | TokenType::RightParen
+                    );
+                    return Ok(Some(token.with_offsets(start, self.offset)));
+                }
             }
         }
     }
     
+    /// Whether the `+` or `-` at the current position is followed by a
+    /// number such as `5` or `.5`.
+    fn sign_starts_number(&self) -> bool {
+        let mut iter = self.input.clone();
+        iter.next();
+        match iter.next() {
+            Some('.') => iter.next().is_some_and(|c| c.is_ascii_digit()),
+            c => c.is_some_and(|c| c.is_ascii_digit()),
+        }
+    }
+
+    fn is_radix_prefix(&mut self) -> bool {
+        self.peek() == Some('0') && matches!(self.peek_next(), Some('x' | 'X' | 'o' | 'O'))
+    }
+
     fn is_part_of_identifier(&mut self) -> bool {
         // Look ahead to check if this numeric start is part of an identifier
         let mut iter = self.input.clone();
@@ -142,7 +182,12 @@ impl<'a> Tokenizer<'a> {
         
         while let Some(c) = self.peek() {
             if c == '"' {
-                self.advance(); // Skip closing quote
+                self.advance();
+                // `""` inside a string is an escaped quote
+                if self.match_char('"') {
+                    string.push('"');
+                    continue;
+                }
                 return Ok(Token::new(TokenType::StringLiteral(string), line, column));
             }
             string.push(c);
@@ -152,6 +197,8 @@ impl<'a> Tokenizer<'a> {
         Err(self.error("Unterminated string literal"))
     }
 
+    /// Decimal, scientific, `0x` hex or `0o` octal. A leading zero does not
+    /// make a number octal: `0644` is decimal 644, as the game reads it.
     fn read_number(&mut self) -> Result<Token, Error> {
         let line = self.line;
         let column = self.column;
@@ -159,17 +206,23 @@ impl<'a> Tokenizer<'a> {
         let mut has_dot = false;
         let mut has_e = false;
 
-        // Handle negative numbers at start
-        if self.peek() == Some('-') {
-            number.push('-');
+        // Handle signed numbers at start; `+` adds nothing to the value
+        if let Some(sign @ ('-' | '+')) = self.peek() {
+            if sign == '-' {
+                number.push('-');
+            }
             self.advance();
             
-            // There must be a digit after the minus sign
-            if !matches!(self.peek(), Some('0'..='9')) {
-                return Err(self.error("Expected digit after '-'"));
+            // There must be a digit after the sign
+            if !matches!(self.peek(), Some('0'..='9' | '.')) {
+                return Err(self.error(&format!("Expected digit after '{}'", sign)));
             }
         }
 
+        if self.is_radix_prefix() {
+            return self.read_radix_number(number, line, column);
+        }
+
         // Main number parsing loop
         while let Some(c) = self.peek() {
             match c {
@@ -231,6 +284,40 @@ impl<'a> Tokenizer<'a> {
         }
     }
 
+    fn read_radix_number(&mut self, mut number: String, line: usize, column: usize) -> Result<Token, Error> {
+        number.push(self.next_char().unwrap()); // '0'
+        let prefix = self.next_char().unwrap(); // 'x' or 'o'
+        number.push(prefix);
+
+        let (radix, name) = match prefix {
+            'x' | 'X' => (16, "hexadecimal"),
+            _ => (8, "octal"),
+        };
+
+        let mut digits = String::new();
+        while let Some(c) = self.peek() {
+            if c.is_digit(radix) {
+                digits.push(c);
+                self.advance();
+            } else if c.is_ascii_alphanumeric() || c == '_' {
+                return Err(self.error(&format!("Invalid {} digit '{}' in number literal", name, c)));
+            } else {
+                break;
+            }
+        }
+        number.push_str(&digits);
+
+        if digits.is_empty() {
+            return Err(self.error(&format!("Expected {} digit after '{}'", name, number)));
+        }
+
+        let value = i64::from_str_radix(&digits, radix)
+            .map_err(|_| self.error(&format!("Invalid number format: {}", number)))?;
+        let value = if number.starts_with('-') { -value } else { value };
+
+        Ok(Token::new(TokenType::NumberLiteral(value as f64), line, column).with_raw(number))
+    }
+
     fn read_identifier(&mut self) -> Token {
         let line = self.line;
         let column = self.column;
@@ -262,6 +349,7 @@ impl<'a> Tokenizer<'a> {
         let token_type = match ident.as_str() {
             "class" => TokenType::Class,
             "enum" => TokenType::Enum,
+            "delete" => TokenType::Delete,
             "public" => TokenType::Public,
             "private" => TokenType::Private,
             "include" => TokenType::Include,
@@ -274,7 +362,8 @@ impl<'a> Tokenizer<'a> {
         Token::new(token_type, line, column)
     }
 
-    fn read_line_comment(&mut self) -> Result<Token, Error> {
+    /// The comment as a token, or `None` when comments are not preserved.
+    fn read_line_comment(&mut self) -> Option<Token> {
         let line = self.line;
         let column = self.column;
         self.advance(); // Skip first '/'
@@ -289,15 +378,10 @@ impl<'a> Tokenizer<'a> {
             self.advance();
         }
 
-        if self.preserve_comments {
-            Ok(Token::new(TokenType::Comment(comment), line, column))
-        } else {
-            self.next_token()?
-                .ok_or_else(|| self.error("Unexpected end of input after comment"))
-        }
+        self.preserve_comments.then(|| Token::new(TokenType::Comment(comment), line, column))
     }
 
-    fn read_block_comment(&mut self) -> Result<Token, Error> {
+    fn read_block_comment(&mut self) -> Result<Option<Token>, Error> {
         let line = self.line;
         let column = self.column;
         self.advance(); // Skip '/'
@@ -334,12 +418,7 @@ impl<'a> Tokenizer<'a> {
             }
         }
 
-        if self.preserve_comments {
-            Ok(Token::new(TokenType::Comment(comment), line, column))
-        } else {
-            self.next_token()?
-                .ok_or_else(|| self.error("Unexpected end of input after comment"))
-        }
+        Ok(self.preserve_comments.then(|| Token::new(TokenType::Comment(comment), line, column)))
     }
 
     fn read_preprocessor_directive(&mut self) -> Result<Token, Error> {
@@ -364,31 +443,37 @@ impl<'a> Tokenizer<'a> {
         Ok(Token::new(TokenType::Equals, line, column))
     }
 
-    fn handle_plus(&mut self) -> Result<Token, Error> {
+    fn handle_plus(&mut self) -> Token {
         let line = self.line;
         let column = self.column;
         self.advance();
         if self.match_char('=') {
-            Ok(Token::new(TokenType::PlusEquals, line, column))
+            Token::new(TokenType::PlusEquals, line, column)
         } else {
-            Err(self.error("Expected '=' after '+'"))
+            Token::new(TokenType::Plus, line, column)
         }
     }
 
-    fn handle_minus(&mut self) -> Result<Token, Error> {
+    fn handle_ampersand(&mut self) -> Result<Token, Error> {
         let line = self.line;
         let column = self.column;
         self.advance();
-        
-        // Check if next character is a digit (negative number)
-        if matches!(self.peek(), Some('0'..='9')) {
-            // Backtrack to let read_number handle it
-            self.column -= 1;
-            self.read_number()
-        } else if self.match_char('=') {
-            Ok(Token::new(TokenType::MinusEquals, line, column))
+        if self.match_char('=') {
+            Ok(Token::new(TokenType::AmpersandEquals, line, column))
+        } else {
+            Err(self.error("Expected '=' after '&'"))
+        }
+    }
+
+    fn handle_minus(&mut self) -> Token {
+        let line = self.line;
+        let column = self.column;
+        self.advance();
+        // Negative numbers are read by `read_number`, so this is an operator
+        if self.match_char('=') {
+            Token::new(TokenType::MinusEquals, line, column)
         } else {
-            Err(self.error("Expected '=' after '-' or digit for negative number"))
+            Token::new(TokenType::Minus, line, column)
         }
     }
 
@@ -402,9 +487,10 @@ impl<'a> Tokenizer<'a> {
         iter.next()
     }
 
-    fn next(&mut self) -> Option<char> {
+    fn next_char(&mut self) -> Option<char> {
         let c = self.input.next();
         if let Some(c) = c {
+            self.offset += c.len_utf8();
             if c == '\n' {
                 self.line += 1;
                 self.column = 0;
@@ -416,7 +502,7 @@ impl<'a> Tokenizer<'a> {
     }
 
     fn advance(&mut self) {
-        self.next();
+        self.next_char();
     }
 
     fn match_char(&mut self, expected: char) -> bool {
@@ -452,7 +538,7 @@ impl<'a> Tokenizer<'a> {
                 self.file_path.clone(),
                 self.line,
                 self.column
-            )
+            ).with_span(self.offset..self.offset + self.input.clone().next().map_or(0, char::len_utf8))
         }
     }
 
@@ -603,6 +689,20 @@ impl ToNumber for Token {
     }
 }
 
+/// Yields tokens until the end of the input or the first error.
+impl Iterator for Tokenizer<'_> {
+    type Item = Result<Token, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+        let token = self.next_token().transpose();
+        self.finished = !matches!(token, Some(Ok(_)));
+        token
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -640,13 +740,13 @@ mod tests {
 
     #[test]
     fn test_number_literals() {
-        let input = "123 -456 789.0";
+        let input = "123, -456 789.0";
         let mut tokenizer = Tokenizer::new(input);
         let tokens = tokenizer.tokenize().unwrap();
         
         assert!(tokens.contains(&Token::new(TokenType::NumberLiteral(123.0), 1, 0)));
-        assert!(tokens.contains(&Token::new(TokenType::NumberLiteral(-456.0), 1, 4)));
-        assert!(tokens.contains(&Token::new(TokenType::NumberLiteral(789.0), 1, 9)));
+        assert!(tokens.contains(&Token::new(TokenType::NumberLiteral(-456.0), 1, 5)));
+        assert!(tokens.contains(&Token::new(TokenType::NumberLiteral(789.0), 1, 10)));
     }
 
     #[test]
@@ -662,7 +762,7 @@ mod tests {
 
     #[test]
     fn test_operators() {
-        let input = "= += -=";
+        let input = "= += -= &=";
         let mut tokenizer = Tokenizer::new(input);
         let tokens = tokenizer.tokenize().unwrap();
         
@@ -670,7 +770,9 @@ mod tests {
             Token::new(TokenType::Equals, 1, 0),
             Token::new(TokenType::PlusEquals, 1, 2),
             Token::new(TokenType::MinusEquals, 1, 5),
+            Token::new(TokenType::AmpersandEquals, 1, 8),
         ]);
+        assert!(Tokenizer::new("&").tokenize().is_err());
     }
 
     #[test]
@@ -904,4 +1006,126 @@ mod tests {
             );
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_hex_and_octal_literals() {
+        let inputs = vec![
+            ("0xFF", 255.0),
+            ("0XFF", 255.0),
+            ("0xff", 255.0),
+            ("0xDEADBEEF", 3735928559.0),
+            ("-0xFF", -255.0),
+            ("0o644", 420.0),
+            ("0O17", 15.0),
+        ];
+
+        for (input, expected) in inputs {
+            let mut tokenizer = Tokenizer::new(input);
+            let result = tokenizer.tokenize().unwrap();
+            assert_eq!(
+                result,
+                vec![Token::new(TokenType::NumberLiteral(expected), 1, 0).with_raw(input)],
+                "Failed for input: {}", input
+            );
+        }
+
+        let tokens = Tokenizer::new("color = 0xFF0000;").tokenize().unwrap();
+        assert_eq!(tokens[2], Token::new(TokenType::NumberLiteral(16711680.0), 1, 8).with_raw("0xFF0000"));
+        assert_eq!(tokens[3].token_type, TokenType::Semicolon);
+
+        // Only the `0o` prefix is octal
+        for (input, expected) in [("0644", 644.0), ("-007", -7.0), ("00.5", 0.5)] {
+            let result = Tokenizer::new(input).tokenize().unwrap();
+            assert_eq!(result[0].token_type, TokenType::NumberLiteral(expected), "Failed for input: {}", input);
+        }
+
+        for input in ["0xGG", "0x", "0xFG", "0o8", "-0x", "+0x"] {
+            let mut tokenizer = Tokenizer::new(input);
+            assert!(tokenizer.tokenize().is_err(), "Expected error for invalid literal: {}", input);
+        }
+    }
+
+    #[test]
+    fn test_iterator() {
+        let input = "class A { value = 1; };";
+        let streamed: Vec<Token> = Tokenizer::new(input).map(Result::unwrap).collect();
+        assert_eq!(streamed, Tokenizer::new(input).tokenize().unwrap());
+
+        // Nothing follows an error
+        let mut tokenizer = Tokenizer::new("a = \"unterminated");
+        assert!(tokenizer.next().unwrap().is_ok());
+        assert!(tokenizer.next().unwrap().is_ok());
+        assert!(tokenizer.next().unwrap().is_err());
+        assert!(tokenizer.next().is_none());
+    }
+
+    #[test]
+    fn test_signed_and_fractional_numbers() {
+        for (input, expected) in [("+5", 5.0), (".5", 0.5), ("-.5", -0.5), ("+.25e1", 2.5), ("+0x10", 16.0)] {
+            let result = Tokenizer::new(input).tokenize().unwrap();
+            assert_eq!(result.len(), 1, "Failed for input: {}", input);
+            assert_eq!(result[0].token_type, TokenType::NumberLiteral(expected), "Failed for input: {}", input);
+        }
+
+        let token_types = Tokenizer::new("{+1, .5, 2+3}").tokenize().unwrap()
+            .into_iter().map(|token| token.token_type).collect::<Vec<_>>();
+        assert_eq!(token_types, vec![
+            TokenType::LeftBrace,
+            TokenType::NumberLiteral(1.0),
+            TokenType::Comma,
+            TokenType::NumberLiteral(0.5),
+            TokenType::Comma,
+            TokenType::NumberLiteral(2.0),
+            TokenType::Plus,
+            TokenType::NumberLiteral(3.0),
+            TokenType::RightBrace,
+        ]);
+    }
+
+    #[test]
+    fn test_arithmetic_tokens() {
+        let token_types = |input: &str| Tokenizer::new(input).tokenize().unwrap()
+            .into_iter().map(|token| token.token_type).collect::<Vec<_>>();
+
+        assert_eq!(token_types("(2-3)*4/x+1"), vec![
+            TokenType::LeftParen,
+            TokenType::NumberLiteral(2.0),
+            TokenType::Minus,
+            TokenType::NumberLiteral(3.0),
+            TokenType::RightParen,
+            TokenType::Star,
+            TokenType::NumberLiteral(4.0),
+            TokenType::Slash,
+            TokenType::Identifier("x".to_string()),
+            TokenType::Plus,
+            TokenType::NumberLiteral(1.0),
+        ]);
+        assert_eq!(token_types("{1,-2} 10 - -3"), vec![
+            TokenType::LeftBrace,
+            TokenType::NumberLiteral(1.0),
+            TokenType::Comma,
+            TokenType::NumberLiteral(-2.0),
+            TokenType::RightBrace,
+            TokenType::NumberLiteral(10.0),
+            TokenType::Minus,
+            TokenType::NumberLiteral(-3.0),
+        ]);
+        // After an operand a `-` subtracts however it is spaced
+        for input in ["1 -2", "1-2", "1 - 2"] {
+            assert_eq!(token_types(input), vec![
+                TokenType::NumberLiteral(1.0),
+                TokenType::Minus,
+                TokenType::NumberLiteral(2.0),
+            ], "Failed for input: {}", input);
+        }
+    }
+
+    #[test]
+    fn test_doubled_quotes() {
+        let tokens = Tokenizer::new(r#""a""b" """" "The ""Best"" Rifle""#).tokenize().unwrap();
+        let strings: Vec<_> = tokens.iter().filter_map(Token::as_string).collect();
+        assert_eq!(strings, vec![r#"a"b"#, r#"""#, r#"The "Best" Rifle"#]);
+        assert_eq!(tokens.len(), 3);
+        assert!(Tokenizer::new(r#""a"""#).tokenize().is_err());
+    }
+}
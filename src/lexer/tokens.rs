@@ -1,8 +1,12 @@
-#[derive(Debug, Clone, PartialEq)]
+use serde::{Deserialize, Serialize};
+use crate::utils::{serialize_f64_finite, deserialize_f64_finite};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum TokenType {
     // Keywords
     Class,
     Enum,  
+    Delete,
     Public,
     Private,
     Include,
@@ -11,9 +15,15 @@ pub enum TokenType {
     // Identifiers and literals
     Identifier(String),
     StringLiteral(String),
-    NumberLiteral(f64),
+    NumberLiteral(#[serde(serialize_with = "serialize_f64_finite", deserialize_with = "deserialize_f64_finite")] f64),
     BooleanLiteral(bool),
-    ARGBColor(u8, u8, u8, f64, f64, f64, f64), // size_x, size_y, channels, r, g, b, a
+    ARGBColor(
+        u8, u8, u8,
+        #[serde(serialize_with = "serialize_f64_finite", deserialize_with = "deserialize_f64_finite")] f64,
+        #[serde(serialize_with = "serialize_f64_finite", deserialize_with = "deserialize_f64_finite")] f64,
+        #[serde(serialize_with = "serialize_f64_finite", deserialize_with = "deserialize_f64_finite")] f64,
+        #[serde(serialize_with = "serialize_f64_finite", deserialize_with = "deserialize_f64_finite")] f64,
+    ), // size_x, size_y, channels, r, g, b, a
     
     // Symbols
     LeftBrace,
@@ -26,18 +36,43 @@ pub enum TokenType {
     Equals,
     PlusEquals,
     MinusEquals,
+    AmpersandEquals,
     ArrayMarker,
+    // Arithmetic in values, e.g. `maxSpeed = 120*0.8;`
+    Plus,
+    Minus,
+    Star,
+    Slash,
+    LeftParen,
+    RightParen,
     
     // Special
     EOL,
     Comment(String),
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Token {
     pub token_type: TokenType,
     pub line: usize,
     pub column: usize,
+    /// Original source text for literals whose notation is lost in `token_type`
+    /// (e.g. `0xFF` for a `NumberLiteral(255.0)`).
+    pub raw: Option<String>,
+    /// Byte range of the token in the tokenized source.
+    pub start: usize,
+    pub end: usize,
+}
+
+// Byte offsets are redundant with line/column for a given source, so they
+// are left out of equality
+impl PartialEq for Token {
+    fn eq(&self, other: &Self) -> bool {
+        self.token_type == other.token_type
+            && self.line == other.line
+            && self.column == other.column
+            && self.raw == other.raw
+    }
 }
 
 impl Token {
@@ -46,17 +81,36 @@ impl Token {
             token_type,
             line,
             column,
+            raw: None,
+            start: 0,
+            end: 0,
         }
     }
 
+    pub fn with_raw(mut self, raw: impl Into<String>) -> Self {
+        self.raw = Some(raw.into());
+        self
+    }
+
+    pub fn with_offsets(mut self, start: usize, end: usize) -> Self {
+        self.start = start;
+        self.end = end;
+        self
+    }
+
     pub fn is_operator(&self) -> bool {
         matches!(self.token_type, 
             TokenType::Equals | 
             TokenType::PlusEquals | 
-            TokenType::MinusEquals
+            TokenType::MinusEquals |
+            TokenType::AmpersandEquals
         )
     }
 
+    pub fn is_arithmetic(&self) -> bool {
+        matches!(self.token_type, TokenType::Plus | TokenType::Minus | TokenType::Star | TokenType::Slash)
+    }
+
     pub fn is_literal(&self) -> bool {
         matches!(self.token_type,
             TokenType::StringLiteral(_) |
@@ -86,6 +140,8 @@ mod tests {
         assert!(token.is_operator());
         let token = Token::new(TokenType::MinusEquals, 1, 1);
         assert!(token.is_operator());
+        let token = Token::new(TokenType::AmpersandEquals, 1, 1);
+        assert!(token.is_operator());
         let token = Token::new(TokenType::Class, 1, 1);
         assert!(!token.is_operator());
         let token = Token::new(TokenType::StringLiteral("test".to_string()), 1, 1);
@@ -1,6 +1,13 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use indexmap::IndexMap;
+use error::SourceLocation;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use regex::Regex;
+use operations::writer::{FormatOptions, Writer};
+use operations::arrays::ArrayOperation;
 
 pub mod lexer;
 pub mod parser;
@@ -10,11 +17,29 @@ pub mod operations;
 pub mod error;
 pub mod utils;
 pub mod models;
+pub mod scanner;
+pub mod query;
 
-pub use error::Error;
-pub use parser::Parser;
+pub use error::{Error, Warning};
+pub use scanner::{ScannerConfig, ScanProgress, ProgressEvent, ScanResult, ScanCache, IncrementalResult, ClassIndex, ResolvedScan};
+pub use parser::{Parser, ParserConfig, DuplicatePolicy};
+pub use lexer::IncludePolicy;
 pub use models::property_value::PropertyValue;
-pub use ast::{PropertyType, ClassNode, AstVisitor};
+pub use ast::{PropertyType, ClassNode, PropertyNode, AstVisitor, MergeStrategy, DotOptions};
+pub use operations::{ArrayElement, ClassRegistry, MissingParent};
+pub use utils::{StringInterner, InternedStr};
+
+/// Classes, warnings and the files included while parsing.
+type ParsedFile = (Vec<ClassNode>, Vec<Warning>, Vec<PathBuf>);
+
+/// Classes parsed from one source, as returned by
+/// [`ClassScanner::parse_file`], with the warnings raised while
+/// preprocessing and parsing it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseOutcome {
+    pub classes: Vec<ClassNode>,
+    pub warnings: Vec<Warning>,
+}
 
 /// A high-level interface for parsing and processing class configuration files.
 ///
@@ -52,9 +77,43 @@ pub use ast::{PropertyType, ClassNode, AstVisitor};
 ///     Ok(())
 /// }
 /// ```
-#[derive(Debug, Clone)]
+#[derive(Debug)]
 pub struct ClassScanner {
     base_path: Option<PathBuf>,
+    scanner_config: ScannerConfig,
+    include_paths: Vec<PathBuf>,
+    /// Addon prefix and the directory it maps to.
+    addon_prefixes: Vec<(String, PathBuf)>,
+    /// Behind a mutex so `&self` methods (including the parallel ones) can
+    /// record warnings.
+    last_warnings: std::sync::Mutex<Vec<Warning>>,
+    missing_parent: operations::MissingParent,
+    array_options: operations::ArrayOptions,
+    duplicate_policy: DuplicatePolicy,
+    parser_config: ParserConfig,
+    include_policy: lexer::IncludePolicy,
+    minify_line_length: Option<usize>,
+    /// Shared by clones, so every parse of a session interns into it.
+    interner: Option<Arc<StringInterner>>,
+}
+
+impl Clone for ClassScanner {
+    fn clone(&self) -> Self {
+        Self {
+            base_path: self.base_path.clone(),
+            scanner_config: self.scanner_config.clone(),
+            include_paths: self.include_paths.clone(),
+            addon_prefixes: self.addon_prefixes.clone(),
+            last_warnings: std::sync::Mutex::new(self.last_warnings()),
+            missing_parent: self.missing_parent,
+            array_options: self.array_options,
+            duplicate_policy: self.duplicate_policy,
+            parser_config: self.parser_config,
+            include_policy: self.include_policy,
+            minify_line_length: self.minify_line_length,
+            interner: self.interner.clone(),
+        }
+    }
 }
 
 impl ClassScanner {
@@ -62,9 +121,67 @@ impl ClassScanner {
     pub fn new() -> Self {
         Self {
             base_path: None,
+            scanner_config: ScannerConfig::default(),
+            include_paths: Vec::new(),
+            addon_prefixes: Vec::new(),
+            last_warnings: std::sync::Mutex::new(Vec::new()),
+            missing_parent: operations::MissingParent::Error,
+            array_options: operations::ArrayOptions::default(),
+            duplicate_policy: DuplicatePolicy::default(),
+            parser_config: ParserConfig::default(),
+            include_policy: lexer::IncludePolicy::default(),
+            minify_line_length: None,
+            interner: None,
         }
     }
 
+    /// Warnings from the most recent `parse_*` or `process_*` call that
+    /// succeeded, such as redefined macros or broken inheritance cycles.
+    pub fn last_warnings(&self) -> Vec<Warning> {
+        self.last_warnings.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    fn set_warnings(&self, warnings: Vec<Warning>) {
+        *self.last_warnings.lock().unwrap_or_else(|e| e.into_inner()) = warnings;
+    }
+
+    /// How `process_*` calls treat a parent class missing from the given
+    /// classes. Defaults to [`MissingParent::Error`](operations::MissingParent::Error);
+    /// with `Collect`, each missing parent is reported in
+    /// [`last_warnings`](Self::last_warnings).
+    pub fn with_missing_parent(mut self, policy: operations::MissingParent) -> Self {
+        self.missing_parent = policy;
+        self
+    }
+
+    /// How `process_*` calls apply array operations, e.g. whether `+=`
+    /// skips values already present. By default duplicates are kept, as
+    /// the game does.
+    pub fn with_array_options(mut self, options: operations::ArrayOptions) -> Self {
+        self.array_options = options;
+        self
+    }
+
+    /// How a property defined twice in one class is treated when parsing.
+    /// By default the later definition wins and a warning is recorded.
+    pub fn with_duplicate_policy(mut self, policy: DuplicatePolicy) -> Self {
+        self.duplicate_policy = policy;
+        self
+    }
+
+    /// Nesting limit, empty class bodies and case folding for parsing.
+    pub fn with_parser_config(mut self, config: ParserConfig) -> Self {
+        self.parser_config = config;
+        self
+    }
+
+    /// How an `#include` of a file that cannot be found is handled. By
+    /// default it is skipped and reported in [`last_warnings`](Self::last_warnings).
+    pub fn with_include_policy(mut self, policy: lexer::IncludePolicy) -> Self {
+        self.include_policy = policy;
+        self
+    }
+
     /// Set the base path for resolving file includes.
     ///
     /// When parsing files with `#include` directives, the preprocessor uses this
@@ -74,6 +191,79 @@ impl ClassScanner {
         self
     }
 
+    /// Add a directory to search for includes, such as a P: drive root.
+    /// Game paths like `\x\cba\addons\main\script_macros.hpp` are looked up
+    /// below it as `x/cba/addons/main/script_macros.hpp`.
+    pub fn with_include_path<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.include_paths.push(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Resolve includes starting with an addon `prefix` into `dir`.
+    /// [`scan_directory`](Self::scan_directory) registers the prefixes of
+    /// the `$PBOPREFIX$` files it finds on its own.
+    pub fn with_addon_prefix<P: AsRef<Path>>(mut self, prefix: &str, dir: P) -> Self {
+        self.addon_prefixes.push((prefix.to_string(), dir.as_ref().to_path_buf()));
+        self
+    }
+
+    /// Column [`minify_file`](Self::minify_file) wraps lines at; by default
+    /// its output is a single line.
+    pub fn with_minify_line_length(mut self, columns: Option<usize>) -> Self {
+        self.minify_line_length = columns;
+        self
+    }
+
+    /// Intern the class and property names of everything this scanner (and
+    /// its clones) parses into one [`StringInterner`], so repeated names
+    /// can be keyed by [`InternedStr`], see
+    /// [`ClassNode::interned_properties`].
+    pub fn with_string_interning(mut self, enabled: bool) -> Self {
+        self.interner = enabled.then(|| Arc::new(StringInterner::new()));
+        self
+    }
+
+    /// The interner of a scanner built with
+    /// [`with_string_interning`](Self::with_string_interning).
+    pub fn string_interner(&self) -> Option<&Arc<StringInterner>> {
+        self.interner.as_ref()
+    }
+
+    /// Add the names in `classes` to the session interner, if there is one.
+    fn intern_names(&self, classes: &[ClassNode]) {
+        fn intern(interner: &StringInterner, class: &ClassNode) {
+            interner.intern(&class.name);
+            for name in class.properties.keys() {
+                interner.intern(name);
+            }
+            for nested in &class.nested_classes {
+                intern(interner, nested);
+            }
+        }
+        if let Some(interner) = &self.interner {
+            // The unnamed root holds the top-level classes again
+            if let Some(root) = classes.first() {
+                for name in root.properties.keys() {
+                    interner.intern(name);
+                }
+                for class in &root.nested_classes {
+                    intern(interner, class);
+                }
+            }
+        }
+    }
+
+    /// Set which files directory scans pick up.
+    pub fn with_scanner_config(mut self, config: ScannerConfig) -> Self {
+        self.scanner_config = config;
+        self
+    }
+
+    /// A scanner using the config in `path`, see [`ScannerConfig::from_file`].
+    pub fn from_config_file<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        Ok(Self::new().with_scanner_config(ScannerConfig::from_file(path)?))
+    }
+
     /// Parse a class configuration file.
     ///
     /// This method reads the file, preprocesses it to handle includes,
@@ -86,10 +276,120 @@ impl ClassScanner {
     /// # Returns
     ///
     /// A `Result` containing a `Vec<ClassNode>` if parsing succeeds, or an `Error` otherwise.
+    /// As with [`parse_string`](Self::parse_string), the first element is the unnamed root
+    /// node and it is followed by each top-level class.
     pub fn parse_file<P: AsRef<Path>>(&self, path: P) -> Result<Vec<ClassNode>, Error> {
-        let path_ref = path.as_ref();
+        let (classes, warnings, _) = self.parse_file_with_warnings(path.as_ref())?;
+        self.set_warnings(warnings);
+        Ok(classes)
+    }
+
+    fn parse_file_with_warnings(&self, path_ref: &Path) -> Result<ParsedFile, Error> {
+        let (root, warnings, includes) = if scanner::pbo::is_pbo_file(path_ref) {
+            self.parse_pbo(path_ref)?
+        } else if parser::rap::is_rap_file(path_ref) {
+            // Binarized configs carry no preprocessor directives
+            let data = std::fs::read(path_ref)?;
+            let root = parser::rap::RapReader::new(&data).with_file_path(path_ref).parse()?;
+            (root, Vec::new(), Vec::new())
+        } else {
+            // Use the preprocessor to handle includes
+            self.parse_source(self.preprocessor_for(path_ref), path_ref)?
+        };
+        let classes = Self::split_top_level(root);
+        self.intern_names(&classes);
+        Ok((classes, warnings, includes))
+    }
+
+    /// Preprocess and parse the text config at `path_ref`, returning the
+    /// unnamed root, the warnings and the files it included.
+    fn parse_source(&self, mut preprocessor: lexer::Preprocessor, path_ref: &Path) -> Result<(ClassNode, Vec<Warning>, Vec<PathBuf>), Error> {
+        let content = preprocessor.process_file(path_ref)?;
+        let source_map = preprocessor.take_source_map();
+        // Point errors at the file and line they were written in rather than
+        // the preprocessed content
+        let remap = |mut error: Error| {
+            if let Some(location) = error.location_mut() {
+                source_map.remap(&content, location);
+            }
+            error
+        };
         
-        // Use the preprocessor to handle includes
+        // Tokenize and parse the preprocessed content
+        let tokenizer = lexer::Tokenizer::with_file_path(&content, path_ref);
+        let mut parser = Parser::from_tokenizer(tokenizer)
+            .with_file_path(path_ref)
+            .with_duplicate_policy(self.duplicate_policy)
+            .with_parser_config(self.parser_config);
+        let mut root = parser.parse().map_err(remap)?;
+        Self::remap_locations(&mut root, &source_map, &content);
+
+        // Preprocessor warnings already point into the original files
+        let mut warnings = preprocessor.take_warnings();
+        for mut warning in parser.take_warnings() {
+            for location in warning.locations_mut() {
+                source_map.remap(&content, location);
+            }
+            warnings.push(warning);
+        }
+        let includes = preprocessor.processed_files().iter().skip(1).cloned().collect();
+        Ok((root, warnings, includes))
+    }
+
+    /// Parse the configs packed in the PBO at `path`, see
+    /// [`Pbo::config_entries`](scanner::Pbo::config_entries), under one root.
+    /// Locations point at `<path>/<entry>`, and includes of a text config
+    /// resolve to the other files in the archive first, by relative path or
+    /// through the archive's prefix.
+    fn parse_pbo(&self, path: &Path) -> Result<(ClassNode, Vec<Warning>, Vec<PathBuf>), Error> {
+        let pbo = scanner::Pbo::open(path)?;
+        let mut root = ClassNode::new(String::new());
+        let mut warnings = Vec::new();
+        let mut includes = Vec::new();
+        for entry in pbo.config_entries() {
+            let entry_path = path.join(entry.path());
+            let data = pbo.read(entry)?;
+            let config = if parser::rap::is_rap(&data) {
+                parser::rap::RapReader::new(&data).with_file_path(&entry_path).parse()?
+            } else {
+                let mut preprocessor = self.preprocessor_for(&entry_path);
+                if let Some(prefix) = pbo.prefix() {
+                    preprocessor.add_addon_prefix(prefix, path);
+                }
+                for source in pbo.source_entries() {
+                    let content = String::from_utf8_lossy(&pbo.read(source)?).into_owned();
+                    preprocessor.add_source(path.join(source.path()), content);
+                }
+                let (config, config_warnings, config_includes) = self.parse_source(preprocessor, &entry_path)?;
+                warnings.extend(config_warnings);
+                // Files in the archive change with it
+                includes.extend(config_includes.into_iter().filter(|include| !include.starts_with(path)));
+                config
+            };
+            root.properties.extend(config.properties);
+            root.enums.extend(config.enums);
+            root.nested_classes.extend(config.nested_classes);
+        }
+        Ok((root, warnings, includes))
+    }
+
+    /// Point the locations of `class` and everything in it at the file and
+    /// line they were written in. Spans stay offsets into `content`.
+    fn remap_locations(class: &mut ClassNode, source_map: &lexer::SourceMap, content: &str) {
+        let locations = class.location.iter_mut()
+            .chain(class.properties.values_mut().filter_map(|property| property.location.as_mut()))
+            .chain(class.enums.iter_mut().filter_map(|node| node.location.as_mut()));
+        for location in locations {
+            source_map.remap(content, location);
+        }
+        for nested in &mut class.nested_classes {
+            Self::remap_locations(nested, source_map, content);
+        }
+    }
+
+    /// Preprocessor resolving includes of the file at `path_ref` the way
+    /// this scanner is configured to.
+    fn preprocessor_for(&self, path_ref: &Path) -> lexer::Preprocessor {
         let base_dir = if let Some(ref base_path) = self.base_path {
             base_path.clone()
         } else {
@@ -98,17 +398,27 @@ impl ClassScanner {
                 .to_path_buf()
         };
         
-        let mut preprocessor = lexer::Preprocessor::new(&base_dir);
+        let mut preprocessor = lexer::Preprocessor::new(&base_dir).with_include_policy(self.include_policy);
+        for include_path in &self.include_paths {
+            preprocessor.add_include_path(include_path);
+        }
+        for (prefix, dir) in &self.addon_prefixes {
+            preprocessor.add_addon_prefix(prefix, dir);
+        }
+        preprocessor
+    }
+
+    /// Preprocess a config file and strip its comments and whitespace, see
+    /// [`Minifier`](lexer::Minifier). Includes are inlined and macros
+    /// expanded, so the result stands on its own.
+    pub fn minify_file<P: AsRef<Path>>(&self, path: P) -> Result<String, Error> {
+        let path_ref = path.as_ref();
+        let mut preprocessor = self.preprocessor_for(path_ref);
         let content = preprocessor.process_file(path_ref)?;
-        
-        // Tokenize and parse the preprocessed content
-        let mut tokenizer = lexer::Tokenizer::with_file_path(&content, path_ref);
-        let tokens = tokenizer.tokenize()?;
-        
-        let mut parser = Parser::new(tokens);
-        let class = parser.parse()?;
-        
-        Ok(vec![class])
+        self.set_warnings(preprocessor.take_warnings());
+        lexer::Minifier::new()
+            .with_max_line_length(self.minify_line_length)
+            .minify(&content)
     }
 
     /// Parse a string containing class definitions.
@@ -121,16 +431,36 @@ impl ClassScanner {
     ///
     /// A `Result` containing a `Vec<ClassNode>` if parsing succeeds, or an `Error` otherwise.
     pub fn parse_string(&self, content: &str) -> Result<Vec<ClassNode>, Error> {
-        let tokens = lexer::Tokenizer::new(content).tokenize()?;
-        let mut parser = Parser::new(tokens);
+        let outcome = self.parse_string_outcome(content)?;
+        self.set_warnings(outcome.warnings);
+        Ok(outcome.classes)
+    }
+
+    /// [`parse_string`](Self::parse_string), returning the warnings with the
+    /// classes rather than through [`last_warnings`](Self::last_warnings).
+    pub fn parse_string_outcome(&self, content: &str) -> Result<ParseOutcome, Error> {
+        let mut parser = Parser::from_tokenizer(lexer::Tokenizer::new(content))
+            .with_duplicate_policy(self.duplicate_policy)
+            .with_parser_config(self.parser_config);
         let root = parser.parse()?;
-        
-        // Extract individual classes from the root node
-        let mut classes = vec![];
-        classes.push(root.clone()); // Include the root node
-        classes.extend(root.nested_classes.clone()); // Add top-level classes
-        
-        Ok(classes)
+        let classes = Self::split_top_level(root);
+        self.intern_names(&classes);
+        Ok(ParseOutcome { classes, warnings: parser.take_warnings() })
+    }
+
+    /// [`parse_file`](Self::parse_file), returning the warnings with the
+    /// classes rather than through [`last_warnings`](Self::last_warnings).
+    pub fn parse_file_outcome<P: AsRef<Path>>(&self, path: P) -> Result<ParseOutcome, Error> {
+        let (classes, warnings, _) = self.parse_file_with_warnings(path.as_ref())?;
+        Ok(ParseOutcome { classes, warnings })
+    }
+
+    /// Extract individual classes from the root node, keeping the root itself first.
+    fn split_top_level(root: ClassNode) -> Vec<ClassNode> {
+        let mut classes = Vec::with_capacity(root.nested_classes.len() + 1);
+        classes.extend(root.nested_classes.iter().cloned()); // Add top-level classes
+        classes.insert(0, root); // Include the root node
+        classes
     }
     
     /// Process inheritance relationships between classes.
@@ -141,16 +471,21 @@ impl ClassScanner {
     /// # Arguments
     ///
     /// * `classes` - A collection of `ClassNode` objects to register for inheritance processing.
-    /// * `target_class_name` - The name of the class for which to process inheritance.
+    /// * `target_class_name` - The name of the class for which to process inheritance, or a
+    ///   slash-separated path to a nested class (e.g. `CfgWeapons/Uniform_Base/ItemInfo`).
     ///
     /// # Returns
     ///
     /// A `Result` containing the processed `ClassNode` with inherited properties, or an `Error` otherwise.
+    /// A parent missing from `classes` is handled as set by
+    /// [`with_missing_parent`](Self::with_missing_parent).
     pub fn process_inheritance<T>(&self, classes: T, target_class_name: &str) -> Result<ClassNode, Error> 
     where 
         T: IntoIterator<Item = ClassNode>,
     {
-        let mut inheritance_visitor = ast::inheritance_visitor::InheritanceVisitor::new();
+        let mut inheritance_visitor = ast::inheritance_visitor::InheritanceVisitor::new()
+            .with_missing_parent(self.missing_parent)
+            .with_array_options(self.array_options);
         
         // Register all classes
         for class in classes {
@@ -158,7 +493,35 @@ impl ClassScanner {
         }
         
         // Process inheritance for the target class
-        inheritance_visitor.process(target_class_name)
+        let processed = inheritance_visitor.process(target_class_name)?;
+        self.set_warnings(inheritance_visitor.take_warnings());
+        Ok(processed)
+    }
+
+    /// Resolve inheritance for every class in one pass.
+    ///
+    /// Classes are resolved parents first, so each parent is merged once and
+    /// shared by all of its children. Unlike `process_inheritance`, lookups are
+    /// by simple class name; nested classes are merged but not registered.
+    ///
+    /// # Returns
+    ///
+    /// The resolved classes in topological order (ancestors before
+    /// descendants), or an `Error` if a parent class is missing and the
+    /// missing-parent policy is `Error`.
+    pub fn process_all_inheritance<T>(&self, classes: T) -> Result<Vec<ClassNode>, Error>
+    where
+        T: IntoIterator<Item = ClassNode>,
+    {
+        let mut resolver = operations::inheritance::InheritanceResolver::new()
+            .with_missing_parent(self.missing_parent)
+            .with_array_options(self.array_options);
+        for class in classes {
+            resolver.add_class(class);
+        }
+        let resolved = resolver.resolve()?;
+        self.set_warnings(resolver.take_warnings());
+        Ok(resolved)
     }
     
     /// Process array operations in a class hierarchy.
@@ -174,7 +537,7 @@ impl ClassScanner {
     ///
     /// A `Result` containing `()` if processing succeeds, or an `Error` otherwise.
     pub fn process_arrays(&self, class: &mut ClassNode) -> Result<(), Error> {
-        let mut array_visitor = ast::array_visitor::ArrayVisitor::new();
+        let mut array_visitor = ast::array_visitor::ArrayVisitor::new().with_options(self.array_options);
         array_visitor.visit_class(class)
     }
 
@@ -194,15 +557,537 @@ impl ClassScanner {
     pub fn process_file<P: AsRef<Path>>(&self, path: P, target_class_name: &str) -> Result<ClassNode, Error> {
         // Parse the file
         let classes = self.parse_file(path)?;
+        let mut warnings = self.last_warnings();
         
         // Process inheritance
         let mut processed_class = self.process_inheritance(classes, target_class_name)?;
+        warnings.extend(self.last_warnings());
         
         // Process arrays
         self.process_arrays(&mut processed_class)?;
         
+        self.set_warnings(warnings);
+        Ok(processed_class)
+    }
+
+    /// A [`ClassRegistry`] of the classes in `paths`, parsed with this
+    /// scanner's settings, to resolve classes whose parents are in other
+    /// files.
+    pub fn build_registry(&self, paths: &[PathBuf]) -> Result<ClassRegistry, Error> {
+        let mut registry = ClassRegistry::with_scanner(self.clone());
+        for path in paths {
+            registry.register_file(path)?;
+        }
+        Ok(registry)
+    }
+
+    /// A [`ClassRegistry`] of the classes of a directory scan, resolving
+    /// classes several addons define by `addon_priority`, highest first. See
+    /// [`ClassRegistry::resolve_with_priority`].
+    pub fn build_registry_with_priority(&self, scan_results: &ScanResult, addon_priority: &[&str]) -> ClassRegistry {
+        let mut registry = ClassRegistry::with_scanner(self.clone()).with_addon_priority(addon_priority);
+        for (path, classes) in &scan_results.classes {
+            registry.register_classes(path, classes.clone());
+        }
+        registry
+    }
+
+    /// Parse several files concurrently, one result per path in input order.
+    ///
+    /// Files are independent, so a failure in one does not stop the others.
+    /// Includes are resolved the same way as in [`parse_file`](Self::parse_file).
+    pub fn parse_files_parallel<P: AsRef<Path> + Send + Sync>(&self, paths: &[P]) -> Vec<Result<Vec<ClassNode>, Error>> {
+        let results = self.parse_files_with_warnings(paths, |_, _| {}, |_, _| {});
+        let mut warnings = Vec::new();
+        let results = results.into_iter()
+            .map(|result| result.map(|(classes, file_warnings, _)| {
+                warnings.extend(file_warnings);
+                classes
+            }))
+            .collect();
+        self.set_warnings(warnings);
+        results
+    }
+
+    /// Parse on a separate thread when the scanner config has a timeout.
+    ///
+    /// A parse that runs over is abandoned rather than interrupted: its
+    /// thread finishes in the background and the result is dropped.
+    fn parse_file_with_timeout(&self, path: &Path) -> Result<ParsedFile, Error> {
+        let Some(timeout) = self.scanner_config.timeout else {
+            return catch_parser_panic(path, || self.parse_file_with_warnings(path));
+        };
+
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let scanner = self.clone();
+        let owned_path = path.to_path_buf();
+        std::thread::spawn(move || {
+            let result = catch_parser_panic(&owned_path, || scanner.parse_file_with_warnings(&owned_path));
+            // The receiver is gone if the parse timed out
+            let _ = sender.send(result);
+        });
+        match receiver.recv_timeout(timeout) {
+            Ok(result) => result,
+            Err(std::sync::mpsc::RecvTimeoutError::Timeout) => Err(Error::Timeout(path.to_path_buf())),
+            Err(std::sync::mpsc::RecvTimeoutError::Disconnected) => Err(Error::ParserPanic(path.to_path_buf())),
+        }
+    }
+
+    /// Parse `paths` on up to `ScannerConfig::threads` worker threads, calling
+    /// `on_start` with its index before each file and `on_file` as soon as it
+    /// is done, both from the worker. Workers pull the next unparsed file, so
+    /// one large file does not hold up a whole batch; results come back in
+    /// input order regardless.
+    fn parse_files_with_warnings<P, S, F>(&self, paths: &[P], on_start: S, on_file: F) -> Vec<Result<ParsedFile, Error>>
+    where
+        P: AsRef<Path> + Send + Sync,
+        S: Fn(usize, &Path) + Sync,
+        F: Fn(&Path, &Result<ParsedFile, Error>) + Sync,
+    {
+        if paths.is_empty() {
+            return Vec::new();
+        }
+
+        let threads = self.scanner_config.threads
+            .unwrap_or_else(|| std::thread::available_parallelism().map_or(1, |n| n.get()))
+            .clamp(1, paths.len());
+        let next = AtomicUsize::new(0);
+        let worker = || {
+            let mut parsed = Vec::new();
+            loop {
+                let index = next.fetch_add(1, Ordering::SeqCst);
+                let Some(path) = paths.get(index) else {
+                    return parsed;
+                };
+                on_start(index, path.as_ref());
+                let result = self.parse_file_with_timeout(path.as_ref());
+                on_file(path.as_ref(), &result);
+                parsed.push((index, result));
+            }
+        };
+
+        let mut results: Vec<Option<Result<ParsedFile, Error>>> = std::iter::repeat_with(|| None).take(paths.len()).collect();
+        std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..threads).map(|_| scope.spawn(worker)).collect();
+            for handle in handles {
+                // Parser panics are already errors, so only a panicking
+                // progress callback gets here
+                for (index, result) in handle.join().expect("scan callback panicked") {
+                    results[index] = Some(result);
+                }
+            }
+        });
+        results.into_iter()
+            .map(|result| result.expect("every file is claimed by a worker"))
+            .collect()
+    }
+
+    /// Parse every file below `dir` accepted by the scanner config (by default
+    /// PBOs and text and binarized configs outside hidden directories) in
+    /// parallel, then resolve `target_class` across all of them.
+    ///
+    /// Classes are merged by simple name; when several files define the same
+    /// class, the one from the last file in path order wins.
+    ///
+    /// As with [`scan_directory`](Self::scan_directory), a file that fails to
+    /// parse does not stop the others: it is left out and reported as a
+    /// [`Warning::SkippedFile`].
+    pub fn process_directory_parallel(&self, dir: &Path, target_class: &str) -> Result<ClassNode, Error> {
+        let files = self.scanner_config.collect_files(dir)?;
+
+        let mut resolver = operations::inheritance::InheritanceResolver::new()
+            .with_missing_parent(self.missing_parent)
+            .with_array_options(self.array_options);
+        let mut warnings = Vec::new();
+        for (path, result) in files.iter().zip(self.parse_files_with_warnings(&files, |_, _| {}, |_, _| {})) {
+            match result {
+                Ok((classes, file_warnings, _)) => {
+                    warnings.extend(file_warnings);
+                    for class in classes {
+                        resolver.add_class(class);
+                    }
+                }
+                Err(e) => warnings.push(Warning::SkippedFile { file: path.clone(), message: e.to_string() }),
+            }
+        }
+
+        let mut processed_class = resolver.resolve_class(target_class)?;
+        self.process_arrays(&mut processed_class)?;
+        warnings.extend(resolver.take_warnings());
+        self.set_warnings(warnings);
         Ok(processed_class)
     }
+
+    /// Parse every file below `dir` accepted by the scanner config.
+    ///
+    /// Results are keyed by path relative to `dir`. A file that fails to
+    /// parse is recorded in [`ScanResult::errors`] and the scan carries on.
+    /// The config's progress callback, if any, is called after each file.
+    ///
+    /// Addon prefixes from `$PBOPREFIX$` files are reported in
+    /// [`ScanResult::prefixes`] and used to resolve includes by game path.
+    pub fn scan_directory(&self, dir: &Path) -> Result<ScanResult, Error> {
+        self.scan_directory_with_progress(dir, |_| {})
+    }
+
+    /// [`scan_directory`](Self::scan_directory), calling `on_progress` as
+    /// each file starts and finishes. Files are parsed on up to
+    /// [`ScannerConfig::threads`] threads, and the callback runs on the
+    /// worker that parses the file, so events of different files interleave.
+    pub fn scan_directory_with_progress<P, F>(&self, dir: P, on_progress: F) -> Result<ScanResult, Error>
+    where
+        P: AsRef<Path>,
+        F: Fn(ProgressEvent) + Send + Sync,
+    {
+        let dir = dir.as_ref();
+        let files = self.scanner_config.collect_files(dir)?;
+        let (scanner, prefixes) = self.with_prefixes_below(dir)?;
+
+        let completed = AtomicUsize::new(0);
+        let classes_found = AtomicUsize::new(0);
+        let errors = AtomicUsize::new(0);
+        let start = |index: usize, path: &Path| {
+            on_progress(ProgressEvent::FileStarted { path: path.to_path_buf(), index, total: files.len() });
+        };
+        let report = |path: &Path, parsed: &Result<ParsedFile, Error>| {
+            match parsed {
+                Ok((classes, ..)) => {
+                    // Not counting the unnamed root
+                    let class_count = classes.len().saturating_sub(1);
+                    classes_found.fetch_add(class_count, Ordering::SeqCst);
+                    on_progress(ProgressEvent::FileCompleted { path: path.to_path_buf(), class_count });
+                }
+                Err(error) => {
+                    errors.fetch_add(1, Ordering::SeqCst);
+                    on_progress(ProgressEvent::FileFailed { path: path.to_path_buf(), error });
+                }
+            }
+            let Some(callback) = self.scanner_config.progress_callback() else {
+                return;
+            };
+            callback(ScanProgress {
+                files_discovered: files.len(),
+                files_completed: completed.fetch_add(1, Ordering::SeqCst) + 1,
+                current_path: path.to_path_buf(),
+                classes_found: classes_found.load(Ordering::SeqCst),
+                errors: errors.load(Ordering::SeqCst),
+            });
+        };
+
+        let mut result = ScanResult::default();
+        let mut warnings = Vec::new();
+        for (path, parsed) in files.iter().zip(scanner.parse_files_with_warnings(&files, start, report)) {
+            let relative = path.strip_prefix(dir).unwrap_or(path).to_path_buf();
+            match parsed {
+                Ok((mut classes, file_warnings, _)) => {
+                    warnings.extend(file_warnings);
+                    classes.remove(0); // The unnamed root; its classes follow it
+                    result.classes.insert(relative, classes);
+                }
+                Err(e) => {
+                    result.errors.insert(relative, e);
+                }
+            }
+        }
+        for (addon_dir, prefix) in prefixes {
+            let relative = addon_dir.strip_prefix(dir).unwrap_or(&addon_dir).to_path_buf();
+            result.prefixes.insert(relative, prefix);
+        }
+        self.set_warnings(warnings);
+        Ok(result)
+    }
+
+    /// [`scan_directory`](Self::scan_directory), parsing only the files that
+    /// are new or whose modification time, or that of a file they include,
+    /// differs from the one in `cache`. The others are taken from the cache,
+    /// which is updated for the next call. Files that failed to parse are
+    /// always parsed again.
+    ///
+    /// Warnings cover every file: those of cached files are replayed from
+    /// the cache along with their classes.
+    pub fn scan_directory_incremental(&self, dir: &Path, cache: &mut ScanCache) -> Result<IncrementalResult, Error> {
+        let files = self.scanner_config.collect_files(dir)?;
+        let (scanner, prefixes) = self.with_prefixes_below(dir)?;
+
+        let mut incremental = IncrementalResult::default();
+        let mut stale = Vec::new();
+        let mut current = std::collections::HashSet::new();
+        let mut warnings = Vec::new();
+        for path in files {
+            let relative = path.strip_prefix(dir).unwrap_or(&path).to_path_buf();
+            let modified = std::fs::metadata(&path)?.modified()?;
+            if let Some((classes, cached_warnings)) = cache.get(&relative, modified) {
+                incremental.result.classes.insert(relative.clone(), classes.clone());
+                warnings.extend_from_slice(cached_warnings);
+            } else {
+                match cache.modified(&relative) {
+                    None => incremental.added.push(relative.clone()),
+                    Some(cached) if cached != modified || cache.includes_changed(&relative) => {
+                        incremental.modified.push(relative.clone());
+                    }
+                    Some(_) => {}
+                }
+                stale.push((path, relative.clone(), modified));
+            }
+            current.insert(relative);
+        }
+        incremental.removed = cache.retain(|path| current.contains(path));
+
+        let paths: Vec<&PathBuf> = stale.iter().map(|(path, _, _)| path).collect();
+        let parsed = scanner.parse_files_with_warnings(&paths, |_, _| {}, |_, _| {});
+        for ((_, relative, modified), parsed) in stale.into_iter().zip(parsed) {
+            match parsed {
+                Ok((mut classes, file_warnings, includes)) => {
+                    classes.remove(0); // The unnamed root; its classes follow it
+                    cache.insert(relative.clone(), modified, Some(classes.clone()), &includes, file_warnings.clone());
+                    warnings.extend(file_warnings);
+                    incremental.result.classes.insert(relative, classes);
+                }
+                Err(e) => {
+                    cache.insert(relative.clone(), modified, None, &[], Vec::new());
+                    incremental.result.errors.insert(relative, e);
+                }
+            }
+        }
+        for (addon_dir, prefix) in prefixes {
+            let relative = addon_dir.strip_prefix(dir).unwrap_or(&addon_dir).to_path_buf();
+            incremental.result.prefixes.insert(relative, prefix);
+        }
+        self.set_warnings(warnings);
+        Ok(incremental)
+    }
+
+    /// A clone of this scanner that also resolves includes through the
+    /// `$PBOPREFIX$` prefixes of the addons below `dir`, and those prefixes.
+    fn with_prefixes_below(&self, dir: &Path) -> Result<(Self, std::collections::BTreeMap<PathBuf, String>), Error> {
+        let prefixes = self.scanner_config.collect_prefixes(dir)?;
+        let mut scanner = self.clone();
+        for (addon_dir, prefix) in &prefixes {
+            scanner.addon_prefixes.push((prefix.clone(), addon_dir.clone()));
+        }
+        Ok((scanner, prefixes))
+    }
+
+    /// Index every class below `paths` (directories or single files), then
+    /// resolve inheritance across all of them, so a class may inherit from
+    /// one defined in another addon.
+    ///
+    /// Files that fail to parse are reported in the result's `errors`, and
+    /// parents found in none of the files in `unresolved`.
+    pub fn scan_and_resolve(&self, paths: &[PathBuf]) -> Result<ResolvedScan, Error> {
+        let mut result = ResolvedScan::default();
+        for path in paths {
+            if path.is_dir() {
+                let mut scan = self.scan_directory(path)?;
+                for (file, error) in std::mem::take(&mut scan.errors) {
+                    result.errors.insert(path.join(file), error);
+                }
+                result.index.add_scan(path, &scan)?;
+            } else {
+                match self.parse_file_with_warnings(path) {
+                    Ok((classes, ..)) => {
+                        let origin = scanner::ClassOrigin {
+                            file: path.clone(),
+                            addon: path.parent()
+                                .and_then(Path::file_name)
+                                .map(|name| name.to_string_lossy().into_owned()),
+                            prefix: None,
+                        };
+                        result.index.add_file(classes, origin)?;
+                    }
+                    Err(e) => {
+                        result.errors.insert(path.clone(), e);
+                    }
+                }
+            }
+        }
+
+        let mut visitor = ast::inheritance_visitor::InheritanceVisitor::new()
+            .with_array_options(self.array_options);
+        let mut pending: Vec<(Vec<&str>, &ClassNode)> = result.index.classes().iter()
+            .rev()
+            .map(|class| (vec![class.name.as_str()], class))
+            .collect();
+        let mut classes = Vec::new();
+        while let Some((path, class)) = pending.pop() {
+            for nested in class.nested_classes.iter().rev() {
+                let mut nested_path = path.clone();
+                nested_path.push(&nested.name);
+                pending.push((nested_path, nested));
+            }
+            classes.push((path, class));
+        }
+        for class in result.index.classes() {
+            visitor.register_class(class.clone());
+        }
+
+        for (path, class) in classes {
+            if class.is_external {
+                continue;
+            }
+            let key = path.join("/");
+            if let Some(parent) = &class.parent {
+                if visitor.find_parent(&key, parent).is_none() {
+                    result.unresolved.push(scanner::UnresolvedParent {
+                        class: path.join("."),
+                        parent: parent.clone(),
+                    });
+                }
+            }
+            match visitor.process(&key) {
+                Ok(resolved) => {
+                    result.resolved.insert(path.join("."), resolved);
+                }
+                // A missing ancestor, already reported above
+                Err(Error::InheritanceError(_)) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(result)
+    }
+
+    /// Combine the results of parsing several files into one set of top-level
+    /// classes.
+    ///
+    /// Classes with the same name (ignoring case) are merged with
+    /// [`MergeStrategy::LastWins`], so later files override earlier ones the
+    /// way the game loads them.
+    pub fn merge_parsed(&self, files: Vec<Vec<ClassNode>>) -> Result<Vec<ClassNode>, Error> {
+        let mut merged: Vec<ClassNode> = Vec::new();
+        for classes in files {
+            // parse_string/parse_file list clones of the root's classes after it
+            let top_level = match classes.first() {
+                Some(root) if root.name.is_empty() => classes.into_iter().next().unwrap().nested_classes,
+                _ => classes,
+            };
+            for class in top_level {
+                match merged.iter_mut().find(|c| c.name.eq_ignore_ascii_case(&class.name)) {
+                    Some(existing) => existing.merge_from(class, MergeStrategy::LastWins)?,
+                    None => merged.push(class),
+                }
+            }
+        }
+        Ok(merged)
+    }
+
+    /// Render the inheritance graph of `classes` as Graphviz DOT, see
+    /// [`ast::graphviz::to_dot_with_options`].
+    pub fn to_dot(&self, classes: &[ClassNode], options: DotOptions) -> String {
+        ast::graphviz::to_dot_with_options(classes, &options)
+    }
+
+    /// Find classes whose name matches a glob pattern, case-insensitively.
+    ///
+    /// `*` matches any run of characters and `?` a single character, so
+    /// `rhs_*_base` matches `RHS_Weapon_Base`. A `.` separates the names of
+    /// enclosing classes, and the pattern matches the end of a class's
+    /// path: `CfgWeapons.*` finds the classes directly in `CfgWeapons`, and
+    /// `*.ItemInfo` every `ItemInfo` nested in another class. Wildcards do
+    /// not match across a `.`. Nested classes are searched too; an unnamed
+    /// parse root is skipped since `parse_string` and `parse_file` already
+    /// list its top-level classes.
+    pub fn find_classes_by_pattern<'a>(&self, classes: &'a [ClassNode], pattern: &str) -> Vec<&'a ClassNode> {
+        let regex = class_path_glob_to_regex(pattern);
+        let mut found = Vec::new();
+        walk_class_paths(classes, "", &mut |path, class| {
+            if regex.is_match(path) {
+                found.push(class);
+            }
+        });
+        found
+    }
+
+    /// Find classes, including nested ones, whose name matches `regex`.
+    pub fn find_classes_by_regex<'a>(&self, classes: &'a [ClassNode], regex: &Regex) -> Vec<&'a ClassNode> {
+        let mut found = Vec::new();
+        walk_classes(classes, &mut |class| {
+            if regex.is_match(&class.name) {
+                found.push(class);
+            }
+        });
+        found
+    }
+
+    /// Every occurrence of property `prop_name` anywhere in the hierarchy,
+    /// with the class that declares it.
+    pub fn find_property_across_classes<'a>(
+        &self,
+        classes: &'a [ClassNode],
+        prop_name: &str,
+    ) -> Vec<(&'a ClassNode, &'a PropertyNode)> {
+        let mut found = Vec::new();
+        walk_classes(classes, &mut |class| {
+            if let Some(property) = class.properties.get(prop_name) {
+                found.push((class, property));
+            }
+        });
+        found
+    }
+}
+
+/// Depth-first walk over named classes and their nested classes.
+fn walk_classes<'a>(classes: &'a [ClassNode], visit: &mut impl FnMut(&'a ClassNode)) {
+    for class in classes {
+        if class.name.is_empty() {
+            continue;
+        }
+        visit(class);
+        walk_classes(&class.nested_classes, visit);
+    }
+}
+
+/// [`walk_classes`] with each class's dot-separated path below `scope`.
+fn walk_class_paths<'a>(classes: &'a [ClassNode], scope: &str, visit: &mut impl FnMut(&str, &'a ClassNode)) {
+    for class in classes {
+        if class.name.is_empty() {
+            continue;
+        }
+        let path = if scope.is_empty() {
+            class.name.clone()
+        } else {
+            format!("{}.{}", scope, class.name)
+        };
+        visit(&path, class);
+        walk_class_paths(&class.nested_classes, &path, visit);
+    }
+}
+
+/// Run `parse` for `path`, turning a panic into `Error::ParserPanic` so one
+/// file the parser chokes on does not abort a whole scan.
+fn catch_parser_panic<F>(path: &Path, parse: F) -> Result<ParsedFile, Error>
+where
+    F: FnOnce() -> Result<ParsedFile, Error>,
+{
+    std::panic::catch_unwind(std::panic::AssertUnwindSafe(parse))
+        .unwrap_or_else(|_| Err(Error::ParserPanic(path.to_path_buf())))
+}
+
+/// Regex matching a dot-separated class path that ends with the segments
+/// of `pattern`, where wildcards stay within a segment.
+fn class_path_glob_to_regex(pattern: &str) -> Regex {
+    let mut regex = String::from(r"(?i)(?:^|\.)");
+    for c in pattern.chars() {
+        match c {
+            '*' => regex.push_str("[^.]*"),
+            '?' => regex.push_str("[^.]"),
+            _ => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    regex.push('$');
+    Regex::new(&regex).expect("escaped glob is a valid regex")
+}
+
+fn glob_to_regex(pattern: &str) -> Regex {
+    let mut regex = String::from("(?i)^");
+    for c in pattern.chars() {
+        match c {
+            '*' => regex.push_str(".*"),
+            '?' => regex.push('.'),
+            _ => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    regex.push('$');
+    Regex::new(&regex).expect("escaped glob is a valid regex")
 }
 
 /// Configuration class representation used for serialization/deserialization.
@@ -213,28 +1098,105 @@ impl ClassScanner {
 pub struct ClassConfig {
     pub name: String,
     pub extends: Option<String>,
-    pub properties: HashMap<String, PropertyValue>,
+    pub properties: IndexMap<String, PropertyValue>,
     pub nested_classes: Vec<ClassConfig>,
     pub raw_block: String,
     pub file_path: Option<String>,
+    #[serde(default)]
+    pub is_external: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub location: Option<SourceLocation>,
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub property_locations: IndexMap<String, SourceLocation>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub doc_comment: Option<String>,
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub property_doc_comments: IndexMap<String, String>,
+    /// `+=`, `-=` and `&=` of array properties; arrays not listed replace
+    /// the inherited value.
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub property_array_operations: IndexMap<String, ArrayOperation>,
+}
+
+impl ClassConfig {
+    /// Config file text for this class, with the default [`FormatOptions`].
+    pub fn to_config_string(&self) -> String {
+        self.to_config_string_with(&FormatOptions::default())
+    }
+
+    pub fn to_config_string_with(&self, options: &FormatOptions) -> String {
+        let mut output = String::new();
+        self.write_config(&mut output, options).expect("writing to a String cannot fail");
+        output
+    }
 }
 
 /// Conversion from ClassNode to ClassConfig for serialization
 impl From<ClassNode> for ClassConfig {
     fn from(node: ClassNode) -> Self {
+        let property_locations = node.properties.iter()
+            .filter_map(|(k, v)| v.location.clone().map(|location| (k.clone(), location)))
+            .collect();
+        let property_doc_comments = node.properties.iter()
+            .filter(|(_, v)| !v.is_deleted())
+            .filter_map(|(k, v)| v.doc_comment.clone().map(|comment| (k.clone(), comment)))
+            .collect();
+        let property_array_operations = node.properties.iter()
+            .filter(|(_, v)| v.value_type == ast::PropertyType::Array)
+            .filter_map(|(k, v)| match v.operation {
+                Some(ArrayOperation::Replace) | None => None,
+                Some(operation) => Some((k.clone(), operation)),
+            })
+            .collect();
+
         ClassConfig {
             name: node.name,
             extends: node.parent,
             properties: node.properties.into_iter()
+                .filter(|(_, v)| !v.is_deleted())
                 .map(|(k, v)| (k, PropertyValue::from(v)))
                 .collect(),
             nested_classes: node.nested_classes.into_iter().map(ClassConfig::from).collect(),
             raw_block: node.raw_block,
             file_path: node.file_path.map(|p| p.to_string_lossy().to_string()),
+            is_external: node.is_external,
+            location: node.location,
+            property_locations,
+            doc_comment: node.doc_comment,
+            property_doc_comments,
+            property_array_operations,
         }
     }
 }
 
+/// Rehydrates the classes and properties of a `ClassConfig`. Source text,
+/// spans and `delete` statements are not kept by `ClassConfig` and stay
+/// empty.
+impl From<ClassConfig> for ClassNode {
+    fn from(config: ClassConfig) -> Self {
+        let mut node = ClassNode::new(config.name);
+        node.parent = config.extends;
+        node.properties = config.properties.into_iter()
+            .map(|(name, value)| {
+                let mut property = PropertyNode::from_value(name.clone(), value);
+                if property.value_type == ast::PropertyType::Array {
+                    property.operation = Some(config.property_array_operations.get(&name).copied().unwrap_or(ArrayOperation::Replace));
+                }
+                property.location = config.property_locations.get(&name).cloned();
+                property.doc_comment = config.property_doc_comments.get(&name).cloned();
+                (name, property)
+            })
+            .collect();
+        node.nested_classes = config.nested_classes.into_iter().map(ClassNode::from).collect();
+        node.raw_block = config.raw_block;
+        node.file_path = config.file_path.map(PathBuf::from);
+        node.is_external = config.is_external;
+        node.location = config.location;
+        node.doc_comment = config.doc_comment;
+        node
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -286,25 +1248,446 @@ mod tests {
     }
 
     #[test]
-    fn test_inheritance() {
-        let base_class = r#"
-            class BaseClass {
-                inherited = "base";
-                overridden = "base_value";
-            }
+    fn test_array_intersect() {
+        let input = r#"
+            class Base {
+                items[] = {"a", "b", "c"};
+            };
+            class Child : Base {
+                items[] &= {"c", "d", "b"};
+            };
         "#;
 
-        let derived_class = r#"
-            class DerivedClass : public BaseClass {
-                new_prop = "derived";
-                overridden = "derived_value";
-            }
-        "#;
+        let scanner = ClassScanner::new();
+        let classes = scanner.parse_string(input).unwrap();
+        let child = classes.iter().find(|c| c.name == "Child").unwrap();
+        assert_eq!(child.properties["items"].operation, Some(ArrayOperation::Intersect));
 
-        let tokens = lexer::Tokenizer::new(base_class).tokenize().unwrap();
-        let mut parser = Parser::new(tokens);
-        let base = parser.parse().unwrap();
-        let base_class = base.nested_classes[0].clone();
+        let resolved = scanner.process_inheritance(classes, "Child").unwrap();
+        assert_eq!(resolved.properties["items"].array_values, vec!["b", "c"]);
+    }
+
+    #[test]
+    fn test_external_class_declarations() {
+        let input = r#"
+            class Base;
+            class Empty {};
+            class Child : Base {
+                x = 1;
+            };
+        "#;
+
+        let scanner = ClassScanner::new();
+        let classes = scanner.parse_string(input).unwrap();
+        let flags: Vec<(&str, bool)> = classes.iter().skip(1).map(|c| (c.name.as_str(), c.is_external)).collect();
+        assert_eq!(flags, vec![("Base", true), ("Empty", false), ("Child", false)]);
+
+        let json = serde_json::to_value(ClassConfig::from(classes[1].clone())).unwrap();
+        assert_eq!(json["is_external"], true);
+
+        let resolved = scanner.process_all_inheritance(classes).unwrap();
+        let child = resolved.iter().find(|c| c.name == "Child").unwrap();
+        assert_eq!(child.properties.len(), 1);
+    }
+
+    #[test]
+    fn test_find_classes() {
+        let input = r#"
+            class CfgWeapons {
+                class rhs_weap_base {
+                    displayName = "Base";
+                };
+                class RHS_Rifle_Base : rhs_weap_base {
+                    class ItemInfo {
+                        displayName = "Info";
+                    };
+                };
+            };
+            class CfgVehicles {
+                class rhs_car_base {};
+            };
+        "#;
+
+        let scanner = ClassScanner::new();
+        let classes = scanner.parse_string(input).unwrap();
+        let names = |found: Vec<&ClassNode>| found.iter().map(|c| c.name.clone()).collect::<Vec<_>>();
+
+        assert_eq!(names(scanner.find_classes_by_pattern(&classes, "rhs_*_base")),
+                   vec!["rhs_weap_base", "RHS_Rifle_Base", "rhs_car_base"]);
+        assert_eq!(names(scanner.find_classes_by_pattern(&classes, "Cfg*")), vec!["CfgWeapons", "CfgVehicles"]);
+        assert_eq!(names(scanner.find_classes_by_pattern(&classes, "iteminf?")), vec!["ItemInfo"]);
+        assert_eq!(names(scanner.find_classes_by_pattern(&classes, "CfgWeapons.*")), vec!["rhs_weap_base", "RHS_Rifle_Base"]);
+        assert_eq!(names(scanner.find_classes_by_pattern(&classes, "cfgweapons.*.iteminfo")), vec!["ItemInfo"]);
+        assert_eq!(names(scanner.find_classes_by_pattern(&classes, "*.rhs_*_base")),
+                   vec!["rhs_weap_base", "RHS_Rifle_Base", "rhs_car_base"]);
+        assert!(scanner.find_classes_by_pattern(&classes, "CfgWeapons*ItemInfo").is_empty());
+
+        let regex = Regex::new(r"^rhs_(weap|car)_base$").unwrap();
+        assert_eq!(names(scanner.find_classes_by_regex(&classes, &regex)), vec!["rhs_weap_base", "rhs_car_base"]);
+
+        let found = scanner.find_property_across_classes(&classes, "displayName");
+        let found: Vec<(&str, &str)> = found.iter().map(|(c, p)| (c.name.as_str(), p.raw_value.as_str())).collect();
+        assert_eq!(found, vec![("rhs_weap_base", "Base"), ("ItemInfo", "Info")]);
+    }
+
+    #[test]
+    fn test_property_declaration_order() {
+        let input = r#"
+            class Base {
+                zeta = 1;
+                alpha = 2;
+                mid = 3;
+            };
+            class Child : Base {
+                second = 5;
+                first = 4;
+                alpha = 6;
+            };
+        "#;
+
+        let scanner = ClassScanner::new();
+        let classes = scanner.parse_string(input).unwrap();
+        let keys = |class: &ClassNode| class.properties.keys().cloned().collect::<Vec<_>>();
+        assert_eq!(keys(&classes[1]), vec!["zeta", "alpha", "mid"]);
+        assert_eq!(keys(&classes[2]), vec!["second", "first", "alpha"]);
+
+        // Inherited properties follow the child's own, in the parent's order
+        let resolved = scanner.process_inheritance(classes.clone(), "Child").unwrap();
+        assert_eq!(keys(&resolved), vec!["second", "first", "alpha", "zeta", "mid"]);
+        let resolved = scanner.process_all_inheritance(classes).unwrap();
+        assert_eq!(keys(&resolved[1]), vec!["second", "first", "alpha", "zeta", "mid"]);
+
+        let json = serde_json::to_string(&ClassConfig::from(resolved[1].clone())).unwrap();
+        let positions: Vec<usize> = ["second", "first", "alpha", "zeta", "mid"].iter()
+            .map(|key| json.find(&format!("\"{}\"", key)).unwrap())
+            .collect();
+        assert!(positions.windows(2).all(|pair| pair[0] < pair[1]), "{}", json);
+    }
+
+    #[test]
+    fn test_source_locations() {
+        let input = "class Base {\n    baseProp = 1;\n};\nclass Child : Base {\n    childProp = \"value\";\n    class Nested {\n        items[] = {1, 2};\n    };\n};\n";
+
+        let scanner = ClassScanner::new();
+        let classes = scanner.parse_string(input).unwrap();
+        let child = classes.iter().find(|c| c.name == "Child").unwrap();
+
+        let location = child.location.as_ref().unwrap();
+        assert_eq!((location.line, location.column), (4, 0));
+        assert_eq!(child.properties["childProp"].location.as_ref().unwrap().line, 5);
+        assert_eq!(child.properties["childProp"].location.as_ref().unwrap().column, 4);
+        let nested = &child.nested_classes[0];
+        assert_eq!(nested.location.as_ref().unwrap().line, 6);
+        assert_eq!(nested.properties["items"].location.as_ref().unwrap().line, 7);
+
+        // Inherited properties keep the parent's location, the child keeps its own
+        let processed = scanner.process_inheritance(classes, "Child").unwrap();
+        assert_eq!(processed.location.as_ref().unwrap().line, 4);
+        assert_eq!(processed.properties["baseProp"].location.as_ref().unwrap().line, 2);
+        assert_eq!(processed.properties["childProp"].location.as_ref().unwrap().line, 5);
+
+        let config = ClassConfig::from(processed);
+        assert_eq!(config.location.as_ref().unwrap().line, 4);
+        assert_eq!(config.property_locations["baseProp"].line, 2);
+        let json = serde_json::to_string(&config).unwrap();
+        let restored: ClassConfig = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored, config);
+    }
+
+    #[test]
+    fn test_source_spans() {
+        let input = "class Base;\nclass Child : Base {\n    name = \"Ünïcode\";\n    items[] += {1, {2, 3}};\n    delete old;\n};\n";
+
+        let tokens = lexer::Tokenizer::new(input).tokenize().unwrap();
+        let root = Parser::new(tokens).parse().unwrap();
+
+        let base = &root.nested_classes[0];
+        assert_eq!(base.span.unwrap().text(input), "class Base;");
+        assert_eq!(base.block_span, None);
+
+        let child = &root.nested_classes[1];
+        assert_eq!(child.name_span.unwrap().text(input), "Child");
+        let block = child.block_span.unwrap();
+        assert!(block.text(input).starts_with('{') && block.text(input).ends_with('}'));
+        assert_eq!((block.line, block.column), (2, 19));
+        assert!(child.span.unwrap().text(input).starts_with("class Child : Base {"));
+
+        let name = child.properties["name"].span.unwrap();
+        assert_eq!(name.text(input), "name = \"Ünïcode\";");
+        assert_eq!((name.line, name.column), (3, 4));
+        assert_eq!(child.properties["items"].span.unwrap().text(input), "items[] += {1, {2, 3}};");
+        assert_eq!(child.properties["old"].span.unwrap().text(input), "delete old;");
+    }
+
+    #[test]
+    fn test_parse_with_recovery() {
+        let input = r#"
+            class Good {
+                value = 1;
+            };
+            class A {
+                ok = 1;
+                broken = ;
+            };
+            class B {
+                bad[] = {1 2};
+                fine = "yes";
+            };
+            class C {
+                = 3;
+            };
+        "#;
+
+        let tokens = lexer::Tokenizer::new(input).tokenize().unwrap();
+        let (root, errors) = Parser::new(tokens).parse_with_recovery();
+
+        let lines: Vec<usize> = errors.iter().map(|e| match e {
+            Error::ParseError { location, .. } => location.line,
+            other => panic!("unexpected error {:?}", other),
+        }).collect();
+        assert_eq!(lines, vec![7, 10, 14]);
+
+        let names: Vec<&str> = root.nested_classes.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["Good", "A", "B", "C"]);
+        assert_eq!(root.nested_classes[0].properties["value"].raw_value, "1");
+        assert_eq!(root.nested_classes[1].properties["ok"].raw_value, "1");
+        assert_eq!(root.nested_classes[2].properties["fine"].raw_value, "yes");
+        assert!(root.nested_classes[3].properties.is_empty());
+    }
+
+    #[test]
+    fn test_error_recovery() {
+        let input = r#"
+            class First {
+                valid = 1;
+                = "missing name";
+                also_valid = 2;
+            };
+            class Second {
+                broken[] = {1, 2 3};
+                class Inner {
+                    inner = "ok";
+                    oops = ;
+                };
+                after = "kept";
+            };
+            class Third {
+                last = 3;
+        "#;
+
+        let tokens = lexer::Tokenizer::new(input).tokenize().unwrap();
+
+        // Without recovery the first error aborts parsing
+        assert!(Parser::new(tokens.clone()).parse().is_err());
+
+        let mut parser = Parser::new(tokens).with_error_recovery(true);
+        let root = parser.parse().unwrap();
+        let errors = parser.take_errors();
+
+        assert_eq!(errors.len(), 4);
+        assert!(errors.iter().all(|e| matches!(e, Error::ParseError { .. })));
+        assert!(parser.take_errors().is_empty());
+
+        assert_eq!(root.nested_classes.len(), 3);
+        let first = &root.nested_classes[0];
+        assert_eq!(first.properties.len(), 2);
+        assert_eq!(first.properties["also_valid"].raw_value, "2");
+
+        let second = &root.nested_classes[1];
+        assert!(!second.properties.contains_key("broken"));
+        assert_eq!(second.properties["after"].raw_value, "kept");
+        assert_eq!(second.nested_classes[0].properties["inner"].raw_value, "ok");
+
+        let third = &root.nested_classes[2];
+        assert_eq!(third.properties["last"].raw_value, "3");
+    }
+
+    #[test]
+    fn test_enum_parsing() {
+        let input = r#"
+            enum {
+                destructengine = 2,
+                destructdefault = 6,
+                destructwreck,
+                stabilizedinaxisx = 0x01,
+                stabilizedinaxesboth = 3,
+                stabilizedinaxesnone = destructengine,
+            };
+            enum Visibility { private = 0, protected = 1, public = 2 };
+            class Vehicle {
+                destrType = 2;
+            };
+        "#;
+
+        let tokens = lexer::Tokenizer::new(input).tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+        let root = parser.parse().unwrap();
+
+        assert_eq!(root.nested_classes.len(), 1);
+        assert_eq!(root.enums.len(), 2);
+
+        let anonymous = &root.enums[0];
+        assert_eq!(anonymous.name, "");
+        assert_eq!(anonymous.get("destructdefault"), Some(6));
+        assert_eq!(anonymous.get("destructwreck"), Some(7));
+        assert_eq!(anonymous.get("stabilizedinaxisx"), Some(1));
+        assert_eq!(anonymous.get("stabilizedinaxesnone"), Some(2));
+        assert_eq!(anonymous.values.keys().next().map(String::as_str), Some("destructengine"));
+        assert_eq!(anonymous.location.as_ref().unwrap().line, 2);
+
+        let visibility = &root.enums[1];
+        assert_eq!(visibility.name, "Visibility");
+        assert_eq!(visibility.values.len(), 3);
+        assert_eq!(visibility.get("public"), Some(2));
+
+        let tokens = lexer::Tokenizer::new("enum { a = \"text\" };").tokenize().unwrap();
+        assert!(Parser::new(tokens).parse().is_err());
+    }
+
+    #[test]
+    fn test_delete_keyword() {
+        let input = r#"
+            class Base {
+                armor = 100;
+                camouflage = 2;
+                class Turrets {};
+            };
+            class Child : Base {
+                delete camouflage;
+                delete Turrets;
+                delete = 1;
+            };
+        "#;
+
+        let scanner = ClassScanner::new();
+        let classes = scanner.parse_string(input).unwrap();
+        let child = classes.iter().find(|c| c.name == "Child").unwrap();
+        assert_eq!(child.properties["camouflage"].value_type, PropertyType::Deleted);
+        assert_eq!(child.properties["Turrets"].value_type, PropertyType::Deleted);
+        // `delete` is still usable as an ordinary property name
+        assert_eq!(child.properties["delete"].value_type, PropertyType::Number);
+        // The markers are the only record of a parsed delete
+        assert!(child.deleted_classes.is_empty());
+
+        let resolved = scanner.process_inheritance(classes, "Child").unwrap();
+        assert_eq!(resolved.properties["armor"].raw_value, "100");
+        assert!(!resolved.properties.contains_key("camouflage"));
+        assert!(!resolved.properties.contains_key("Turrets"));
+        assert!(resolved.nested_classes.is_empty());
+
+        assert!(scanner.parse_string("class A { delete; };").is_err());
+    }
+
+    #[test]
+    fn test_class_node_json_round_trip() {
+        let input = r#"
+            class Base {
+                weapons[] = {"M4A1", "Glock"};
+                class Turrets {
+                    class MainTurret { gunner = "B_Soldier_F"; };
+                };
+            };
+            class Child : Base {
+                weapons[] += {"M16"};
+                magazines[] -= {"30Rnd"};
+                armor = 0x1F;
+            };
+        "#;
+
+        let scanner = ClassScanner::new();
+        let classes = scanner.parse_string(input).unwrap();
+
+        let json = serde_json::to_string(&classes).unwrap();
+        let restored: Vec<ClassNode> = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored, classes);
+
+        let child = restored.iter().find(|c| c.name == "Child").unwrap();
+        assert_eq!(child.properties["weapons"].operation, Some(ArrayOperation::Append));
+        assert_eq!(child.properties["magazines"].operation, Some(ArrayOperation::Remove));
+        assert_eq!(child.properties["armor"].raw_value, "0x1F");
+
+        let mut expected = scanner.process_inheritance(classes, "Child").unwrap();
+        let mut actual = scanner.process_inheritance(restored, "Child").unwrap();
+        scanner.process_arrays(&mut expected).unwrap();
+        scanner.process_arrays(&mut actual).unwrap();
+        assert_eq!(actual, expected);
+        assert_eq!(actual.nested_classes[0].nested_classes[0].name, "MainTurret");
+    }
+
+    #[test]
+    fn test_nested_array_parsing() {
+        let input = r#"
+            class Loadout {
+                weapons[] = {{"M4A1","ACOG"},{"Glock","Flashlight"}};
+                mixed[] = {"single", {1, {2, 3}}, {}};
+                flat[] = {"a", "b"};
+            };
+        "#;
+
+        let tokens = lexer::Tokenizer::new(input).tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+        let result = parser.parse().unwrap();
+
+        let loadout = &result.nested_classes[0];
+        let weapons = loadout.get_array("weapons").unwrap();
+        assert_eq!(weapons.len(), 2);
+        assert_eq!(weapons[0], "{M4A1,ACOG}");
+        assert_eq!(weapons[1], "{Glock,Flashlight}");
+        assert_eq!(loadout.properties["weapons"].raw_value, "{{M4A1,ACOG},{Glock,Flashlight}}");
+
+        assert_eq!(loadout.get_array("mixed").unwrap(), &["single", "{1,{2,3}}", "{}"]);
+        assert_eq!(loadout.properties["mixed"].raw_value, "{single,{1,{2,3}},{}}");
+        assert_eq!(loadout.get_array("flat").unwrap(), &["a", "b"]);
+    }
+
+    #[test]
+    fn test_number_notation_preserved() {
+        let input = r#"
+            class Colors {
+                color = 0xFF0000;
+                mask = -0x0F;
+                mode = 0o644;
+                padded = 0644;
+                plain = 42;
+                values[] = {0x10, 16};
+            }
+        "#;
+
+        let tokens = lexer::Tokenizer::new(input).tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+        let result = parser.parse().unwrap();
+
+        let colors = &result.nested_classes[0];
+        assert_eq!(colors.properties["color"].raw_value, "0xFF0000");
+        assert_eq!(colors.properties["color"].value_type, PropertyType::Number);
+        assert_eq!(colors.properties["mask"].raw_value, "-0x0F");
+        assert_eq!(colors.properties["mode"].raw_value, "0o644");
+        // A leading zero is not octal, and decimals are not kept as written
+        assert_eq!(colors.properties["padded"].raw_value, "644");
+        assert_eq!(colors.properties["plain"].raw_value, "42");
+        assert_eq!(colors.properties["values"].array_values, vec!["0x10", "16"]);
+    }
+
+    #[test]
+    fn test_inheritance() {
+        let base_class = r#"
+            class BaseClass {
+                inherited = "base";
+                overridden = "base_value";
+            }
+        "#;
+
+        let derived_class = r#"
+            class DerivedClass : public BaseClass {
+                new_prop = "derived";
+                overridden = "derived_value";
+            }
+        "#;
+
+        let tokens = lexer::Tokenizer::new(base_class).tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+        let base = parser.parse().unwrap();
+        let base_class = base.nested_classes[0].clone();
 
         let tokens = lexer::Tokenizer::new(derived_class).tokenize().unwrap();
         let mut parser = Parser::new(tokens);
@@ -353,4 +1736,877 @@ mod tests {
         let array = processed.get_array("array").unwrap();
         assert_eq!(array, &["item1", "item2", "item3"]); // Base array + appended item
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_parse_file_top_level_classes() {
+        use std::io::Write;
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let config_path = temp_dir.path().join("config.cpp");
+        std::fs::File::create(&config_path).unwrap().write_all(br#"
+            class CfgPatches {
+                class TestAddon {
+                    units[] = {};
+                };
+            };
+            class CfgWeapons {
+                class Rifle_Base;
+                class TestRifle : Rifle_Base {
+                    mass = 10;
+                };
+            };
+            class CfgVehicles {
+                class TestCar {
+                    crew = 1;
+                };
+            };
+        "#).unwrap();
+
+        let scanner = ClassScanner::new();
+        let classes = scanner.parse_file(&config_path).unwrap();
+        assert!(classes.len() >= 3);
+
+        let names: Vec<&str> = classes.iter().map(|c| c.name.as_str()).collect();
+        assert!(names.contains(&"CfgPatches"));
+        assert!(names.contains(&"CfgWeapons"));
+        assert!(names.contains(&"CfgVehicles"));
+
+        // Top-level classes are directly visible to inheritance processing
+        let vehicles = scanner.process_inheritance(classes, "CfgVehicles").unwrap();
+        assert_eq!(vehicles.nested_classes[0].name, "TestCar");
+    }
+
+    /// Classes `C0` to `C{count - 1}`, each inheriting from the one before
+    /// and adding one property.
+    fn inheritance_chain(count: usize) -> Vec<ClassNode> {
+        let mut input = String::from("class C0 { p0 = 0; };\n");
+        for i in 1..count {
+            input.push_str(&format!("class C{}: C{} {{ p{} = {}; }};\n", i, i - 1, i, i));
+        }
+        let tokens = lexer::Tokenizer::new(&input).tokenize().unwrap();
+        Parser::new(tokens).parse().unwrap().nested_classes
+    }
+
+    #[test]
+    fn test_process_all_inheritance_long_chain() {
+        let classes = inheritance_chain(1000);
+        let scanner = ClassScanner::new();
+        let resolved = scanner.process_all_inheritance(classes.clone()).unwrap();
+
+        assert_eq!(resolved.len(), 1000);
+        for (i, class) in resolved.iter().enumerate() {
+            assert_eq!(class.name, format!("C{}", i));
+            assert_eq!(class.properties.len(), i + 1);
+        }
+
+        for i in [0, 1, 100, 200] {
+            let single = scanner.process_inheritance(classes.clone(), &format!("C{}", i)).unwrap();
+            let mut expected: Vec<_> = single.properties.iter().map(|(k, v)| (k.clone(), v.raw_value.clone())).collect();
+            let mut actual: Vec<_> = resolved[i].properties.iter().map(|(k, v)| (k.clone(), v.raw_value.clone())).collect();
+            expected.sort();
+            actual.sort();
+            assert_eq!(actual, expected);
+        }
+    }
+
+    #[test]
+    fn test_parse_files_parallel() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let addon = temp_dir.path().join("addon");
+        std::fs::create_dir_all(addon.join("sub")).unwrap();
+        std::fs::write(addon.join("base.hpp"), "class Vehicle { crew = 1; weapons[] = {\"Horn\"}; };").unwrap();
+        std::fs::write(addon.join("sub").join("config.cpp"), "class Car : Vehicle { wheels = 4; weapons[] += {\"Radio\"}; };").unwrap();
+        std::fs::write(addon.join("readme.txt"), "not a config").unwrap();
+
+        let scanner = ClassScanner::new();
+        let paths = vec![addon.join("base.hpp"), addon.join("missing.cpp"), addon.join("sub").join("config.cpp")];
+        let results = scanner.parse_files_parallel(&paths);
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].as_ref().unwrap()[1].name, "Vehicle");
+        assert!(results[1].is_err());
+        assert_eq!(results[2].as_ref().unwrap()[1].name, "Car");
+
+        let car = scanner.process_directory_parallel(&addon, "Car").unwrap();
+        assert_eq!(car.properties["crew"].raw_value, "1");
+        assert_eq!(car.properties["wheels"].raw_value, "4");
+        assert_eq!(car.properties["weapons"].array_values, vec!["Horn", "Radio"]);
+        assert!(scanner.last_warnings().is_empty());
+
+        // A broken file is skipped rather than failing the whole directory
+        std::fs::write(addon.join("broken.cpp"), "class Broken {").unwrap();
+        let car = scanner.process_directory_parallel(&addon, "Car").unwrap();
+        assert_eq!(car.properties["crew"].raw_value, "1");
+        assert!(matches!(scanner.last_warnings().as_slice(),
+            [Warning::SkippedFile { file, .. }] if *file == addon.join("broken.cpp")));
+    }
+
+    #[test]
+    fn test_merge_parsed() {
+        let scanner = ClassScanner::new();
+        let base = scanner.parse_string(r#"
+            class CfgPatches { class Base { units[] = {}; }; };
+            class CfgVehicles { class Car { speed = 100; }; };
+        "#).unwrap();
+        let patch = scanner.parse_string(r#"
+            class CfgPatches { class Patch { requiredAddons[] = {"Base"}; }; };
+            class cfgVehicles { class Car { speed = 120; }; class Truck: Car {}; };
+        "#).unwrap();
+
+        let merged = scanner.merge_parsed(vec![base, patch]).unwrap();
+        let names: Vec<&str> = merged.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["CfgPatches", "CfgVehicles"]);
+        assert_eq!(merged[0].nested_classes.len(), 2);
+        assert_eq!(merged[1].get_path("Car").unwrap().properties["speed"].raw_value, "120");
+        assert_eq!(merged[1].get_path("Truck").unwrap().parent.as_deref(), Some("Car"));
+    }
+
+    #[test]
+    fn test_last_warnings() {
+        let scanner = ClassScanner::new();
+        let classes = scanner.parse_string(r#"
+            class A: C { list = {1, 2}; };
+            class B: A { single[] = 3; };
+            class C: B {};
+        "#).unwrap();
+
+        let a = &classes[1];
+        assert_eq!(a.properties["list"].value_type, PropertyType::Array);
+        assert_eq!(a.properties["list"].array_values, vec!["1", "2"]);
+        assert_eq!(classes[2].properties["single"].value_type, PropertyType::Number);
+        assert_eq!(classes[2].properties["single"].operation, None);
+
+        let mismatches: Vec<(String, usize)> = scanner.last_warnings().into_iter().map(|w| match w {
+            Warning::PropertyTypeMismatch { name, location, .. } => (name, location.line),
+            other => panic!("unexpected warning {:?}", other),
+        }).collect();
+        assert_eq!(mismatches, vec![("list".to_string(), 2), ("single".to_string(), 3)]);
+
+        let resolved = scanner.process_all_inheritance(classes.into_iter().skip(1)).unwrap();
+        assert_eq!(resolved.len(), 3);
+        assert!(matches!(
+            scanner.last_warnings().as_slice(),
+            [Warning::CircularInheritanceResolved { chain }] if chain.len() == 4
+        ));
+        assert!(scanner.clone().last_warnings().len() == 1);
+    }
+
+    #[test]
+    fn test_process_file_keeps_parse_warnings() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("config.cpp");
+        std::fs::write(&path, "class A { x = 1; x = 2; };").unwrap();
+
+        let scanner = ClassScanner::new();
+        scanner.parse_file(&path).unwrap();
+        assert_eq!(scanner.last_warnings().len(), 1);
+        let class = scanner.process_file(&path, "A").unwrap();
+        assert_eq!(class.properties["x"].raw_value, "2");
+        assert!(matches!(scanner.last_warnings().as_slice(), [Warning::DuplicateProperty { .. }]));
+    }
+
+    #[test]
+    fn test_string_interning_shared_by_clones() {
+        assert!(ClassScanner::new().string_interner().is_none());
+
+        let scanner = ClassScanner::new().with_string_interning(true);
+        let clone = scanner.clone();
+        scanner.parse_string("class A { scope = 2; model = \"a\"; };").unwrap();
+        let classes = clone.parse_string("class B { scope = 1; class C { scope = 0; }; };").unwrap();
+
+        let interner = scanner.string_interner().unwrap();
+        assert!(Arc::ptr_eq(interner, clone.string_interner().unwrap()));
+        // A, scope, model, B and C
+        assert_eq!(interner.len(), 5);
+        let properties = classes[1].interned_properties(interner);
+        assert_eq!(properties.keys().collect::<Vec<_>>(), vec![&interner.get("scope").unwrap()]);
+        assert_eq!(interner.len(), 5);
+    }
+
+    #[test]
+    fn test_typed_property_declarations() {
+        let input = r#"
+            class A {
+                string name = "x";
+                int values[] = {1, 2};
+                plain = 3;
+            };
+        "#;
+        let tokens = lexer::Tokenizer::new(input).tokenize().unwrap();
+        let root = Parser::new(tokens).parse().unwrap();
+        let a = &root.nested_classes[0];
+        assert_eq!(a.properties.keys().collect::<Vec<_>>(), vec!["name", "values", "plain"]);
+        assert_eq!(a.properties["name"].raw_value, "x");
+        assert_eq!(a.properties["values"].array_values, vec!["1", "2"]);
+    }
+
+    #[test]
+    fn test_parser_checkpoint_and_restore() {
+        let tokens = lexer::Tokenizer::new("a = 1;").tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+        let start = parser.checkpoint();
+        assert!(matches!(parser.peek_n(1).map(|t| &t.token_type), Some(lexer::tokens::TokenType::Equals)));
+        assert!(parser.peek_n(100).is_none());
+        assert!(parser.peek_n(usize::MAX).is_none());
+        parser.restore(2);
+        assert_eq!(parser.checkpoint(), 2);
+        parser.restore(start);
+        assert_eq!(parser.checkpoint(), start);
+        parser.restore(usize::MAX);
+        assert!(parser.peek_n(0).is_none());
+    }
+
+    #[test]
+    fn test_failed_speculative_parse_restores_position() {
+        // Both the untyped and the typed reading of `string bad = ;` fail;
+        // recovery must start from the statement, keeping `good` and `next`
+        let input = r#"
+            class A {
+                string bad = ;
+                good = 1;
+            };
+            class B { next = 2; };
+        "#;
+        let tokens = lexer::Tokenizer::new(input).tokenize().unwrap();
+        let mut parser = Parser::new(tokens).with_error_recovery(true);
+        let root = parser.parse().unwrap();
+        let errors = parser.take_errors();
+
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(&errors[0], Error::ParseError { location, .. } if location.line == 3));
+        assert_eq!(root.nested_classes[0].properties.keys().collect::<Vec<_>>(), vec!["good"]);
+        assert_eq!(root.nested_classes[1].properties["next"].raw_value, "2");
+    }
+
+    #[test]
+    fn test_missing_parent_policy() {
+        let scanner = ClassScanner::new();
+        let classes = scanner.parse_string(r#"
+            class Vest_Base: Vanilla_Base { armor = 10; };
+            class Vest: Vest_Base { mass = 5; };
+        "#).unwrap();
+
+        assert!(scanner.process_inheritance(classes.clone(), "Vest").is_err());
+        assert!(scanner.process_all_inheritance(classes.clone()).is_err());
+
+        let scanner = scanner.with_missing_parent(MissingParent::Collect);
+        let vest = scanner.process_inheritance(classes.clone(), "Vest").unwrap();
+        assert_eq!(vest.properties["armor"].raw_value, "10");
+        assert!(matches!(scanner.last_warnings().as_slice(),
+            [Warning::MissingParent { parent, .. }] if parent == "Vanilla_Base"));
+
+        let resolved = scanner.process_all_inheritance(classes.into_iter().skip(1)).unwrap();
+        assert_eq!(resolved[1].properties["armor"].raw_value, "10");
+        assert_eq!(scanner.last_warnings().len(), 1);
+    }
+
+    #[test]
+    fn test_inherited_append_keeps_duplicates() {
+        let scanner = ClassScanner::new();
+        let classes = scanner.parse_string(r#"
+            class Base { magazines[] = {"mag"}; };
+            class Loadout: Base { magazines[] += {"mag", "mag"}; };
+        "#).unwrap();
+
+        let loadout = scanner.process_inheritance(classes.clone(), "Loadout").unwrap();
+        assert_eq!(loadout.properties["magazines"].array_values, vec!["mag"; 3]);
+
+        let scanner = scanner.with_array_options(operations::ArrayOptions { dedup_on_append: true });
+        let resolved = scanner.process_all_inheritance(classes.into_iter().skip(1)).unwrap();
+        assert_eq!(resolved[1].properties["magazines"].array_values, vec!["mag"]);
+    }
+
+    #[test]
+    fn test_typed_array_values() {
+        let classes = ClassScanner::new().parse_string(r#"
+            class Base { weights[] = {1, "2", true, {0x10, 2.5}, mag}; };
+            class Child: Base { weights[] -= {1.0, {16, 2.5}}; };
+        "#).unwrap();
+
+        let weights = &classes[1].properties["weights"];
+        assert_eq!(weights.array_values, vec!["1", "2", "true", "{0x10,2.5}", "mag"]);
+        assert_eq!(weights.typed_values, vec![
+            ArrayElement::Number(1.0),
+            ArrayElement::String("2".to_string()),
+            ArrayElement::Boolean(true),
+            ArrayElement::Array(vec![ArrayElement::Number(16.0), ArrayElement::Number(2.5)]),
+            ArrayElement::String("mag".to_string()),
+        ]);
+        let total: f64 = weights.typed_values.iter().filter_map(ArrayElement::as_f64).sum();
+        assert_eq!(total, 1.0);
+
+        let child = ClassScanner::new().process_inheritance(classes[1..].to_vec(), "Child").unwrap();
+        assert_eq!(child.properties["weights"].array_values, vec!["2", "true", "mag"]);
+        assert_eq!(child.properties["weights"].typed_values.len(), 3);
+    }
+
+    #[test]
+    fn test_expression_values() {
+        let classes = ClassScanner::new().parse_string(r#"
+            class Vehicle {
+                maxSpeed = 120*0.8;
+                armor = 2+3*4;
+                ratio = (1+2)/2;
+                sum = 0.1+0.2;
+                third = 1/3;
+                offset = 10 - -3;
+                scaled = foo*2;
+                broken = 1/0;
+                values[] = {1+1, -2, size*2};
+            };
+        "#).unwrap();
+        let vehicle = &classes[1];
+        let value = |name: &str| (vehicle.properties[name].value_type.clone(), vehicle.properties[name].raw_value.as_str());
+
+        assert_eq!(value("maxSpeed"), (PropertyType::Number, "96"));
+        assert_eq!(value("armor"), (PropertyType::Number, "14"));
+        assert_eq!(value("ratio"), (PropertyType::Number, "1.5"));
+        assert_eq!(value("sum"), (PropertyType::Number, "0.3"));
+        assert_eq!(value("third"), (PropertyType::Number, "0.333333333333"));
+        assert_eq!(value("offset"), (PropertyType::Number, "13"));
+        assert_eq!(value("scaled"), (PropertyType::String, "foo*2"));
+        assert_eq!(value("broken"), (PropertyType::String, "1/0"));
+        assert_eq!(vehicle.properties["values"].array_values, vec!["2", "-2", "size*2"]);
+        assert_eq!(vehicle.properties["values"].typed_values[2], ArrayElement::String("size*2".to_string()));
+
+        // Written back without float noise
+        let written = operations::ConfigWriter::new().write_class(vehicle);
+        assert!(written.contains("sum = 0.3;") && written.contains("third = 0.333333333333;"), "{}", written);
+        assert!(written.contains("maxSpeed = 96;"), "{}", written);
+
+        assert!(ClassScanner::new().parse_string("class A { value = (1+2; };").is_err());
+        assert!(ClassScanner::new().parse_string("class A { value = 1+; };").is_err());
+    }
+
+    #[test]
+    fn test_doubled_quote_strings() {
+        let input = r#"
+            class Rifle {
+                displayName = "The ""Best"" Rifle";
+                quote = """";
+                names[] = {"a""b", """", {"""nested"""}};
+            };
+        "#;
+        let classes = ClassScanner::new().parse_string(input).unwrap();
+        let rifle = &classes[1];
+        assert_eq!(rifle.properties["displayName"].raw_value, r#"The "Best" Rifle"#);
+        assert_eq!(rifle.properties["quote"].raw_value, r#"""#);
+        assert_eq!(rifle.properties["names"].array_values, vec![r#"a"b"#, r#"""#, r#"{"nested"}"#]);
+        assert_eq!(rifle.properties["names"].typed_values[2], ArrayElement::Array(vec![ArrayElement::String(r#""nested""#.to_string())]));
+
+        // Written back with the quotes doubled again
+        let reparsed = ClassScanner::new().parse_string(&classes[0].to_config_string()).unwrap();
+        assert_eq!(reparsed[1], classes[1]);
+    }
+
+    #[test]
+    fn test_hex_and_signed_number_values() {
+        let classes = ClassScanner::new().parse_string(r#"
+            class RscText {
+                color = 0xFF0000;
+                style = 0x4000;
+                offset = +5;
+                alpha = .5;
+                colors[] = {0xFF, 1.5, -.25, +2};
+            };
+        "#).unwrap();
+        let text = &classes[1];
+        let value = |name: &str| PropertyValue::from(text.properties[name].clone());
+
+        assert_eq!(text.properties["color"].value_type, PropertyType::Number);
+        assert_eq!(text.properties["color"].raw_value, "0xFF0000");
+        assert_eq!(value("color"), PropertyValue::Number(16711680.0));
+        assert_eq!(value("style"), PropertyValue::Number(16384.0));
+        assert_eq!(value("offset"), PropertyValue::Number(5.0));
+        assert_eq!(value("alpha"), PropertyValue::Number(0.5));
+
+        let colors = &text.properties["colors"];
+        assert_eq!(colors.array_values, vec!["0xFF", "1.5", "-0.25", "2"]);
+        assert_eq!(colors.typed_values, vec![
+            ArrayElement::Number(255.0),
+            ArrayElement::Number(1.5),
+            ArrayElement::Number(-0.25),
+            ArrayElement::Number(2.0),
+        ]);
+    }
+
+    #[test]
+    fn test_error_spans() {
+        let input = "class A {\n    name = \"Café\" oops;\n};";
+        let tokens = lexer::Tokenizer::new(input).tokenize().unwrap();
+        let string = tokens.iter().find(|token| matches!(token.token_type, lexer::tokens::TokenType::StringLiteral(_))).unwrap();
+        assert_eq!((string.start, string.end), (21, 28));
+        assert_eq!(&input[string.start..string.end], "\"Café\"");
+
+        let Err(Error::ParseError { location, .. }) = Parser::new(tokens).parse() else {
+            panic!("expected a parse error");
+        };
+        assert_eq!(location.span, Some(29..33));
+        assert_eq!(location.snippet(input), "2 |     name = \"Café\" oops;\n  |                   ^^^^");
+    }
+
+    #[test]
+    fn test_doc_comments_in_class_config() {
+        let input = r#"
+            /* Base rifle */
+            class Rifle {
+                // Visible in the arsenal
+                scope = 2; // public
+                mass = 10; /* kg */
+                // Not a trailing comment of mass
+                class Optics {};
+            };
+        "#;
+        let tokens = lexer::Tokenizer::new(input).with_comments(true).tokenize().unwrap();
+        let root = Parser::new(tokens).with_comments(true).parse().unwrap();
+        let rifle = &root.nested_classes[0];
+        assert_eq!(rifle.properties["scope"].doc_comment.as_deref(), Some("Visible in the arsenal\npublic"));
+        assert_eq!(rifle.properties["mass"].doc_comment.as_deref(), Some("kg"));
+        assert_eq!(rifle.nested_classes[0].doc_comment.as_deref(), Some("Not a trailing comment of mass"));
+
+        let config = ClassConfig::from(rifle.clone());
+        assert_eq!(config.doc_comment.as_deref(), Some("Base rifle"));
+        assert_eq!(config.property_doc_comments["scope"], "Visible in the arsenal\npublic");
+        let json = serde_json::to_value(&config).unwrap();
+        assert_eq!(json["doc_comment"], "Base rifle");
+        assert_eq!(json["property_doc_comments"]["mass"], "kg");
+        assert_eq!(serde_json::from_value::<ClassConfig>(json).unwrap(), config);
+
+        assert_eq!(config.to_config_string(), concat!(
+            "// Base rifle\n",
+            "class Rifle {\n",
+            "    // Visible in the arsenal\n",
+            "    // public\n",
+            "    scope = 2;\n",
+            "    // kg\n",
+            "    mass = 10;\n",
+            "    // Not a trailing comment of mass\n",
+            "    class Optics {};\n",
+            "};\n",
+        ));
+    }
+
+    #[test]
+    fn test_parse_warnings() {
+        let outcome = ClassScanner::new().parse_string_outcome(concat!(
+            "class A {\n",
+            "    x = 1;\n",
+            "    y = 2;;\n",
+            "    x = 3;\n",
+            "    class B {};\n",
+            "};\n",
+            "enum { E1, E2 }\n",
+        )).unwrap();
+        assert_eq!(outcome.classes[1].properties["x"].raw_value, "3");
+
+        let duplicates: Vec<_> = outcome.warnings.iter()
+            .filter_map(|warning| match warning {
+                Warning::DuplicateProperty { class, name, location, previous } => Some((class.as_str(), name.as_str(), location.line, previous.line)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(duplicates, vec![("A", "x", 4, 2)]);
+        assert!(outcome.warnings.iter().any(|warning| matches!(warning, Warning::StraySemicolon { location } if location.line == 3)));
+        assert!(outcome.warnings.iter().any(|warning| matches!(warning, Warning::MissingSemicolon { after, .. } if after == "enum")));
+        assert_eq!(outcome.warnings.len(), 3);
+    }
+
+    #[test]
+    fn test_scan_directory_incremental() {
+        use std::time::{Duration, SystemTime};
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        let written = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
+        let write = |name: &str, content: &str, modified: SystemTime| {
+            std::fs::write(dir.join(name), content).unwrap();
+            std::fs::File::options().write(true).open(dir.join(name)).unwrap().set_modified(modified).unwrap();
+        };
+        write("a.cpp", "class A { x = 1; };", written);
+        write("b.cpp", "class B {};", written);
+
+        let scanner = ClassScanner::new();
+        let mut cache = ScanCache::new();
+        let first = scanner.scan_directory_incremental(dir, &mut cache).unwrap();
+        assert_eq!(first.added, vec![PathBuf::from("a.cpp"), PathBuf::from("b.cpp")]);
+        assert!(first.modified.is_empty() && first.removed.is_empty());
+        assert_eq!(first.result.classes[Path::new("a.cpp")][0].name, "A");
+
+        // Same modification time: the cached classes are used, not the new content
+        write("a.cpp", "class Changed {};", written);
+        let cache_path = dir.join("cache.json");
+        cache.save(&cache_path).unwrap();
+        let mut cache = ScanCache::load(&cache_path).unwrap();
+        let unchanged = scanner.scan_directory_incremental(dir, &mut cache).unwrap();
+        assert!(unchanged.added.is_empty() && unchanged.modified.is_empty());
+        assert_eq!(unchanged.result.classes[Path::new("a.cpp")][0].name, "A");
+
+        write("a.cpp", "class Changed {};", written + Duration::from_secs(1));
+        std::fs::remove_file(dir.join("b.cpp")).unwrap();
+        write("c.cpp", "class C {};", written);
+        let changed = scanner.scan_directory_incremental(dir, &mut cache).unwrap();
+        assert_eq!(changed.added, vec![PathBuf::from("c.cpp")]);
+        assert_eq!(changed.modified, vec![PathBuf::from("a.cpp")]);
+        assert_eq!(changed.removed, vec![PathBuf::from("b.cpp")]);
+        assert_eq!(changed.result.classes[Path::new("a.cpp")][0].name, "Changed");
+        assert_eq!(changed.result.classes.len(), 2);
+        assert_eq!(cache.len(), 2);
+    }
+
+    #[test]
+    fn test_scan_directory_incremental_includes() {
+        use std::time::{Duration, SystemTime};
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        let written = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
+        let write = |name: &str, content: &str, modified: SystemTime| {
+            std::fs::write(dir.join(name), content).unwrap();
+            std::fs::File::options().write(true).open(dir.join(name)).unwrap().set_modified(modified).unwrap();
+        };
+        write("config.cpp", "#define SCOPE 1\n#define SCOPE 2\nclass CfgVehicles {\n#include \"CfgVehicles.hpp\"\n};", written);
+        write("CfgVehicles.hpp", "class Car {};", written);
+
+        let scanner = ClassScanner::new().with_scanner_config(ScannerConfig::default().with_extensions(["cpp"]));
+        let mut cache = ScanCache::new();
+        scanner.scan_directory_incremental(dir, &mut cache).unwrap();
+        let redefined = |warnings: Vec<Warning>| warnings.iter().any(|warning| matches!(warning, Warning::MacroRedefined { .. }));
+        assert!(redefined(scanner.last_warnings()));
+
+        // Served from the cache, warnings included
+        let cached = scanner.scan_directory_incremental(dir, &mut cache).unwrap();
+        assert!(cached.modified.is_empty());
+        assert!(redefined(scanner.last_warnings()));
+
+        // Only the included file changes
+        write("CfgVehicles.hpp", "class Tank {};", written + Duration::from_secs(1));
+        let changed = scanner.scan_directory_incremental(dir, &mut cache).unwrap();
+        assert_eq!(changed.modified, vec![PathBuf::from("config.cpp")]);
+        assert_eq!(changed.result.classes[Path::new("config.cpp")][0].nested_classes[0].name, "Tank");
+        let unchanged = scanner.scan_directory_incremental(dir, &mut cache).unwrap();
+        assert!(unchanged.modified.is_empty());
+        assert_eq!(unchanged.result.classes[Path::new("config.cpp")][0].nested_classes[0].name, "Tank");
+    }
+
+    #[test]
+    fn test_duplicate_policy() {
+        let input = "class A {\n    scope = 1;\n    scope = 2;\n};\n";
+        let parse = |policy| ClassScanner::new().with_duplicate_policy(policy).parse_string_outcome(input);
+
+        let outcome = parse(DuplicatePolicy::LastWins).unwrap();
+        assert_eq!(outcome.classes[1].properties["scope"].raw_value, "2");
+        assert!(outcome.warnings.is_empty());
+
+        let outcome = parse(DuplicatePolicy::FirstWins).unwrap();
+        assert_eq!(outcome.classes[1].properties["scope"].raw_value, "1");
+        assert!(outcome.warnings.is_empty());
+
+        let outcome = parse(DuplicatePolicy::Warn).unwrap();
+        assert_eq!(outcome.classes[1].properties["scope"].raw_value, "2");
+        assert!(matches!(&outcome.warnings[..], [Warning::DuplicateProperty { location, previous, .. }]
+            if location.line == 3 && previous.line == 2));
+
+        match parse(DuplicatePolicy::Error) {
+            Err(Error::ParseError { location, .. }) => assert_eq!(location.line, 3),
+            other => panic!("expected a parse error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_array_operation_within_class_is_not_duplicate() {
+        let scanner = ClassScanner::new().with_duplicate_policy(DuplicatePolicy::Error);
+        let outcome = scanner.parse_string_outcome(r#"
+            class A {
+                items[] = {"a", "b"};
+                items[] += {"c"};
+                items[] -= {"a"};
+                extra[] += {1};
+                extra[] += {2};
+            };
+        "#).unwrap();
+        let class = &outcome.classes[1];
+        assert_eq!(class.properties["items"].array_values, vec!["b", "c"]);
+        assert_eq!(class.properties["items"].raw_value, "{b,c}");
+        assert_eq!(class.properties["items"].operation, Some(ArrayOperation::Replace));
+        assert_eq!(class.properties["extra"].array_values, vec!["1", "2"]);
+        assert_eq!(class.properties["extra"].operation, Some(ArrayOperation::Append));
+        assert!(outcome.warnings.is_empty());
+
+        // Replacing the array again is still a duplicate
+        assert!(scanner.parse_string("class A { items[] = {1}; items[] = {2}; };").is_err());
+    }
+
+    #[test]
+    fn test_conflicting_array_operations_within_class() {
+        for input in [
+            r#"class A { x[] += {"a"}; x[] -= {"b"}; };"#,
+            r#"class A { x[] -= {"b"}; x[] += {"a"}; };"#,
+            r#"class A { x[] += {"a"}; x[] &= {"a"}; };"#,
+        ] {
+            // Not a duplicate, so not subject to the policy
+            let scanner = ClassScanner::new().with_duplicate_policy(DuplicatePolicy::LastWins);
+            match scanner.parse_string(input) {
+                Err(Error::ParseError { message, location }) => {
+                    assert!(message.contains("'x'") && message.contains("cannot take both"), "{}", message);
+                    assert_eq!(location.column, 24, "{}", input);
+                }
+                other => panic!("expected a parse error for {}, got {:?}", input, other),
+            }
+        }
+
+        // Two intersections keep what is in both lists
+        let classes = ClassScanner::new().parse_string(r#"class A { x[] &= {"a", "b"}; x[] &= {"b", "c"}; };"#).unwrap();
+        assert_eq!(classes[1].properties["x"].array_values, vec!["b"]);
+        assert_eq!(classes[1].properties["x"].operation, Some(ArrayOperation::Intersect));
+    }
+
+    #[test]
+    fn test_array_operations_within_class_and_inherited() {
+        let scanner = ClassScanner::new();
+        let outcome = scanner.parse_string_outcome(r#"
+            class Base {
+                items[] = {"base"};
+            };
+            class Combined : Base {
+                items[] = {"a"};
+                items[] += {"b"};
+            };
+            class Removed : Base {
+                items[] = {"a", "b"};
+                items[] -= {"a"};
+            };
+            class Appended : Base {
+                items[] += {"b"};
+                items[] += {"c"};
+            };
+            class Replaced : Base {
+                items[] += {"b"};
+                items[] = {"a"};
+            };
+        "#).unwrap();
+
+        let items = |name: &str| {
+            let class = scanner.process_inheritance(outcome.classes.clone(), name).unwrap();
+            (class.properties["items"].array_values.clone(), class.properties["items"].operation)
+        };
+        // The class's own array replaces the inherited one once combined
+        assert_eq!(items("Combined"), (vec!["a".to_string(), "b".to_string()], Some(ArrayOperation::Replace)));
+        assert_eq!(items("Removed").0, vec!["b"]);
+        assert_eq!(items("Appended").0, vec!["base", "b", "c"]);
+        // Assigning after an operation replaces it, like any redefinition
+        assert_eq!(items("Replaced").0, vec!["a"]);
+        assert!(matches!(&outcome.warnings[..], [Warning::DuplicateProperty { class, .. }] if class == "Replaced"));
+    }
+
+    #[test]
+    fn test_parser_config() {
+        let input = r#"
+            class CfgVehicles {
+                class Car_F;
+                class MyCar : car_f {
+                    Scope = 1;
+                    scope = 2;
+                    class Turrets {};
+                };
+            };
+        "#;
+
+        let config = ParserConfig { case_sensitive: false, ..ParserConfig::default() };
+        let outcome = ClassScanner::new().with_parser_config(config).parse_string_outcome(input).unwrap();
+        let vehicles = &outcome.classes[1];
+        assert_eq!(vehicles.name, "cfgvehicles");
+        let car = &vehicles.nested_classes[1];
+        assert_eq!((car.name.as_str(), car.parent.as_deref()), ("mycar", Some("car_f")));
+        assert_eq!(car.properties.keys().collect::<Vec<_>>(), vec!["scope"]);
+        assert_eq!(car.properties["scope"].raw_value, "2");
+        let duplicates: Vec<&Warning> = outcome.warnings.iter()
+            .filter(|warning| matches!(warning, Warning::DuplicateProperty { .. }))
+            .collect();
+        assert!(matches!(&duplicates[..], [Warning::DuplicateProperty { name, .. }] if name == "scope"));
+
+        // Case is kept by default, so both spellings are separate properties
+        let classes = ClassScanner::new().parse_string(input).unwrap();
+        assert_eq!(classes[1].nested_classes[1].properties.len(), 2);
+
+        let parse = |config| Parser::with_config(lexer::Tokenizer::new(input).tokenize().unwrap(), config).parse();
+        assert!(parse(ParserConfig { max_depth: Some(3), ..ParserConfig::default() }).is_ok());
+        match parse(ParserConfig { max_depth: Some(2), ..ParserConfig::default() }) {
+            Err(Error::ParseError { message, location }) => {
+                assert!(message.contains("'Turrets'"), "{}", message);
+                assert_eq!(location.line, 7);
+            }
+            other => panic!("expected a depth error, got {:?}", other),
+        }
+        match parse(ParserConfig { allow_empty_blocks: false, ..ParserConfig::default() }) {
+            Err(Error::ParseError { message, .. }) => assert!(message.contains("'Turrets' has an empty body"), "{}", message),
+            other => panic!("expected an empty block error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_missing_include_policy() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let config = temp_dir.path().join("config.cpp");
+        std::fs::write(&config, "#include \"script_macros.hpp\"\nclass A {};").unwrap();
+
+        let scanner = ClassScanner::new();
+        assert_eq!(scanner.parse_file(&config).unwrap()[1].name, "A");
+        assert!(matches!(scanner.last_warnings().as_slice(),
+            [Warning::MissingInclude { include, location, .. }] if include == "script_macros.hpp" && location.line == 1));
+
+        let scanner = ClassScanner::new().with_include_policy(IncludePolicy::Error);
+        assert_eq!(scanner.parse_file(&config).unwrap_err().code(), "E0102");
+    }
+
+    #[test]
+    fn test_parser_panic_is_an_error() {
+        let path = Path::new("broken.cpp");
+        let error = catch_parser_panic(path, || panic!("parser bug")).unwrap_err();
+        assert_eq!(error.code(), "E0704");
+        assert!(matches!(&error, Error::ParserPanic(panicked) if panicked == path));
+        assert!(catch_parser_panic(path, || Ok((Vec::new(), Vec::new(), Vec::new()))).is_ok());
+    }
+
+    #[test]
+    fn test_build_registry_with_priority() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let root = temp_dir.path();
+        for (addon, mass) in [("@rhsusaf", 100), ("@compatibility_patch", 80)] {
+            let dir = root.join(addon).join("addons/weapons");
+            std::fs::create_dir_all(&dir).unwrap();
+            std::fs::write(dir.join("config.cpp"), format!("class rhs_weap_m4 {{ mass = {}; }};", mass)).unwrap();
+        }
+
+        let scanner = ClassScanner::new();
+        let scan = scanner.scan_directory(root).unwrap();
+        for (priority, mass) in [(["@compatibility_patch", "@rhsusaf"], "80"), (["@rhsusaf", "@compatibility_patch"], "100")] {
+            let mut registry = scanner.build_registry_with_priority(&scan, &priority);
+            assert_eq!(registry.resolve("rhs_weap_m4").unwrap().properties["mass"].raw_value, mass);
+        }
+    }
+
+    #[test]
+    fn test_locations_in_included_file() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let config = temp_dir.path().join("config.cpp");
+        let header = temp_dir.path().join("weapons.hpp");
+        std::fs::write(&config, "#define MASS 2\n#include \"weapons.hpp\"\nclass Main {};").unwrap();
+        std::fs::write(&header, "\nclass Rifle {\n    mass = 1;\n    mass = MASS;\n};").unwrap();
+
+        let scanner = ClassScanner::new();
+        let classes = scanner.parse_file(&config).unwrap();
+        let at = |location: &Option<error::SourceLocation>| location.as_ref().map(|location| (location.file.clone().unwrap(), location.line));
+        assert_eq!(at(&classes[1].location), Some((header.clone(), 2)));
+        assert_eq!(at(&classes[1].properties["mass"].location), Some((header.clone(), 4)));
+        assert_eq!(at(&classes[2].location), Some((config.clone(), 3)));
+
+        match scanner.last_warnings().as_slice() {
+            [Warning::DuplicateProperty { location, previous, .. }] => {
+                assert_eq!((location.file.as_ref(), location.line), (Some(&header), 4));
+                assert_eq!((previous.file.as_ref(), previous.line), (Some(&header), 3));
+            }
+            other => panic!("expected a duplicate property warning, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_eval_in_properties_and_arrays() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let config = temp_dir.path().join("config.cpp");
+        std::fs::write(&config, r#"
+            #define SLOTS 6
+            class Vest {
+                __EXEC(_count = 0)
+                capacity = __EVAL(SLOTS * 10);
+                slots[] = {__EVAL(SLOTS - 1), __EVAL(SLOTS + 1)};
+                origin = __FILE__;
+            };
+        "#).unwrap();
+
+        let scanner = ClassScanner::new();
+        let vest = &scanner.parse_file(&config).unwrap()[1];
+        assert_eq!(vest.properties["capacity"].raw_value, "60");
+        assert_eq!(vest.properties["slots"].array_values, vec!["5", "7"]);
+        assert!(vest.properties["origin"].raw_value.ends_with("config.cpp"));
+        assert!(matches!(scanner.last_warnings().as_slice(), [Warning::UnsupportedBuiltin { name, .. }] if name == "__EXEC"));
+    }
+
+    #[test]
+    fn test_nested_array_property_value() {
+        let classes = ClassScanner::new().parse_string("class Grid { cells[] = {{1,2},{3,4}}; };").unwrap();
+        let config = ClassConfig::from(classes[1].clone());
+        let number = |n: f64| PropertyValue::Number(n);
+        assert_eq!(config.properties["cells"], PropertyValue::Array(vec![
+            PropertyValue::Array(vec![number(1.0), number(2.0)]),
+            PropertyValue::Array(vec![number(3.0), number(4.0)]),
+        ]));
+
+        let json = serde_json::to_string(&config.properties["cells"]).unwrap();
+        assert_eq!(json, "[[1.0,2.0],[3.0,4.0]]");
+        assert_eq!(serde_json::from_str::<PropertyValue>(&json).unwrap(), config.properties["cells"]);
+        assert!(config.to_config_string().contains("cells[] = {{1, 2}, {3, 4}};"));
+    }
+
+    #[test]
+    fn test_class_config_keeps_array_operations() {
+        let input = r#"
+            class Base {
+                replaced[] = {"a", 1};
+                appended[] = {"a"};
+                removed[] = {1, 2};
+                kept[] = {"a", "b"};
+            };
+            class Child: Base {
+                name = "child";
+                mass = 4.5;
+                enabled = true;
+                replaced[] = {"x", {1, true}};
+                appended[] += {"b"};
+                removed[] -= {1};
+                kept[] &= {"a"};
+                class Turret { gunner = "crew"; };
+            };
+        "#;
+        let classes = ClassScanner::new().parse_string(input).unwrap();
+        let child = classes.iter().find(|class| class.name == "Child").unwrap();
+        let config = ClassConfig::from(child.clone());
+        assert_eq!(config.property_array_operations, IndexMap::from([
+            ("appended".to_string(), ArrayOperation::Append),
+            ("removed".to_string(), ArrayOperation::Remove),
+            ("kept".to_string(), ArrayOperation::Intersect),
+        ]));
+
+        let json = serde_json::to_value(&config).unwrap();
+        assert_eq!(json["property_array_operations"]["appended"], "Append");
+        assert_eq!(serde_json::from_value::<ClassConfig>(json).unwrap(), config);
+
+        let rehydrated = ClassNode::from(config.clone());
+        assert_eq!(rehydrated.parent.as_deref(), Some("Base"));
+        for (name, property) in &child.properties {
+            let other = &rehydrated.properties[name];
+            assert_eq!(other.value_type, property.value_type, "{}", name);
+            assert_eq!(other.operation, property.operation, "{}", name);
+            assert_eq!(other.typed_values, property.typed_values, "{}", name);
+            assert_eq!(other.location, property.location, "{}", name);
+        }
+        assert_eq!(rehydrated.nested_classes[0].properties["gunner"].raw_value, "crew");
+        assert_eq!(ClassConfig::from(rehydrated), config);
+
+        // The config text keeps the operators, so inheriting it still appends
+        let written = config.to_config_string();
+        assert!(written.contains(r#"appended[] += {"b"};"#), "{}", written);
+        let reparsed = ClassScanner::new().parse_string(&written).unwrap();
+        assert_eq!(reparsed[1].properties["kept"].operation, Some(ArrayOperation::Intersect));
+    }
+
+    /// Resolves a 1000 class inheritance chain. Run with
+    /// `cargo test --release -- --ignored --nocapture bench_process_all_inheritance`.
+    #[test]
+    #[ignore]
+    fn bench_process_all_inheritance() {
+        let classes = inheritance_chain(1000);
+        let scanner = ClassScanner::new();
+        let start = std::time::Instant::now();
+        let resolved = scanner.process_all_inheritance(classes).unwrap();
+        println!("Resolved {} chained classes in {:?}", resolved.len(), start.elapsed());
+    }
+}
@@ -1,6 +1,10 @@
+use regex::Regex;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::ops::{Add, Div, Mul, Sub};
 use crate::ast::{PropertyNode, PropertyType};
+use crate::error::Error;
+use crate::operations::arrays::{parse_number, ArrayElement};
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(untagged)]
@@ -8,7 +12,7 @@ pub enum PropertyValue {
     String(String),
     Number(f64),
     Bool(bool),
-    Array(Vec<String>),
+    Array(Vec<PropertyValue>),
     Object(HashMap<String, PropertyValue>),
 }
 
@@ -18,12 +22,9 @@ impl PropertyValue {
             PropertyType::String => {
                 PropertyValue::String(value.to_string())
             },
-            PropertyType::Number => {
-                if let Ok(n) = value.parse() {
-                    PropertyValue::Number(n)
-                } else {
-                    PropertyValue::String(value.to_string())
-                }
+            PropertyType::Number => match parse_number(value) {
+                Some(n) => PropertyValue::Number(n),
+                None => PropertyValue::String(value.to_string()),
             },
             PropertyType::Boolean => {
                 if let Ok(b) = value.parse() {
@@ -35,14 +36,26 @@ impl PropertyValue {
             PropertyType::Array => {
                 PropertyValue::Array(Vec::new())
             },
-            PropertyType::Object => {
+            PropertyType::Object | PropertyType::Deleted => {
                 PropertyValue::Object(HashMap::new())
             },
         }
     }
 
+    /// An array of `values` as held in `PropertyNode::array_values`, each
+    /// typed as by [`ArrayElement::parse`].
     pub fn with_array(_name: &str, _raw_value: &str, values: Vec<String>) -> Self {
-        PropertyValue::Array(values)
+        PropertyValue::Array(values.iter().map(|value| ArrayElement::parse(value).into()).collect())
+    }
+
+    /// The array a `{...}` literal holds, with nested arrays kept as arrays:
+    /// `{{1,2},{3,4}}` is two arrays of two numbers each. Text without
+    /// braces is an array of that one element.
+    pub fn parse_array(raw_value: &str) -> Self {
+        match ArrayElement::parse(raw_value) {
+            ArrayElement::Array(elements) => PropertyValue::Array(elements.into_iter().map(Into::into).collect()),
+            element => PropertyValue::Array(vec![element.into()]),
+        }
     }
 
     pub fn as_string(&self) -> Option<&str> {
@@ -66,7 +79,7 @@ impl PropertyValue {
         }
     }
 
-    pub fn as_array(&self) -> Option<&Vec<String>> {
+    pub fn as_array(&self) -> Option<&Vec<PropertyValue>> {
         match self {
             PropertyValue::Array(a) => Some(a),
             _ => None,
@@ -79,18 +92,73 @@ impl PropertyValue {
             _ => None,
         }
     }
+
+    /// Whether this is a string matching `pattern`; other values never match.
+    pub fn matches_pattern(&self, pattern: &Regex) -> bool {
+        self.as_string().is_some_and(|s| pattern.is_match(s))
+    }
+
+    pub fn try_add(&self, rhs: &PropertyValue) -> Result<PropertyValue, Error> {
+        self.arithmetic(rhs, "add", |a, b| a + b)
+    }
+
+    pub fn try_sub(&self, rhs: &PropertyValue) -> Result<PropertyValue, Error> {
+        self.arithmetic(rhs, "subtract", |a, b| a - b)
+    }
+
+    pub fn try_mul(&self, rhs: &PropertyValue) -> Result<PropertyValue, Error> {
+        self.arithmetic(rhs, "multiply", |a, b| a * b)
+    }
+
+    pub fn try_div(&self, rhs: &PropertyValue) -> Result<PropertyValue, Error> {
+        if rhs.as_number() == Some(0.0) {
+            return Err(Error::TypeError("Division by zero".to_string()));
+        }
+        self.arithmetic(rhs, "divide", |a, b| a / b)
+    }
+
+    /// Multiply a number by `factor`, e.g. `0.5` to halve an armor value.
+    pub fn apply_scalar(&self, factor: f64) -> Result<PropertyValue, Error> {
+        self.try_mul(&PropertyValue::Number(factor))
+    }
+
+    /// Both operands must be numbers, and so must the result: overflow to
+    /// infinity is an error rather than a value no config can hold.
+    fn arithmetic(&self, rhs: &PropertyValue, operation: &str, op: impl Fn(f64, f64) -> f64) -> Result<PropertyValue, Error> {
+        let (Some(a), Some(b)) = (self.as_number(), rhs.as_number()) else {
+            return Err(Error::TypeError(format!("Cannot {} {:?} and {:?}", operation, self, rhs)));
+        };
+        let result = op(a, b);
+        if !result.is_finite() {
+            return Err(Error::TypeError(format!("Cannot {} {} and {}: result out of range", operation, a, b)));
+        }
+        Ok(PropertyValue::Number(result))
+    }
 }
 
+macro_rules! impl_arithmetic {
+    ($($trait:ident $method:ident $try_method:ident),*) => {
+        $(
+            impl $trait<&PropertyValue> for &PropertyValue {
+                type Output = Result<PropertyValue, Error>;
+
+                fn $method(self, rhs: &PropertyValue) -> Self::Output {
+                    self.$try_method(rhs)
+                }
+            }
+        )*
+    };
+}
+
+impl_arithmetic!(Add add try_add, Sub sub try_sub, Mul mul try_mul, Div div try_div);
+
 impl From<PropertyNode> for PropertyValue {
     fn from(node: PropertyNode) -> Self {
         match node.value_type {
             PropertyType::String => PropertyValue::String(node.raw_value),
-            PropertyType::Number => {
-                if let Ok(n) = node.raw_value.parse() {
-                    PropertyValue::Number(n)
-                } else {
-                    PropertyValue::String(node.raw_value)
-                }
+            PropertyType::Number => match parse_number(&node.raw_value) {
+                Some(n) => PropertyValue::Number(n),
+                None => PropertyValue::String(node.raw_value),
             },
             PropertyType::Boolean => {
                 if let Ok(b) = node.raw_value.parse() {
@@ -99,8 +167,195 @@ impl From<PropertyNode> for PropertyValue {
                     PropertyValue::String(node.raw_value)
                 }
             },
-            PropertyType::Array => PropertyValue::Array(node.array_values),
-            PropertyType::Object => PropertyValue::Object(HashMap::new()),
+            PropertyType::Array => PropertyValue::Array(node.elements().into_iter().map(Into::into).collect()),
+            PropertyType::Object | PropertyType::Deleted => PropertyValue::Object(HashMap::new()),
+        }
+    }
+}
+
+impl From<ArrayElement> for PropertyValue {
+    fn from(element: ArrayElement) -> Self {
+        match element {
+            ArrayElement::String(s) => PropertyValue::String(s),
+            ArrayElement::Number(n) => PropertyValue::Number(n),
+            ArrayElement::Boolean(b) => PropertyValue::Bool(b),
+            ArrayElement::Array(elements) => PropertyValue::Array(elements.into_iter().map(Into::into).collect()),
+        }
+    }
+}
+
+impl TryFrom<&PropertyValue> for ArrayElement {
+    type Error = Error;
+
+    /// Objects cannot be array elements.
+    fn try_from(value: &PropertyValue) -> Result<Self, Error> {
+        match value {
+            PropertyValue::String(s) => Ok(ArrayElement::String(s.clone())),
+            PropertyValue::Number(n) => Ok(ArrayElement::Number(*n)),
+            PropertyValue::Bool(b) => Ok(ArrayElement::Boolean(*b)),
+            PropertyValue::Array(values) => values.iter()
+                .map(ArrayElement::try_from)
+                .collect::<Result<_, _>>()
+                .map(ArrayElement::Array),
+            PropertyValue::Object(_) => Err(Error::TypeError(format!("Object {:?} is not an array element", value))),
+        }
+    }
+}
+
+impl TryFrom<&PropertyValue> for Vec<String> {
+    type Error = Error;
+
+    /// The text of each element of an array, as in `PropertyNode::array_values`:
+    /// numbers as written by Rust and nested arrays as `{a,b}`.
+    fn try_from(value: &PropertyValue) -> Result<Self, Error> {
+        let Some(values) = value.as_array() else {
+            return Err(Error::TypeError(format!("{:?} is not an array", value)));
+        };
+        values.iter()
+            .map(|value| ArrayElement::try_from(value).map(|element| element.to_string()))
+            .collect()
+    }
+}
+
+impl From<PropertyValue> for serde_json::Value {
+    /// Non-finite numbers have no JSON form and become `null`.
+    fn from(value: PropertyValue) -> Self {
+        match value {
+            PropertyValue::String(s) => serde_json::Value::String(s),
+            PropertyValue::Number(n) => serde_json::Number::from_f64(n)
+                .map_or(serde_json::Value::Null, serde_json::Value::Number),
+            PropertyValue::Bool(b) => serde_json::Value::Bool(b),
+            PropertyValue::Array(values) => values.into_iter().map(serde_json::Value::from).collect(),
+            PropertyValue::Object(map) => serde_json::Value::Object(
+                map.into_iter().map(|(k, v)| (k, v.into())).collect()
+            ),
         }
     }
-}
\ No newline at end of file
+}
+
+impl TryFrom<serde_json::Value> for PropertyValue {
+    type Error = Error;
+
+    /// `null` is rejected, also inside arrays and objects.
+    fn try_from(value: serde_json::Value) -> Result<Self, Error> {
+        match value {
+            serde_json::Value::String(s) => Ok(PropertyValue::String(s)),
+            serde_json::Value::Number(n) => n.as_f64()
+                .map(PropertyValue::Number)
+                .ok_or_else(|| Error::TypeError(format!("Number {} is out of range", n))),
+            serde_json::Value::Bool(b) => Ok(PropertyValue::Bool(b)),
+            serde_json::Value::Array(values) => values.into_iter()
+                .map(PropertyValue::try_from)
+                .collect::<Result<_, _>>()
+                .map(PropertyValue::Array),
+            serde_json::Value::Object(map) => map.into_iter()
+                .map(|(k, v)| Ok((k, PropertyValue::try_from(v)?)))
+                .collect::<Result<_, Error>>()
+                .map(PropertyValue::Object),
+            serde_json::Value::Null => Err(Error::TypeError("null has no property value".to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn string(s: &str) -> PropertyValue {
+        PropertyValue::String(s.to_string())
+    }
+
+    #[test]
+    fn test_json_value_conversion() {
+        let value = PropertyValue::Object(HashMap::from([
+            ("name".to_string(), PropertyValue::String("Rifle".to_string())),
+            ("mass".to_string(), PropertyValue::Number(4.5)),
+            ("scope".to_string(), PropertyValue::Number(2.0)),
+            ("enabled".to_string(), PropertyValue::Bool(true)),
+            ("magazines".to_string(), PropertyValue::Array(vec![string("30Rnd"), string("20Rnd")])),
+            ("offsets".to_string(), PropertyValue::Array(vec![
+                PropertyValue::Array(vec![PropertyValue::Number(1.0), string("x")]),
+                PropertyValue::Bool(false),
+            ])),
+        ]));
+
+        let json = serde_json::Value::from(value.clone());
+        assert_eq!(json, json!({
+            "name": "Rifle",
+            "mass": 4.5,
+            "scope": 2.0,
+            "enabled": true,
+            "magazines": ["30Rnd", "20Rnd"],
+            "offsets": [[1.0, "x"], false],
+        }));
+        assert_eq!(PropertyValue::try_from(json).unwrap(), value);
+
+        assert_eq!(serde_json::Value::from(PropertyValue::Number(f64::NAN)), serde_json::Value::Null);
+        assert!(matches!(PropertyValue::try_from(json!([1, [null]])), Err(Error::TypeError(_))));
+        assert!(matches!(PropertyValue::try_from(serde_json::Value::Null), Err(Error::TypeError(_))));
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let number = |n: f64| PropertyValue::Number(n);
+        assert_eq!((&number(2.0) + &number(3.0)).unwrap(), number(5.0));
+        assert_eq!((&number(2.0) - &number(3.0)).unwrap(), number(-1.0));
+        assert_eq!((&number(2.0) * &number(3.0)).unwrap(), number(6.0));
+        assert_eq!((&number(3.0) / &number(2.0)).unwrap(), number(1.5));
+        assert_eq!(number(200.0).apply_scalar(0.5).unwrap(), number(100.0));
+
+        // No integer variant, so i64::MAX + 1 widens like any f64 sum
+        assert_eq!(number(i64::MAX as f64).try_add(&number(1.0)).unwrap(), number(i64::MAX as f64 + 1.0));
+        assert!(matches!(number(f64::MAX).try_add(&number(f64::MAX)), Err(Error::TypeError(_))));
+        assert!(matches!(number(f64::MAX).apply_scalar(2.0), Err(Error::TypeError(_))));
+
+        let error = number(1.0).try_div(&number(0.0)).unwrap_err();
+        assert!(error.to_string().contains("Division by zero"), "{}", error);
+        assert!(matches!(&number(0.0) / &number(-0.0), Err(Error::TypeError(_))));
+
+        assert!(matches!(number(1.0).try_add(&PropertyValue::String("1".to_string())), Err(Error::TypeError(_))));
+        assert!(matches!(PropertyValue::Bool(true).apply_scalar(2.0), Err(Error::TypeError(_))));
+    }
+
+    #[test]
+    fn test_matches_pattern() {
+        let model = Regex::new(r"\.p3d$").unwrap();
+        assert!(PropertyValue::String("\\a3\\car.p3d".to_string()).matches_pattern(&model));
+        assert!(!PropertyValue::String("car.paa".to_string()).matches_pattern(&model));
+        assert!(!PropertyValue::Number(3.0).matches_pattern(&Regex::new("3").unwrap()));
+        assert!(!PropertyValue::Array(vec![string("car.p3d")]).matches_pattern(&model));
+    }
+
+    #[test]
+    fn test_nested_arrays() {
+        let number = |n: f64| PropertyValue::Number(n);
+        let matrix = PropertyValue::parse_array("{{1,2},{3,4}}");
+        assert_eq!(matrix, PropertyValue::Array(vec![
+            PropertyValue::Array(vec![number(1.0), number(2.0)]),
+            PropertyValue::Array(vec![number(3.0), number(4.0)]),
+        ]));
+        assert_eq!(matrix.as_array().unwrap()[1].as_array().unwrap()[0], number(3.0));
+
+        let mixed = PropertyValue::parse_array("{a, 0x10, {}, {b, {2}}}");
+        assert_eq!(mixed, PropertyValue::Array(vec![
+            string("a"),
+            number(16.0),
+            PropertyValue::Array(vec![]),
+            PropertyValue::Array(vec![string("b"), PropertyValue::Array(vec![number(2.0)])]),
+        ]));
+        assert_eq!(PropertyValue::with_array("items", "{a,{1}}", vec!["a".to_string(), "{1}".to_string()]),
+            PropertyValue::Array(vec![string("a"), PropertyValue::Array(vec![number(1.0)])]));
+
+        // Flattened back to text for callers expecting strings
+        assert_eq!(Vec::<String>::try_from(&mixed).unwrap(), vec!["a", "16", "{}", "{b,{2}}"]);
+        assert!(matches!(Vec::<String>::try_from(&number(1.0)), Err(Error::TypeError(_))));
+        let with_object = PropertyValue::Array(vec![PropertyValue::Object(HashMap::new())]);
+        assert!(matches!(Vec::<String>::try_from(&with_object), Err(Error::TypeError(_))));
+
+        for value in [matrix, mixed] {
+            let json = serde_json::to_string(&value).unwrap();
+            assert_eq!(serde_json::from_str::<PropertyValue>(&json).unwrap(), value);
+        }
+    }
+}
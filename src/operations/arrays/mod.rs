@@ -1,41 +1,238 @@
 use std::collections::HashSet;
+use std::fmt;
+use std::hash::Hash;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum ArrayOperation {
     Append,   // +=
     Remove,   // -=
     Replace,  // =
+    Intersect, // &=
 }
 
-pub struct ArrayProcessor;
+/// One element of an array value. Numbers compare by value, so `1` and
+/// `1.0` are equal, and nested arrays compare element by element.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ArrayElement {
+    String(String),
+    Number(f64),
+    Boolean(bool),
+    Array(Vec<ArrayElement>),
+}
 
-impl ArrayProcessor {
-    pub fn process(base: &[String], values: &[String], operation: ArrayOperation) -> Vec<String> {
-        match operation {
-            ArrayOperation::Append => Self::append_operation(base, values),
-            ArrayOperation::Remove => Self::remove_operation(base, values),
-            ArrayOperation::Replace => values.to_vec(),
+impl ArrayElement {
+    /// Element for `text` as held in `PropertyNode::array_values`, where
+    /// quotes are already stripped: a number, a `{...}` nested array, or
+    /// else a string.
+    pub fn parse(text: &str) -> Self {
+        let trimmed = text.trim();
+        if let Some(inner) = trimmed.strip_prefix('{').and_then(|rest| rest.strip_suffix('}')) {
+            return ArrayElement::Array(split_top_level(inner).into_iter().map(ArrayElement::parse).collect());
+        }
+        match parse_number(trimmed) {
+            Some(number) => ArrayElement::Number(number),
+            None => ArrayElement::String(text.to_string()),
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            ArrayElement::Number(number) => Some(*number),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            ArrayElement::String(text) => Some(text),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[ArrayElement]> {
+        match self {
+            ArrayElement::Array(elements) => Some(elements),
+            _ => None,
+        }
+    }
+
+    /// Hashable form of the element, equal for elements that are equal.
+    pub(crate) fn key(&self) -> ElementKey {
+        match self {
+            ArrayElement::String(text) => ElementKey::String(text.clone()),
+            // -0.0 == 0.0, so both take the bits of 0.0
+            ArrayElement::Number(number) => ElementKey::Number(if *number == 0.0 { 0 } else { number.to_bits() }),
+            ArrayElement::Boolean(value) => ElementKey::Boolean(*value),
+            ArrayElement::Array(elements) => ElementKey::Array(elements.iter().map(ArrayElement::key).collect()),
+        }
+    }
+}
+
+/// See [`ArrayElement::key`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) enum ElementKey {
+    String(String),
+    Number(u64),
+    Boolean(bool),
+    Array(Vec<ElementKey>),
+}
+
+/// Consistent with `PartialEq`: `0.0` and `-0.0` hash alike.
+impl Hash for ArrayElement {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
+        match self {
+            ArrayElement::String(text) => text.hash(state),
+            ArrayElement::Number(number) => (if *number == 0.0 { 0.0 } else { *number }).to_bits().hash(state),
+            ArrayElement::Boolean(value) => value.hash(state),
+            ArrayElement::Array(elements) => elements.hash(state),
+        }
+    }
+}
+
+/// The flat text of the element, as stored in `array_values`: strings
+/// unquoted and nested arrays as `{a,b}`.
+impl fmt::Display for ArrayElement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ArrayElement::String(text) => f.write_str(text),
+            ArrayElement::Number(number) => write!(f, "{}", number),
+            ArrayElement::Boolean(value) => write!(f, "{}", value),
+            ArrayElement::Array(elements) => {
+                f.write_str("{")?;
+                for (i, element) in elements.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(",")?;
+                    }
+                    write!(f, "{}", element)?;
+                }
+                f.write_str("}")
+            }
         }
     }
+}
 
-    fn append_operation(base: &[String], to_append: &[String]) -> Vec<String> {
-        // For array append operations, we should remove duplicates
-        let mut result = base.to_vec();
-        for item in to_append {
-            if !result.contains(item) {
-                result.push(item.clone());
+/// Value of a number literal as the tokenizer reads it: decimal,
+/// scientific, `0x` hex or `0o` octal, with an optional sign. Words
+/// `f64::from_str` also accepts, such as `inf` and `NaN`, are not numbers.
+pub(crate) fn parse_number(text: &str) -> Option<f64> {
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, text.strip_prefix('+').unwrap_or(text)),
+    };
+    let radix = match digits.get(..2) {
+        Some("0x" | "0X") => Some(16),
+        Some("0o" | "0O") => Some(8),
+        _ => None,
+    };
+    let value = match radix {
+        Some(radix) => i64::from_str_radix(&digits[2..], radix).ok()? as f64,
+        None if digits.starts_with(|c: char| c.is_ascii_digit() || c == '.') => digits.parse().ok()?,
+        None => return None,
+    };
+    Some(if negative { -value } else { value })
+}
+
+/// Split on commas outside of braces.
+fn split_top_level(text: &str) -> Vec<&str> {
+    if text.trim().is_empty() {
+        return Vec::new();
+    }
+    let mut parts = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0;
+    for (i, c) in text.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => depth = depth.saturating_sub(1),
+            ',' if depth == 0 => {
+                parts.push(&text[start..i]);
+                start = i + 1;
             }
+            _ => {}
         }
-        result
+    }
+    parts.push(&text[start..]);
+    parts
+}
+
+/// How array operations are applied.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ArrayOptions {
+    /// Skip appended values that are already present. The game keeps them
+    /// (`magazines[] += {"mag", "mag"}` adds two), so this is off by default.
+    pub dedup_on_append: bool,
+}
+
+pub struct ArrayProcessor;
+
+impl ArrayProcessor {
+    pub fn process(base: &[String], values: &[String], operation: ArrayOperation) -> Vec<String> {
+        Self::process_with_options(base, values, operation, ArrayOptions::default())
     }
 
-    fn remove_operation(base: &[String], to_remove: &[String]) -> Vec<String> {
-        let remove_set: HashSet<_> = to_remove.iter().collect();
-        base.iter()
-            .filter(|item| !remove_set.contains(item))
-            .cloned()
+    pub fn process_with_options(
+        base: &[String],
+        values: &[String],
+        operation: ArrayOperation,
+        options: ArrayOptions,
+    ) -> Vec<String> {
+        // Compared as elements so "1" matches "1.0", but the text is kept
+        let parsed = |items: &[String]| -> Vec<(String, ArrayElement)> {
+            items.iter().map(|item| (item.clone(), ArrayElement::parse(item))).collect()
+        };
+        Self::process_by(&parsed(base), &parsed(values), operation, options, |(_, element)| element.key())
+            .into_iter()
+            .map(|(text, _)| text)
             .collect()
     }
+
+    /// The operation on typed elements.
+    pub fn process_typed(
+        base: &[ArrayElement],
+        values: &[ArrayElement],
+        operation: ArrayOperation,
+        options: ArrayOptions,
+    ) -> Vec<ArrayElement> {
+        Self::process_by(base, values, operation, options, ArrayElement::key)
+    }
+
+    /// The operation with elements matched by equal `key`s.
+    pub(crate) fn process_by<T: Clone, K: Eq + Hash>(
+        base: &[T],
+        values: &[T],
+        operation: ArrayOperation,
+        options: ArrayOptions,
+        key: impl Fn(&T) -> K,
+    ) -> Vec<T> {
+        let keys = |items: &[T]| items.iter().map(&key).collect::<HashSet<K>>();
+        match operation {
+            ArrayOperation::Append => {
+                let mut result = base.to_vec();
+                if !options.dedup_on_append {
+                    result.extend_from_slice(values);
+                    return result;
+                }
+                let mut present = keys(base);
+                for item in values {
+                    if present.insert(key(item)) {
+                        result.push(item.clone());
+                    }
+                }
+                result
+            }
+            ArrayOperation::Remove => {
+                let removed = keys(values);
+                base.iter().filter(|item| !removed.contains(&key(item))).cloned().collect()
+            }
+            ArrayOperation::Replace => values.to_vec(),
+            ArrayOperation::Intersect => {
+                let kept = keys(values);
+                base.iter().filter(|item| kept.contains(&key(item))).cloned().collect()
+            }
+        }
+    }
 }
 
 #[cfg(test)]
@@ -62,6 +259,22 @@ mod tests {
         assert_eq!(result, vec!["x", "y"]);
     }
 
+    #[test]
+    fn test_intersect_operation() {
+        let base = vec!["a".to_string(), "b".to_string(), "c".to_string()];
+        let values = vec!["b".to_string(), "c".to_string(), "d".to_string()];
+        let result = ArrayProcessor::process(&base, &values, ArrayOperation::Intersect);
+        assert_eq!(result, vec!["b", "c"]);
+
+        // Order follows the base array, not the intersected values
+        let values = vec!["c".to_string(), "a".to_string()];
+        let result = ArrayProcessor::process(&base, &values, ArrayOperation::Intersect);
+        assert_eq!(result, vec!["a", "c"]);
+
+        let empty: Vec<String> = vec![];
+        assert_eq!(ArrayProcessor::process(&base, &empty, ArrayOperation::Intersect), empty);
+    }
+
     #[test]
     fn test_edge_cases() {
         // Empty base array
@@ -167,4 +380,48 @@ mod tests {
         let result = ArrayProcessor::process(&base, &append, ArrayOperation::Append);
         assert_eq!(result, vec!["1.0", "1.08", "-0.06", "0.5"]);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_append_keeps_duplicates() {
+        let base = vec!["mag".to_string()];
+        let values = vec!["mag".to_string(), "mag".to_string(), "grenade".to_string()];
+        assert_eq!(ArrayProcessor::process(&base, &values, ArrayOperation::Append), vec!["mag", "mag", "mag", "grenade"]);
+
+        let options = ArrayOptions { dedup_on_append: true };
+        assert_eq!(ArrayProcessor::process_with_options(&base, &values, ArrayOperation::Append, options), vec!["mag", "grenade"]);
+    }
+
+    #[test]
+    fn test_structural_comparison() {
+        assert_eq!(ArrayElement::parse("1.0"), ArrayElement::Number(1.0));
+        assert_eq!(ArrayElement::parse("-0x10"), ArrayElement::Number(-16.0));
+        assert_eq!(ArrayElement::parse("inf"), ArrayElement::String("inf".to_string()));
+        assert_eq!(ArrayElement::parse("0o17"), ArrayElement::Number(15.0));
+        assert_eq!(ArrayElement::parse("+.5"), ArrayElement::Number(0.5));
+        assert_eq!(ArrayElement::parse("0x"), ArrayElement::String("0x".to_string()));
+        assert_eq!(ArrayElement::parse("{1,{a,2}}"), ArrayElement::Array(vec![
+            ArrayElement::Number(1.0),
+            ArrayElement::Array(vec![ArrayElement::String("a".to_string()), ArrayElement::Number(2.0)]),
+        ]));
+        assert_eq!(ArrayElement::parse("{}").to_string(), "{}");
+
+        let base = vec!["1".to_string(), "2".to_string(), "{1,2}".to_string()];
+        let values = vec!["1.0".to_string(), "{1.0,2}".to_string()];
+        assert_eq!(ArrayProcessor::process(&base, &values, ArrayOperation::Remove), vec!["2"]);
+        assert_eq!(ArrayProcessor::process(&base, &values, ArrayOperation::Intersect), vec!["1", "{1,2}"]);
+
+        let options = ArrayOptions { dedup_on_append: true };
+        assert_eq!(ArrayProcessor::process_with_options(&base, &values, ArrayOperation::Append, options), base);
+
+        // Typed elements keep a quoted "1" apart from the number 1
+        let base = vec![ArrayElement::String("1".to_string()), ArrayElement::Number(1.0)];
+        let result = ArrayProcessor::process_typed(&base, &[ArrayElement::Number(1.0)], ArrayOperation::Remove, options);
+        assert_eq!(result, vec![ArrayElement::String("1".to_string())]);
+
+        // -0 is 0, and appended duplicates within the values are skipped too
+        let base = vec!["-0".to_string(), "0x10".to_string()];
+        let values = vec!["0".to_string(), "3".to_string(), "3.0".to_string()];
+        assert_eq!(ArrayProcessor::process(&base, &values, ArrayOperation::Remove), vec!["0x10"]);
+        assert_eq!(ArrayProcessor::process_with_options(&base, &values, ArrayOperation::Append, options), vec!["-0", "0x10", "3"]);
+    }
+}
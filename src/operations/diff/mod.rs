@@ -0,0 +1,428 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use crate::ast::{ClassNode, PropertyNode, PropertyType};
+use crate::error::Error;
+
+mod patch;
+pub use patch::{generate_patch, Patch, PatchOp};
+
+/// Structural difference between two versions of a class.
+///
+/// Source locations and raw blocks are not compared, so a class that only
+/// moved within its file produces an empty diff.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ClassDiff {
+    pub name: String,
+    /// The old class's name when two differently named classes were diffed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub renamed_from: Option<String>,
+    /// `Some(new_parent)` when the class's parent changed.
+    pub parent: Option<Option<String>>,
+    pub properties: BTreeMap<String, DiffKind>,
+    pub nested_classes: Vec<NestedClassDiff>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum DiffKind {
+    Added(Box<PropertyNode>),
+    Removed(Box<PropertyNode>),
+    Modified { before: Box<PropertyNode>, after: Box<PropertyNode> },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum NestedClassDiff {
+    /// A class present only in `after`, at `index` in its nested class list.
+    Added { index: usize, class: Box<ClassNode> },
+    Removed(String),
+    Modified(ClassDiff),
+}
+
+/// Controls what `diff_with_options` treats as a change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct DiffOptions {
+    /// Arrays holding the same elements in a different order are equal.
+    pub ignore_array_order: bool,
+    /// Match property and nested class names ignoring case, as the game
+    /// does. Diffs are keyed by the old name, so they still apply to the
+    /// old tree.
+    pub case_insensitive: bool,
+}
+
+impl DiffOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_ignore_array_order(mut self, enabled: bool) -> Self {
+        self.ignore_array_order = enabled;
+        self
+    }
+
+    pub fn with_case_insensitive(mut self, enabled: bool) -> Self {
+        self.case_insensitive = enabled;
+        self
+    }
+
+    fn same_name(&self, a: &str, b: &str) -> bool {
+        if self.case_insensitive {
+            a.eq_ignore_ascii_case(b)
+        } else {
+            a == b
+        }
+    }
+}
+
+impl ClassDiff {
+    pub fn is_empty(&self) -> bool {
+        self.renamed_from.is_none() && self.parent.is_none() && self.properties.is_empty() && self.nested_classes.is_empty()
+    }
+
+    fn write_indented(&self, f: &mut fmt::Formatter<'_>, level: usize) -> fmt::Result {
+        let indent = "  ".repeat(level);
+        match &self.renamed_from {
+            Some(old) => writeln!(f, "{}class {} -> {}", indent, old, self.name)?,
+            None => writeln!(f, "{}class {}", indent, self.name)?,
+        }
+
+        let indent = "  ".repeat(level + 1);
+        if let Some(parent) = &self.parent {
+            writeln!(f, "{}parent: {}", indent, parent.as_deref().unwrap_or("(none)"))?;
+        }
+        for (name, kind) in &self.properties {
+            match kind {
+                DiffKind::Added(property) => writeln!(f, "{}+ {} = {}", indent, name, property.raw_value)?,
+                DiffKind::Removed(property) => writeln!(f, "{}- {} = {}", indent, name, property.raw_value)?,
+                DiffKind::Modified { before, after } => {
+                    writeln!(f, "{}~ {}: {} -> {}", indent, name, before.raw_value, after.raw_value)?
+                }
+            }
+        }
+        for nested in &self.nested_classes {
+            match nested {
+                NestedClassDiff::Added { class, .. } => writeln!(f, "{}+ class {}", indent, class.name)?,
+                NestedClassDiff::Removed(name) => writeln!(f, "{}- class {}", indent, name)?,
+                NestedClassDiff::Modified(diff) => diff.write_indented(f, level + 1)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// One block per changed class: `+`, `-` and `~` mark added, removed and
+/// modified members, and nested class changes are indented below.
+impl fmt::Display for ClassDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write_indented(f, 0)
+    }
+}
+
+pub fn diff(before: &ClassNode, after: &ClassNode) -> ClassDiff {
+    diff_with_options(before, after, &DiffOptions::default())
+}
+
+/// Diff two classes. They may have different names; the old one is then
+/// recorded in `renamed_from`.
+pub fn diff_with_options(before: &ClassNode, after: &ClassNode, options: &DiffOptions) -> ClassDiff {
+    let parent = (before.parent != after.parent).then(|| after.parent.clone());
+    // A change in case only is not a rename when names are case-insensitive
+    let renamed = !options.same_name(&before.name, &after.name);
+    let name = if renamed { &after.name } else { &before.name };
+    let renamed_from = renamed.then(|| before.name.clone());
+
+    let find_property = |properties: &IndexMap<String, PropertyNode>, name: &str| {
+        properties.get(name).cloned().or_else(|| {
+            properties.values().find(|p| options.same_name(&p.name, name)).cloned()
+        })
+    };
+
+    let mut properties = BTreeMap::new();
+    for (name, old) in &before.properties {
+        match find_property(&after.properties, name) {
+            None => {
+                properties.insert(name.clone(), DiffKind::Removed(Box::new(old.clone())));
+            }
+            Some(new) if !same_property(old, &new, options) => {
+                properties.insert(name.clone(), DiffKind::Modified { before: Box::new(old.clone()), after: Box::new(new) });
+            }
+            Some(_) => {}
+        }
+    }
+    for (name, new) in &after.properties {
+        if find_property(&before.properties, name).is_none() {
+            properties.insert(name.clone(), DiffKind::Added(Box::new(new.clone())));
+        }
+    }
+
+    let mut nested_classes = Vec::new();
+    for old in &before.nested_classes {
+        match find_class(&after.nested_classes, &old.name, options) {
+            None => nested_classes.push(NestedClassDiff::Removed(old.name.clone())),
+            Some(new) => {
+                let nested = diff_with_options(old, new, options);
+                if !nested.is_empty() {
+                    nested_classes.push(NestedClassDiff::Modified(nested));
+                }
+            }
+        }
+    }
+    for (index, new) in after.nested_classes.iter().enumerate() {
+        if find_class(&before.nested_classes, &new.name, options).is_none() {
+            nested_classes.push(NestedClassDiff::Added { index, class: Box::new(new.clone()) });
+        }
+    }
+
+    ClassDiff {
+        name: name.clone(),
+        renamed_from,
+        parent,
+        properties,
+        nested_classes,
+    }
+}
+
+/// Reconstruct `after` from `before` and `diff(before, after)`.
+///
+/// Fails if `base` does not match the state the diff was computed against.
+pub fn apply_diff(base: &mut ClassNode, diff: &ClassDiff) -> Result<(), Error> {
+    let expected_name = diff.renamed_from.as_ref().unwrap_or(&diff.name);
+    if &base.name != expected_name {
+        return Err(Error::DiffError(format!("Diff for class {} applied to class {}", expected_name, base.name)));
+    }
+    base.name = diff.name.clone();
+
+    if let Some(parent) = &diff.parent {
+        base.parent = parent.clone();
+    }
+
+    for (name, kind) in &diff.properties {
+        match kind {
+            DiffKind::Added(property) => {
+                if base.properties.contains_key(name) {
+                    return Err(Error::DiffError(format!("Property {}.{} already exists", base.name, name)));
+                }
+                base.properties.insert(name.clone(), (**property).clone());
+            }
+            DiffKind::Removed(_) => {
+                if base.properties.shift_remove(name).is_none() {
+                    return Err(Error::DiffError(format!("Property {}.{} not found", base.name, name)));
+                }
+            }
+            DiffKind::Modified { after, .. } => {
+                if !replace_property(&mut base.properties, name, after) {
+                    return Err(Error::DiffError(format!("Property {}.{} not found", base.name, name)));
+                }
+            }
+        }
+    }
+
+    // Removals first so `Added` indices refer to the final layout
+    for change in &diff.nested_classes {
+        if let NestedClassDiff::Removed(name) = change {
+            let count = base.nested_classes.len();
+            base.nested_classes.retain(|c| &c.name != name);
+            if base.nested_classes.len() == count {
+                return Err(Error::DiffError(format!("Nested class {}.{} not found", base.name, name)));
+            }
+        }
+    }
+    for change in &diff.nested_classes {
+        if let NestedClassDiff::Modified(nested_diff) = change {
+            let nested_name = nested_diff.renamed_from.as_ref().unwrap_or(&nested_diff.name);
+            let nested = base.nested_classes.iter_mut()
+                .find(|c| &c.name == nested_name)
+                .ok_or_else(|| Error::DiffError(format!("Nested class {}.{} not found", base.name, nested_name)))?;
+            apply_diff(nested, nested_diff)?;
+        }
+    }
+    for change in &diff.nested_classes {
+        if let NestedClassDiff::Added { index, class } = change {
+            let index = (*index).min(base.nested_classes.len());
+            base.nested_classes.insert(index, (**class).clone());
+        }
+    }
+
+    Ok(())
+}
+
+/// Replace the property keyed `name` with `property` in place, re-keying it
+/// when a case-insensitive diff matched it to a differently cased name.
+pub(super) fn replace_property(properties: &mut IndexMap<String, PropertyNode>, name: &str, property: &PropertyNode) -> bool {
+    let Some(index) = properties.get_index_of(name) else {
+        return false;
+    };
+    properties.shift_remove_index(index);
+    properties.shift_insert(index, property.name.clone(), property.clone());
+    true
+}
+
+/// Exact matches win over case-insensitive ones.
+fn find_class<'a>(classes: &'a [ClassNode], name: &str, options: &DiffOptions) -> Option<&'a ClassNode> {
+    classes.iter().find(|c| c.name == name)
+        .or_else(|| classes.iter().find(|c| options.same_name(&c.name, name)))
+}
+
+fn same_property(a: &PropertyNode, b: &PropertyNode, options: &DiffOptions) -> bool {
+    if a.value_type != b.value_type || a.operation != b.operation {
+        return false;
+    }
+    if a.value_type == PropertyType::Array && options.ignore_array_order {
+        let mut left = a.array_values.clone();
+        let mut right = b.array_values.clone();
+        left.sort();
+        right.sort();
+        return left == right;
+    }
+    a.raw_value == b.raw_value && a.array_values == b.array_values
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Tokenizer;
+    use crate::parser::Parser;
+
+    fn parse_class(input: &str) -> ClassNode {
+        let tokens = Tokenizer::new(input).tokenize().unwrap();
+        Parser::new(tokens).parse().unwrap().nested_classes.remove(0)
+    }
+
+    #[test]
+    fn test_diff_and_apply() {
+        let before = parse_class(r#"
+            class Vehicle: Car {
+                armor = 100;
+                displayName = "Old";
+                weapons[] = {"M4"};
+                class Turrets {
+                    class MainTurret { gunner = "A"; };
+                };
+                class Sounds {};
+            };
+        "#);
+        let after = parse_class(r#"
+            class Vehicle: Tank {
+
+                armor = 100;
+                displayName = "New";
+                weapons[] += {"M4", "M16"};
+                crew = "B_crew_F";
+                class HitPoints {};
+                class Turrets {
+                    class MainTurret { gunner = "B"; };
+                };
+            };
+        "#);
+
+        let changes = diff(&before, &after);
+        assert_eq!(changes.parent, Some(Some("Tank".to_string())));
+        assert_eq!(changes.properties.len(), 3);
+        assert!(matches!(changes.properties["crew"], DiffKind::Added(_)));
+        assert!(matches!(changes.properties["weapons"], DiffKind::Modified { .. }));
+        match &changes.properties["displayName"] {
+            DiffKind::Modified { before, after } => {
+                assert_eq!(before.raw_value, "Old");
+                assert_eq!(after.raw_value, "New");
+            }
+            other => panic!("unexpected diff {:?}", other),
+        }
+        // `armor` only moved a line down
+        assert!(!changes.properties.contains_key("armor"));
+        assert!(changes.nested_classes.contains(&NestedClassDiff::Removed("Sounds".to_string())));
+        assert!(changes.nested_classes.iter().any(|c| matches!(c, NestedClassDiff::Added { index: 0, class } if class.name == "HitPoints")));
+
+        let mut rebuilt = before.clone();
+        apply_diff(&mut rebuilt, &changes).unwrap();
+        assert_eq!(rebuilt.parent, after.parent);
+        assert_eq!(rebuilt.properties["crew"], after.properties["crew"]);
+        assert_eq!(rebuilt.properties["weapons"], after.properties["weapons"]);
+        let names = |class: &ClassNode| class.nested_classes.iter().map(|c| c.name.clone()).collect::<Vec<_>>();
+        assert_eq!(names(&rebuilt), names(&after));
+        assert!(diff(&rebuilt, &after).is_empty());
+    }
+
+    #[test]
+    fn test_apply_diff_to_wrong_base() {
+        let before = parse_class("class A { x = 1; };");
+        let after = parse_class("class A { };");
+        let changes = diff(&before, &after);
+
+        let mut other = parse_class("class B { x = 1; };");
+        assert!(apply_diff(&mut other, &changes).is_err());
+
+        let mut missing = after.clone();
+        assert!(apply_diff(&mut missing, &changes).is_err());
+        assert!(diff(&before, &before).is_empty());
+    }
+
+    #[test]
+    fn test_diff_options() {
+        let before = parse_class(r#"class Car { Armor = 100; weapons[] = {"A", "B"}; class HitPoints {}; };"#);
+        let after = parse_class(r#"class Car { armor = 100; weapons[] = {"B", "A"}; class hitpoints {}; };"#);
+
+        let strict = diff(&before, &after);
+        assert!(matches!(strict.properties["Armor"], DiffKind::Removed(_)));
+        assert!(matches!(strict.properties["armor"], DiffKind::Added(_)));
+        assert!(matches!(strict.properties["weapons"], DiffKind::Modified { .. }));
+        assert_eq!(strict.nested_classes.len(), 2);
+
+        let options = DiffOptions::new().with_case_insensitive(true).with_ignore_array_order(true);
+        assert!(diff_with_options(&before, &after, &options).is_empty());
+
+        // Order is ignored, content is not
+        let changed = parse_class(r#"class Car { Armor = 100; weapons[] = {"B", "C"}; class HitPoints {}; };"#);
+        let changes = diff_with_options(&before, &changed, &options);
+        assert_eq!(changes.properties.keys().collect::<Vec<_>>(), vec!["weapons"]);
+    }
+
+    #[test]
+    fn test_apply_case_insensitive_diff() {
+        let before = parse_class("class Car { Armor = 100; speed = 1; };");
+        let options = DiffOptions::new().with_case_insensitive(true);
+        // Recasing alone is no change
+        let recased = parse_class("class Car { armor = 100; speed = 1; };");
+        assert!(diff_with_options(&before, &recased, &options).is_empty());
+
+        // With a change the diff is keyed by the old name but applies the new one
+        let after = parse_class("class Car { armor = 150; speed = 1; };");
+        let changes = diff_with_options(&before, &after, &options);
+        assert_eq!(changes.properties.keys().collect::<Vec<_>>(), vec!["Armor"]);
+
+        let mut rebuilt = before.clone();
+        apply_diff(&mut rebuilt, &changes).unwrap();
+        assert_eq!(rebuilt.properties.keys().collect::<Vec<_>>(), vec!["armor", "speed"]);
+        assert_eq!(rebuilt.properties["armor"].name, "armor");
+        assert_eq!(rebuilt.properties["armor"].raw_value, "150");
+        assert!(diff(&rebuilt, &after).is_empty());
+    }
+
+    #[test]
+    fn test_diff_renamed_class() {
+        let before = parse_class(r#"class Car_v1: Car { speed = 100; model = "car.p3d"; class Turrets { class Main { gunner = 1; }; }; };"#);
+        let after = parse_class(r#"class Car_v2: Car_F { speed = 120; crew = "B_crew_F"; class Turrets { class Main { gunner = 2; }; }; class Sounds {}; };"#);
+
+        let changes = diff(&before, &after);
+        assert_eq!(changes.renamed_from.as_deref(), Some("Car_v1"));
+        assert_eq!(changes.to_string(), concat!(
+            "class Car_v1 -> Car_v2\n",
+            "  parent: Car_F\n",
+            "  + crew = B_crew_F\n",
+            "  - model = car.p3d\n",
+            "  ~ speed: 100 -> 120\n",
+            "  class Turrets\n",
+            "    class Main\n",
+            "      ~ gunner: 1 -> 2\n",
+            "  + class Sounds\n",
+        ));
+
+        let json = serde_json::to_string(&changes).unwrap();
+        let restored: ClassDiff = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored, changes);
+
+        let mut rebuilt = before.clone();
+        apply_diff(&mut rebuilt, &changes).unwrap();
+        assert_eq!(rebuilt.name, "Car_v2");
+        assert!(diff(&rebuilt, &after).is_empty());
+    }
+}
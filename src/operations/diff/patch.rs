@@ -0,0 +1,280 @@
+use serde::{Deserialize, Serialize};
+use crate::ast::{ClassNode, PropertyNode};
+use crate::error::Error;
+use crate::operations::writer::ConfigWriter;
+use super::{diff, replace_property, ClassDiff, DiffKind, NestedClassDiff};
+
+/// One change of a [`Patch`]. `path` names the class it applies to, from
+/// the patched class down, e.g. `["Vehicle", "Turrets"]`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum PatchOp {
+    AddProperty { path: Vec<String>, property: PropertyNode },
+    RemoveProperty { path: Vec<String>, property: PropertyNode },
+    ChangeProperty { path: Vec<String>, before: Box<PropertyNode>, after: Box<PropertyNode> },
+    /// `index` is the class's position among its siblings once the patch
+    /// is applied.
+    AddNestedClass { path: Vec<String>, index: usize, class: Box<ClassNode> },
+    RemoveNestedClass { path: Vec<String>, class: Box<ClassNode> },
+    SetParent { path: Vec<String>, before: Option<String>, after: Option<String> },
+}
+
+impl PatchOp {
+    pub fn path(&self) -> &[String] {
+        match self {
+            PatchOp::AddProperty { path, .. }
+            | PatchOp::RemoveProperty { path, .. }
+            | PatchOp::ChangeProperty { path, .. }
+            | PatchOp::AddNestedClass { path, .. }
+            | PatchOp::RemoveNestedClass { path, .. }
+            | PatchOp::SetParent { path, .. } => path,
+        }
+    }
+}
+
+/// Changes turning one version of a class into another, in the order they
+/// apply. Unlike a [`ClassDiff`] it is flat, and removals carry what was
+/// removed so they can be shown.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Patch(pub Vec<PatchOp>);
+
+/// Patch turning `before` into `after`. Nested classes are matched by
+/// name, so a renamed nested class is removed and added again.
+pub fn generate_patch(before: &ClassNode, after: &ClassNode) -> Patch {
+    let mut ops = Vec::new();
+    collect_ops(before, &diff(before, after), vec![before.name.clone()], &mut ops);
+    Patch(ops)
+}
+
+/// Ops in the order [`apply_diff`](super::apply_diff) makes its changes:
+/// parent and properties, then nested removals, changes and additions.
+fn collect_ops(before: &ClassNode, changes: &ClassDiff, path: Vec<String>, ops: &mut Vec<PatchOp>) {
+    if let Some(parent) = &changes.parent {
+        ops.push(PatchOp::SetParent { path: path.clone(), before: before.parent.clone(), after: parent.clone() });
+    }
+    for kind in changes.properties.values() {
+        let path = path.clone();
+        ops.push(match kind {
+            DiffKind::Added(property) => PatchOp::AddProperty { path, property: (**property).clone() },
+            DiffKind::Removed(property) => PatchOp::RemoveProperty { path, property: (**property).clone() },
+            DiffKind::Modified { before, after } => PatchOp::ChangeProperty { path, before: before.clone(), after: after.clone() },
+        });
+    }
+
+    let old_class = |name: &str| before.nested_classes.iter().find(|class| class.name == name);
+    for change in &changes.nested_classes {
+        if let NestedClassDiff::Removed(name) = change {
+            if let Some(class) = old_class(name) {
+                ops.push(PatchOp::RemoveNestedClass { path: path.clone(), class: Box::new(class.clone()) });
+            }
+        }
+    }
+    for change in &changes.nested_classes {
+        if let NestedClassDiff::Modified(nested) = change {
+            let name = nested.renamed_from.as_ref().unwrap_or(&nested.name);
+            if let Some(class) = old_class(name) {
+                let mut nested_path = path.clone();
+                nested_path.push(name.clone());
+                collect_ops(class, nested, nested_path, ops);
+            }
+        }
+    }
+    for change in &changes.nested_classes {
+        if let NestedClassDiff::Added { index, class } = change {
+            ops.push(PatchOp::AddNestedClass { path: path.clone(), index: *index, class: class.clone() });
+        }
+    }
+}
+
+impl Patch {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn ops(&self) -> &[PatchOp] {
+        &self.0
+    }
+
+    /// Unified-diff style text: an `@@ Class.Nested @@` header whenever the
+    /// class changes, then `-` lines for old and `+` lines for new config.
+    pub fn to_diff_string(&self) -> String {
+        let writer = ConfigWriter::new();
+        let mut output = String::new();
+        let mut current: Option<&[String]> = None;
+        for op in &self.0 {
+            if current != Some(op.path()) {
+                output.push_str(&format!("@@ {} @@\n", op.path().join(".")));
+                current = Some(op.path());
+            }
+            match op {
+                PatchOp::AddProperty { property, .. } => push_lines('+', &writer.write_property(property), &mut output),
+                PatchOp::RemoveProperty { property, .. } => push_lines('-', &writer.write_property(property), &mut output),
+                PatchOp::ChangeProperty { before, after, .. } => {
+                    push_lines('-', &writer.write_property(before), &mut output);
+                    push_lines('+', &writer.write_property(after), &mut output);
+                }
+                PatchOp::AddNestedClass { class, .. } => push_lines('+', &writer.write_class(class), &mut output),
+                PatchOp::RemoveNestedClass { class, .. } => push_lines('-', &writer.write_class(class), &mut output),
+                PatchOp::SetParent { path, before, after } => {
+                    let name = path.last().map_or("", String::as_str);
+                    let header = |parent: &Option<String>| match parent {
+                        Some(parent) => format!("class {}: {}", name, parent),
+                        None => format!("class {}", name),
+                    };
+                    push_lines('-', &header(before), &mut output);
+                    push_lines('+', &header(after), &mut output);
+                }
+            }
+        }
+        output
+    }
+
+    /// Apply every op to `target`, which must be the class the patch was
+    /// generated from. Fails, leaving `target` unchanged, if an op does not
+    /// fit: a removed property or class that does not exist, an added
+    /// property that already does, or a path that is not in `target`.
+    pub fn apply(&self, target: &mut ClassNode) -> Result<(), Error> {
+        let mut patched = target.clone();
+        for op in &self.0 {
+            apply_op(&mut patched, op)?;
+        }
+        *target = patched;
+        Ok(())
+    }
+}
+
+fn push_lines(prefix: char, text: &str, output: &mut String) {
+    for line in text.lines() {
+        output.push(prefix);
+        output.push_str(line);
+        output.push('\n');
+    }
+}
+
+fn apply_op(root: &mut ClassNode, op: &PatchOp) -> Result<(), Error> {
+    let path = op.path();
+    let class = find_path(root, path)
+        .ok_or_else(|| Error::DiffError(format!("Class {} not found", path.join("."))))?;
+    let location = path.join(".");
+
+    match op {
+        PatchOp::AddProperty { property, .. } => {
+            if class.properties.contains_key(&property.name) {
+                return Err(Error::DiffError(format!("Property {}.{} already exists", location, property.name)));
+            }
+            class.properties.insert(property.name.clone(), property.clone());
+        }
+        PatchOp::RemoveProperty { property, .. } => {
+            if class.properties.shift_remove(&property.name).is_none() {
+                return Err(Error::DiffError(format!("Property {}.{} not found", location, property.name)));
+            }
+        }
+        PatchOp::ChangeProperty { before, after, .. } => {
+            if !replace_property(&mut class.properties, &before.name, after) {
+                return Err(Error::DiffError(format!("Property {}.{} not found", location, before.name)));
+            }
+        }
+        PatchOp::AddNestedClass { index, class: nested, .. } => {
+            if class.nested_classes.iter().any(|c| c.name == nested.name) {
+                return Err(Error::DiffError(format!("Nested class {}.{} already exists", location, nested.name)));
+            }
+            let index = (*index).min(class.nested_classes.len());
+            class.nested_classes.insert(index, (**nested).clone());
+        }
+        PatchOp::RemoveNestedClass { class: nested, .. } => {
+            let index = class.nested_classes.iter().position(|c| c.name == nested.name)
+                .ok_or_else(|| Error::DiffError(format!("Nested class {}.{} not found", location, nested.name)))?;
+            class.nested_classes.remove(index);
+        }
+        PatchOp::SetParent { after, .. } => class.parent = after.clone(),
+    }
+    Ok(())
+}
+
+fn find_path<'a>(root: &'a mut ClassNode, path: &[String]) -> Option<&'a mut ClassNode> {
+    let (first, rest) = path.split_first()?;
+    if &root.name != first {
+        return None;
+    }
+    rest.iter().try_fold(root, |class, name| {
+        class.nested_classes.iter_mut().find(|nested| &nested.name == name)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ClassScanner;
+
+    fn parse_class(input: &str) -> ClassNode {
+        ClassScanner::new().parse_string(input).unwrap().remove(1)
+    }
+
+    #[test]
+    fn test_generate_and_apply_patch() {
+        let before = parse_class(r#"
+            class Vehicle: Car {
+                armor = 100;
+                displayName = "Old";
+                class Turrets {
+                    class MainTurret { gunner = "A"; };
+                };
+                class Sounds {};
+            };
+        "#);
+        let after = parse_class(r#"
+            class Vehicle: Tank {
+                displayName = "New";
+                crew = "B_crew_F";
+                class HitPoints {};
+                class Turrets {
+                    class MainTurret { gunner = "B"; };
+                };
+            };
+        "#);
+
+        let patch = generate_patch(&before, &after);
+        assert_eq!(patch.to_diff_string(), concat!(
+            "@@ Vehicle @@\n",
+            "-class Vehicle: Car\n",
+            "+class Vehicle: Tank\n",
+            "-armor = 100;\n",
+            "+crew = \"B_crew_F\";\n",
+            "-displayName = \"Old\";\n",
+            "+displayName = \"New\";\n",
+            "-class Sounds {};\n",
+            "@@ Vehicle.Turrets.MainTurret @@\n",
+            "-gunner = \"A\";\n",
+            "+gunner = \"B\";\n",
+            "@@ Vehicle @@\n",
+            "+class HitPoints {};\n",
+        ));
+
+        let mut patched = before.clone();
+        patch.apply(&mut patched).unwrap();
+        assert_eq!(patched, after);
+        assert!(generate_patch(&patched, &after).is_empty());
+
+        let json = serde_json::to_string(&patch).unwrap();
+        assert_eq!(serde_json::from_str::<Patch>(&json).unwrap(), patch);
+    }
+
+    #[test]
+    fn test_apply_patch_errors() {
+        let before = parse_class("class A { x = 1; y = 2; };");
+        let after = parse_class("class A { y = 3; };");
+        let patch = generate_patch(&before, &after);
+
+        // `x` is already gone, and the target is left as it was
+        let mut target = after.clone();
+        let error = patch.apply(&mut target).unwrap_err();
+        assert!(error.to_string().contains("Property A.x not found"), "{}", error);
+        assert_eq!(target, after);
+
+        let mut other = parse_class("class B { x = 1; y = 2; };");
+        assert!(patch.apply(&mut other).is_err());
+    }
+}
@@ -0,0 +1,60 @@
+use std::collections::HashMap;
+use crate::lexer::expression::{ExpressionEvaluator, NumericMode};
+
+/// Evaluate constant arithmetic such as `120*0.8` or `(1+2)/2` with the
+/// usual precedence: unary signs, then `*` and `/`, then `+` and `-`.
+///
+/// Returns `None` when the expression is not constant (`foo*2`), is
+/// malformed, or divides by zero; callers keep the expression text then.
+/// Numbers may be decimal, scientific, `0x` hex or `0o` octal. The parser is
+/// the one the preprocessor uses for `#if`, in floating point mode.
+pub fn evaluate(expression: &str) -> Option<f64> {
+    ExpressionEvaluator::evaluate(expression, NumericMode::Float, &HashMap::new())
+        .ok()
+        .filter(|value| value.is_finite())
+}
+
+/// `value` as a config number literal: whole numbers without a fraction,
+/// others rounded to 12 significant digits so float error, as in
+/// `0.1+0.2`, does not end up in the text.
+pub fn format_number(value: f64) -> String {
+    let rounded: f64 = format!("{:.11e}", value).parse().unwrap_or(value);
+    // Also drops the sign of `-0`
+    if rounded == 0.0 {
+        return "0".to_string();
+    }
+    rounded.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_evaluate() {
+        assert_eq!(evaluate("2+3*4"), Some(14.0));
+        assert_eq!(evaluate("(1+2)/2"), Some(1.5));
+        assert_eq!(evaluate("120 * 0.8"), Some(96.0));
+        assert_eq!(evaluate("-(2 - -3)"), Some(-5.0));
+        assert_eq!(evaluate("1e-3*1000 + 0x10"), Some(17.0));
+        assert_eq!(evaluate("10-2-3"), Some(5.0));
+
+        assert_eq!(evaluate("foo*2"), None);
+        assert_eq!(evaluate("1/0"), None);
+        assert_eq!(evaluate("1/(2-2)"), None);
+        assert_eq!(evaluate("(1+2"), None);
+        assert_eq!(evaluate("1 2"), None);
+        assert_eq!(evaluate(""), None);
+    }
+
+    #[test]
+    fn test_format_number() {
+        assert_eq!(format_number(96.0), "96");
+        assert_eq!(format_number(0.1 + 0.2), "0.3");
+        assert_eq!(format_number(1.0 / 3.0), "0.333333333333");
+        assert_eq!(format_number(-2.5), "-2.5");
+        assert_eq!(format_number(1e-7), "0.0000001");
+        assert_eq!(format_number(-0.0), "0");
+        assert_eq!(format_number(1e15), "1000000000000000");
+    }
+}
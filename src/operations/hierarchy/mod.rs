@@ -0,0 +1,68 @@
+use crate::ast::graphviz::{to_dot_with_options, DotOptions};
+use crate::ast::ClassNode;
+
+/// Render the inheritance hierarchy of `classes` as a Graphviz digraph, one
+/// node per class labelled with its property count.
+///
+/// `root_filter` is a dot-separated class path such as `CfgVehicles`,
+/// matched ignoring case; when given, only the classes inside it are rendered, with ids relative to it.
+/// Parents outside the rendered classes become dashed nodes. An unknown
+/// root renders an empty graph.
+pub fn export_dot(classes: &[ClassNode], root_filter: Option<&str>) -> String {
+    let classes = match classes.first() {
+        Some(root) if root.name.is_empty() => &root.nested_classes[..],
+        _ => classes,
+    };
+    let options = DotOptions::new().with_property_counts(true);
+
+    let Some(path) = root_filter else {
+        return to_dot_with_options(classes, &options);
+    };
+    let (first, rest) = path.split_once('.').unwrap_or((path, ""));
+    let root = classes.iter()
+        .find(|class| class.name.eq_ignore_ascii_case(first))
+        .and_then(|class| class.get_path(&rest.replace('.', "/")));
+    match root {
+        Some(root) => to_dot_with_options(&root.nested_classes, &options),
+        None => to_dot_with_options(&[], &options),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ClassScanner;
+
+    #[test]
+    fn test_export_dot() {
+        let classes = ClassScanner::new().parse_string(r#"
+            class CfgVehicles {
+                class Vehicle { crew = 1; };
+                class Car: Vehicle { wheels = 4; maxSpeed = 120; };
+                class Tank: Tracked_Base {
+                    class Turrets { class MainTurret {}; };
+                };
+            };
+            class CfgWeapons {
+                class Rifle {};
+            };
+        "#).unwrap();
+
+        let dot = export_dot(&classes, Some("cfgvehicles"));
+        assert!(dot.starts_with("digraph classes {"));
+        assert!(dot.contains("\"Car\" -> \"Vehicle\";"));
+        assert!(dot.contains("\"Car\" [label=\"Car\\n(2 properties)\"];"));
+        assert!(dot.contains("\"Vehicle\" [label=\"Vehicle\\n(1 property)\", shape=box];"));
+        assert!(dot.contains("\"Tracked_Base\" [label=\"Tracked_Base\", style=dashed];"));
+        assert!(dot.contains("subgraph \"cluster_Tank/Turrets\""));
+        assert!(!dot.contains("Rifle"));
+        assert_eq!(dot.matches('{').count(), dot.matches('}').count());
+        // Every node and edge statement uses quoted ids
+        for line in dot.lines().map(str::trim).skip(2).filter(|line| line.ends_with("];") || line.contains("->")) {
+            assert!(line.starts_with('"'), "unquoted id in {}", line);
+        }
+
+        assert!(export_dot(&classes, None).contains("\"CfgVehicles/Car\" -> \"CfgVehicles/Vehicle\";"));
+        assert!(!export_dot(&classes, Some("CfgVehicles.Missing")).contains("->"));
+    }
+}
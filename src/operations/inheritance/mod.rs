@@ -1,113 +1,414 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{HashMap, HashSet, VecDeque};
 use crate::ast::{ClassNode, PropertyNode, PropertyType};
-use crate::error::Error;
-use crate::operations::arrays::ArrayOperation;
+use crate::error::{Error, Warning};
+use crate::operations::arrays::{ArrayOperation, ArrayOptions};
+use tracing::warn;
+
+mod parallel;
+mod registry;
+
+pub use parallel::ParallelInheritanceResolver;
+pub use registry::{ClassRegistry, SourceAddon};
+
+/// What inheritance resolution does with a parent class that was never
+/// registered, typically one defined by the base game rather than the parsed
+/// files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MissingParent {
+    /// Fail with "Class X not found".
+    #[default]
+    Error,
+    /// Resolve the class as if its parent were empty.
+    Ignore,
+    /// Like `Ignore`, but record the parent's name for `unresolved_parents()`
+    /// and raise a [`Warning::MissingParent`].
+    Collect,
+}
 
 pub struct InheritanceResolver {
     class_map: HashMap<String, ClassNode>,
-    processed: HashSet<String>,
+    resolved_cache: HashMap<String, ClassNode>,
+    /// Parent key -> keys of classes whose cached form merged that parent.
+    dependents: HashMap<String, Vec<String>>,
+    case_insensitive: bool,
+    missing_parent: MissingParent,
+    strict_cycles: bool,
+    array_options: ArrayOptions,
+    unresolved_parents: Vec<String>,
+    warnings: Vec<Warning>,
 }
 
 impl InheritanceResolver {
     pub fn new() -> Self {
         Self {
             class_map: HashMap::new(),
-            processed: HashSet::new(),
+            resolved_cache: HashMap::new(),
+            dependents: HashMap::new(),
+            case_insensitive: true,
+            missing_parent: MissingParent::Error,
+            strict_cycles: false,
+            array_options: ArrayOptions::default(),
+            unresolved_parents: Vec::new(),
+            warnings: Vec::new(),
         }
     }
 
+    /// Match class names ignoring case, as the game does. On by default.
+    pub fn with_case_insensitive(mut self, enabled: bool) -> Self {
+        self.case_insensitive = enabled;
+        self
+    }
+
+    pub fn with_missing_parent(mut self, policy: MissingParent) -> Self {
+        self.missing_parent = policy;
+        self
+    }
+
+    /// Fail on an inheritance cycle with an error naming it, instead of
+    /// leaving the parent that closes the cycle unmerged and raising a
+    /// [`Warning::CircularInheritanceResolved`]. Off by default.
+    pub fn with_strict_cycles(mut self, enabled: bool) -> Self {
+        self.strict_cycles = enabled;
+        self
+    }
+
+    /// How inherited arrays are combined with `+=`, `-=` and `&=`.
+    pub fn with_array_options(mut self, options: ArrayOptions) -> Self {
+        self.array_options = options;
+        self
+    }
+
+    /// Parent names that were not found, in the order they were first met.
+    /// Only filled with [`MissingParent::Collect`].
+    pub fn unresolved_parents(&self) -> &[String] {
+        &self.unresolved_parents
+    }
+
+    /// Register a class by name. The parser's unnamed root registers its
+    /// top-level classes instead.
     pub fn add_class(&mut self, class: ClassNode) {
-        self.class_map.insert(class.name.clone(), class);
+        if class.name.is_empty() {
+            for nested in class.nested_classes {
+                self.add_class(nested);
+            }
+            return;
+        }
+
+        let key = self.key(&class.name);
+        if let Some(existing) = self.class_map.get(&key) {
+            if existing.name != class.name {
+                warn!(existing = %existing.name, replacement = %class.name, "Class names differ only by case; keeping the last one");
+            }
+        }
+        if class.is_external && self.class_map.get(&key).is_some_and(|existing| !existing.is_external) {
+            // A forward declaration must not shadow the real definition
+            return;
+        }
+        self.invalidate(&class.name);
+        self.class_map.insert(key, class);
     }
 
-    pub fn resolve(&mut self) -> Result<Vec<ClassNode>, Error> {
-        let mut resolved_classes = Vec::new();
-        let class_names: Vec<String> = self.class_map.keys().cloned().collect();
+    /// Take the warnings collected while resolving, such as inheritance
+    /// cycles that were broken.
+    pub fn take_warnings(&mut self) -> Vec<Warning> {
+        std::mem::take(&mut self.warnings)
+    }
+
+    /// Fully resolved classes from earlier calls, keyed by (lowercased when
+    /// case-insensitive) class name.
+    pub fn resolved_cache(&self) -> &HashMap<String, ClassNode> {
+        &self.resolved_cache
+    }
+
+    /// Drop the cached resolution of `class_name` and of every class that
+    /// transitively inherits from it.
+    pub fn invalidate(&mut self, class_name: &str) {
+        let mut pending = vec![self.key(class_name)];
+        let mut seen = HashSet::new();
+        while let Some(key) = pending.pop() {
+            if !seen.insert(key.clone()) {
+                continue;
+            }
+            self.resolved_cache.remove(&key);
+            if let Some(children) = self.dependents.remove(&key) {
+                pending.extend(children);
+            }
+        }
+    }
+
+    /// Names of the ancestors of `class_name`, nearest first. A parent that
+    /// is not registered ends the chain. An inheritance cycle is an error
+    /// naming the cycle.
+    pub fn ancestors(&self, class_name: &str) -> Result<Vec<String>, Error> {
+        let key = self.key(class_name);
+        if !self.class_map.contains_key(&key) {
+            return Err(Error::InheritanceError(format!("Class {} not found", class_name)));
+        }
+        let (chain, cycle) = self.walk_ancestors(key);
+        match cycle {
+            Some(cycle) => Err(Error::InheritanceError(format!("Circular inheritance detected: {}", cycle.join(" -> ")))),
+            None => Ok(chain),
+        }
+    }
+
+    /// Whether `ancestor` is among the ancestors of `class_name`. A cycle
+    /// ends the walk rather than failing it.
+    pub fn derives_from(&self, class_name: &str, ancestor: &str) -> bool {
+        let key = self.key(class_name);
+        if !self.class_map.contains_key(&key) {
+            return false;
+        }
+        let ancestor = self.key(ancestor);
+        self.walk_ancestors(key).0.iter().any(|name| self.key(name) == ancestor)
+    }
 
-        for class_name in class_names {
-            if !self.processed.contains(&class_name) {
-                let resolved = self.resolve_class_with_cycle_detection(&class_name, &mut HashSet::new())?;
-                resolved_classes.push(resolved);
+    /// Names of every class that inherits from `class_name`, directly or
+    /// not, nearest first.
+    pub fn descendants(&self, class_name: &str) -> Vec<String> {
+        let mut keys: Vec<&String> = self.class_map.keys().collect();
+        keys.sort();
+        let mut children: HashMap<String, Vec<&String>> = HashMap::new();
+        for key in keys {
+            if let Some(parent) = &self.class_map[key].parent {
+                children.entry(self.key(parent)).or_default().push(key);
             }
         }
 
-        Ok(resolved_classes)
+        let root = self.key(class_name);
+        let mut descendants = Vec::new();
+        let mut seen = HashSet::from([root.clone()]);
+        let mut queue = VecDeque::from([root]);
+        while let Some(key) = queue.pop_front() {
+            for &child in children.get(&key).into_iter().flatten() {
+                if seen.insert(child.clone()) {
+                    descendants.push(self.class_map[child].name.clone());
+                    queue.push_back(child.clone());
+                }
+            }
+        }
+        descendants
     }
 
-    fn resolve_class(&mut self, class_name: &str) -> Result<ClassNode, Error> {
-        // Use a separate set for cycle detection during a single resolve operation
-        self.resolve_class_with_cycle_detection(class_name, &mut HashSet::new())
+    /// The ancestor names of the class at `key` and, if the walk ran into a
+    /// cycle, the cycle's names.
+    fn walk_ancestors(&self, mut key: String) -> (Vec<String>, Option<Vec<String>>) {
+        let mut chain = Vec::new();
+        let mut seen = vec![key.clone()];
+        while let Some(parent) = self.class_map.get(&key).and_then(|class| class.parent.as_ref()) {
+            let parent_key = self.key(parent);
+            let Some(parent_class) = self.class_map.get(&parent_key) else {
+                chain.push(parent.clone());
+                break;
+            };
+            if let Some(start) = seen.iter().position(|k| *k == parent_key) {
+                let mut cycle: Vec<String> = seen[start..].iter().map(|k| self.class_map[k].name.clone()).collect();
+                cycle.push(parent_class.name.clone());
+                return (chain, Some(cycle));
+            }
+            chain.push(parent_class.name.clone());
+            seen.push(parent_key.clone());
+            key = parent_key;
+        }
+        (chain, None)
     }
 
-    fn resolve_class_with_cycle_detection(
+    fn key(&self, name: &str) -> String {
+        if self.case_insensitive {
+            name.to_lowercase()
+        } else {
+            name.to_string()
+        }
+    }
+
+    /// Resolve every registered class, parents before children, so each
+    /// parent is merged exactly once and children merge from its resolved
+    /// form by reference.
+    ///
+    /// The resolved classes are moved out of the cache rather than cloned;
+    /// registered classes are kept.
+    pub fn resolve(&mut self) -> Result<Vec<ClassNode>, Error> {
+        let order = self.resolution_order();
+        for key in &order {
+            self.ensure_resolved(key, &mut Vec::new())?;
+        }
+
+        self.dependents.clear();
+        let mut resolved = std::mem::take(&mut self.resolved_cache);
+        Ok(order.iter()
+            .filter_map(|key| resolved.remove(key))
+            .collect())
+    }
+
+    /// Registered class keys in topological order (ancestors first). Classes
+    /// in an inheritance cycle are ordered arbitrarily; `ensure_resolved`
+    /// leaves the cyclic edge unmerged.
+    fn resolution_order(&self) -> Vec<String> {
+        let mut keys: Vec<&String> = self.class_map.keys().collect();
+        keys.sort();
+
+        let mut visited = HashSet::new();
+        let mut order = Vec::with_capacity(keys.len());
+        for key in keys {
+            // Walk up to the first already ordered ancestor, then emit the
+            // chain top-down
+            let mut chain = Vec::new();
+            let mut on_chain = HashSet::new();
+            let mut current = Some(key.clone());
+            while let Some(key) = current {
+                if visited.contains(&key) || !on_chain.insert(key.clone()) {
+                    break;
+                }
+                current = self.class_map.get(&key)
+                    .and_then(|class| class.parent.as_ref())
+                    .map(|parent| self.key(parent))
+                    .filter(|parent| self.class_map.contains_key(parent));
+                chain.push(key);
+            }
+            for key in chain.into_iter().rev() {
+                visited.insert(key.clone());
+                order.push(key);
+            }
+        }
+        order
+    }
+
+    /// Resolve a single class, reusing cached ancestors from earlier calls.
+    pub fn resolve_class(&mut self, class_name: &str) -> Result<ClassNode, Error> {
+        // Use a separate stack for cycle detection during a single resolve operation
+        let key = self.ensure_resolved(class_name, &mut Vec::new())?;
+        Ok(self.resolved_cache[&key].clone())
+    }
+
+    /// Resolve `class_name` into the cache and return its key.
+    fn ensure_resolved(
         &mut self, 
         class_name: &str, 
-        processing_stack: &mut HashSet<String>
-    ) -> Result<ClassNode, Error> {
-        // Return already processed classes directly
-        if self.processed.contains(class_name) {
-            return Ok(self.class_map.get(class_name).unwrap().clone());
+        processing_stack: &mut Vec<String>
+    ) -> Result<String, Error> {
+        let key = self.key(class_name);
+
+        if self.resolved_cache.contains_key(&key) {
+            return Ok(key);
         }
 
         // Check for circular inheritance
-        if processing_stack.contains(class_name) {
+        if let Some(start) = processing_stack.iter().position(|k| *k == key) {
+            let mut chain: Vec<String> = processing_stack[start..].iter()
+                .map(|k| self.class_map[k].name.clone())
+                .collect();
+            chain.push(class_name.to_string());
+            if self.strict_cycles {
+                return Err(Error::InheritanceError(format!("Circular inheritance detected: {}", chain.join(" -> "))));
+            }
+            self.warnings.push(Warning::CircularInheritanceResolved { chain });
             return Err(Error::InheritanceError(
                 format!("Circular inheritance detected involving class {}", class_name)
             ));
         }
 
-        let mut class = self.class_map.get(class_name)
+        let mut class = self.class_map.get(&key)
             .ok_or_else(|| Error::InheritanceError(format!("Class {} not found", class_name)))?
             .clone();
 
         // Mark this class as being processed to detect cycles
-        processing_stack.insert(class_name.to_string());
+        processing_stack.push(key.clone());
 
         if let Some(parent_name) = &class.parent {
-            // Try to process the parent class, which might detect a cycle
-            match self.resolve_class_with_cycle_detection(parent_name, processing_stack) {
-                Ok(parent) => self.merge_with_parent(&mut class, parent)?,
-                Err(Error::InheritanceError(msg)) if msg.contains("Circular inheritance") => {
-                    // If it's a circular reference, we can still use what we have
-                    // Just continue with the current class without merging parent properties
-                },
-                Err(e) => return Err(e),  // Propagate other errors
+            if !self.class_map.contains_key(&self.key(parent_name)) {
+                self.missing_parent(&class.name, parent_name)?;
+                // Registering the parent later must evict this class
+                let parent_key = self.key(parent_name);
+                self.dependents.entry(parent_key).or_default().push(key.clone());
+            } else {
+                // Try to process the parent class, which might detect a cycle
+                match self.ensure_resolved(parent_name, processing_stack) {
+                    Ok(parent_key) => {
+                        self.merge_with_parent(&mut class, &self.resolved_cache[&parent_key])?;
+                        self.dependents.entry(parent_key).or_default().push(key.clone());
+                    }
+                    Err(Error::InheritanceError(msg)) if !self.strict_cycles && msg.contains("Circular inheritance") => {
+                        // If it's a circular reference, we can still use what we have
+                        // Just continue with the current class without merging parent properties
+                        let parent_key = self.key(parent_name);
+                        self.dependents.entry(parent_key).or_default().push(key.clone());
+                    },
+                    Err(e) => return Err(e),  // Propagate other errors
+                }
             }
         }
 
         // Remove this class from the processing stack since we're done with it
-        processing_stack.remove(class_name);
+        processing_stack.pop();
         
-        // Mark as fully processed for future reference
-        self.processed.insert(class_name.to_string());
+        // Cache the resolved form so descendants inherit merged members
+        // (including deletions) rather than the raw class
+        self.resolved_cache.insert(key.clone(), class);
         
-        Ok(class)
+        Ok(key)
+    }
+
+    /// Apply the missing-parent policy to `class`'s unregistered `parent`.
+    fn missing_parent(&mut self, class: &str, parent: &str) -> Result<(), Error> {
+        match self.missing_parent {
+            MissingParent::Error => Err(Error::InheritanceError(format!("Class {} not found", parent))),
+            MissingParent::Ignore => Ok(()),
+            MissingParent::Collect => {
+                let key = self.key(parent);
+                if !self.unresolved_parents.iter().any(|name| self.key(name) == key) {
+                    self.unresolved_parents.push(parent.to_string());
+                }
+                self.warnings.push(Warning::MissingParent { class: class.to_string(), parent: parent.to_string() });
+                Ok(())
+            }
+        }
     }
 
-    fn merge_with_parent(&self, child: &mut ClassNode, parent: ClassNode) -> Result<(), Error> {
+    fn merge_with_parent(&self, child: &mut ClassNode, parent: &ClassNode) -> Result<(), Error> {
+        // `delete name;` in the child drops the inherited member entirely
+        let deleted: HashSet<String> = child.properties.values()
+            .filter(|prop| prop.is_deleted())
+            .map(|prop| &prop.name)
+            .chain(&child.deleted_classes)
+            .map(|name| self.key(name))
+            .collect();
+        child.properties.retain(|_, prop| !prop.is_deleted());
+        child.deleted_classes.clear();
+        child.nested_classes.retain(|nested| !deleted.contains(&self.key(&nested.name)));
+
         // Merge properties from parent that don't exist in child
-        for (name, parent_prop) in parent.properties {
-            if !child.properties.contains_key(&name) {
-                child.properties.insert(name, parent_prop);
-            } else if let Some(child_prop) = child.properties.get_mut(&name) {
-                self.merge_property(child_prop, &parent_prop)?;
+        child.properties.reserve(parent.properties.len());
+        for (name, parent_prop) in &parent.properties {
+            if !deleted.is_empty() && deleted.contains(&self.key(name)) {
+                continue;
+            }
+            // `get_mut` first so the name is only cloned for new entries
+            match child.properties.get_mut(name) {
+                Some(child_prop) => self.merge_property(child_prop, parent_prop)?,
+                None => {
+                    child.properties.insert(name.clone(), parent_prop.clone());
+                }
             }
         }
 
         // Merge nested classes
-        let mut nested_map: HashMap<String, ClassNode> = parent.nested_classes
-            .into_iter()
-            .map(|c| (c.name.clone(), c))
+        let mut nested_map: HashMap<String, &ClassNode> = parent.nested_classes
+            .iter()
+            .map(|c| (self.key(&c.name), c))
+            .filter(|(key, _)| !deleted.contains(key))
             .collect();
 
         for nested_child in &mut child.nested_classes {
-            if let Some(nested_parent) = nested_map.remove(&nested_child.name) {
+            if let Some(nested_parent) = nested_map.remove(&self.key(&nested_child.name)) {
                 self.merge_with_parent(nested_child, nested_parent)?;
             }
         }
 
-        // Add remaining parent nested classes
-        child.nested_classes.extend(nested_map.into_values());
+        // Add remaining parent nested classes, keeping the parent's order
+        for nested_parent in &parent.nested_classes {
+            if nested_map.remove(&self.key(&nested_parent.name)).is_some() {
+                child.nested_classes.push(nested_parent.clone());
+            }
+        }
 
         Ok(())
     }
@@ -116,59 +417,103 @@ impl InheritanceResolver {
         // Only merge array properties with operations
         if child.value_type == PropertyType::Array && parent.value_type == PropertyType::Array {
             if let Some(op) = child.operation {
-                use crate::operations::arrays::ArrayProcessor;
-                child.array_values = ArrayProcessor::process(
-                    &parent.array_values,
-                    &child.array_values,
-                    op,
-                );
+                child.apply_array_operation(parent, op, self.array_options);
             }
         }
         Ok(())
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::ast::AccessModifier;
+/// Order `classes` so every parent comes before its children, using Kahn's
+/// algorithm. Parents are matched by name ignoring case; a parent missing
+/// from `classes` (e.g. only forward-declared elsewhere) makes the class a
+/// root. Classes that are ready at the same time keep their input order.
+/// Nested classes are not reordered.
+pub fn topological_sort(classes: &[ClassNode]) -> Result<Vec<&ClassNode>, Error> {
+    Ok(topological_order(classes)?.into_iter().map(|index| &classes[index]).collect())
+}
+
+/// [`topological_sort`] for callers that own their classes.
+pub fn topological_sort_owned(classes: Vec<ClassNode>) -> Result<Vec<ClassNode>, Error> {
+    let order = topological_order(&classes)?;
+    let mut slots: Vec<Option<ClassNode>> = classes.into_iter().map(Some).collect();
+    Ok(order.into_iter().filter_map(|index| slots[index].take()).collect())
+}
 
-    fn create_test_property(name: &str, value: &str, prop_type: PropertyType) -> PropertyNode {
-        PropertyNode::new(name, prop_type, value)
+fn topological_order(classes: &[ClassNode]) -> Result<Vec<usize>, Error> {
+    let mut index_by_name = HashMap::with_capacity(classes.len());
+    for (index, class) in classes.iter().enumerate() {
+        index_by_name.entry(class.name.to_lowercase()).or_insert(index);
+    }
+    let parent_of = |index: usize| classes[index].parent.as_ref()
+        .and_then(|parent| index_by_name.get(&parent.to_lowercase()).copied());
+
+    let mut children = vec![Vec::new(); classes.len()];
+    let mut in_degree = vec![0usize; classes.len()];
+    for (index, degree) in in_degree.iter_mut().enumerate() {
+        if let Some(parent) = parent_of(index) {
+            children[parent].push(index);
+            *degree += 1;
+        }
     }
 
-    fn create_array_property(name: &str, values: Vec<&str>, op: Option<ArrayOperation>) -> PropertyNode {
-        let mut prop = PropertyNode::new(
-            name,
-            PropertyType::Array,
-            format!("{{{}}}", values.join(","))
-        );
-        prop.array_values = values.into_iter().map(String::from).collect();
-        if let Some(operation) = op {
-            prop = prop.with_array_op(operation);
+    let mut queue: VecDeque<usize> = (0..classes.len()).filter(|&index| in_degree[index] == 0).collect();
+    let mut order = Vec::with_capacity(classes.len());
+    while let Some(index) = queue.pop_front() {
+        order.push(index);
+        for &child in &children[index] {
+            in_degree[child] -= 1;
+            if in_degree[child] == 0 {
+                queue.push_back(child);
+            }
         }
-        prop
     }
 
+    if order.len() < classes.len() {
+        // Every class left over is in a cycle or below one; following the
+        // parents from any of them ends up going round the cycle
+        let mut current = (0..classes.len()).find(|&index| in_degree[index] > 0).unwrap();
+        let mut path = Vec::new();
+        while !path.contains(&current) {
+            path.push(current);
+            current = parent_of(current).unwrap();
+        }
+        let start = path.iter().position(|&index| index == current).unwrap();
+        let mut chain: Vec<&str> = path[start..].iter().map(|&index| classes[index].name.as_str()).collect();
+        chain.push(&classes[current].name);
+        return Err(Error::InheritanceError(format!("Circular inheritance detected: {}", chain.join(" -> "))));
+    }
+    Ok(order)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::{AccessModifier, ClassNodeBuilder};
+
     #[test]
     fn test_deep_inheritance_chain() {
         let mut resolver = InheritanceResolver::new();
 
         // Create a deep inheritance chain: Great -> Grand -> Parent -> Child
-        let mut great = ClassNode::new("Great".to_string());
-        great.properties.insert("prop1".to_string(), create_test_property("prop1", "great", PropertyType::String));
+        let great = ClassNodeBuilder::new("Great")
+            .property_raw("prop1", PropertyType::String, "great")
+            .build().unwrap();
 
-        let mut grand = ClassNode::new("Grand".to_string())
-            .with_parent("Great");
-        grand.properties.insert("prop2".to_string(), create_test_property("prop2", "grand", PropertyType::String));
+        let grand = ClassNodeBuilder::new("Grand")
+            .parent("Great")
+            .property_raw("prop2", PropertyType::String, "grand")
+            .build().unwrap();
 
-        let mut parent = ClassNode::new("Parent".to_string())
-            .with_parent("Grand");
-        parent.properties.insert("prop3".to_string(), create_test_property("prop3", "parent", PropertyType::String));
+        let parent = ClassNodeBuilder::new("Parent")
+            .parent("Grand")
+            .property_raw("prop3", PropertyType::String, "parent")
+            .build().unwrap();
 
-        let mut child = ClassNode::new("Child".to_string())
-            .with_parent("Parent");
-        child.properties.insert("prop4".to_string(), create_test_property("prop4", "child", PropertyType::String));
+        let child = ClassNodeBuilder::new("Child")
+            .parent("Parent")
+            .property_raw("prop4", PropertyType::String, "child")
+            .build().unwrap();
 
         // Register classes in random order to test resolution
         resolver.add_class(parent);
@@ -198,23 +543,21 @@ mod tests {
         //     \    /
         //     Target
 
-        let mut base = ClassNode::new("Base".to_string());
-        base.properties.insert("common".to_string(), 
-            create_test_property("common", "base", PropertyType::String));
+        let base = ClassNodeBuilder::new("Base")
+            .property_raw("common", PropertyType::String, "base")
+            .build().unwrap();
 
-        let mut left = ClassNode::new("Left".to_string())
-            .with_parent("Base");
-        left.properties.insert("left_prop".to_string(),
-            create_test_property("left_prop", "left", PropertyType::String));
-        left.properties.insert("common".to_string(),
-            create_test_property("common", "left", PropertyType::String));
+        let left = ClassNodeBuilder::new("Left")
+            .parent("Base")
+            .property_raw("left_prop", PropertyType::String, "left")
+            .property_raw("common", PropertyType::String, "left")
+            .build().unwrap();
 
-        let mut right = ClassNode::new("Right".to_string())
-            .with_parent("Base");
-        right.properties.insert("right_prop".to_string(),
-            create_test_property("right_prop", "right", PropertyType::String));
-        right.properties.insert("common".to_string(),
-            create_test_property("common", "right", PropertyType::String));
+        let right = ClassNodeBuilder::new("Right")
+            .parent("Base")
+            .property_raw("right_prop", PropertyType::String, "right")
+            .property_raw("common", PropertyType::String, "right")
+            .build().unwrap();
 
         let target = ClassNode::new("Target".to_string())
             .with_parent("Left");  // Target inherits from Left
@@ -238,18 +581,17 @@ mod tests {
         let mut resolver = InheritanceResolver::new();
 
         // Create classes with nested structures
-        let mut base = ClassNode::new("Base".to_string());
-        let mut nested1 = ClassNode::new("Nested1".to_string());
-        nested1.properties.insert("nested_prop".to_string(),
-            create_test_property("nested_prop", "base_nested", PropertyType::String));
-        base.nested_classes.push(nested1);
-
-        let mut child = ClassNode::new("Child".to_string())
-            .with_parent("Base");
-        let mut nested2 = ClassNode::new("Nested1".to_string());  // Same name as parent's nested
-        nested2.properties.insert("child_prop".to_string(),
-            create_test_property("child_prop", "child_nested", PropertyType::String));
-        child.nested_classes.push(nested2);
+        let base = ClassNodeBuilder::new("Base")
+            .nested_class(ClassNodeBuilder::new("Nested1")
+                .property_raw("nested_prop", PropertyType::String, "base_nested"))
+            .build().unwrap();
+
+        let child = ClassNodeBuilder::new("Child")
+            .parent("Base")
+            // Same name as parent's nested
+            .nested_class(ClassNodeBuilder::new("Nested1")
+                .property_raw("child_prop", PropertyType::String, "child_nested"))
+            .build().unwrap();
 
         resolver.add_class(base);
         resolver.add_class(child);
@@ -265,23 +607,85 @@ mod tests {
         assert_eq!(nested.properties["child_prop"].raw_value, "child_nested");
     }
 
+    #[test]
+    fn test_deleted_members() {
+        let mut resolver = InheritanceResolver::new();
+
+        let base = ClassNodeBuilder::new("Base")
+            .property_raw("kept", PropertyType::String, "base")
+            .property_raw("removed", PropertyType::String, "base")
+            .nested_class(ClassNodeBuilder::new("Turrets"))
+            .nested_class(ClassNodeBuilder::new("Sounds"))
+            .build().unwrap();
+
+        let child = ClassNodeBuilder::new("Child")
+            .parent("Base")
+            .property_node(PropertyNode::deleted("removed"))
+            .property_node(PropertyNode::deleted("Turrets"))
+            .build().unwrap();
+
+        let grandchild = ClassNode::new("GrandChild".to_string()).with_parent("Child");
+
+        resolver.add_class(base);
+        resolver.add_class(child);
+        resolver.add_class(grandchild);
+
+        for name in ["Child", "GrandChild"] {
+            let resolved = resolver.resolve_class(name).unwrap();
+            assert!(resolved.properties.contains_key("kept"));
+            assert!(!resolved.properties.contains_key("removed"));
+            assert!(!resolved.properties.contains_key("Turrets"));
+            let nested: Vec<&str> = resolved.nested_classes.iter().map(|c| c.name.as_str()).collect();
+            assert_eq!(nested, vec!["Sounds"]);
+        }
+    }
+
+    #[test]
+    fn test_case_insensitive_lookup() {
+        let parent = ClassNodeBuilder::new("Parent")
+            .property_raw("prop", PropertyType::String, "parent")
+            .nested_class(ClassNodeBuilder::new("ItemInfo").property_raw("mass", PropertyType::Number, "10"))
+            .build().unwrap();
+
+        let child = ClassNodeBuilder::new("child")
+            .parent("PARENT")
+            .nested_class(ClassNodeBuilder::new("itemInfo").parent("ItemInfo"))
+            .build().unwrap();
+
+        let mut resolver = InheritanceResolver::new();
+        resolver.add_class(parent.clone());
+        resolver.add_class(child.clone());
+
+        let resolved = resolver.resolve_class("Child").unwrap();
+        assert_eq!(resolved.name, "child");
+        assert_eq!(resolved.properties["prop"].raw_value, "parent");
+        assert_eq!(resolved.nested_classes.len(), 1);
+        assert_eq!(resolved.nested_classes[0].name, "itemInfo");
+        assert_eq!(resolved.nested_classes[0].properties["mass"].raw_value, "10");
+
+        let mut strict = InheritanceResolver::new().with_case_insensitive(false);
+        strict.add_class(parent);
+        strict.add_class(child);
+        assert!(strict.resolve_class("child").is_err());
+    }
+
     #[test]
     fn test_array_inheritance() {
         let mut resolver = InheritanceResolver::new();
 
         // Create parent with array properties
-        let mut parent = ClassNode::new("Parent".to_string());
-        parent.properties.insert("base_array".to_string(),
-            create_array_property("base_array", vec!["item1", "item2"], None));
+        let parent = ClassNodeBuilder::new("Parent")
+            .array_property("base_array", vec!["item1", "item2"])
+            .build().unwrap();
 
         // Create child with array operations
-        let mut child = ClassNode::new("Child".to_string())
-            .with_parent("Parent");
-        child.properties.insert("base_array".to_string(),
-            create_array_property("base_array", vec!["item3", "item4"], Some(ArrayOperation::Append)));
-        
-        child.properties.insert("new_array".to_string(),
-            create_array_property("new_array", vec!["new1", "new2"], Some(ArrayOperation::Replace)));
+        let child = ClassNodeBuilder::new("Child")
+            .parent("Parent")
+            .property_node(PropertyNode::new("base_array", PropertyType::Array, "{item3,item4}")
+                .with_array_values(vec!["item3".to_string(), "item4".to_string()])
+                .with_array_op(ArrayOperation::Append))
+            .array_property("new_array", ["new1", "new2"])
+            .build().unwrap();
 
         resolver.add_class(parent);
         resolver.add_class(child);
@@ -312,6 +716,16 @@ mod tests {
         let b = ClassNode::new("B".to_string()).with_parent("C");
         let c = ClassNode::new("C".to_string()).with_parent("A");
 
+        let mut strict = InheritanceResolver::new().with_strict_cycles(true);
+        for class in [&a, &b, &c] {
+            strict.add_class(class.clone());
+        }
+        match strict.resolve() {
+            Err(Error::InheritanceError(message)) => assert_eq!(message, "Circular inheritance detected: C -> A -> B -> C"),
+            other => panic!("expected a cycle error, got {:?}", other),
+        }
+        assert!(strict.take_warnings().is_empty());
+
         resolver.add_class(a);
         resolver.add_class(b);
         resolver.add_class(c);
@@ -319,6 +733,156 @@ mod tests {
         let result = resolver.resolve_class("A");
         // Should resolve despite circular reference due to processed tracking
         assert!(result.is_ok());
+        assert_eq!(resolver.take_warnings(), vec![Warning::CircularInheritanceResolved {
+            chain: vec!["A".to_string(), "B".to_string(), "C".to_string(), "A".to_string()],
+        }]);
+        assert!(resolver.take_warnings().is_empty());
+    }
+
+    #[test]
+    fn test_resolve_all_in_topological_order() {
+        let mut resolver = InheritanceResolver::new();
+        let root = ClassNodeBuilder::new("")
+            .nested_class(ClassNodeBuilder::new("C").parent("B"))
+            .nested_class(ClassNodeBuilder::new("B").parent("A"))
+            .nested_class(ClassNodeBuilder::new("A").property_raw("x", PropertyType::Number, "1"))
+            .build().unwrap();
+        resolver.add_class(root);
+
+        let resolved = resolver.resolve().unwrap();
+        let names: Vec<&str> = resolved.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["A", "B", "C"]);
+        assert!(resolved.iter().all(|c| c.properties["x"].raw_value == "1"));
+    }
+
+    /// `All -> AllVehicles -> LandVehicle -> Car_F` with 196 cars on top.
+    fn vehicle_hierarchy() -> Vec<ClassNode> {
+        let mut classes = Vec::new();
+        let mut parent: Option<&str> = None;
+        for name in ["All", "AllVehicles", "LandVehicle", "Car_F"] {
+            let mut class = ClassNodeBuilder::new(name);
+            if let Some(parent) = parent {
+                class = class.parent(parent);
+            }
+            for i in 0..20 {
+                class = class.property_raw(format!("{}_{}", name, i), PropertyType::Number, "1");
+            }
+            classes.push(class.build().unwrap());
+            parent = Some(name);
+        }
+        for i in 0..196 {
+            let car = ClassNodeBuilder::new(format!("Car_{}", i))
+                .parent("Car_F")
+                .property_raw("speed", PropertyType::Number, i.to_string());
+            classes.push(car.build().unwrap());
+        }
+        classes
+    }
+
+    /// Each class resolved by a fresh resolver, i.e. without reusing any
+    /// cached parents.
+    fn resolve_uncached(classes: &[ClassNode]) -> Vec<ClassNode> {
+        classes.iter()
+            .map(|class| {
+                let mut resolver = InheritanceResolver::new();
+                for class in classes {
+                    resolver.add_class(class.clone());
+                }
+                resolver.resolve_class(&class.name).unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_cached_resolution() {
+        let classes = vehicle_hierarchy();
+        let uncached_results = resolve_uncached(&classes);
+
+        let mut resolver = InheritanceResolver::new();
+        for class in &classes {
+            resolver.add_class(class.clone());
+        }
+        let cached_results: Vec<ClassNode> = classes.iter()
+            .map(|class| resolver.resolve_class(&class.name).unwrap())
+            .collect();
+
+        assert_eq!(cached_results, uncached_results);
+        assert_eq!(resolver.resolved_cache().len(), 200);
+        assert_eq!(cached_results[199].properties.len(), 81);
+
+        // A leaf drops out on its own and resolves to the same class again
+        resolver.invalidate("Car_0");
+        assert_eq!(resolver.resolved_cache().len(), 199);
+        assert!(!resolver.resolved_cache().contains_key("Car_0"));
+        assert_eq!(resolver.resolve_class("Car_0").unwrap(), uncached_results[4]);
+        assert_eq!(resolver.resolved_cache().len(), 200);
+    }
+
+    /// Compares resolving every class with and without the shared cache. Run
+    /// with `cargo test --release -- --ignored --nocapture bench_cached_resolution`.
+    #[test]
+    #[ignore]
+    fn bench_cached_resolution() {
+        let classes = vehicle_hierarchy();
+
+        let start = std::time::Instant::now();
+        resolve_uncached(&classes);
+        let uncached = start.elapsed();
+
+        let mut resolver = InheritanceResolver::new();
+        for class in &classes {
+            resolver.add_class(class.clone());
+        }
+        let start = std::time::Instant::now();
+        for class in &classes {
+            resolver.resolve_class(&class.name).unwrap();
+        }
+        println!("Resolved {} classes: cached {:?}, uncached {:?}", classes.len(), start.elapsed(), uncached);
+    }
+
+    #[test]
+    fn test_invalidate_dependents() {
+        let mut resolver = InheritanceResolver::new();
+        for class in vehicle_hierarchy() {
+            resolver.add_class(class);
+        }
+        resolver.add_class(ClassNode::new("Tank_F".to_string()).with_parent("AllVehicles"));
+        for name in ["Car_0", "Car_1", "Tank_F"] {
+            resolver.resolve_class(name).unwrap();
+        }
+        assert_eq!(resolver.resolved_cache().len(), 7);
+
+        resolver.invalidate("LandVehicle");
+        let mut cached: Vec<&str> = resolver.resolved_cache().values().map(|c| c.name.as_str()).collect();
+        cached.sort();
+        assert_eq!(cached, vec!["All", "AllVehicles", "Tank_F"]);
+
+        // Re-registering a class invalidates it and its descendants too
+        let all = ClassNodeBuilder::new("All").property_raw("armor", PropertyType::Number, "5").build().unwrap();
+        resolver.add_class(all);
+        assert!(resolver.resolved_cache().is_empty());
+        assert_eq!(resolver.resolve_class("Car_0").unwrap().properties["armor"].raw_value, "5");
+    }
+
+    #[test]
+    fn test_external_parent() {
+        let car = ClassNodeBuilder::new("Car_F").property_raw("armor", PropertyType::Number, "50").build().unwrap();
+        let mut external = ClassNode::new("Car_F".to_string());
+        external.is_external = true;
+        let child = ClassNode::new("MyCar".to_string()).with_parent("Car_F");
+
+        // Only the forward declaration is known: resolves with nothing inherited
+        let mut resolver = InheritanceResolver::new();
+        resolver.add_class(external.clone());
+        resolver.add_class(child.clone());
+        assert!(resolver.resolve_class("MyCar").unwrap().properties.is_empty());
+
+        // A later forward declaration does not replace the definition
+        let mut resolver = InheritanceResolver::new();
+        resolver.add_class(car);
+        resolver.add_class(external);
+        resolver.add_class(child);
+        assert_eq!(resolver.resolve_class("MyCar").unwrap().properties["armor"].raw_value, "50");
     }
 
     #[test]
@@ -348,19 +912,23 @@ mod tests {
         let mut resolver = InheritanceResolver::new();
 
         // Create a chain of array modifications
-        let mut base = ClassNode::new("Base".to_string());
-        base.properties.insert("items".to_string(),
-            create_array_property("items", vec!["a", "b", "c"], None));
-
-        let mut middle = ClassNode::new("Middle".to_string())
-            .with_parent("Base");
-        middle.properties.insert("items".to_string(),
-            create_array_property("items", vec!["d", "e"], Some(ArrayOperation::Append)));
-
-        let mut child = ClassNode::new("Child".to_string())
-            .with_parent("Middle");
-        child.properties.insert("items".to_string(),
-            create_array_property("items", vec!["b", "c"], Some(ArrayOperation::Remove)));
+        let base = ClassNodeBuilder::new("Base")
+            .array_property("items", vec!["a", "b", "c"])
+            .build().unwrap();
+
+        let middle = ClassNodeBuilder::new("Middle")
+            .parent("Base")
+            .property_node(PropertyNode::new("items", PropertyType::Array, "{d,e}")
+                .with_array_values(vec!["d".to_string(), "e".to_string()])
+                .with_array_op(ArrayOperation::Append))
+            .build().unwrap();
+
+        let child = ClassNodeBuilder::new("Child")
+            .parent("Middle")
+            .property_node(PropertyNode::new("items", PropertyType::Array, "{b,c}")
+                .with_array_values(vec!["b".to_string(), "c".to_string()])
+                .with_array_op(ArrayOperation::Remove))
+            .build().unwrap();
 
         resolver.add_class(base);
         resolver.add_class(middle);
@@ -372,4 +940,105 @@ mod tests {
         assert_eq!(resolved.properties["items"].array_values,
                   vec!["a", "d", "e"]);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_topological_sort() {
+        let classes = vec![
+            ClassNode::new("Child".to_string()).with_parent("parent"),
+            ClassNode::new("Orphan".to_string()).with_parent("Missing"),
+            ClassNode::new("Parent".to_string()).with_parent("Base"),
+            ClassNode::new("Base".to_string()),
+            ClassNode::new("Sibling".to_string()).with_parent("Base"),
+        ];
+
+        let names: Vec<String> = topological_sort(&classes).unwrap().iter().map(|c| c.name.clone()).collect();
+        assert_eq!(names, vec!["Orphan", "Base", "Parent", "Sibling", "Child"]);
+
+        let owned: Vec<String> = topological_sort_owned(classes).unwrap().into_iter().map(|c| c.name).collect();
+        assert_eq!(owned, names);
+
+        let cyclic = vec![
+            ClassNode::new("Leaf".to_string()).with_parent("A"),
+            ClassNode::new("A".to_string()).with_parent("C"),
+            ClassNode::new("B".to_string()).with_parent("A"),
+            ClassNode::new("C".to_string()).with_parent("B"),
+        ];
+        match topological_sort(&cyclic) {
+            Err(Error::InheritanceError(message)) => assert!(message.ends_with("A -> C -> B -> A"), "{}", message),
+            other => panic!("expected a cycle error, got {:?}", other.map(|v| v.len())),
+        }
+    }
+
+    #[test]
+    fn test_ancestors_and_descendants() {
+        let mut resolver = InheritanceResolver::new();
+        resolver.add_class(ClassNode::new("ItemCore".to_string()));
+        resolver.add_class(ClassNode::new("Vest_Base".to_string()).with_parent("itemcore"));
+        resolver.add_class(ClassNode::new("Vest".to_string()).with_parent("Vest_Base"));
+        resolver.add_class(ClassNode::new("Helmet".to_string()).with_parent("ItemCore"));
+        resolver.add_class(ClassNode::new("Orphan".to_string()).with_parent("Missing"));
+        resolver.add_class(ClassNode::new("A".to_string()).with_parent("B"));
+        resolver.add_class(ClassNode::new("B".to_string()).with_parent("A"));
+
+        assert_eq!(resolver.ancestors("vest").unwrap(), vec!["Vest_Base", "ItemCore"]);
+        assert_eq!(resolver.ancestors("Orphan").unwrap(), vec!["Missing"]);
+        match resolver.ancestors("A") {
+            Err(Error::InheritanceError(message)) => assert!(message.ends_with("A -> B -> A"), "{}", message),
+            other => panic!("expected a cycle error, got {:?}", other),
+        }
+        assert!(resolver.ancestors("Unknown").is_err());
+
+        assert!(resolver.derives_from("Vest", "ITEMCORE"));
+        assert!(!resolver.derives_from("Helmet", "Vest_Base"));
+        assert!(!resolver.derives_from("A", "ItemCore"));
+
+        assert_eq!(resolver.descendants("ItemCore"), vec!["Helmet", "Vest_Base", "Vest"]);
+        assert_eq!(resolver.descendants("A"), vec!["B"]);
+        assert!(resolver.descendants("Vest").is_empty());
+    }
+
+    #[test]
+    fn test_missing_parent_policy() {
+        let classes = || {
+            [
+                ClassNodeBuilder::new("Vest_Base").parent("Vanilla_Base").property_raw("armor", PropertyType::Number, "10"),
+                ClassNodeBuilder::new("Vest").parent("Vest_Base").property_raw("mass", PropertyType::Number, "5"),
+                ClassNodeBuilder::new("Helmet").parent("vanilla_base"),
+            ].map(|class| class.build().unwrap())
+        };
+        let resolver = |policy| {
+            let mut resolver = InheritanceResolver::new().with_missing_parent(policy);
+            classes().into_iter().for_each(|class| resolver.add_class(class));
+            resolver
+        };
+
+        match resolver(MissingParent::Error).resolve_class("Vest") {
+            Err(Error::InheritanceError(message)) => assert_eq!(message, "Class Vanilla_Base not found"),
+            other => panic!("expected a missing class error, got {:?}", other),
+        }
+
+        let mut ignoring = resolver(MissingParent::Ignore);
+        assert_eq!(ignoring.resolve_class("Vest").unwrap().properties["armor"].raw_value, "10");
+        assert!(ignoring.unresolved_parents().is_empty());
+        assert!(ignoring.take_warnings().is_empty());
+
+        let mut collecting = resolver(MissingParent::Collect);
+        let resolved = collecting.resolve().unwrap();
+        assert_eq!(resolved.len(), 3);
+        let vest = resolved.iter().find(|class| class.name == "Vest").unwrap();
+        assert_eq!(vest.properties.keys().collect::<Vec<_>>(), vec!["mass", "armor"]);
+        // Helmet is resolved first and names the parent in lowercase
+        assert_eq!(collecting.unresolved_parents(), ["vanilla_base"]);
+        assert_eq!(collecting.take_warnings().len(), 2);
+    }
+
+    #[test]
+    fn test_missing_parent_registered_later() {
+        let mut resolver = InheritanceResolver::new().with_missing_parent(MissingParent::Ignore);
+        resolver.add_class(ClassNode::new("C".to_string()).with_parent("P"));
+        assert!(resolver.resolve_class("C").unwrap().properties.is_empty());
+
+        resolver.add_class(ClassNodeBuilder::new("P").property_raw("armor", PropertyType::Number, "5").build().unwrap());
+        assert_eq!(resolver.resolve_class("C").unwrap().properties["armor"].raw_value, "5");
+    }
+}
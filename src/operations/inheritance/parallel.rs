@@ -0,0 +1,217 @@
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use rayon::ThreadPool;
+use crate::ast::ClassNode;
+use crate::error::{Error, Warning};
+use super::InheritanceResolver;
+
+/// Resolves the classes of an [`InheritanceResolver`] on the rayon thread
+/// pool.
+///
+/// Classes are grouped by their depth in the hierarchy. Every class of a
+/// depth only needs parents of the depth above, which are already in the
+/// shared cache, so each depth is resolved concurrently.
+pub struct ParallelInheritanceResolver {
+    resolver: InheritanceResolver,
+    resolved: Arc<RwLock<HashMap<String, ClassNode>>>,
+    /// Dedicated pool from [`with_threads`](Self::with_threads); the global
+    /// rayon pool otherwise.
+    pool: Option<ThreadPool>,
+}
+
+impl ParallelInheritanceResolver {
+    /// Resolve the classes registered with `resolver`, using its options.
+    pub fn new(resolver: InheritanceResolver) -> Self {
+        Self {
+            resolver,
+            resolved: Arc::default(),
+            pool: None,
+        }
+    }
+
+    /// Number of threads to resolve with; by default those of the global
+    /// rayon pool. Falls back to the global pool if the threads cannot be
+    /// started.
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.pool = rayon::ThreadPoolBuilder::new().num_threads(threads).build().ok();
+        self
+    }
+
+    /// Resolved classes, keyed like [`InheritanceResolver::resolved_cache`].
+    pub fn resolved_cache(&self) -> Arc<RwLock<HashMap<String, ClassNode>>> {
+        Arc::clone(&self.resolved)
+    }
+
+    /// Warnings collected while resolving, such as missing parents.
+    pub fn take_warnings(&mut self) -> Vec<Warning> {
+        self.resolver.take_warnings()
+    }
+
+    pub fn unresolved_parents(&self) -> &[String] {
+        self.resolver.unresolved_parents()
+    }
+
+    /// [`InheritanceResolver::resolve`] on several threads, with the same
+    /// result. The resolved classes stay in the shared cache, so a second
+    /// call only clones them.
+    ///
+    /// A hierarchy with an inheritance cycle is resolved sequentially, as
+    /// the cycle has no top to start from.
+    pub fn resolve_all_parallel(&mut self) -> Result<Vec<ClassNode>, Error> {
+        let order = self.resolver.resolution_order();
+        if order.iter().any(|key| self.resolver.walk_ancestors(key.clone()).1.is_some()) {
+            let classes = self.resolver.resolve()?;
+            let mut resolved = self.resolved.write().unwrap_or_else(|e| e.into_inner());
+            for class in &classes {
+                resolved.insert(self.resolver.key(&class.name), class.clone());
+            }
+            return Ok(classes);
+        }
+
+        for level in self.levels(&order)? {
+            let merged = self.resolve_level(&level)?;
+            self.resolved.write().unwrap_or_else(|e| e.into_inner()).extend(merged);
+        }
+
+        let resolved = self.resolved.read().unwrap_or_else(|e| e.into_inner());
+        Ok(order.iter().filter_map(|key| resolved.get(key).cloned()).collect())
+    }
+
+    /// Keys of `order` not resolved yet, grouped by depth, roots first. The
+    /// missing-parent policy is applied here, in `order`, so warnings come
+    /// out as they do from a sequential resolve.
+    fn levels(&mut self, order: &[String]) -> Result<Vec<Vec<String>>, Error> {
+        let mut depths: HashMap<&String, usize> = HashMap::with_capacity(order.len());
+        let mut levels: Vec<Vec<String>> = Vec::new();
+        for key in order {
+            let class = &self.resolver.class_map[key];
+            let parent_depth = match &class.parent {
+                Some(parent) => match depths.get(&self.resolver.key(parent)) {
+                    Some(&depth) => Some(depth),
+                    None => {
+                        let (name, parent) = (class.name.clone(), parent.clone());
+                        self.resolver.missing_parent(&name, &parent)?;
+                        None
+                    }
+                },
+                None => None,
+            };
+            let depth = parent_depth.map_or(0, |depth| depth + 1);
+            depths.insert(key, depth);
+
+            if self.resolved.read().unwrap_or_else(|e| e.into_inner()).contains_key(key) {
+                continue;
+            }
+            if levels.len() <= depth {
+                levels.resize_with(depth + 1, Vec::new);
+            }
+            levels[depth].push(key.clone());
+        }
+        Ok(levels)
+    }
+
+    /// Merge every class of `level` with its already resolved parent, one
+    /// rayon task per slice of the level.
+    fn resolve_level(&self, level: &[String]) -> Result<Vec<(String, ClassNode)>, Error> {
+        let threads = self.pool.as_ref().map_or_else(rayon::current_num_threads, ThreadPool::current_num_threads);
+        let chunk_size = level.len().div_ceil(threads).max(1);
+        let resolver = &self.resolver;
+        let resolved = self.resolved.read().unwrap_or_else(|e| e.into_inner());
+        let resolved = &*resolved;
+
+        let mut chunks: Vec<Result<Vec<(String, ClassNode)>, Error>> = Vec::new();
+        chunks.resize_with(level.len().div_ceil(chunk_size), || Ok(Vec::new()));
+        let mut run = || rayon::scope(|scope| {
+            for (keys, merged) in level.chunks(chunk_size).zip(chunks.iter_mut()) {
+                scope.spawn(move |_| {
+                    *merged = keys.iter().map(|key| {
+                        let mut class = resolver.class_map[key].clone();
+                        let parent = class.parent.as_ref().and_then(|parent| resolved.get(&resolver.key(parent)));
+                        if let Some(parent) = parent {
+                            resolver.merge_with_parent(&mut class, parent)?;
+                        }
+                        Ok((key.clone(), class))
+                    }).collect();
+                });
+            }
+        });
+        match &self.pool {
+            Some(pool) => pool.install(run),
+            None => run(),
+        }
+
+        let mut merged = Vec::with_capacity(level.len());
+        for chunk in chunks {
+            merged.extend(chunk?);
+        }
+        Ok(merged)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::{PropertyNode, PropertyType};
+    use crate::operations::arrays::ArrayOperation;
+    use crate::operations::MissingParent;
+
+    /// 50 classes in five trees, each class adding a property and an array
+    /// entry to what it inherits.
+    fn hierarchy() -> Vec<ClassNode> {
+        (0..50).map(|i| {
+            let mut class = ClassNode::new(format!("Class{}", i));
+            if i >= 5 {
+                class = class.with_parent(format!("Class{}", (i - 5) / 2));
+            }
+            class.properties.insert(format!("p{}", i), PropertyNode::new(format!("p{}", i), PropertyType::Number, i.to_string()));
+            let mut items = PropertyNode::new("items", PropertyType::Array, format!("{{{}}}", i));
+            items.array_values = vec![i.to_string()];
+            items = items.with_array_op(if i < 5 { ArrayOperation::Replace } else { ArrayOperation::Append });
+            class.properties.insert("items".to_string(), items);
+            class
+        }).collect()
+    }
+
+    fn resolver(classes: Vec<ClassNode>) -> InheritanceResolver {
+        let mut resolver = InheritanceResolver::new().with_missing_parent(MissingParent::Collect);
+        for class in classes {
+            resolver.add_class(class);
+        }
+        resolver
+    }
+
+    #[test]
+    fn test_parallel_matches_sequential() {
+        let sequential = resolver(hierarchy()).resolve().unwrap();
+        let mut parallel = ParallelInheritanceResolver::new(resolver(hierarchy())).with_threads(4);
+        let resolved = parallel.resolve_all_parallel().unwrap();
+        assert_eq!(resolved, sequential);
+        assert_eq!(parallel.resolved_cache().read().unwrap().len(), 50);
+
+        // Class49 -> Class22 -> Class8 -> Class1
+        let deepest = resolved.iter().find(|class| class.name == "Class49").unwrap();
+        assert_eq!(deepest.properties["items"].array_values, vec!["1", "8", "22", "49"]);
+
+        // Resolving again serves the cache
+        assert_eq!(parallel.resolve_all_parallel().unwrap(), sequential);
+    }
+
+    #[test]
+    fn test_parallel_missing_parent_and_cycle() {
+        let mut classes = hierarchy();
+        classes.push(ClassNode::new("Orphan".to_string()).with_parent("Unknown"));
+        let mut sequential = resolver(classes.clone());
+        let expected = sequential.resolve().unwrap();
+        let mut parallel = ParallelInheritanceResolver::new(resolver(classes));
+        assert_eq!(parallel.resolve_all_parallel().unwrap(), expected);
+        assert_eq!(parallel.take_warnings(), sequential.take_warnings());
+        assert_eq!(parallel.unresolved_parents(), ["Unknown"]);
+
+        let cycle = vec![
+            ClassNode::new("A".to_string()).with_parent("B"),
+            ClassNode::new("B".to_string()).with_parent("A"),
+        ];
+        let expected = resolver(cycle.clone()).resolve().unwrap();
+        assert_eq!(ParallelInheritanceResolver::new(resolver(cycle)).resolve_all_parallel().unwrap(), expected);
+    }
+}
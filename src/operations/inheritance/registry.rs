@@ -0,0 +1,442 @@
+use std::path::{Component, Path, PathBuf};
+use indexmap::IndexMap;
+use crate::ast::{ClassNode, MergeStrategy};
+use crate::ast::inheritance_visitor::InheritanceVisitor;
+use crate::error::{Error, Warning};
+use crate::ClassScanner;
+
+/// Classes from many files resolved as one set, so a class may inherit from
+/// one defined in another file, as mods do with the base game's classes.
+///
+/// Each class keeps the file it was parsed from in `ClassNode::file_path`.
+/// Definitions of the same class are merged in registration order, the way
+/// [`ClassIndex`](crate::scanner::ClassIndex) merges them, so a mod adding
+/// to `CfgWeapons` keeps the base game's weapons; a forward declaration
+/// (`class Rifle_Base_F;`) contributes nothing. Every definition is kept
+/// per class path with its [`SourceAddon`] though, so
+/// [`resolve_with_priority`](Self::resolve_with_priority) can pick the one
+/// the game would load for each class instead.
+pub struct ClassRegistry {
+    scanner: ClassScanner,
+    /// Definitions by lowercased dot-separated path, e.g.
+    /// `cfgweapons.rhs_weap_m4`, in registration order. Each holds only the
+    /// class's own members; its nested classes are under their own paths.
+    definitions: IndexMap<String, Vec<(ClassNode, SourceAddon)>>,
+    /// Resolves the merged definitions; dropped when more are registered.
+    visitor: Option<InheritanceVisitor>,
+    /// Resolves the definitions picked for the addon priority it is stored
+    /// with; dropped when more are registered.
+    priority_visitor: Option<(Vec<String>, InheritanceVisitor)>,
+    /// Used by `resolve` when set, see `with_addon_priority`.
+    addon_priority: Vec<String>,
+    files: Vec<PathBuf>,
+    warnings: Vec<Warning>,
+}
+
+/// The addon a class was loaded from: the innermost `@mod` folder in its
+/// file's path, such as `@rhsusaf`, or else the folder the file is in.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SourceAddon(pub String);
+
+impl SourceAddon {
+    pub fn from_path(path: &Path) -> Self {
+        let folders: Vec<String> = path.parent()
+            .into_iter()
+            .flat_map(Path::components)
+            .filter_map(|component| match component {
+                Component::Normal(name) => Some(name.to_string_lossy().into_owned()),
+                _ => None,
+            })
+            .collect();
+        let name = folders.iter().rev().find(|folder| folder.starts_with('@'))
+            .or(folders.last())
+            .cloned()
+            .unwrap_or_default();
+        Self(name)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.0
+    }
+
+    /// Whether this is the addon called `name`, ignoring case and a leading `@`.
+    pub fn matches(&self, name: &str) -> bool {
+        self.0.trim_start_matches('@').eq_ignore_ascii_case(name.trim_start_matches('@'))
+    }
+}
+
+impl ClassRegistry {
+    pub fn new() -> Self {
+        Self::with_scanner(ClassScanner::new())
+    }
+
+    /// Parse files the way `scanner` does, with its include paths, missing
+    /// parent policy and array options.
+    pub fn with_scanner(scanner: ClassScanner) -> Self {
+        Self {
+            scanner,
+            definitions: IndexMap::new(),
+            visitor: None,
+            priority_visitor: None,
+            addon_priority: Vec::new(),
+            files: Vec::new(),
+            warnings: Vec::new(),
+        }
+    }
+
+    fn new_visitor(&self) -> InheritanceVisitor {
+        InheritanceVisitor::new()
+            .with_missing_parent(self.scanner.missing_parent)
+            .with_array_options(self.scanner.array_options)
+    }
+
+    /// Make [`resolve`](Self::resolve) pick definitions by `addon_priority`,
+    /// as [`resolve_with_priority`](Self::resolve_with_priority) does.
+    pub fn with_addon_priority(mut self, addon_priority: &[&str]) -> Self {
+        self.addon_priority = addon_priority.iter().map(|addon| addon.to_string()).collect();
+        self
+    }
+
+    /// Preprocess and parse `path` and register its top-level classes.
+    pub fn register_file(&mut self, path: &Path) -> Result<(), Error> {
+        let mut classes = self.scanner.parse_file(path)?;
+        self.warnings.extend(self.scanner.last_warnings());
+        // The unnamed root; its top-level classes follow it
+        classes.remove(0);
+        self.register_classes(path, classes);
+        Ok(())
+    }
+
+    /// Register top-level classes already parsed from `path`, such as those
+    /// of a [`ScanResult`](crate::ScanResult). The addon they belong to is
+    /// taken from `path`.
+    pub fn register_classes(&mut self, path: &Path, classes: Vec<ClassNode>) {
+        let addon = SourceAddon::from_path(path);
+        for class in classes {
+            self.record(class, "", &addon);
+        }
+        self.visitor = None;
+        self.priority_visitor = None;
+        self.files.push(path.to_path_buf());
+    }
+
+    /// Record `class` under its path in `scope`, before its nested classes
+    /// so a parent's path always precedes its children's.
+    fn record(&mut self, mut class: ClassNode, scope: &str, addon: &SourceAddon) {
+        let key = if scope.is_empty() {
+            class.name.to_lowercase()
+        } else {
+            format!("{}.{}", scope, class.name.to_lowercase())
+        };
+        let nested = std::mem::take(&mut class.nested_classes);
+        self.definitions.entry(key.clone()).or_default().push((class, addon.clone()));
+        for nested in nested {
+            self.record(nested, &key, addon);
+        }
+    }
+
+    /// The top-level classes of every registered file, with definitions of
+    /// the same class merged in registration order.
+    pub fn classes(&self) -> Result<Vec<ClassNode>, Error> {
+        self.assemble(|definitions| {
+            let mut merged = definitions[0].0.clone();
+            for (class, _) in &definitions[1..] {
+                merged.merge_from(class.clone(), MergeStrategy::LastWins)?;
+            }
+            Ok(merged)
+        })
+    }
+
+    /// Build the top-level classes from one class per path, made by `pick`
+    /// from that path's definitions.
+    fn assemble<F>(&self, pick: F) -> Result<Vec<ClassNode>, Error>
+    where
+        F: Fn(&[(ClassNode, SourceAddon)]) -> Result<ClassNode, Error>,
+    {
+        let mut classes = self.definitions.values()
+            .map(|definitions| pick(definitions).map(Some))
+            .collect::<Result<Vec<_>, Error>>()?;
+        // Children come after their parent, so walking backwards moves each
+        // class into its parent once its own children are in place. They
+        // arrive last first and are put back in order before the move.
+        for index in (0..classes.len()).rev() {
+            classes[index].as_mut().unwrap().nested_classes.reverse();
+            let (path, _) = self.definitions.get_index(index).unwrap();
+            if let Some((parent, _)) = path.rsplit_once('.') {
+                let class = classes[index].take().unwrap();
+                let parent = self.definitions.get_index_of(parent).unwrap();
+                classes[parent].as_mut().unwrap().nested_classes.push(class);
+            }
+        }
+        Ok(classes.into_iter().flatten().collect())
+    }
+
+    /// Resolve the class at `class_name` against the classes of every
+    /// registered file, with array operations applied. `class_name` is a
+    /// dot-separated path such as `CfgWeapons.arifle_MX_F`, or a simple
+    /// name, which prefers a top-level class as
+    /// [`InheritanceVisitor::process`] does.
+    pub fn resolve(&mut self, class_name: &str) -> Result<ClassNode, Error> {
+        if !self.addon_priority.is_empty() {
+            let addon_priority = std::mem::take(&mut self.addon_priority);
+            let priority: Vec<&str> = addon_priority.iter().map(String::as_str).collect();
+            let resolved = self.resolve_with_priority(class_name, &priority);
+            self.addon_priority = addon_priority;
+            return resolved;
+        }
+        let mut visitor = match self.visitor.take() {
+            Some(visitor) => visitor,
+            None => {
+                let mut visitor = self.new_visitor();
+                for class in self.classes()? {
+                    visitor.register_class(class);
+                }
+                visitor
+            }
+        };
+        let resolved = self.resolve_in(&mut visitor, class_name);
+        self.visitor = Some(visitor);
+        resolved
+    }
+
+    fn resolve_in(&mut self, visitor: &mut InheritanceVisitor, class_name: &str) -> Result<ClassNode, Error> {
+        let resolved = visitor.process(&class_name.replace('.', "/"));
+        self.warnings.extend(visitor.take_warnings());
+        let mut class = resolved?;
+        self.scanner.process_arrays(&mut class)?;
+        Ok(class)
+    }
+
+    /// Like [`resolve`](Self::resolve), but where several addons define a
+    /// class (`class_name`, any of its ancestors or any class nested in
+    /// them), the definition of the addon listed first in `addon_priority`
+    /// is used, as the game loads the patch that overrides a class after the
+    /// addon it overrides.
+    ///
+    /// The choice is made per class path: a patch overriding
+    /// `CfgWeapons.rhs_weap_m4` replaces only that class, not every class in
+    /// `CfgWeapons`. Addons not listed rank below those listed; among them,
+    /// and between definitions from the same addon, the last registered
+    /// wins. A forward declaration is only used when there is no definition.
+    ///
+    /// The classes picked are kept for the next call with the same
+    /// `addon_priority` until more files are registered.
+    pub fn resolve_with_priority(&mut self, class_name: &str, addon_priority: &[&str]) -> Result<ClassNode, Error> {
+        let mut visitor = match self.priority_visitor.take() {
+            Some((priority, visitor)) if priority.iter().map(String::as_str).eq(addon_priority.iter().copied()) => visitor,
+            _ => {
+                let rank = |addon: &SourceAddon| addon_priority.iter().position(|name| addon.matches(name)).unwrap_or(usize::MAX);
+                let classes = self.assemble(|definitions| {
+                    let (_, (class, _)) = definitions.iter()
+                        .enumerate()
+                        .max_by_key(|(order, (class, addon))| (!class.is_external, std::cmp::Reverse(rank(addon)), *order))
+                        .unwrap();
+                    Ok(class.clone())
+                })?;
+                let mut visitor = self.new_visitor();
+                for class in classes {
+                    visitor.register_class(class);
+                }
+                visitor
+            }
+        };
+        let resolved = self.resolve_in(&mut visitor, class_name);
+        let priority = addon_priority.iter().map(|addon| addon.to_string()).collect();
+        self.priority_visitor = Some((priority, visitor));
+        resolved
+    }
+
+    /// Every definition of the class at the dot-separated `path` with the
+    /// addon it came from, in registration order. Each holds the class's own
+    /// members only, without its nested classes.
+    pub fn definitions(&self, path: &str) -> &[(ClassNode, SourceAddon)] {
+        self.definitions.get(&path.to_lowercase()).map_or(&[], Vec::as_slice)
+    }
+
+    /// Registered files, in registration order.
+    pub fn files(&self) -> &[PathBuf] {
+        &self.files
+    }
+
+    /// Take the warnings from parsing and resolving so far.
+    pub fn take_warnings(&mut self) -> Vec<Warning> {
+        std::mem::take(&mut self.warnings)
+    }
+}
+
+impl Default for ClassRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_resolve_across_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::create_dir_all(root.join("weapons_f")).unwrap();
+        fs::create_dir_all(root.join("mymod")).unwrap();
+        let base = root.join("weapons_f/config.cpp");
+        let weapon = root.join("mymod/config.cpp");
+        fs::write(&base, r#"
+            class Rifle_Base_F {
+                scope = 0;
+                mass = 100;
+                magazines[] = {"30Rnd"};
+            };
+        "#).unwrap();
+        fs::write(&weapon, r#"
+            class Rifle_Base_F;
+            class MyWeapon: Rifle_Base_F {
+                scope = 2;
+                magazines[] += {"20Rnd"};
+            };
+        "#).unwrap();
+
+        // Registered before the definition, the forward declaration must not win
+        let mut registry = ClassScanner::new().build_registry(&[weapon.clone(), base.clone()]).unwrap();
+        assert_eq!(registry.files(), &[weapon.clone(), base.clone()]);
+
+        let resolved = registry.resolve("MyWeapon").unwrap();
+        assert_eq!(resolved.properties["scope"].raw_value, "2");
+        assert_eq!(resolved.properties["mass"].raw_value, "100");
+        assert_eq!(resolved.properties["magazines"].array_values, vec!["30Rnd", "20Rnd"]);
+        assert_eq!(resolved.file_path.as_deref(), Some(weapon.as_path()));
+        assert_eq!(registry.resolve("Rifle_Base_F").unwrap().file_path.as_deref(), Some(base.as_path()));
+
+        assert!(matches!(registry.resolve("Missing"), Err(Error::InheritanceError(_))));
+        assert!(registry.register_file(&root.join("missing.cpp")).is_err());
+    }
+
+    #[test]
+    fn test_resolve_nested_across_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::create_dir_all(root.join("weapons_f")).unwrap();
+        fs::create_dir_all(root.join("mymod")).unwrap();
+        let base = root.join("weapons_f/config.cpp");
+        let weapon = root.join("mymod/config.cpp");
+        fs::write(&base, r#"
+            class CfgWeapons {
+                class Rifle_Base_F { scope = 0; mass = 100; };
+                class Other { scope = 1; };
+            };
+        "#).unwrap();
+        fs::write(&weapon, r#"
+            class CfgWeapons {
+                class Rifle_Base_F;
+                class MyWeapon: Rifle_Base_F { scope = 2; };
+            };
+        "#).unwrap();
+
+        let mut registry = ClassScanner::new().build_registry(&[base.clone(), weapon.clone()]).unwrap();
+        let weapons = registry.resolve("CfgWeapons").unwrap();
+        let names: Vec<&str> = weapons.nested_classes.iter().map(|class| class.name.as_str()).collect();
+        assert_eq!(names, ["Rifle_Base_F", "Other", "MyWeapon"]);
+        assert_eq!(weapons.nested_classes[0].properties["mass"].raw_value, "100");
+
+        for name in ["MyWeapon", "cfgweapons.myweapon"] {
+            let resolved = registry.resolve(name).unwrap();
+            assert_eq!(resolved.properties["scope"].raw_value, "2");
+            assert_eq!(resolved.properties["mass"].raw_value, "100");
+        }
+        assert_eq!(registry.resolve("CfgWeapons.Other").unwrap().properties["scope"].raw_value, "1");
+        assert!(matches!(registry.resolve("CfgWeapons.Missing"), Err(Error::InheritanceError(_))));
+    }
+
+    #[test]
+    fn test_source_addon() {
+        let addon = |path: &str| SourceAddon::from_path(Path::new(path)).0;
+        assert_eq!(addon("@rhsusaf/addons/rhsusf_c_weapons/config.cpp"), "@rhsusaf");
+        assert_eq!(addon("/mods/@outer/@inner/addons/main/config.cpp"), "@inner");
+        assert_eq!(addon("weapons_f/config.cpp"), "weapons_f");
+        assert_eq!(addon("config.cpp"), "");
+        assert!(SourceAddon("@RHSUSAF".to_string()).matches("rhsusaf"));
+    }
+
+    #[test]
+    fn test_resolve_with_priority() {
+        let parse = |input: &str| {
+            let mut classes = ClassScanner::new().parse_string(input).unwrap();
+            classes.remove(0);
+            classes
+        };
+        let mut registry = ClassRegistry::new();
+        registry.register_classes(Path::new("@patch/addons/compat/config.cpp"), parse(r#"
+            class Rifle_Base_F;
+            class rhs_weap_m4: Rifle_Base_F { mass = 80; };
+        "#));
+        registry.register_classes(Path::new("@rhsusaf/addons/weapons/config.cpp"), parse(r#"
+            class Rifle_Base_F { scope = 0; };
+            class rhs_weap_m4: Rifle_Base_F { mass = 100; magazines[] = {"30Rnd"}; };
+            class rhs_weap_m4a1: rhs_weap_m4 { scope = 2; };
+        "#));
+        assert_eq!(registry.definitions("RHS_WEAP_M4").len(), 2);
+
+        // Without a priority the last registered wins
+        assert_eq!(registry.resolve("rhs_weap_m4a1").unwrap().properties["mass"].raw_value, "100");
+
+        let resolved = registry.resolve_with_priority("rhs_weap_m4a1", &["@patch", "@rhsusaf"]).unwrap();
+        assert_eq!(resolved.properties["mass"].raw_value, "80");
+        assert!(!resolved.properties.contains_key("magazines"));
+        // The patch only declares the base class, so the definition is used
+        assert_eq!(resolved.properties["scope"].raw_value, "2");
+        assert_eq!(registry.resolve_with_priority("Rifle_Base_F", &["@patch"]).unwrap().properties["scope"].raw_value, "0");
+
+        // Unlisted addons rank below listed ones
+        let resolved = registry.resolve_with_priority("rhs_weap_m4", &["rhsusaf"]).unwrap();
+        assert_eq!(resolved.properties["mass"].raw_value, "100");
+        let mut registry = registry.with_addon_priority(&["@patch"]);
+        assert_eq!(registry.resolve("rhs_weap_m4").unwrap().properties["mass"].raw_value, "80");
+
+        // Registering more classes replaces the ones picked for a priority
+        registry.register_classes(Path::new("@patch/addons/compat2/config.cpp"), parse(r#"
+            class rhs_weap_m4: Rifle_Base_F { mass = 70; };
+        "#));
+        assert_eq!(registry.resolve("rhs_weap_m4").unwrap().properties["mass"].raw_value, "70");
+    }
+
+    #[test]
+    fn test_resolve_with_priority_per_class_path() {
+        let parse = |input: &str| {
+            let mut classes = ClassScanner::new().parse_string(input).unwrap();
+            classes.remove(0);
+            classes
+        };
+        let mut registry = ClassRegistry::new();
+        registry.register_classes(Path::new("@rhsusaf/addons/weapons/config.cpp"), parse(r#"
+            class CfgWeapons {
+                class Rifle_Base_F { scope = 0; };
+                class rhs_weap_m4: Rifle_Base_F { mass = 100; };
+                class rhs_weap_m16: Rifle_Base_F { mass = 110; };
+            };
+        "#));
+        registry.register_classes(Path::new("@patch/addons/compat/config.cpp"), parse(r#"
+            class CfgWeapons {
+                class Rifle_Base_F;
+                class rhs_weap_m4: Rifle_Base_F { mass = 80; };
+            };
+        "#));
+        assert_eq!(registry.definitions("CfgWeapons.RHS_WEAP_M4").len(), 2);
+        assert!(registry.definitions("CfgWeapons")[0].0.nested_classes.is_empty());
+
+        for priority in [["@patch", "@rhsusaf"], ["@rhsusaf", "@patch"]] {
+            let weapons = registry.resolve_with_priority("CfgWeapons", &priority).unwrap();
+            let names: Vec<&str> = weapons.nested_classes.iter().map(|class| class.name.as_str()).collect();
+            assert_eq!(names, ["Rifle_Base_F", "rhs_weap_m4", "rhs_weap_m16"]);
+        }
+        let resolved = registry.resolve_with_priority("CfgWeapons.rhs_weap_m4", &["@patch"]).unwrap();
+        assert_eq!(resolved.properties["mass"].raw_value, "80");
+        assert_eq!(resolved.properties["scope"].raw_value, "0");
+        let resolved = registry.resolve_with_priority("rhs_weap_m16", &["@patch"]).unwrap();
+        assert_eq!(resolved.properties["mass"].raw_value, "110");
+        let resolved = registry.resolve_with_priority("rhs_weap_m4", &["@rhsusaf"]).unwrap();
+        assert_eq!(resolved.properties["mass"].raw_value, "100");
+    }
+}
@@ -1,5 +1,12 @@
 pub mod arrays;
+pub mod diff;
+pub mod eval;
+pub mod hierarchy;
 pub mod inheritance;
+pub mod writer;
 
-pub use arrays::ArrayOperation;
-pub use inheritance::InheritanceResolver;
\ No newline at end of file
+pub use arrays::{ArrayElement, ArrayOperation, ArrayOptions};
+pub use diff::{diff, diff_with_options, generate_patch, ClassDiff, DiffOptions, Patch, PatchOp};
+pub use eval::{evaluate, format_number};
+pub use inheritance::{ClassRegistry, SourceAddon, InheritanceResolver, MissingParent, ParallelInheritanceResolver, topological_sort, topological_sort_owned};
+pub use writer::{ConfigWriter, Writer, FormatOptions, BraceStyle};
\ No newline at end of file
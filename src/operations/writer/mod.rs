@@ -0,0 +1,525 @@
+use std::fmt;
+use crate::ast::{ClassNode, EnumNode, PropertyNode, PropertyType};
+use crate::models::property_value::PropertyValue;
+use crate::operations::arrays::{parse_number, ArrayElement, ArrayOperation};
+use crate::ClassConfig;
+
+/// Serializes a `ClassNode` tree back into config (`.cpp`/`.hpp`) text.
+///
+/// A class with an empty name is treated as the parse root: its enums,
+/// properties and nested classes are written at the top level.
+pub struct ConfigWriter {
+    indent_width: usize,
+    emit_array_operations: bool,
+    sort_properties: bool,
+}
+
+impl Default for ConfigWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ConfigWriter {
+    pub fn new() -> Self {
+        Self {
+            indent_width: 4,
+            emit_array_operations: true,
+            sort_properties: false,
+        }
+    }
+
+    pub fn with_indent(mut self, width: usize) -> Self {
+        self.indent_width = width;
+        self
+    }
+
+    /// Emit `+=`/`-=` for arrays that carry an operation. When disabled every
+    /// array is written as a plain assignment of its (resolved) values.
+    pub fn with_array_operations(mut self, emit: bool) -> Self {
+        self.emit_array_operations = emit;
+        self
+    }
+
+    /// Write properties ordered by name, ignoring case, instead of in
+    /// declaration order.
+    pub fn with_sorted_properties(mut self, sort: bool) -> Self {
+        self.sort_properties = sort;
+        self
+    }
+
+    pub fn write_class(&self, class: &ClassNode) -> String {
+        let mut output = String::new();
+        if class.name.is_empty() {
+            self.write_body(class, 0, &mut output);
+        } else {
+            self.write_class_at(class, 0, &mut output);
+        }
+        output
+    }
+
+    fn write_class_at(&self, class: &ClassNode, level: usize, output: &mut String) {
+        let indent = self.indent(level);
+        self.write_doc_comment(class.doc_comment.as_deref(), level, output);
+        output.push_str(&indent);
+        output.push_str("class ");
+        output.push_str(&class.name);
+        if let Some(parent) = &class.parent {
+            output.push_str(": ");
+            output.push_str(parent);
+        }
+
+        if class.is_external {
+            output.push_str(";\n");
+            return;
+        }
+
+        if class.properties.is_empty() && class.nested_classes.is_empty() && class.enums.is_empty()
+            && class.deleted_classes.is_empty() {
+            output.push_str(" {};\n");
+            return;
+        }
+
+        output.push_str(" {\n");
+        self.write_body(class, level + 1, output);
+        output.push_str(&indent);
+        output.push_str("};\n");
+    }
+
+    fn write_body(&self, class: &ClassNode, level: usize, output: &mut String) {
+        for node in &class.enums {
+            self.write_enum(node, level, output);
+        }
+
+        let mut properties: Vec<&PropertyNode> = class.properties.values().collect();
+        if self.sort_properties {
+            properties.sort_by_cached_key(|property| property.name.to_lowercase());
+        }
+        for property in properties {
+            self.write_property_at(property, level, output);
+        }
+
+        for name in &class.deleted_classes {
+            output.push_str(&self.indent(level));
+            output.push_str(&format!("delete {};\n", name));
+        }
+
+        for nested in &class.nested_classes {
+            self.write_class_at(nested, level, output);
+        }
+    }
+
+    fn write_enum(&self, node: &EnumNode, level: usize, output: &mut String) {
+        let indent = self.indent(level);
+        output.push_str(&indent);
+        output.push_str("enum ");
+        if !node.name.is_empty() {
+            output.push_str(&node.name);
+            output.push(' ');
+        }
+        output.push_str("{\n");
+
+        let entry_indent = self.indent(level + 1);
+        let entries: Vec<String> = node.values.iter()
+            .map(|(name, value)| format!("{}{} = {}", entry_indent, name, value))
+            .collect();
+        output.push_str(&entries.join(",\n"));
+        output.push('\n');
+        output.push_str(&indent);
+        output.push_str("};\n");
+    }
+
+    /// A single `name = value;` line, as written inside a class.
+    pub fn write_property(&self, property: &PropertyNode) -> String {
+        let mut output = String::new();
+        self.write_property_at(property, 0, &mut output);
+        output
+    }
+
+    fn write_property_at(&self, property: &PropertyNode, level: usize, output: &mut String) {
+        self.write_doc_comment(property.doc_comment.as_deref(), level, output);
+        output.push_str(&self.indent(level));
+        if property.is_deleted() {
+            output.push_str(&format!("delete {};\n", property.name));
+            return;
+        }
+        output.push_str(&property.name);
+
+        match property.value_type {
+            PropertyType::Array => {
+                let operator = if self.emit_array_operations { array_operator(property.operation) } else { "=" };
+                let elements: Vec<String> = property.array_values.iter()
+                    .zip(property.elements())
+                    .map(|(text, element)| write_array_element(text, &element))
+                    .collect();
+                output.push_str(&format!("[] {} {{{}}};\n", operator, elements.join(", ")));
+            }
+            PropertyType::String => {
+                output.push_str(&format!(" = {};\n", quote(&property.raw_value)));
+            }
+            _ => {
+                output.push_str(&format!(" = {};\n", property.raw_value));
+            }
+        }
+    }
+
+    fn write_doc_comment(&self, comment: Option<&str>, level: usize, output: &mut String) {
+        for line in comment.into_iter().flat_map(str::lines) {
+            output.push_str(&self.indent(level));
+            output.push_str("// ");
+            output.push_str(line);
+            output.push('\n');
+        }
+    }
+
+    fn indent(&self, level: usize) -> String {
+        " ".repeat(level * self.indent_width)
+    }
+}
+
+/// Where the opening brace of a class body goes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BraceStyle {
+    /// `class Name: Parent {`
+    #[default]
+    SameLine,
+    /// The brace on a line of its own, at the class's indent.
+    NextLine,
+}
+
+/// Layout of the text written by [`Writer`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormatOptions {
+    pub indent_size: usize,
+    /// Close class bodies with `};` rather than `}`.
+    pub trailing_semicolon: bool,
+    pub brace_style: BraceStyle,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        Self {
+            indent_size: 4,
+            trailing_semicolon: true,
+            brace_style: BraceStyle::SameLine,
+        }
+    }
+}
+
+/// Streams a value as config (`.cpp`/`.hpp`) text.
+pub trait Writer {
+    fn write_config(&self, out: &mut dyn fmt::Write, options: &FormatOptions) -> fmt::Result;
+}
+
+/// A class with an empty name is written as the parse root, with its
+/// properties and nested classes at the top level. `Object` values have no
+/// config syntax and are skipped.
+impl Writer for ClassConfig {
+    fn write_config(&self, out: &mut dyn fmt::Write, options: &FormatOptions) -> fmt::Result {
+        if self.name.is_empty() {
+            write_config_body(self, 0, out, options)
+        } else {
+            write_config_class(self, 0, out, options)
+        }
+    }
+}
+
+fn write_config_class(class: &ClassConfig, level: usize, out: &mut dyn fmt::Write, options: &FormatOptions) -> fmt::Result {
+    let indent = " ".repeat(level * options.indent_size);
+    write_config_comment(class.doc_comment.as_deref(), &indent, out)?;
+    write!(out, "{}class {}", indent, class.name)?;
+    if let Some(parent) = &class.extends {
+        write!(out, ": {}", parent)?;
+    }
+    if class.is_external {
+        return writeln!(out, ";");
+    }
+
+    let close = if options.trailing_semicolon { "};" } else { "}" };
+    if class.properties.is_empty() && class.nested_classes.is_empty() {
+        return writeln!(out, " {{{}", close);
+    }
+    match options.brace_style {
+        BraceStyle::SameLine => writeln!(out, " {{")?,
+        BraceStyle::NextLine => write!(out, "\n{}{{\n", indent)?,
+    }
+    write_config_body(class, level + 1, out, options)?;
+    writeln!(out, "{}{}", indent, close)
+}
+
+fn write_config_body(class: &ClassConfig, level: usize, out: &mut dyn fmt::Write, options: &FormatOptions) -> fmt::Result {
+    let indent = " ".repeat(level * options.indent_size);
+    for (name, value) in &class.properties {
+        if !matches!(value, PropertyValue::Object(_)) {
+            write_config_comment(class.property_doc_comments.get(name).map(String::as_str), &indent, out)?;
+        }
+        match value {
+            PropertyValue::String(s) => writeln!(out, "{}{} = {};", indent, name, quote(s))?,
+            PropertyValue::Number(n) => writeln!(out, "{}{} = {};", indent, name, n)?,
+            PropertyValue::Bool(b) => writeln!(out, "{}{} = {};", indent, name, b)?,
+            PropertyValue::Array(values) => {
+                let operator = array_operator(class.property_array_operations.get(name).copied());
+                writeln!(out, "{}{}[] {} {};", indent, name, operator, write_value_array(values))?;
+            }
+            PropertyValue::Object(_) => {}
+        }
+    }
+    for nested in &class.nested_classes {
+        write_config_class(nested, level, out, options)?;
+    }
+    Ok(())
+}
+
+fn write_config_comment(comment: Option<&str>, indent: &str, out: &mut dyn fmt::Write) -> fmt::Result {
+    for line in comment.into_iter().flat_map(str::lines) {
+        writeln!(out, "{}// {}", indent, line)?;
+    }
+    Ok(())
+}
+
+fn array_operator(operation: Option<ArrayOperation>) -> &'static str {
+    match operation {
+        Some(ArrayOperation::Append) => "+=",
+        Some(ArrayOperation::Remove) => "-=",
+        Some(ArrayOperation::Intersect) => "&=",
+        Some(ArrayOperation::Replace) | None => "=",
+    }
+}
+
+/// `{...}` of typed array elements; objects have no config form and are
+/// left out.
+fn write_value_array(values: &[PropertyValue]) -> String {
+    let elements: Vec<String> = values.iter()
+        .filter_map(|value| match value {
+            PropertyValue::String(s) => Some(quote(s)),
+            PropertyValue::Number(n) => Some(n.to_string()),
+            PropertyValue::Bool(b) => Some(b.to_string()),
+            PropertyValue::Array(values) => Some(write_value_array(values)),
+            PropertyValue::Object(_) => None,
+        })
+        .collect();
+    format!("{{{}}}", elements.join(", "))
+}
+
+/// Array text loses its quoting when parsed, so the typed element decides
+/// what is quoted; `text` only keeps a number's notation, such as hex.
+fn write_array_element(text: &str, element: &ArrayElement) -> String {
+    match element {
+        ArrayElement::String(value) => quote(value),
+        ArrayElement::Number(number) if parse_number(text.trim()) == Some(*number) => text.trim().to_string(),
+        ArrayElement::Number(number) => number.to_string(),
+        ArrayElement::Boolean(value) => value.to_string(),
+        ArrayElement::Array(elements) => {
+            let inner = text.trim().strip_prefix('{').and_then(|t| t.strip_suffix('}')).unwrap_or("");
+            // Quotes are gone from the text, so it only lines up with the
+            // elements when no string held a comma or brace
+            let texts = split_top_level(inner);
+            let written: Vec<String> = elements.iter()
+                .enumerate()
+                .map(|(i, element)| write_array_element(texts.get(i).copied().unwrap_or(""), element))
+                .collect();
+            format!("{{{}}}", written.join(", "))
+        }
+    }
+}
+
+/// Split a nested array's raw text at commas that are not inside braces.
+fn split_top_level(inner: &str) -> Vec<&str> {
+    if inner.trim().is_empty() {
+        return Vec::new();
+    }
+
+    let mut parts = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0;
+    for (i, c) in inner.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => depth = depth.saturating_sub(1),
+            ',' if depth == 0 => {
+                parts.push(&inner[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&inner[start..]);
+    parts
+}
+
+/// Config strings escape quotes by doubling them; backslashes are literal.
+fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('"', "\"\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Tokenizer;
+    use crate::parser::Parser;
+
+    fn parse(input: &str) -> ClassNode {
+        let tokens = Tokenizer::new(input).tokenize().unwrap();
+        Parser::new(tokens).parse().unwrap()
+    }
+
+    /// Drop everything that legitimately differs between two parses of
+    /// equivalent text.
+    fn normalize(mut class: ClassNode) -> ClassNode {
+        class.location = None;
+        class.span = None;
+        class.name_span = None;
+        class.block_span = None;
+        class.raw_block.clear();
+        for property in class.properties.values_mut() {
+            property.location = None;
+            property.span = None;
+        }
+        for node in &mut class.enums {
+            node.location = None;
+        }
+        class.nested_classes = class.nested_classes.into_iter().map(normalize).collect();
+        class
+    }
+
+    fn assert_round_trip(input: &str, writer: &ConfigWriter) {
+        let original = parse(input);
+        let written = writer.write_class(&original);
+        let reparsed = parse(&written);
+        assert_eq!(normalize(reparsed), normalize(original), "written config:\n{}", written);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let input = r#"
+            enum { destructengine = 2, destructwreck = 7 };
+            class Base {
+                displayName = "Base Vehicle";
+                model = "\A3\Soft_F\MRAP_01\MRAP_01_unarmed_F";
+                armor = 0x1F;
+                speed = 1.5e2;
+                enabled = true;
+                side = WEST;
+                weapons[] = {"M4A1", "Glock 17", 5};
+                loadout[] = {{"M4A1", "ACOG"}, {}, {1, {2, 3}}};
+                class Turrets {};
+                class Sounds;
+            };
+            class Child: Base {
+                weapons[] += {"M16"};
+                magazines[] -= {"30Rnd"};
+                requiredAddons[] &= {"A3_Data_F"};
+                delete model;
+                class Turrets: Turrets {
+                    class MainTurret {
+                        gunnerName = "Gunner";
+                    };
+                };
+            };
+        "#;
+
+        assert_round_trip(input, &ConfigWriter::new());
+        assert_round_trip(input, &ConfigWriter::new().with_indent(2));
+    }
+
+    #[test]
+    fn test_round_trip_quoted_array_strings() {
+        let input = r#"class A { arr[] = {"007", "{x}", "1e5", 5, 0x10, {"1,2", 3, "true"}}; };"#;
+        let original = parse(input);
+        let written = ConfigWriter::new().write_class(&original);
+        assert!(written.contains(r#"arr[] = {"007", "{x}", "1e5", 5, 0x10, {"1,2", 3, "true"}};"#), "{}", written);
+        assert_eq!(normalize(parse(&written)), normalize(original));
+    }
+
+    #[test]
+    fn test_write_class_output() {
+        let root = parse(r#"class Child: Base { name = "\A3\My Mod\icon.paa"; items[] += {1, "two"}; class Inner {}; };"#);
+        let output = ConfigWriter::new().with_indent(2).write_class(&root.nested_classes[0]);
+
+        assert_eq!(output, concat!(
+            "class Child: Base {\n",
+            "  name = \"\\A3\\My Mod\\icon.paa\";\n",
+            "  items[] += {1, \"two\"};\n",
+            "  class Inner {};\n",
+            "};\n",
+        ));
+    }
+
+    #[test]
+    fn test_quote_doubles_embedded_quotes() {
+        assert_eq!(quote(r#"say "hi""#), r#""say ""hi""""#);
+        let write = |text: &str| write_array_element(text, &ArrayElement::parse(text));
+        assert_eq!(write("{a,{1,b}}"), r#"{"a", {1, "b"}}"#);
+        assert_eq!(write("-0x1F"), "-0x1F");
+    }
+
+    #[test]
+    fn test_resolved_arrays_without_operations() {
+        let root = parse(r#"class Child { weapons[] += {"M16"}; magazines[] -= {"30Rnd"}; };"#);
+        let output = ConfigWriter::new()
+            .with_array_operations(false)
+            .write_class(&root.nested_classes[0]);
+
+        assert!(output.contains("weapons[] = {\"M16\"};"));
+        assert!(output.contains("magazines[] = {\"30Rnd\"};"));
+        assert!(!output.contains("+="));
+        assert!(!output.contains("-="));
+    }
+
+    /// Clear what a re-parse cannot reproduce: source positions and text.
+    fn normalize_config(mut config: ClassConfig) -> ClassConfig {
+        config.raw_block.clear();
+        config.file_path = None;
+        config.location = None;
+        config.property_locations.clear();
+        config.nested_classes = config.nested_classes.into_iter().map(normalize_config).collect();
+        config
+    }
+
+    #[test]
+    fn test_class_config_round_trip() {
+        let original = ClassConfig::from(parse(r#"
+            class Base {
+                displayName = "Base Vehicle";
+                model = "\A3\Soft_F\MRAP_01\MRAP_01_unarmed_F";
+                armor = 150;
+                speed = -1.5;
+                enabled = true;
+                weapons[] = {"M4A1", "Glock 17", 5};
+                loadout[] = {{"M4A1", "ACOG"}, {}};
+                class Sounds;
+            };
+            class Child: Base {
+                label = "42";
+                class Turrets: Turrets {
+                    class MainTurret {};
+                };
+            };
+        "#));
+
+        for options in [
+            FormatOptions::default(),
+            FormatOptions { indent_size: 2, trailing_semicolon: false, brace_style: BraceStyle::NextLine },
+        ] {
+            let written = original.to_config_string_with(&options);
+            let reparsed = ClassConfig::from(parse(&written));
+            assert_eq!(normalize_config(reparsed), normalize_config(original.clone()), "written config:\n{}", written);
+        }
+    }
+
+    #[test]
+    fn test_class_config_format_options() {
+        let mut config = ClassConfig::from(parse("class Child: Base { class Inner {}; };").nested_classes.remove(0));
+        config.properties.insert("name".to_string(), PropertyValue::String(r#"say "hi""#.to_string()));
+        let options = FormatOptions { indent_size: 2, trailing_semicolon: false, brace_style: BraceStyle::NextLine };
+
+        assert_eq!(config.to_config_string_with(&options), concat!(
+            "class Child: Base\n",
+            "{\n",
+            "  name = \"say \"\"hi\"\"\";\n",
+            "  class Inner {}\n",
+            "}\n",
+        ));
+        assert!(config.to_config_string().starts_with("class Child: Base {\n    name = "));
+    }
+}
@@ -1,48 +1,238 @@
 use crate::lexer::tokens::TokenType;
 use crate::lexer::{Token, Tokenizer};
-use crate::ast::{ClassNode, PropertyNode, PropertyType, AccessModifier};
-use crate::error::{Error, SourceLocation};
-use crate::operations::arrays::ArrayOperation;
+use crate::ast::{ClassNode, EnumNode, PropertyNode, PropertyType, AccessModifier, Span};
+use crate::error::{Error, SourceLocation, Warning};
+use crate::operations::arrays::{ArrayElement, ArrayOperation, ArrayOptions, ArrayProcessor};
+use crate::operations::eval::{evaluate, format_number};
+use std::collections::{BTreeMap, VecDeque};
+use std::convert::Infallible;
 use std::path::{Path, PathBuf};
 use std::fs;
+use serde::{Deserialize, Serialize};
 use tracing::{debug, trace, instrument};
 
-pub struct Parser {
-    tokens: Vec<Token>,
+/// A property value's type, its text, and for arrays each element's text
+/// and typed form.
+type ParsedValue = (PropertyType, String, Vec<(String, ArrayElement)>);
+
+pub mod rap;
+
+/// What the parser does with a property defined twice in one class. An
+/// array operation after an array of the same name, as in `x[] = {...};
+/// x[] += {...};`, is not a duplicate: it is applied to the earlier value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum DuplicatePolicy {
+    /// Keep the later definition, as the game does.
+    LastWins,
+    FirstWins,
+    /// Fail with a parse error at the later definition.
+    Error,
+    /// Keep the later definition and record a [`Warning::DuplicateProperty`].
+    #[default]
+    Warn,
+}
+
+/// Limits and normalization applied while parsing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ParserConfig {
+    /// Deepest class nesting accepted, top-level classes being depth 1.
+    /// Unlimited by default.
+    pub max_depth: Option<usize>,
+    /// Accept `class X {};`. When false it is a parse error; `class X;`
+    /// declarations are still accepted.
+    pub allow_empty_blocks: bool,
+    /// When false, class names, parent names and property names are
+    /// lowercased, so members differing only by case are the same member,
+    /// as in the game.
+    pub case_sensitive: bool,
+}
+
+impl Default for ParserConfig {
+    fn default() -> Self {
+        Self {
+            max_depth: None,
+            allow_empty_blocks: true,
+            case_sensitive: true,
+        }
+    }
+}
+
+/// Tokens read past the current one, enough for the parser's own lookahead.
+const LOOKAHEAD: usize = 3;
+
+/// Recursive-descent parser over a token stream.
+///
+/// Tokens are pulled from the source as parsing reaches them. Only a small
+/// window is kept: the previous token, the current one, [`LOOKAHEAD`] after
+/// it, and those after any pending [`checkpoint`](Self::checkpoint).
+pub struct Parser<'a> {
+    source: Box<dyn Iterator<Item = Result<Token, Error>> + Send + 'a>,
+    /// Set once `source` is exhausted or returned an error.
+    source_done: bool,
+    /// First error from the source, reported by `parse` over any parse
+    /// error it caused.
+    lex_error: Option<Error>,
+    /// Code tokens from position `window_start` on.
+    tokens: VecDeque<Token>,
+    window_start: usize,
     current: usize,
+    /// Positions of pending checkpoints; tokens from the oldest are kept.
+    checkpoints: Vec<usize>,
+    /// `{` minus `}` before the current position, for error recovery.
+    brace_depth: isize,
     file_path: Option<PathBuf>,
+    error_recovery: bool,
+    errors: Vec<Error>,
+    warnings: Vec<Warning>,
+    /// Comment tokens, keyed by the position of the token that follows them.
+    comments: BTreeMap<usize, Vec<Token>>,
+    attach_comments: bool,
+    duplicate_policy: DuplicatePolicy,
+    config: ParserConfig,
+    /// Returned by `peek` past the last token.
+    end_of_input: Token,
 }
 
-impl Parser {
-    pub fn new(tokens: Vec<Token>) -> Self {
-        Self {
-            tokens,
+impl<'a> Parser<'a> {
+    pub fn new<I>(tokens: I) -> Self
+    where
+        I: IntoIterator<Item = Token>,
+        I::IntoIter: Send + 'a,
+    {
+        Self::from_source(Box::new(tokens.into_iter().map(Ok)))
+    }
+
+    pub fn with_config<I>(tokens: I, config: ParserConfig) -> Self
+    where
+        I: IntoIterator<Item = Token>,
+        I::IntoIter: Send + 'a,
+    {
+        Self::new(tokens).with_parser_config(config)
+    }
+
+    /// Parser reading straight from `tokenizer` as it goes. A lexer error is
+    /// returned by [`parse`](Self::parse) in place of any parse error.
+    pub fn from_tokenizer(tokenizer: Tokenizer<'a>) -> Self {
+        Self::from_source(Box::new(tokenizer))
+    }
+
+    fn from_source(source: Box<dyn Iterator<Item = Result<Token, Error>> + Send + 'a>) -> Self {
+        let mut parser = Self {
+            source,
+            source_done: false,
+            lex_error: None,
+            tokens: VecDeque::with_capacity(LOOKAHEAD + 2),
+            window_start: 0,
             current: 0,
+            checkpoints: Vec::new(),
+            brace_depth: 0,
             file_path: None,
-        }
+            error_recovery: false,
+            errors: Vec::new(),
+            warnings: Vec::new(),
+            comments: BTreeMap::new(),
+            attach_comments: false,
+            duplicate_policy: DuplicatePolicy::default(),
+            config: ParserConfig::default(),
+            end_of_input: Token::new(TokenType::EOL, 0, 0),
+        };
+        parser.fill(LOOKAHEAD);
+        parser
+    }
+
+    /// Attach comments directly preceding a class or property to its
+    /// `doc_comment`. Requires a tokenizer built `with_comments(true)`.
+    pub fn with_comments(mut self, enabled: bool) -> Self {
+        self.attach_comments = enabled;
+        self
+    }
+
+    /// Attribute parsed classes and source locations to `path`.
+    pub fn with_file_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.file_path = Some(path.into());
+        self
+    }
+
+    pub fn with_parser_config(mut self, config: ParserConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// How a property defined twice in one class is treated.
+    pub fn with_duplicate_policy(mut self, policy: DuplicatePolicy) -> Self {
+        self.duplicate_policy = policy;
+        self
+    }
+
+    /// Enable or disable error recovery.
+    ///
+    /// When enabled, a parse error inside a class does not abort parsing.
+    /// The error is recorded, tokens are skipped up to the next `;` or the
+    /// closing `}` of the enclosing block, and parsing continues. Collected
+    /// errors are available from [`take_errors`](Self::take_errors).
+    pub fn with_error_recovery(mut self, enabled: bool) -> Self {
+        self.error_recovery = enabled;
+        self
+    }
+
+    /// Take the non-fatal errors collected while parsing in recovery mode.
+    pub fn take_errors(&mut self) -> Vec<Error> {
+        std::mem::take(&mut self.errors)
+    }
+
+    /// Take the warnings collected while parsing, such as array values
+    /// assigned to non-array properties.
+    pub fn take_warnings(&mut self) -> Vec<Warning> {
+        std::mem::take(&mut self.warnings)
+    }
+
+    /// Parse with error recovery enabled for this call, returning the partial
+    /// tree together with every error encountered.
+    pub fn parse_with_recovery(&mut self) -> (ClassNode, Vec<Error>) {
+        let previous = std::mem::replace(&mut self.error_recovery, true);
+        let result = self.parse();
+        self.error_recovery = previous;
+
+        let mut errors = self.take_errors();
+        let root = result.unwrap_or_else(|e| {
+            errors.push(e);
+            ClassNode::new(String::new())
+        });
+        (root, errors)
     }
 
     pub fn new_from_file<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
         let content = fs::read_to_string(&path)?;
-        let tokens = crate::lexer::Tokenizer::with_file_path(&content, path.as_ref()).tokenize()?;
-        Ok(Self {
-            tokens,
-            current: 0,
-            file_path: Some(path.as_ref().to_path_buf()),
-        })
+        let tokens = Tokenizer::with_file_path(&content, path.as_ref()).tokenize()?;
+        Ok(Self::new(tokens).with_file_path(path.as_ref()))
     }
 
     pub fn parse(&mut self) -> Result<ClassNode, Error> {
+        let result = self.parse_root();
+        match self.lex_error.take() {
+            Some(error) => Err(error),
+            None => result,
+        }
+    }
+
+    fn parse_root(&mut self) -> Result<ClassNode, Error> {
         // Create a root node to hold all top-level classes
         let mut root = ClassNode::new("".to_string());
         
         while !self.is_at_end() {
             if self.check(TokenType::Class) {
-                let class = self.parse_class()?;
-                root.nested_classes.push(class);
+                let start = self.brace_depth;
+                match self.parse_class(1) {
+                    Ok(class) => root.nested_classes.push(class),
+                    Err(e) => self.recover(e, start)?,
+                }
             } else if self.check(TokenType::Enum) {
-                // Skip over enum blocks since we don't process them
-                self.skip_enum_block()?;
+                let start = self.brace_depth;
+                match self.parse_enum_block() {
+                    Ok(node) => root.enums.push(node),
+                    Err(e) => self.recover(e, start)?,
+                }
             } else {
                 self.advance(); // Skip non-class tokens
             }
@@ -56,15 +246,22 @@ impl Parser {
         Ok(root)
     }
 
+    /// Parse a class nested `depth` levels deep, top-level classes being 1.
     #[instrument(skip(self))]
-    fn parse_class(&mut self) -> Result<ClassNode, Error> {
+    fn parse_class(&mut self, depth: usize) -> Result<ClassNode, Error> {
+        let doc_comment = self.doc_comment();
+        let class_token = self.peek().clone();
         self.expect_token(TokenType::Class)?;
         
         let name_token = self.consume()?;
         match &name_token.token_type {
             TokenType::Identifier(name) => {
+                let name = self.fold_case(name);
                 let mut class = ClassNode::new(name.clone());
                 class.file_path = self.file_path.clone();
+                class.location = Some(self.location_of(&class_token));
+                class.name_span = Some(Span::between(&name_token, &name_token));
+                class.doc_comment = doc_comment;
 
                 // Check for inheritance
                 if self.check(TokenType::Colon) {
@@ -74,56 +271,252 @@ impl Parser {
                     }
                     if let TokenType::Identifier(parent) = self.consume()?.token_type {
                         debug!(class_name = %name, parent = %parent, "Class inheritance");
-                        class = class.with_parent(parent);
+                        class = class.with_parent(self.fold_case(&parent));
                     }
                 }
 
                 // Handle empty class declarations (class Name;)
                 if self.check(TokenType::Semicolon) {
                     self.advance();
-                    debug!(class_name = %name, "Empty class declaration");
+                    debug!(class_name = %name, "External class declaration");
+                    class.is_external = true;
+                    class.span = Some(Span::between(&class_token, self.previous()));
                     return Ok(class);
                 }
 
+                if let Some(max) = self.config.max_depth.filter(|&max| depth > max) {
+                    return Err(Error::ParseError {
+                        message: format!("Class '{}' is nested deeper than the maximum depth of {}", name, max),
+                        location: self.location_of(&class_token),
+                    });
+                }
+                let open_brace = self.peek().clone();
                 self.expect_token(TokenType::LeftBrace)?;
+                if !self.config.allow_empty_blocks && self.check(TokenType::RightBrace) {
+                    return Err(Error::ParseError {
+                        message: format!("Class '{}' has an empty body", name),
+                        location: self.location_of(&open_brace),
+                    });
+                }
 
                 while !self.check(TokenType::RightBrace) && !self.is_at_end() {
+                    let start = self.brace_depth;
                     if self.check(TokenType::Class) {
-                        let nested_class = self.parse_class()?;
-                        debug!(class_name = %name, nested = %nested_class.name, "Adding nested class");
-                        class.nested_classes.push(nested_class);
+                        match self.parse_class(depth + 1) {
+                            Ok(nested_class) => {
+                                debug!(class_name = %name, nested = %nested_class.name, "Adding nested class");
+                                class.nested_classes.push(nested_class);
+                            }
+                            Err(e) => self.recover(e, start)?,
+                        }
                     } else if self.check(TokenType::Semicolon) {
-                        // Skip stray semicolons
                         debug!(class_name = %name, "Skipping stray semicolon");
+                        let location = self.location_of(self.peek());
+                        self.warnings.push(Warning::StraySemicolon { location });
                         self.advance();
                     } else {
-                        let property = self.parse_property()?;
-                        debug!(class_name = %name, property = %property.name, "Adding property");
-                        class.properties.insert(property.name.clone(), property);
+                        match self.parse_property() {
+                            Ok(mut property) => {
+                                property.name = self.fold_case(&property.name);
+                                debug!(class_name = %name, property = %property.name, "Adding property");
+                                // The property is already consumed, so there is nothing to skip
+                                match self.add_property(&mut class, property) {
+                                    Err(e) if self.error_recovery => self.errors.push(e),
+                                    result => result?,
+                                }
+                            }
+                            Err(e) => self.recover(e, start)?,
+                        }
                     }
                 }
 
-                self.expect_token(TokenType::RightBrace)?;
+                if let Err(e) = self.expect_token(TokenType::RightBrace) {
+                    // In recovery mode an unclosed class at end of input keeps
+                    // whatever was parsed so far
+                    if !self.error_recovery {
+                        return Err(e);
+                    }
+                    self.errors.push(e);
+                }
+                class.block_span = Some(Span::between(&open_brace, self.previous()));
+                class.span = Some(Span::between(&class_token, self.previous()));
+                // The `;` closing the definition is not a stray one
+                if self.check(TokenType::Semicolon) {
+                    self.advance();
+                }
                 Ok(class)
             },
             _ => Err(Error::ParseError {
                 message: "Expected class name".to_string(),
-                location: SourceLocation::new(self.file_path.clone(), name_token.line, name_token.column)
+                location: self.location_of(&name_token)
             }),
         }
     }
 
+    /// Current position in the token stream, to go back to with
+    /// [`restore`](Self::restore). Tokens from here on are kept until the
+    /// checkpoint is restored or [`release`](Self::release)d.
+    pub fn checkpoint(&mut self) -> usize {
+        self.checkpoints.push(self.current);
+        self.current
+    }
+
+    /// Rewind (or skip) to `checkpoint`, releasing it and every checkpoint
+    /// after it. Rewinding is only possible to a position that is still
+    /// held by a checkpoint.
+    ///
+    /// # Panics
+    ///
+    /// If `checkpoint` lies before the tokens still kept.
+    pub fn restore(&mut self, checkpoint: usize) {
+        assert!(checkpoint >= self.window_start, "restoring a released checkpoint");
+        while self.current > checkpoint {
+            self.current -= 1;
+            self.brace_depth -= brace_delta(&self.tokens[self.current - self.window_start]);
+        }
+        while self.current < checkpoint && !self.is_at_end() {
+            self.advance();
+        }
+        self.release(checkpoint);
+    }
+
+    /// Stop keeping tokens for `checkpoint` and every checkpoint after it,
+    /// without moving.
+    pub fn release(&mut self, checkpoint: usize) {
+        self.checkpoints.retain(|&position| position < checkpoint);
+        self.trim();
+    }
+
+    /// The token `n` positions ahead of the current one, without advancing.
+    pub fn peek_n(&mut self, n: usize) -> Option<&Token> {
+        self.fill(n.saturating_add(1));
+        self.token_at(n)
+    }
+
+    /// `peek_n` within the window, which always reaches [`LOOKAHEAD`]
+    /// tokens past the current one.
+    fn lookahead(&self, n: usize) -> Option<&Token> {
+        debug_assert!(n < LOOKAHEAD);
+        self.token_at(n)
+    }
+
+    fn token_at(&self, n: usize) -> Option<&Token> {
+        self.current.checked_add(n)
+            .and_then(|position| self.tokens.get(position - self.window_start))
+    }
+
+    /// Run `parse`, rolling the position and any warnings it added back if
+    /// it fails.
+    fn speculate<T>(&mut self, parse: impl FnOnce(&mut Self) -> Result<T, Error>) -> Result<T, Error> {
+        let checkpoint = self.checkpoint();
+        let warnings = self.warnings.len();
+        let result = parse(self);
+        if result.is_err() {
+            self.restore(checkpoint);
+            self.warnings.truncate(warnings);
+        } else {
+            self.release(checkpoint);
+        }
+        result
+    }
+
     #[instrument(skip(self))]
     fn parse_property(&mut self) -> Result<PropertyNode, Error> {
         trace!(token = ?self.peek(), "Starting property parse");
+        if self.check(TokenType::Delete) && !self.delete_is_property_name() {
+            return self.parse_delete();
+        }
+
+        let error = match self.speculate(Self::parse_assignment) {
+            Ok(property) => return Ok(property),
+            Err(error) => error,
+        };
+        if !self.is_typed_declaration() {
+            return Err(error);
+        }
+
+        // `type name = value;`: the type name carries no meaning in configs
+        let checkpoint = self.checkpoint();
+        let doc_comment = self.doc_comment();
+        debug!(type_name = ?self.peek().token_type, "Skipping type name of typed declaration");
+        self.advance();
+        let mut property = self.speculate(Self::parse_assignment).inspect_err(|_| self.restore(checkpoint))?;
+        self.release(checkpoint);
+        property.doc_comment = property.doc_comment.or(doc_comment);
+        Ok(property)
+    }
+
+    /// `name` as stored, lowercased unless the config is case-sensitive.
+    fn fold_case(&self, name: &str) -> String {
+        if self.config.case_sensitive {
+            name.to_string()
+        } else {
+            name.to_lowercase()
+        }
+    }
+
+    /// Add `property` to `class`, applying the duplicate policy if the class
+    /// already has one of that name.
+    fn add_property(&mut self, class: &mut ClassNode, property: PropertyNode) -> Result<(), Error> {
+        let Some(existing) = class.properties.get_mut(&property.name) else {
+            class.properties.insert(property.name.clone(), property);
+            return Ok(());
+        };
+        let location = property.location.clone().unwrap_or_else(SourceLocation::unknown);
+        match combine_arrays(existing, &property) {
+            Ok(true) => {
+                trace!(class_name = %class.name, property = %property.name, "Applied array operation within class");
+                return Ok(());
+            }
+            Ok(false) => {}
+            // No single operation has the effect of both, and keeping only
+            // the last would drop the first
+            Err((first, second)) => return Err(Error::ParseError {
+                message: format!(
+                    "Array '{}' of class '{}' cannot take both {:?} and {:?} operations",
+                    property.name, class.name, first, second
+                ),
+                location,
+            }),
+        }
+
+        match self.duplicate_policy {
+            DuplicatePolicy::LastWins => {}
+            DuplicatePolicy::FirstWins => return Ok(()),
+            DuplicatePolicy::Error => return Err(Error::ParseError {
+                message: format!("Property '{}' of class '{}' is already defined", property.name, class.name),
+                location,
+            }),
+            DuplicatePolicy::Warn => self.warnings.push(Warning::DuplicateProperty {
+                class: class.name.clone(),
+                name: property.name.clone(),
+                location,
+                previous: existing.location.clone().unwrap_or_else(SourceLocation::unknown),
+            }),
+        }
+        *existing = property;
+        Ok(())
+    }
+
+    /// `Identifier Identifier =` or `Identifier Identifier[]`.
+    fn is_typed_declaration(&self) -> bool {
+        let is_identifier = |n| self.lookahead(n).is_some_and(|token| matches!(token.token_type, TokenType::Identifier(_)));
+        is_identifier(0) && is_identifier(1)
+            && self.lookahead(2).is_some_and(|token| matches!(token.token_type, TokenType::Equals | TokenType::ArrayMarker))
+    }
+
+    fn parse_assignment(&mut self) -> Result<PropertyNode, Error> {
+        let doc_comment = self.doc_comment();
+
         let name_token = self.consume()?;
         let name = match &name_token.token_type {
             TokenType::Identifier(name) => name.clone(),
+            TokenType::Delete => "delete".to_string(),
             token => {
                 debug!(unexpected_token = ?token, "Expected property name");
                 return Err(Error::ParseError { 
                     message: "Expected property name".to_string(),
-                    location: SourceLocation::new(self.file_path.clone(), name_token.line, name_token.column)
+                    location: self.location_of(&name_token)
                 });
             }
         };
@@ -142,6 +535,9 @@ impl Parser {
             } else if self.check(TokenType::MinusEquals) {
                 self.advance();
                 operation = Some(ArrayOperation::Remove);
+            } else if self.check(TokenType::AmpersandEquals) {
+                self.advance();
+                operation = Some(ArrayOperation::Intersect);
             } else {
                 self.expect_token(TokenType::Equals)?;
                 operation = Some(ArrayOperation::Replace);
@@ -150,7 +546,19 @@ impl Parser {
             self.expect_token(TokenType::Equals)?;
         }
 
-        let (value_type, raw_value, array_values) = if is_array {
+        // `name = {...}` and `name[] = value` are accepted as written, with
+        // a warning, rather than failing the whole parse
+        let value_token = self.peek().clone();
+        let (value_type, raw_value, array_values) = if is_array && !self.check(TokenType::LeftBrace) {
+            let value = self.parse_single_value()?;
+            self.warn_type_mismatch(&name, "array", &value.0, &value_token);
+            operation = None;
+            value
+        } else if !is_array && self.check(TokenType::LeftBrace) {
+            let (raw_value, values) = self.parse_array_literal()?;
+            self.warn_type_mismatch(&name, "single value", &PropertyType::Array, &value_token);
+            (PropertyType::Array, raw_value, values)
+        } else if is_array {
             trace!(property = %name, "Parsing array value");
             self.parse_array_value()?
         } else {
@@ -159,77 +567,280 @@ impl Parser {
         };
         
         self.expect_token(TokenType::Semicolon)?;
+        let doc_comment = match (doc_comment, self.trailing_comment()) {
+            (Some(leading), Some(trailing)) => Some(format!("{}\n{}", leading, trailing)),
+            (leading, trailing) => leading.or(trailing),
+        };
 
         Ok(PropertyNode {
             name,
             value_type,
             raw_value,
             operation,
-            array_values,
+            array_values: array_values.iter().map(|(text, _)| text.clone()).collect(),
+            typed_values: array_values.into_iter().map(|(_, element)| element).collect(),
+            location: Some(self.location_of(&name_token)),
+            span: Some(Span::between(&name_token, self.previous())),
+            doc_comment,
         })
     }
 
-    fn parse_single_value(&mut self) -> Result<(PropertyType, String, Vec<String>), Error> {
-        match self.peek().token_type {
-            TokenType::StringLiteral(s) => {
-                self.advance();
-                let clean_str = s.trim_matches('"').to_string();
-                Ok((PropertyType::String, clean_str, vec![]))
-            }
-            TokenType::NumberLiteral(n) => {
-                self.advance();
-                Ok((PropertyType::Number, n.to_string(), vec![]))
-            }
-            TokenType::BooleanLiteral(b) => {
-                self.advance();
-                Ok((PropertyType::Boolean, b.to_string(), vec![]))
-            }
-            TokenType::Identifier(s) => {
-                self.advance();
-                Ok((PropertyType::String, s, vec![]))
-            }
-            _ => Err(Error::ParseError {
-                message: "Expected value".to_string(),
-                location: SourceLocation::unknown()
+    fn warn_type_mismatch(&mut self, name: &str, expected: &str, found: &PropertyType, token: &Token) {
+        let found = match found {
+            PropertyType::Array => "array",
+            PropertyType::Number => "number",
+            PropertyType::Boolean => "boolean",
+            _ => "string",
+        };
+        self.warnings.push(Warning::PropertyTypeMismatch {
+            name: name.to_string(),
+            expected: expected.to_string(),
+            found: found.to_string(),
+            location: self.location_of(token),
+        });
+    }
+
+    /// `delete name;` marks an inherited property or nested class as removed.
+    fn parse_delete(&mut self) -> Result<PropertyNode, Error> {
+        let delete_token = self.peek().clone();
+        self.expect_token(TokenType::Delete)?;
+        let name_token = self.consume()?;
+        let name = match &name_token.token_type {
+            TokenType::Identifier(name) => name.clone(),
+            _ => return Err(Error::ParseError {
+                message: "Expected member name after 'delete'".to_string(),
+                location: self.location_of(&name_token)
             }),
+        };
+        self.expect_token(TokenType::Semicolon)?;
+
+        let mut property = PropertyNode::deleted(name).with_location(self.location_of(&name_token));
+        property.span = Some(Span::between(&delete_token, self.previous()));
+        Ok(property)
+    }
+
+    /// `delete` is only a keyword when it is not itself being assigned to.
+    fn delete_is_property_name(&self) -> bool {
+        self.lookahead(1).is_some_and(|token| matches!(
+            token.token_type,
+            TokenType::Equals | TokenType::ArrayMarker
+        ))
+    }
+
+    fn parse_single_value(&mut self) -> Result<ParsedValue, Error> {
+        if self.is_expression() {
+            let (value_type, raw_value) = self.parse_expression()?;
+            return Ok((value_type, raw_value, vec![]));
         }
+        let token = self.peek();
+        let value = match &token.token_type {
+            TokenType::StringLiteral(s) => (PropertyType::String, s.clone()),
+            TokenType::NumberLiteral(n) => (PropertyType::Number, token.raw.clone().unwrap_or_else(|| n.to_string())),
+            TokenType::BooleanLiteral(b) => (PropertyType::Boolean, b.to_string()),
+            TokenType::Identifier(s) => (PropertyType::String, s.clone()),
+            _ => return Err(Error::ParseError {
+                message: "Expected value".to_string(),
+                location: self.location_of(token)
+            }),
+        };
+        self.advance();
+        Ok((value.0, value.1, vec![]))
     }
 
-    fn parse_array_value(&mut self) -> Result<(PropertyType, String, Vec<String>), Error> {
+    /// Whether the value at the current position is arithmetic, such as
+    /// `120*0.8`, `-(1)` or `(1+2)/2`.
+    fn is_expression(&self) -> bool {
         match self.peek().token_type {
-            TokenType::LeftBrace => {
-                self.advance();
-                let mut values = Vec::new();
-                
-                while !self.check(TokenType::RightBrace) {
-                    let value = match self.consume()?.token_type {
-                        TokenType::StringLiteral(s) => s.trim_matches('"').to_string(),
-                        TokenType::NumberLiteral(n) => n.to_string(),
-                        TokenType::Identifier(s) => s,
-                        _ => return Err(Error::ParseError {
-                            message: "Invalid array element".to_string(),
-                            location: SourceLocation::unknown()
-                        }),
-                    };
-                    values.push(value);
-
-                    if !self.check(TokenType::RightBrace) {
-                        self.expect_token(TokenType::Comma)?;
-                    }
+            TokenType::LeftParen | TokenType::Minus | TokenType::Plus => true,
+            TokenType::NumberLiteral(_) | TokenType::Identifier(_) => self.lookahead(1).is_some_and(Token::is_arithmetic),
+            _ => false,
+        }
+    }
+
+    /// Consume an arithmetic value and evaluate it. Constant arithmetic
+    /// gives a number; anything else, such as `foo*2` or a division by
+    /// zero, is kept as its text.
+    fn parse_expression(&mut self) -> Result<(PropertyType, String), Error> {
+        let location = self.location_of(self.peek());
+        let mut text = String::new();
+        let mut depth = 0usize;
+        let mut expect_operand = true;
+        loop {
+            let token = self.peek();
+            let part = match (&token.token_type, expect_operand) {
+                (TokenType::NumberLiteral(n), true) => token.raw.clone().unwrap_or_else(|| n.to_string()),
+                (TokenType::Identifier(s), true) => s.clone(),
+                (TokenType::LeftParen, true) => {
+                    depth += 1;
+                    "(".to_string()
                 }
-                
-                self.expect_token(TokenType::RightBrace)?;
-                
-                // Format raw value without extra quotes
-                let raw_value = format!("{{{}}}", values.join(","));
-                
-                Ok((PropertyType::Array, raw_value, values))
-            }
-            _ => Err(Error::ParseError {
+                (TokenType::RightParen, false) if depth > 0 => {
+                    depth -= 1;
+                    ")".to_string()
+                }
+                // Signs are also unary, before an operand
+                (TokenType::Plus, _) => "+".to_string(),
+                (TokenType::Minus, _) => "-".to_string(),
+                (TokenType::Star, false) => "*".to_string(),
+                (TokenType::Slash, false) => "/".to_string(),
+                _ => break,
+            };
+            expect_operand = !matches!(
+                token.token_type,
+                TokenType::NumberLiteral(_) | TokenType::Identifier(_) | TokenType::RightParen
+            );
+            text.push_str(&part);
+            self.advance();
+        }
+
+        if expect_operand || depth > 0 {
+            return Err(Error::ParseError {
+                message: format!("Incomplete expression '{}'", text),
+                location,
+            });
+        }
+        Ok(match evaluate(&text) {
+            Some(value) => (PropertyType::Number, format_number(value)),
+            None => (PropertyType::String, text),
+        })
+    }
+
+    fn parse_array_value(&mut self) -> Result<ParsedValue, Error> {
+        if !self.check(TokenType::LeftBrace) {
+            return Err(Error::ParseError {
                 message: "Expected array value".to_string(),
-                location: SourceLocation::unknown()
-            }),
+                location: self.location_of(self.peek())
+            });
+        }
+
+        let (raw_value, values) = self.parse_array_literal()?;
+        Ok((PropertyType::Array, raw_value, values))
+    }
+
+    /// Parse a braced array literal, recursing into nested arrays.
+    ///
+    /// Each element comes with its text: numbers as written and nested
+    /// arrays as `{M4A1,ACOG}`.
+    fn parse_array_literal(&mut self) -> Result<(String, Vec<(String, ArrayElement)>), Error> {
+        self.expect_token(TokenType::LeftBrace)?;
+        let mut values = Vec::new();
+        
+        while !self.check(TokenType::RightBrace) {
+            let value = if self.check(TokenType::LeftBrace) {
+                let (raw_value, nested) = self.parse_array_literal()?;
+                (raw_value, ArrayElement::Array(nested.into_iter().map(|(_, element)| element).collect()))
+            } else if self.is_expression() {
+                let (_, text) = self.parse_expression()?;
+                let element = ArrayElement::parse(&text);
+                (text, element)
+            } else {
+                let token = self.consume()?;
+                match token.token_type {
+                    TokenType::StringLiteral(s) => (s.clone(), ArrayElement::String(s)),
+                    TokenType::NumberLiteral(n) => (token.raw.unwrap_or_else(|| n.to_string()), ArrayElement::Number(n)),
+                    TokenType::BooleanLiteral(b) => (b.to_string(), ArrayElement::Boolean(b)),
+                    TokenType::Identifier(s) => (s.clone(), ArrayElement::String(s)),
+                    _ => return Err(Error::ParseError {
+                        message: "Invalid array element".to_string(),
+                        location: self.location_of(&token)
+                    }),
+                }
+            };
+            values.push(value);
+
+            if !self.check(TokenType::RightBrace) {
+                self.expect_token(TokenType::Comma)?;
+            }
+        }
+        
+        self.expect_token(TokenType::RightBrace)?;
+
+        // Format raw value without extra quotes
+        let texts: Vec<&str> = values.iter().map(|(text, _)| text.as_str()).collect();
+        let raw_value = format!("{{{}}}", texts.join(","));
+
+        Ok((raw_value, values))
+    }
+
+    /// Record `error` and resynchronize when recovery is enabled, otherwise
+    /// propagate it. `start` is the brace depth at the first token of the
+    /// construct that failed to parse.
+    fn recover(&mut self, error: Error, start: isize) -> Result<(), Error> {
+        if !self.error_recovery {
+            return Err(error);
+        }
+        debug!(error = %error, "Recovering from parse error");
+        self.errors.push(error);
+
+        // Braces the failed construct opened but never closed still need to
+        // be matched before we are back at the enclosing block's level
+        let open_braces = self.brace_depth - start;
+        self.synchronize(open_braces.max(0) as usize);
+        Ok(())
+    }
+
+    /// Skip tokens until just past the next `;` or up to (not including) the
+    /// `}` that closes the current block. Braces opened while skipping are
+    /// matched so a malformed array value does not end the enclosing class.
+    fn synchronize(&mut self, mut depth: usize) {
+        while !self.is_at_end() {
+            match self.peek().token_type {
+                TokenType::Semicolon if depth == 0 => {
+                    self.advance();
+                    return;
+                }
+                TokenType::LeftBrace => depth += 1,
+                TokenType::RightBrace if depth == 0 => return,
+                TokenType::RightBrace => depth -= 1,
+                _ => {}
+            }
+            self.advance();
+        }
+    }
+
+    /// The comments directly before the current token, with comment markers
+    /// stripped. A comment on the same line as the previous token trails that
+    /// statement and is not included.
+    fn doc_comment(&self) -> Option<String> {
+        let previous_line = (self.current > 0).then(|| self.previous().line);
+        self.comment_text(|comment| Some(comment.line) != previous_line)
+    }
+
+    /// The comment on the same line as the token just consumed, such as
+    /// `// public` after `scope = 2;`.
+    fn trailing_comment(&self) -> Option<String> {
+        let line = self.previous().line;
+        self.comment_text(|comment| comment.line == line)
+    }
+
+    fn comment_text(&self, include: impl Fn(&Token) -> bool) -> Option<String> {
+        if !self.attach_comments {
+            return None;
         }
+        let comments = self.comments.get(&self.current)?;
+        let lines: Vec<&str> = comments.iter()
+            .filter(|comment| include(comment))
+            .filter_map(|comment| match &comment.token_type {
+                TokenType::Comment(text) => Some(text.as_str()),
+                _ => None,
+            })
+            .flat_map(str::lines)
+            .map(|line| line.trim().trim_matches(|c| c == '/' || c == '*').trim())
+            .filter(|line| !line.is_empty())
+            .collect();
+
+        (!lines.is_empty()).then(|| lines.join("\n"))
+    }
+
+    /// The most recently consumed token.
+    fn previous(&self) -> &Token {
+        self.current.checked_sub(1)
+            .and_then(|position| self.tokens.get(position - self.window_start))
+            .unwrap_or(&self.end_of_input)
+    }
+
+    fn location_of(&self, token: &Token) -> SourceLocation {
+        SourceLocation::new(self.file_path.clone(), token.line, token.column).with_span(token.start..token.end)
     }
 
     fn consume(&mut self) -> Result<Token, Error> {
@@ -240,7 +851,7 @@ impl Parser {
                 location: SourceLocation::unknown()
             })
         } else {
-            let token = self.tokens[self.current].clone();
+            let token = self.peek().clone();
             self.advance();
             trace!(consumed_token = ?token, "Token consumed");
             Ok(token)
@@ -255,109 +866,219 @@ impl Parser {
         } else {
             Err(Error::ParseError {
                 message: format!("Expected token {:?}, found {:?}", expected_type, token.token_type),
-                location: SourceLocation::new(self.file_path.clone(), token.line, token.column)
+                location: self.location_of(token)
             })
         }
     }
 
     fn check(&self, token_type: TokenType) -> bool {
-        if self.is_at_end() {
-            false
-        } else {
-            std::mem::discriminant(&self.tokens[self.current].token_type) == std::mem::discriminant(&token_type)
-        }
+        !self.is_at_end() && std::mem::discriminant(&self.peek().token_type) == std::mem::discriminant(&token_type)
     }
 
     fn advance(&mut self) {
-        if !self.is_at_end() {
-            self.current += 1;
+        if self.is_at_end() {
+            return;
         }
+        self.brace_depth += brace_delta(self.peek());
+        self.current += 1;
+        self.trim();
+        self.fill(LOOKAHEAD);
     }
 
-    fn peek(&self) -> Token {
-        if self.is_at_end() {
-            Token::new(TokenType::EOL, 0, 0)
-        } else {
-            self.tokens[self.current].clone()
-        }
+    fn peek(&self) -> &Token {
+        self.tokens.get(self.current - self.window_start).unwrap_or(&self.end_of_input)
     }
 
     fn is_at_end(&self) -> bool {
-        self.current >= self.tokens.len()
+        self.current - self.window_start >= self.tokens.len()
     }
 
-    fn skip_enum_block(&mut self) -> Result<(), Error> {
-        self.advance(); // Skip 'enum' token
-        
-        // Skip until we find the opening brace
-        while !self.check(TokenType::LeftBrace) && !self.is_at_end() {
-            self.advance();
-        }
-        
-        if self.is_at_end() {
-            return Err(Error::ParseError {
-                message: "Unexpected end of file while skipping enum block".to_string(),
-                location: SourceLocation::unknown()
-            });
-        }
-        
-        // Skip the opening brace
-        self.advance();
-        debug!("Starting to skip enum block");
-        
-        // Keep track of nested braces
-        let mut depth = 1;
-        let mut enum_values = Vec::new();
-        
-        while depth > 0 && !self.is_at_end() {
-            match self.peek().token_type {
-                TokenType::LeftBrace => {
-                    depth += 1;
-                    self.advance();
+    /// Read from the source until `count` tokens from the current one on
+    /// are buffered or it runs out. Comments are set aside for the token
+    /// that follows them.
+    fn fill(&mut self, count: usize) {
+        while !self.source_done && self.window_start + self.tokens.len() < self.current.saturating_add(count) {
+            match self.source.next() {
+                Some(Ok(token)) if matches!(token.token_type, TokenType::Comment(_)) => {
+                    let position = self.window_start + self.tokens.len();
+                    self.comments.entry(position).or_default().push(token);
                 }
-                TokenType::RightBrace => {
-                    depth -= 1;
-                    self.advance();
-                    if depth == 0 {
-                        // After closing brace, expect semicolon
-                        if self.check(TokenType::Semicolon) {
-                            self.advance();
-                        } else {
-                            debug!("Warning: No semicolon after enum block");
-                        }
-                    }
+                Some(Ok(token)) => self.tokens.push_back(token),
+                Some(Err(error)) => {
+                    self.lex_error = Some(error);
+                    self.source_done = true;
                 }
-                TokenType::Identifier(name) if depth == 1 => {
-                    enum_values.push(name.clone());
-                    self.advance();
-                    // Skip any value assignment (=)
-                    if self.check(TokenType::Equals) {
-                        self.advance();
-                        if let TokenType::NumberLiteral(_) = self.peek().token_type {
-                            self.advance();
-                        }
-                    }
-                    // Skip comma
-                    if self.check(TokenType::Comma) {
-                        self.advance();
-                    }
+                None => self.source_done = true,
+            }
+        }
+    }
+
+    /// Drop the tokens (and their comments) before the previous token, both
+    /// here and at the oldest checkpoint.
+    fn trim(&mut self) {
+        let keep_from = self.checkpoints.iter().copied()
+            .fold(self.current, usize::min)
+            .saturating_sub(1);
+        while self.window_start < keep_from && !self.tokens.is_empty() {
+            self.tokens.pop_front();
+            self.window_start += 1;
+        }
+        if self.comments.first_key_value().is_some_and(|(&position, _)| position < self.window_start) {
+            self.comments = self.comments.split_off(&self.window_start);
+        }
+    }
+
+    /// Parse `enum [Name] { a = 0, b, c = 5 };`. Entries without an explicit
+    /// value continue counting from the previous one, as in C.
+    #[instrument(skip(self))]
+    fn parse_enum_block(&mut self) -> Result<EnumNode, Error> {
+        let enum_token = self.peek().clone();
+        self.expect_token(TokenType::Enum)?;
+
+        let name = match &self.peek().token_type {
+            TokenType::Identifier(name) => {
+                let name = name.clone();
+                self.advance();
+                name
+            }
+            _ => String::new(),
+        };
+        let mut node = EnumNode::new(name);
+        node.location = Some(self.location_of(&enum_token));
+
+        self.expect_token(TokenType::LeftBrace)?;
+
+        let mut next_value = 0i64;
+        while !self.check(TokenType::RightBrace) {
+            let entry_token = self.consume()?;
+            // Keywords are valid entry names, e.g. `enum { private = 0, public = 2 }`
+            let entry = match &entry_token.token_type {
+                TokenType::Identifier(entry) => entry.clone(),
+                TokenType::Private => "private".to_string(),
+                TokenType::Public => "public".to_string(),
+                other => {
+                    return Err(Error::ParseError {
+                        message: format!("Expected enum entry name, found {:?}", other),
+                        location: self.location_of(&entry_token),
+                    });
                 }
-                _ => self.advance()
+            };
+
+            if self.check(TokenType::Equals) {
+                self.advance();
+                next_value = self.parse_enum_value(&node)?;
+            }
+
+            trace!(entry = %entry, value = next_value, "Enum entry");
+            node.values.insert(entry, next_value);
+            next_value = next_value.wrapping_add(1);
+
+            if self.check(TokenType::Comma) {
+                self.advance();
+            } else if !self.check(TokenType::RightBrace) {
+                let token = self.peek();
+                return Err(Error::ParseError {
+                    message: format!("Expected ',' or '}}' in enum, found {:?}", token.token_type),
+                    location: self.location_of(token),
+                });
             }
         }
-        
-        if depth > 0 {
-            return Err(Error::ParseError {
-                message: "Unclosed enum block".to_string(),
-                location: SourceLocation::unknown()
-            });
+        self.expect_token(TokenType::RightBrace)?;
+
+        if self.check(TokenType::Semicolon) {
+            self.advance();
+        } else {
+            let location = self.location_of(self.previous());
+            self.warnings.push(Warning::MissingSemicolon { after: "enum".to_string(), location });
         }
-        
-        debug!("Skipped enum block with {} values", enum_values.len());
-        if !enum_values.is_empty() {
-            trace!("Enum values: {:?}", enum_values);
+
+        debug!(name = %node.name, values = node.values.len(), "Parsed enum block");
+        Ok(node)
+    }
+
+    /// An enum value is an integer literal or a previously declared entry.
+    fn parse_enum_value(&mut self, node: &EnumNode) -> Result<i64, Error> {
+        let token = self.consume()?;
+        match &token.token_type {
+            TokenType::NumberLiteral(n) if n.fract() == 0.0 => Ok(*n as i64),
+            TokenType::Identifier(name) if node.values.contains_key(name) => Ok(node.values[name]),
+            other => Err(Error::ParseError {
+                message: format!("Invalid enum value {:?}", other),
+                location: self.location_of(&token),
+            }),
         }
-        
-        Ok(())
     }
-}
\ No newline at end of file
+}
+
+/// How `token` changes the brace depth.
+fn brace_delta(token: &Token) -> isize {
+    match token.token_type {
+        TokenType::LeftBrace => 1,
+        TokenType::RightBrace => -1,
+        _ => 0,
+    }
+}
+
+/// Apply the array operation of `property` to `existing`, an array the same
+/// class defined earlier. False if `property` is a redefinition rather than
+/// an operation, e.g. when it replaces the array outright, and the two
+/// operations if they cannot be merged into one, such as `+=` then `-=`.
+fn combine_arrays(existing: &mut PropertyNode, property: &PropertyNode) -> Result<bool, (ArrayOperation, ArrayOperation)> {
+    let (Some(first), Some(second)) = (existing.operation, property.operation) else {
+        return Ok(false);
+    };
+    let operation = match (first, second) {
+        (_, ArrayOperation::Replace) => return Ok(false),
+        (ArrayOperation::Replace, operation) => operation,
+        // Two appends, or two removals, are one with both lists
+        (ArrayOperation::Append, ArrayOperation::Append) | (ArrayOperation::Remove, ArrayOperation::Remove) => ArrayOperation::Append,
+        // Keeping what is in both lists
+        (ArrayOperation::Intersect, ArrayOperation::Intersect) => ArrayOperation::Intersect,
+        operations => return Err(operations),
+    };
+
+    let elements = |property: &PropertyNode| -> Vec<(String, ArrayElement)> {
+        property.array_values.iter().cloned().zip(property.typed_values.iter().cloned()).collect()
+    };
+    let combined = ArrayProcessor::process_by(
+        &elements(existing), &elements(property), operation, ArrayOptions::default(), |(_, element)| element.key(),
+    );
+    (existing.array_values, existing.typed_values) = combined.into_iter().unzip();
+    existing.raw_value = format!("{{{}}}", existing.array_values.join(","));
+    Ok(true)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Preprocessor;
+    use std::time::Instant;
+
+    /// Parses the 3DEN config from the tokenizer as it goes and from a fully
+    /// tokenized `Vec`, reporting the time and the tokens held by each. Run
+    /// with `cargo test --release -- --ignored --nocapture bench_streaming_parse`.
+    #[test]
+    #[ignore]
+    fn bench_streaming_parse() {
+        let data_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data");
+        let path = data_dir.join("a3_Addons_3den_a3_3den_config.cpp");
+        let content = Preprocessor::new(&data_dir).process_file(&path).unwrap();
+
+        let start = Instant::now();
+        let tokens = Tokenizer::new(&content).tokenize().unwrap();
+        let token_count = tokens.len();
+        let buffered = Parser::new(tokens).parse().unwrap();
+        println!("Tokenize then parse: {:?}, {} tokens held", start.elapsed(), token_count);
+
+        let start = Instant::now();
+        let mut parser = Parser::from_tokenizer(Tokenizer::new(&content));
+        let streamed = parser.parse().unwrap();
+        // The window never shrinks its allocation, so its capacity bounds
+        // the most tokens it held at once
+        println!("Streaming parse: {:?}, at most {} tokens held", start.elapsed(), parser.tokens.capacity());
+
+        assert_eq!(streamed, buffered);
+        assert!(parser.tokens.capacity() < token_count);
+    }
+}
@@ -0,0 +1,307 @@
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use indexmap::IndexMap;
+use crate::ast::{ClassNode, EnumNode, PropertyNode, PropertyType};
+use crate::error::{Error, SourceLocation};
+use crate::operations::arrays::{ArrayElement, ArrayOperation};
+
+/// First bytes of a binarized (raP) config such as `config.bin`.
+pub const RAP_SIGNATURE: &[u8; 4] = b"\0raP";
+
+/// Nesting limit for classes and arrays, so corrupt offsets cannot recurse
+/// forever.
+const MAX_DEPTH: usize = 256;
+
+pub fn is_rap(data: &[u8]) -> bool {
+    data.starts_with(RAP_SIGNATURE)
+}
+
+/// Whether the file at `path` starts with the raP signature. Unreadable files
+/// are reported as not binarized.
+pub fn is_rap_file(path: &Path) -> bool {
+    let mut signature = [0u8; 4];
+    File::open(path)
+        .and_then(|mut file| file.read_exact(&mut signature))
+        .is_ok_and(|_| &signature == RAP_SIGNATURE)
+}
+
+/// Parse a binarized config into the same tree [`Parser::parse`](super::Parser::parse)
+/// produces for its source: an unnamed root holding the top-level classes.
+pub fn parse_rap(data: &[u8]) -> Result<ClassNode, Error> {
+    RapReader::new(data).parse()
+}
+
+/// Reader for the raP format.
+///
+/// Layout: the signature, two reserved words, the offset of the enum table,
+/// then the root class body at offset 16. A class body is the parent name,
+/// a compressed entry count and the entries; nested class bodies are reached
+/// through absolute offsets. Integers are little-endian, strings are
+/// NUL-terminated, and counts use 7-bit variable-length encoding.
+pub struct RapReader<'a> {
+    data: &'a [u8],
+    position: usize,
+    file_path: Option<PathBuf>,
+}
+
+impl<'a> RapReader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            position: 0,
+            file_path: None,
+        }
+    }
+
+    /// Attribute parsed classes and errors to `path`.
+    pub fn with_file_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.file_path = Some(path.into());
+        self
+    }
+
+    pub fn parse(&mut self) -> Result<ClassNode, Error> {
+        if !is_rap(self.data) {
+            return Err(self.error("missing raP signature"));
+        }
+        self.position = 12;
+        let enum_offset = self.read_u32()? as usize;
+
+        let mut root = ClassNode::new(String::new());
+        self.read_class_body(&mut root, 0)?;
+
+        if enum_offset != 0 {
+            self.position = enum_offset;
+            let count = self.read_u32()?;
+            let mut values = IndexMap::new();
+            for _ in 0..count {
+                let name = self.read_string()?;
+                values.insert(name, self.read_i32()? as i64);
+            }
+            if !values.is_empty() {
+                let mut node = EnumNode::new(String::new());
+                node.values = values;
+                root.enums.push(node);
+            }
+        }
+        Ok(root)
+    }
+
+    /// Read the body at the current position into `class`.
+    fn read_class_body(&mut self, class: &mut ClassNode, depth: usize) -> Result<(), Error> {
+        if depth > MAX_DEPTH {
+            return Err(self.error("classes nested too deeply"));
+        }
+
+        let parent = self.read_string()?;
+        if !parent.is_empty() {
+            class.parent = Some(parent);
+        }
+
+        let count = self.read_compressed()?;
+        for _ in 0..count {
+            let entry_type = self.read_u8()?;
+            match entry_type {
+                0 => {
+                    let name = self.read_string()?;
+                    let offset = self.read_u32()? as usize;
+                    let resume = self.position;
+
+                    let mut nested = self.new_class(name);
+                    self.position = offset;
+                    self.read_class_body(&mut nested, depth + 1)?;
+                    self.position = resume;
+                    class.nested_classes.push(nested);
+                }
+                1 => {
+                    let value_type = self.read_u8()?;
+                    let name = self.read_string()?;
+                    let (property_type, raw_value) = match value_type {
+                        0 => (PropertyType::String, self.read_string()?),
+                        1 => (PropertyType::Number, self.read_f32()?.to_string()),
+                        2 => (PropertyType::Number, self.read_i32()?.to_string()),
+                        // Unquoted identifiers parse as strings in the text format
+                        4 => (PropertyType::String, self.read_string()?),
+                        6 => (PropertyType::Number, self.read_i64()?.to_string()),
+                        other => return Err(self.error(&format!("unknown value type {}", other))),
+                    };
+                    class.properties.insert(name.clone(), PropertyNode::new(name, property_type, raw_value));
+                }
+                2 | 5 => {
+                    let operation = if entry_type == 5 {
+                        match self.read_u32()? {
+                            1 => ArrayOperation::Append,
+                            2 => ArrayOperation::Remove,
+                            other => return Err(self.error(&format!("unknown array flag {}", other))),
+                        }
+                    } else {
+                        ArrayOperation::Replace
+                    };
+                    let name = self.read_string()?;
+                    let (raw_value, values) = self.read_array(depth)?;
+                    let mut property = PropertyNode::new(name.clone(), PropertyType::Array, raw_value)
+                        .with_array_op(operation);
+                    (property.array_values, property.typed_values) = values.into_iter().unzip();
+                    class.properties.insert(name, property);
+                }
+                3 => {
+                    let name = self.read_string()?;
+                    let mut external = self.new_class(name);
+                    external.is_external = true;
+                    class.nested_classes.push(external);
+                }
+                4 => {
+                    let name = self.read_string()?;
+                    class.properties.insert(name.clone(), PropertyNode::deleted(name));
+                }
+                other => return Err(self.error(&format!("unknown entry type {}", other))),
+            }
+        }
+        Ok(())
+    }
+
+    /// The array's `{a,b}` text and its elements, each with its text as
+    /// the text parser stores it.
+    fn read_array(&mut self, depth: usize) -> Result<(String, Vec<(String, ArrayElement)>), Error> {
+        if depth > MAX_DEPTH {
+            return Err(self.error("arrays nested too deeply"));
+        }
+        let count = self.read_compressed()?;
+        let mut values = Vec::with_capacity(count.min(1024));
+        for _ in 0..count {
+            let value = match self.read_u8()? {
+                0 | 4 => {
+                    let text = self.read_string()?;
+                    (text.clone(), ArrayElement::String(text))
+                }
+                1 => number(self.read_f32()?),
+                2 => number(self.read_i32()?),
+                3 => {
+                    let (text, nested) = self.read_array(depth + 1)?;
+                    (text, ArrayElement::Array(nested.into_iter().map(|(_, element)| element).collect()))
+                }
+                6 => {
+                    let value = self.read_i64()?;
+                    (value.to_string(), ArrayElement::Number(value as f64))
+                }
+                other => return Err(self.error(&format!("unknown array element type {}", other))),
+            };
+            values.push(value);
+        }
+        let texts: Vec<&str> = values.iter().map(|(text, _)| text.as_str()).collect();
+        Ok((format!("{{{}}}", texts.join(",")), values))
+    }
+
+    fn new_class(&self, name: String) -> ClassNode {
+        let mut class = ClassNode::new(name);
+        class.file_path = self.file_path.clone();
+        class
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        let bytes = self.position.checked_add(len)
+            .and_then(|end| self.data.get(self.position..end))
+            .ok_or_else(|| self.error("unexpected end of data"))?;
+        self.position += len;
+        Ok(bytes)
+    }
+
+    fn read_u8(&mut self) -> Result<u8, Error> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn read_u32(&mut self) -> Result<u32, Error> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn read_i32(&mut self) -> Result<i32, Error> {
+        Ok(i32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn read_i64(&mut self) -> Result<i64, Error> {
+        Ok(i64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn read_f32(&mut self) -> Result<f32, Error> {
+        Ok(f32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    /// 7 bits per byte, least significant group first; the high bit marks
+    /// that another byte follows.
+    fn read_compressed(&mut self) -> Result<usize, Error> {
+        let mut value = 0usize;
+        for shift in (0..32).step_by(7) {
+            let byte = self.read_u8()?;
+            value |= ((byte & 0x7f) as usize) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(self.error("compressed integer too long"))
+    }
+
+    fn read_string(&mut self) -> Result<String, Error> {
+        let rest = &self.data[self.position.min(self.data.len())..];
+        let len = rest.iter().position(|&b| b == 0)
+            .ok_or_else(|| self.error("unterminated string"))?;
+        let text = String::from_utf8_lossy(&rest[..len]).into_owned();
+        self.position += len + 1;
+        Ok(text)
+    }
+
+    fn error(&self, message: &str) -> Error {
+        Error::ParseError {
+            message: format!("Invalid raP data at byte {}: {}", self.position, message),
+            location: SourceLocation::new(self.file_path.clone(), 0, 0),
+        }
+    }
+}
+
+fn number(value: impl Into<f64> + ToString) -> (String, ArrayElement) {
+    (value.to_string(), ArrayElement::Number(value.into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_rap() {
+        // class Base { value = 1; }; with `class Child: Base {};` nested
+        let mut data = Vec::new();
+        data.extend_from_slice(RAP_SIGNATURE);
+        data.extend_from_slice(&0u32.to_le_bytes());
+        data.extend_from_slice(&8u32.to_le_bytes());
+        data.extend_from_slice(&0u32.to_le_bytes());
+        // Root body: no parent, one class entry
+        data.extend_from_slice(b"\0\x01\x00Base\0");
+        let base_offset = data.len() + 4;
+        data.extend_from_slice(&(base_offset as u32).to_le_bytes());
+        // Base body: value = 1 (long), items[] += {"a", 2.5, {1}}, class Child
+        data.extend_from_slice(b"\0\x03");
+        data.extend_from_slice(b"\x01\x02value\0");
+        data.extend_from_slice(&1i32.to_le_bytes());
+        data.extend_from_slice(b"\x05");
+        data.extend_from_slice(&1u32.to_le_bytes());
+        data.extend_from_slice(b"items\0\x03\x00a\0\x01");
+        data.extend_from_slice(&2.5f32.to_le_bytes());
+        data.extend_from_slice(b"\x03\x01\x02");
+        data.extend_from_slice(&1i32.to_le_bytes());
+        data.extend_from_slice(b"\x00Child\0");
+        let child_offset = data.len() + 4;
+        data.extend_from_slice(&(child_offset as u32).to_le_bytes());
+        data.extend_from_slice(b"Base\0\x00");
+
+        let root = parse_rap(&data).unwrap();
+        let base = &root.nested_classes[0];
+        assert_eq!(base.name, "Base");
+        assert_eq!(base.properties["value"].raw_value, "1");
+        assert_eq!(base.properties["items"].operation, Some(ArrayOperation::Append));
+        assert_eq!(base.properties["items"].array_values, vec!["a", "2.5", "{1}"]);
+        assert_eq!(base.properties["items"].typed_values[2], ArrayElement::Array(vec![ArrayElement::Number(1.0)]));
+        assert_eq!(base.nested_classes[0].parent.as_deref(), Some("Base"));
+
+        assert!(matches!(parse_rap(b"class A {};"), Err(Error::ParseError { .. })));
+        assert!(matches!(parse_rap(&data[..data.len() - 3]), Err(Error::ParseError { .. })));
+    }
+}
@@ -0,0 +1,195 @@
+use regex::Regex;
+use crate::ast::inheritance_visitor::InheritanceVisitor;
+use crate::ast::{ClassNode, PropertyNode, PropertyType};
+
+type ClassPredicate<'q> = Box<dyn Fn(&ClassNode) -> bool + 'q>;
+
+/// Fluent filter over a parsed class tree.
+///
+/// ```
+/// use class_scanner::{ClassScanner, query::Query};
+///
+/// let classes = ClassScanner::new().parse_string(r#"
+///     class CfgWeapons {
+///         class Rifle_Base_F { scope = 0; };
+///         class arifle_MX_F: Rifle_Base_F { scope = 2; magazines[] = {"30Rnd_65x39_caseless_mag"}; };
+///     };
+/// "#).unwrap();
+///
+/// let found = Query::new()
+///     .under("CfgWeapons")
+///     .where_property("scope", |v| v.raw_value == "2")
+///     .where_array_contains("magazines", "30Rnd_65x39_caseless_mag")
+///     .derives_from("Rifle_Base_F")
+///     .run(&classes[0]);
+/// assert_eq!(found[0].name, "arifle_MX_F");
+/// ```
+///
+/// Every class below the starting point matches when it passes all
+/// predicates. Class and property names compare ignoring case, as the game
+/// does. Property predicates see a class's own properties only; resolve
+/// inheritance first to match inherited values.
+#[derive(Default)]
+pub struct Query<'q> {
+    under: Option<String>,
+    max_depth: Option<usize>,
+    predicates: Vec<ClassPredicate<'q>>,
+    ancestors: Vec<String>,
+}
+
+impl<'q> Query<'q> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only search below the class at this dot-separated path, e.g.
+    /// `CfgWeapons` or `CfgVehicles.Car.Turrets`, matched ignoring case.
+    pub fn under(mut self, path: impl Into<String>) -> Self {
+        self.under = Some(path.into());
+        self
+    }
+
+    /// How many levels below the starting point to descend; `Some(0)` only
+    /// looks at its direct children.
+    pub fn max_depth(mut self, depth: Option<usize>) -> Self {
+        self.max_depth = depth;
+        self
+    }
+
+    /// Classes with a property `name` for which `predicate` holds.
+    pub fn where_property<F>(self, name: &'q str, predicate: F) -> Self
+    where
+        F: Fn(&PropertyNode) -> bool + 'q,
+    {
+        self.filter(move |class| property(class, name).is_some_and(&predicate))
+    }
+
+    pub fn has_property(self, name: &'q str) -> Self {
+        self.filter(move |class| property(class, name).is_some())
+    }
+
+    /// Classes whose array property `name` holds `value`, ignoring case.
+    pub fn where_array_contains(self, name: &'q str, value: &'q str) -> Self {
+        self.filter(move |class| property(class, name).is_some_and(|property| {
+            property.value_type == PropertyType::Array
+                && property.array_values.iter().any(|element| element.eq_ignore_ascii_case(value))
+        }))
+    }
+
+    /// Classes whose name matches a glob pattern (`*`, `?`), ignoring case.
+    pub fn name_matches(self, pattern: &str) -> Self {
+        let regex: Regex = crate::glob_to_regex(pattern);
+        self.filter(move |class| regex.is_match(&class.name))
+    }
+
+    /// Classes whose parent chain, looked up with the game's scoping rules,
+    /// passes through a class named `ancestor`. The chain may end at a
+    /// forward declaration or a parent missing from the tree.
+    pub fn derives_from(mut self, ancestor: impl Into<String>) -> Self {
+        self.ancestors.push(ancestor.into());
+        self
+    }
+
+    /// Classes for which `predicate` holds.
+    pub fn filter<F>(mut self, predicate: F) -> Self
+    where
+        F: Fn(&ClassNode) -> bool + 'q,
+    {
+        self.predicates.push(Box::new(predicate));
+        self
+    }
+
+    /// Matching classes below `root` in depth-first order. `root` is usually
+    /// the parser's unnamed root, so paths start at the top-level classes.
+    pub fn run<'a>(&self, root: &'a ClassNode) -> Vec<&'a ClassNode> {
+        let start = match &self.under {
+            Some(path) => match root.get_path(&path.replace('.', "/")) {
+                Some(class) => class,
+                None => return Vec::new(),
+            },
+            None => root,
+        };
+        let start_path: Vec<&str> = self.under.iter()
+            .flat_map(|path| path.split('.'))
+            .filter(|segment| !segment.is_empty())
+            .collect();
+
+        // Parent lookup needs the whole tree, not just the searched part
+        let visitor = (!self.ancestors.is_empty()).then(|| {
+            let mut visitor = InheritanceVisitor::new();
+            for class in &root.nested_classes {
+                visitor.register_class(class.clone());
+            }
+            visitor
+        });
+
+        let mut matches = Vec::new();
+        let mut pending: Vec<(Vec<&str>, &ClassNode, usize)> = start.nested_classes.iter().rev()
+            .map(|class| (with_segment(&start_path, &class.name), class, 0))
+            .collect();
+        while let Some((path, class, depth)) = pending.pop() {
+            if self.max_depth.is_none_or(|max| depth < max) {
+                for nested in class.nested_classes.iter().rev() {
+                    pending.push((with_segment(&path, &nested.name), nested, depth + 1));
+                }
+            }
+            if !self.predicates.iter().all(|predicate| predicate(class)) {
+                continue;
+            }
+            if let Some(visitor) = &visitor {
+                let key = path.join("/");
+                if !self.ancestors.iter().all(|ancestor| visitor.derives_from(&key, ancestor)) {
+                    continue;
+                }
+            }
+            matches.push(class);
+        }
+        matches
+    }
+}
+
+fn with_segment<'a>(path: &[&'a str], segment: &'a str) -> Vec<&'a str> {
+    let mut path = path.to_vec();
+    path.push(segment);
+    path
+}
+
+fn property<'a>(class: &'a ClassNode, name: &str) -> Option<&'a PropertyNode> {
+    class.properties.get(name)
+        .or_else(|| class.properties.values().find(|property| property.name.eq_ignore_ascii_case(name)))
+        .filter(|property| !property.is_deleted())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ClassScanner;
+
+    #[test]
+    fn test_query() {
+        let classes = ClassScanner::new().parse_string(r#"
+            class CfgWeapons {
+                class Rifle_Base_F;
+                class Rifle_Long_Base_F: Rifle_Base_F { scope = 0; };
+                class srifle_A: Rifle_Long_Base_F { scope = 2; magazines[] = {"10Rnd_A"}; };
+                class srifle_B: srifle_A { SCOPE = 1; class Attachments { class Optic: srifle_A {}; }; };
+                class Loop_A: Loop_B {};
+                class Loop_B: Loop_A {};
+            };
+            class CfgVehicles { class Car { scope = 2; }; };
+        "#).unwrap();
+        let root = &classes[0];
+        let names = |found: Vec<&ClassNode>| found.iter().map(|c| c.name.clone()).collect::<Vec<_>>();
+
+        assert_eq!(names(Query::new().where_property("scope", |v| v.raw_value == "2").run(root)), vec!["srifle_A", "Car"]);
+        assert_eq!(names(Query::new().under("cfgweapons").has_property("Scope").run(root)),
+            vec!["Rifle_Long_Base_F", "srifle_A", "srifle_B"]);
+        assert_eq!(names(Query::new().where_array_contains("magazines", "10rnd_a").run(root)), vec!["srifle_A"]);
+        assert_eq!(names(Query::new().under("CfgWeapons").name_matches("srifle_*").max_depth(Some(0)).run(root)),
+            vec!["srifle_A", "srifle_B"]);
+        assert_eq!(names(Query::new().derives_from("Rifle_Base_F").run(root)),
+            vec!["Rifle_Long_Base_F", "srifle_A", "srifle_B", "Optic"]);
+        assert!(Query::new().derives_from("Missing").run(root).is_empty());
+        assert!(Query::new().under("CfgMissing").run(root).is_empty());
+    }
+}
@@ -0,0 +1,116 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use serde::{Deserialize, Serialize};
+use crate::ast::ClassNode;
+use crate::error::{Error, Warning};
+use super::ScanResult;
+
+/// Parsed files of earlier scans by their modification time and those of
+/// the files they include, for
+/// [`ClassScanner::scan_directory_incremental`](crate::ClassScanner::scan_directory_incremental).
+/// Paths are relative to the scanned directory.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ScanCache {
+    files: HashMap<PathBuf, CachedFile>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CachedFile {
+    modified: SystemTime,
+    /// `None` if the file failed to parse; it is parsed again next time.
+    classes: Option<Vec<ClassNode>>,
+    /// Files pulled in with `#include`, as resolved, and their modification
+    /// times when parsed.
+    #[serde(default)]
+    includes: Vec<(PathBuf, SystemTime)>,
+    #[serde(default)]
+    warnings: Vec<Warning>,
+}
+
+impl CachedFile {
+    /// Whether an included file was modified or removed since parsing.
+    fn includes_changed(&self) -> bool {
+        self.includes.iter().any(|(path, modified)| {
+            fs::metadata(path).and_then(|metadata| metadata.modified()).ok() != Some(*modified)
+        })
+    }
+}
+
+impl ScanCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Read a cache written by [`save`](Self::save).
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let content = fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&content).map_err(io::Error::from)?)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        let content = serde_json::to_string(self).map_err(io::Error::from)?;
+        fs::write(path, content)?;
+        Ok(())
+    }
+
+    /// Number of files in the cache, including ones that failed to parse.
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// When `path` was last modified as of the scan that cached it.
+    pub fn modified(&self, path: &Path) -> Option<SystemTime> {
+        self.files.get(path).map(|file| file.modified)
+    }
+
+    /// The cached classes and warnings of `path`, if it parsed and neither
+    /// it nor a file it includes has changed since.
+    pub(crate) fn get(&self, path: &Path, modified: SystemTime) -> Option<(&Vec<ClassNode>, &[Warning])> {
+        let file = self.files.get(path).filter(|file| file.modified == modified && !file.includes_changed())?;
+        Some((file.classes.as_ref()?, &file.warnings))
+    }
+
+    pub(crate) fn includes_changed(&self, path: &Path) -> bool {
+        self.files.get(path).is_some_and(CachedFile::includes_changed)
+    }
+
+    /// Cache `path` as parsed at `modified`, recording the current
+    /// modification times of `includes`.
+    pub(crate) fn insert(&mut self, path: PathBuf, modified: SystemTime, classes: Option<Vec<ClassNode>>, includes: &[PathBuf], warnings: Vec<Warning>) {
+        let includes = includes.iter()
+            .filter_map(|include| Some((include.clone(), fs::metadata(include).ok()?.modified().ok()?)))
+            .collect();
+        self.files.insert(path, CachedFile { modified, classes, includes, warnings });
+    }
+
+    /// Drop the files `keep` rejects and return their paths, sorted.
+    pub(crate) fn retain(&mut self, keep: impl Fn(&Path) -> bool) -> Vec<PathBuf> {
+        let mut removed: Vec<PathBuf> = self.files.keys().filter(|path| !keep(path)).cloned().collect();
+        for path in &removed {
+            self.files.remove(path);
+        }
+        removed.sort();
+        removed
+    }
+}
+
+/// The result of an incremental scan and how the files changed since the
+/// cached one. Paths are relative to the scanned directory.
+#[derive(Debug, Default)]
+pub struct IncrementalResult {
+    /// Files that were not in the cache.
+    pub added: Vec<PathBuf>,
+    /// Files whose modification time changed; they were parsed again.
+    pub modified: Vec<PathBuf>,
+    /// Cached files that no longer exist or are no longer picked up.
+    pub removed: Vec<PathBuf>,
+    /// Every current file, whether parsed now or taken from the cache.
+    pub result: ScanResult,
+}
@@ -0,0 +1,226 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use indexmap::IndexMap;
+use crate::ast::{ClassNode, MergeStrategy};
+use crate::error::Error;
+use super::ScanResult;
+
+/// Where an indexed class was defined.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClassOrigin {
+    pub file: PathBuf,
+    /// Name of the addon: that of the PBO without its extension, of the
+    /// directory holding `$PBOPREFIX$`, or else of the directory of the file.
+    pub addon: Option<String>,
+    /// The addon's prefix, if it declares one.
+    pub prefix: Option<String>,
+}
+
+/// Every class from a set of scanned files, with classes of the same path
+/// merged the way the game merges configs of several addons.
+///
+/// Classes are keyed by their lowercased dot-separated path, e.g.
+/// `cfgweapons.arifle_mx_f`, so definitions in different addons meet.
+#[derive(Debug, Clone, Default)]
+pub struct ClassIndex {
+    classes: Vec<ClassNode>,
+    origins: HashMap<String, Vec<ClassOrigin>>,
+    /// Path keys by lowercased simple class name, in indexing order.
+    paths_by_name: HashMap<String, Vec<String>>,
+}
+
+impl ClassIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the classes parsed from one file. Later files override earlier
+    /// ones, as with [`ClassScanner::merge_parsed`](crate::ClassScanner::merge_parsed).
+    pub fn add_file(&mut self, classes: Vec<ClassNode>, origin: ClassOrigin) -> Result<(), Error> {
+        let top_level = match classes.first() {
+            Some(root) if root.name.is_empty() => classes.into_iter().next().unwrap().nested_classes,
+            _ => classes,
+        };
+        for class in top_level {
+            self.record(&class, "", &origin);
+            match self.classes.iter_mut().find(|c| c.name.eq_ignore_ascii_case(&class.name)) {
+                Some(existing) => existing.merge_from(class, MergeStrategy::LastWins)?,
+                None => self.classes.push(class),
+            }
+        }
+        Ok(())
+    }
+
+    /// Add every file of a directory scan. `dir` is the scanned directory
+    /// the result's paths are relative to.
+    pub fn add_scan(&mut self, dir: &Path, scan: &ScanResult) -> Result<(), Error> {
+        for (file, classes) in &scan.classes {
+            let (addon_dir, prefix) = match scan.prefix_of(file) {
+                Some((addon_dir, prefix)) => (dir.join(addon_dir), Some(prefix.clone())),
+                None if super::pbo::is_pbo_file(file) => (dir.join(file), None),
+                None => (dir.join(file).parent().unwrap_or(dir).to_path_buf(), None),
+            };
+            let addon = if super::pbo::is_pbo_file(&addon_dir) { addon_dir.file_stem() } else { addon_dir.file_name() };
+            let origin = ClassOrigin {
+                file: dir.join(file),
+                addon: addon.map(|name| name.to_string_lossy().into_owned()),
+                prefix,
+            };
+            self.add_file(classes.clone(), origin)?;
+        }
+        Ok(())
+    }
+
+    fn record(&mut self, class: &ClassNode, scope: &str, origin: &ClassOrigin) {
+        let key = if scope.is_empty() {
+            class.name.to_lowercase()
+        } else {
+            format!("{}.{}", scope, class.name.to_lowercase())
+        };
+        for nested in &class.nested_classes {
+            self.record(nested, &key, origin);
+        }
+        if class.is_external {
+            return;
+        }
+
+        let origins = self.origins.entry(key.clone()).or_default();
+        if origins.is_empty() {
+            self.paths_by_name.entry(class.name.to_lowercase()).or_default().push(key);
+        }
+        origins.push(origin.clone());
+    }
+
+    /// Find a defined class by dot-separated path or by simple name,
+    /// ignoring case. A simple name prefers a top-level class, then the most
+    /// recently indexed nested class of that name.
+    pub fn resolve(&self, name: &str) -> Option<&ClassNode> {
+        let key = name.to_lowercase();
+        let key = if key.contains('.') || self.origins.contains_key(&key) {
+            key
+        } else {
+            self.paths_by_name.get(&key)?.last()?.clone()
+        };
+        if !self.origins.contains_key(&key) {
+            return None;
+        }
+
+        let mut segments = key.split('.');
+        let first = segments.next()?;
+        let top = self.classes.iter().find(|class| class.name.eq_ignore_ascii_case(first))?;
+        segments.try_fold(top, |class, segment| {
+            class.nested_classes.iter().find(|nested| nested.name.eq_ignore_ascii_case(segment))
+        })
+    }
+
+    /// Files defining the class at `path`, in indexing order.
+    pub fn origins(&self, path: &str) -> &[ClassOrigin] {
+        self.origins.get(&path.to_lowercase()).map_or(&[], Vec::as_slice)
+    }
+
+    /// The merged top-level classes.
+    pub fn classes(&self) -> &[ClassNode] {
+        &self.classes
+    }
+
+    /// Number of defined classes, nested ones included.
+    pub fn len(&self) -> usize {
+        self.origins.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.origins.is_empty()
+    }
+}
+
+/// A parent name that matched no indexed class.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnresolvedParent {
+    /// Dot-separated path of the class declaring the parent.
+    pub class: String,
+    pub parent: String,
+}
+
+/// Result of [`ClassScanner::scan_and_resolve`](crate::ClassScanner::scan_and_resolve).
+#[derive(Debug, Default)]
+pub struct ResolvedScan {
+    pub index: ClassIndex,
+    /// Classes with inheritance applied, keyed by dot-separated path in
+    /// index order. Classes with an unresolved ancestor are left out.
+    pub resolved: IndexMap<String, ClassNode>,
+    pub unresolved: Vec<UnresolvedParent>,
+    pub errors: BTreeMap<PathBuf, Error>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use crate::ClassScanner;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_scan_and_resolve_across_addons() {
+        let temp_dir = TempDir::new().unwrap();
+        let vanilla = temp_dir.path().join("vanilla");
+        let weapons_f = vanilla.join("addons/weapons_f");
+        fs::create_dir_all(&weapons_f).unwrap();
+        fs::write(weapons_f.join("$PBOPREFIX$"), "a3\\weapons_f").unwrap();
+        fs::write(weapons_f.join("config.cpp"), "class CfgWeapons {
+            class Rifle_Base_F { mass = 100; };
+            class arifle_MX_F: Rifle_Base_F { scope = 2; };
+        };").unwrap();
+
+        let modpack = temp_dir.path().join("@mymod");
+        fs::create_dir_all(modpack.join("addons/main")).unwrap();
+        fs::write(modpack.join("addons/main/config.cpp"), "class CfgWeapons {
+            class arifle_MX_F;
+            class my_rifle: arifle_MX_F { scope = 1; };
+            class broken: Missing_F {};
+        };").unwrap();
+
+        let result = ClassScanner::new().scan_and_resolve(&[vanilla, modpack]).unwrap();
+
+        let rifle = &result.resolved["CfgWeapons.my_rifle"];
+        assert_eq!(rifle.properties["mass"].raw_value, "100");
+        assert_eq!(rifle.properties["scope"].raw_value, "1");
+        assert!(!result.resolved.contains_key("CfgWeapons.broken"));
+        assert_eq!(result.unresolved, vec![UnresolvedParent {
+            class: "CfgWeapons.broken".to_string(),
+            parent: "Missing_F".to_string(),
+        }]);
+
+        let index = &result.index;
+        assert_eq!(index.resolve("ARIFLE_MX_F").unwrap().parent.as_deref(), Some("Rifle_Base_F"));
+        assert_eq!(index.resolve("CfgWeapons.my_rifle").unwrap().name, "my_rifle");
+        assert!(index.resolve("Missing_F").is_none());
+        assert_eq!(index.origins("CfgWeapons.arifle_MX_F"), &[ClassOrigin {
+            file: weapons_f.join("config.cpp"),
+            addon: Some("weapons_f".to_string()),
+            prefix: Some("a3\\weapons_f".to_string()),
+        }]);
+        assert_eq!(index.origins("cfgweapons").len(), 2);
+        assert_eq!(index.origins("cfgweapons.my_rifle")[0].addon.as_deref(), Some("main"));
+        assert_eq!(index.len(), 5);
+    }
+
+    #[test]
+    fn test_pbo_origin() {
+        let temp_dir = TempDir::new().unwrap();
+        let addons = temp_dir.path().join("addons");
+        fs::create_dir_all(&addons).unwrap();
+        let pbo = crate::scanner::pbo::tests::write_pbo(&[("prefix", "x\\mod\\addons\\packed")], &[
+            ("config.cpp", b"class CfgPatches { class Packed {}; };"),
+        ]);
+        fs::write(addons.join("packed.pbo"), pbo).unwrap();
+        fs::write(addons.join("plain.pbo"), crate::scanner::pbo::tests::write_pbo(&[], &[("config.cpp", b"class Plain {};")])).unwrap();
+
+        let result = ClassScanner::new().scan_and_resolve(&[temp_dir.path().to_path_buf()]).unwrap();
+        assert_eq!(result.index.origins("CfgPatches"), &[ClassOrigin {
+            file: addons.join("packed.pbo"),
+            addon: Some("packed".to_string()),
+            prefix: Some("x\\mod\\addons\\packed".to_string()),
+        }]);
+        assert_eq!(result.index.origins("Plain")[0].addon.as_deref(), Some("plain"));
+    }
+}
@@ -0,0 +1,696 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use crate::ast::ClassNode;
+use crate::error::Error;
+use crate::parser::rap::is_rap_file;
+
+pub mod cache;
+pub mod index;
+pub mod pbo;
+#[cfg(feature = "watch")]
+mod watch;
+
+pub use cache::{IncrementalResult, ScanCache};
+pub use index::{ClassIndex, ClassOrigin, ResolvedScan, UnresolvedParent};
+pub use pbo::{Pbo, PboEntry};
+#[cfg(feature = "watch")]
+pub use watch::{WatchEvent, WatchHandle};
+
+/// Callback invoked by directory scans after each file.
+pub type ProgressCallback = Arc<dyn Fn(ScanProgress) + Send + Sync>;
+
+/// Snapshot passed to the progress callback after each scanned file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScanProgress {
+    pub files_discovered: usize,
+    pub files_completed: usize,
+    /// The file that was just finished.
+    pub current_path: PathBuf,
+    /// Top-level classes parsed so far.
+    pub classes_found: usize,
+    /// Files that failed to parse so far.
+    pub errors: usize,
+}
+
+/// Per-file event passed to the callback of
+/// [`ClassScanner::scan_directory_with_progress`](crate::ClassScanner::scan_directory_with_progress).
+#[derive(Debug)]
+pub enum ProgressEvent<'a> {
+    /// A worker picked up the file. `index` is its position among the
+    /// `total` files of the scan, in path order.
+    FileStarted { path: PathBuf, index: usize, total: usize },
+    /// The file parsed; `class_count` is its number of top-level classes.
+    FileCompleted { path: PathBuf, class_count: usize },
+    /// The file failed to parse. The error is also recorded in
+    /// [`ScanResult::errors`].
+    FileFailed { path: PathBuf, error: &'a Error },
+}
+
+/// Which files [`ClassScanner::scan_directory`](crate::ClassScanner::scan_directory)
+/// picks up while walking a directory tree.
+///
+/// Serializes to the format read by [`from_file`](Self::from_file); the
+/// progress callback is not part of it.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ScannerConfig {
+    /// File extensions to parse, without the dot and matched ignoring case.
+    /// Text and binarized (raP) configs can be parsed; `.bin` files without
+    /// the raP signature are skipped. A `.pbo` archive is parsed as one file
+    /// holding the configs packed in it, see [`Pbo::config_entries`].
+    pub extensions: Vec<String>,
+    /// How many directory levels below the scanned one to enter; `Some(0)`
+    /// only looks at the directory itself.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_depth: Option<usize>,
+    pub follow_symlinks: bool,
+    /// Glob patterns (`*`, `?`) for file and directory names to skip. The
+    /// default skips hidden entries such as `.git`.
+    pub ignore: Vec<String>,
+    /// Number of files parsed at once; defaults to the available
+    /// parallelism. Results do not depend on it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub threads: Option<usize>,
+    /// Give up on a file that takes longer than this to parse and record
+    /// `Error::Timeout` for it instead. Written as seconds in config files.
+    #[serde(with = "crate::utils::optional_secs", skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<Duration>,
+    #[serde(skip)]
+    on_progress: Option<ProgressCallback>,
+}
+
+impl fmt::Debug for ScannerConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ScannerConfig")
+            .field("extensions", &self.extensions)
+            .field("max_depth", &self.max_depth)
+            .field("follow_symlinks", &self.follow_symlinks)
+            .field("ignore", &self.ignore)
+            .field("threads", &self.threads)
+            .field("timeout", &self.timeout)
+            .field("on_progress", &self.on_progress.is_some())
+            .finish()
+    }
+}
+
+impl Default for ScannerConfig {
+    fn default() -> Self {
+        Self {
+            extensions: ["pbo", "cpp", "hpp", "bin"].map(String::from).to_vec(),
+            max_depth: None,
+            follow_symlinks: false,
+            ignore: vec![".*".to_string()],
+            threads: None,
+            timeout: None,
+            on_progress: None,
+        }
+    }
+}
+
+impl ScannerConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Read a config from a `.json` or `.toml` file; TOML needs the `toml`
+    /// feature. Fields the file leaves out keep their defaults, and unknown
+    /// fields are an error so typos do not go unnoticed.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let invalid = |message: String| Error::ConfigError(format!("{}: {}", path.display(), message));
+        let extension = path.extension().map(|ext| ext.to_string_lossy().to_ascii_lowercase());
+        match extension.as_deref() {
+            Some("json") => serde_json::from_str(&fs::read_to_string(path)?).map_err(|e| invalid(e.to_string())),
+            #[cfg(feature = "toml")]
+            Some("toml") => toml::from_str(&fs::read_to_string(path)?).map_err(|e| invalid(e.to_string())),
+            #[cfg(not(feature = "toml"))]
+            Some("toml") => Err(invalid("TOML configs need the `toml` feature".to_string())),
+            _ => Err(invalid("expected a .json or .toml file".to_string())),
+        }
+    }
+
+    pub fn with_extensions<I, S>(mut self, extensions: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.extensions = extensions.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn with_max_depth(mut self, max_depth: Option<usize>) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    pub fn with_follow_symlinks(mut self, follow: bool) -> Self {
+        self.follow_symlinks = follow;
+        self
+    }
+
+    pub fn with_ignore<I, S>(mut self, patterns: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.ignore = patterns.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn with_threads(mut self, threads: Option<usize>) -> Self {
+        self.threads = threads;
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Report progress after each file of a scan. Files are parsed in
+    /// parallel, so the callback may run on several threads at once and
+    /// `current_path` does not arrive in path order.
+    pub fn on_progress(mut self, callback: Box<dyn Fn(ScanProgress) + Send + Sync>) -> Self {
+        self.on_progress = Some(Arc::from(callback));
+        self
+    }
+
+    pub fn progress_callback(&self) -> Option<&ProgressCallback> {
+        self.on_progress.as_ref()
+    }
+
+    /// Matching files below `dir`, sorted by path.
+    pub fn collect_files(&self, dir: &Path) -> Result<Vec<PathBuf>, Error> {
+        let mut files = Vec::new();
+        self.walk(dir, |path| {
+            // `.bin` is shared with unrelated data; only binarized configs count
+            if self.matches_extension(path) && (!has_extension(path, "bin") || is_rap_file(path)) {
+                files.push(path.to_path_buf());
+            }
+            Ok(())
+        })?;
+
+        // A config.bin is what the game loads, so it wins over the config.cpp
+        // it was built from
+        let binarized: HashSet<PathBuf> = files.iter()
+            .filter(|path| is_named(path, "config.bin"))
+            .filter_map(|path| path.parent().map(Path::to_path_buf))
+            .collect();
+        files.retain(|path| !(is_named(path, "config.cpp") && path.parent().is_some_and(|dir| binarized.contains(dir))));
+
+        files.sort();
+        Ok(files)
+    }
+
+    /// Addon prefixes declared by `$PBOPREFIX$` files below `dir`, keyed by
+    /// the directory holding the file, and by the headers of the PBOs the
+    /// scan picks up, keyed by the archive.
+    pub fn collect_prefixes(&self, dir: &Path) -> Result<BTreeMap<PathBuf, String>, Error> {
+        let mut prefixes = BTreeMap::new();
+        self.walk(dir, |path| {
+            if is_named(path, PREFIX_FILE) {
+                if let Some(prefix) = parse_prefix_file(&fs::read_to_string(path)?) {
+                    prefixes.insert(path.parent().unwrap_or(dir).to_path_buf(), prefix);
+                }
+            } else if pbo::is_pbo_file(path) && self.matches_extension(path) {
+                // A broken archive is reported when it is parsed
+                if let Some(prefix) = Pbo::open(path).ok().and_then(|pbo| pbo.prefix().map(str::to_string)) {
+                    prefixes.insert(path.to_path_buf(), prefix);
+                }
+            }
+            Ok(())
+        })?;
+        Ok(prefixes)
+    }
+
+    /// Call `visit` for every file below `dir` that is not ignored.
+    fn walk(&self, dir: &Path, mut visit: impl FnMut(&Path) -> Result<(), Error>) -> Result<(), Error> {
+        let ignore: Vec<Regex> = self.ignore.iter().map(|p| crate::glob_to_regex(p)).collect();
+        let is_ignored = |path: &Path| path.file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| ignore.iter().any(|pattern| pattern.is_match(name)));
+
+        // Canonical directories already entered, so symlink loops terminate
+        let mut visited = HashSet::new();
+        let mut pending = vec![(dir.to_path_buf(), 0)];
+        while let Some((dir, depth)) = pending.pop() {
+            if !visited.insert(fs::canonicalize(&dir)?) {
+                continue;
+            }
+            for entry in fs::read_dir(&dir)? {
+                let entry = entry?;
+                let path = entry.path();
+                if is_ignored(&path) {
+                    continue;
+                }
+
+                let mut file_type = entry.file_type()?;
+                if file_type.is_symlink() {
+                    if !self.follow_symlinks {
+                        continue;
+                    }
+                    match fs::metadata(&path) {
+                        Ok(metadata) => file_type = metadata.file_type(),
+                        Err(_) => continue, // Dangling link
+                    }
+                }
+
+                if file_type.is_dir() {
+                    if self.max_depth.is_none_or(|max| depth < max) {
+                        pending.push((path, depth + 1));
+                    }
+                } else {
+                    visit(&path)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn matches_extension(&self, path: &Path) -> bool {
+        self.extensions.iter().any(|ext| has_extension(path, ext))
+    }
+}
+
+fn has_extension(path: &Path, extension: &str) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case(extension))
+}
+
+fn is_named(path: &Path, name: &str) -> bool {
+    path.file_name()
+        .and_then(|file_name| file_name.to_str())
+        .is_some_and(|file_name| file_name.eq_ignore_ascii_case(name))
+}
+
+/// Name of the file declaring an unpacked addon's prefix.
+pub const PREFIX_FILE: &str = "$PBOPREFIX$";
+
+/// The prefix from the contents of a `$PBOPREFIX$` file: either the prefix
+/// alone on the first line or a `prefix=...` line among other properties.
+pub fn parse_prefix_file(content: &str) -> Option<String> {
+    let lines = content.lines().map(str::trim).filter(|line| !line.is_empty());
+    let mut first = None;
+    for line in lines {
+        match line.split_once('=') {
+            Some((key, value)) if key.trim().eq_ignore_ascii_case("prefix") => {
+                first = Some(value.trim());
+                break;
+            }
+            Some(_) => {}
+            None => {
+                first.get_or_insert(line);
+            }
+        }
+    }
+    first.map(|prefix| prefix.trim_matches('\\').to_string())
+}
+
+/// Outcome of scanning a directory, keyed by path relative to it.
+#[derive(Debug, Default)]
+pub struct ScanResult {
+    /// Top-level classes of each parsed file.
+    pub classes: BTreeMap<PathBuf, Vec<ClassNode>>,
+    /// Files that failed to parse; they do not stop the scan.
+    pub errors: BTreeMap<PathBuf, Error>,
+    /// Addon prefixes from `$PBOPREFIX$` files, keyed by addon directory,
+    /// and from PBO headers, keyed by archive.
+    pub prefixes: BTreeMap<PathBuf, String>,
+}
+
+impl ScanResult {
+    /// The in-game path of a scanned file, e.g. `x\cba\addons\main\config.cpp`,
+    /// if it lies in an addon with a known prefix.
+    pub fn game_path(&self, file: &Path) -> Option<String> {
+        let (dir, prefix) = self.prefix_of(file)?;
+        let rest = file.strip_prefix(dir).ok()?;
+        let mut path = prefix.clone();
+        for component in rest.components() {
+            path.push('\\');
+            path.push_str(&component.as_os_str().to_string_lossy());
+        }
+        Some(path)
+    }
+
+    /// The innermost addon directory containing `file` that declares a
+    /// prefix, with that prefix.
+    pub fn prefix_of(&self, file: &Path) -> Option<(&PathBuf, &String)> {
+        self.prefixes.iter()
+            .filter(|(dir, _)| file.starts_with(dir))
+            .max_by_key(|(dir, _)| dir.components().count())
+    }
+
+    /// Number of top-level classes across all files.
+    pub fn class_count(&self) -> usize {
+        self.classes.values().map(Vec::len).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ClassScanner;
+    use tempfile::TempDir;
+
+    fn fixture() -> TempDir {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        for dir in ["addons/main/sub", "addons/main/.git", ".hidden"] {
+            fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        fs::write(root.join("addons/main/config.cpp"), "class CfgPatches { class Main {}; };").unwrap();
+        fs::write(root.join("addons/main/sub/weapons.HPP"), "class Rifle {}; class Pistol {};").unwrap();
+        fs::write(root.join("addons/main/.git/config.cpp"), "class Git {};").unwrap();
+        fs::write(root.join(".hidden/config.cpp"), "class Hidden {};").unwrap();
+        fs::write(root.join("addons/broken.cpp"), "class Broken {").unwrap();
+        fs::write(root.join("addons/main.pbo"), pbo::tests::write_pbo(&[], &[("config.cpp", b"class Packed {};")])).unwrap();
+        temp_dir
+    }
+
+    #[test]
+    fn test_scan_directory() {
+        let temp_dir = fixture();
+        let result = ClassScanner::new().scan_directory(temp_dir.path()).unwrap();
+
+        let paths: Vec<&Path> = result.classes.keys().map(PathBuf::as_path).collect();
+        assert_eq!(paths, vec![
+            Path::new("addons/main/config.cpp"),
+            Path::new("addons/main/sub/weapons.HPP"),
+            Path::new("addons/main.pbo"),
+        ]);
+        assert_eq!(result.classes[Path::new("addons/main/sub/weapons.HPP")][1].name, "Pistol");
+        assert_eq!(result.class_count(), 4);
+        assert!(result.errors.contains_key(Path::new("addons/broken.cpp")));
+    }
+
+    #[test]
+    fn test_scan_pbo_and_unpacked_addon() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::create_dir_all(root.join("@mod/addons/loose")).unwrap();
+        fs::write(root.join("@mod/addons/loose/config.cpp"), "class CfgPatches { class Loose {}; };").unwrap();
+        let packed = pbo::tests::write_pbo(&[("prefix", "x\\mod\\addons\\packed")], &[
+            ("config.cpp", b"#include \"script_macros.hpp\"\n#include \"\\x\\mod\\addons\\packed\\sub\\weapons.hpp\"\nclass CfgPatches { class Packed { version = VERSION; }; };"),
+            ("script_macros.hpp", b"#define VERSION 2"),
+            ("sub\\weapons.hpp", b"class CfgWeapons { class Rifle {}; };"),
+            ("data\\icon.paa", b"\x00\x01"),
+        ]);
+        fs::write(root.join("@mod/addons/packed.pbo"), packed).unwrap();
+        fs::write(root.join("@mod/addons/broken.pbo"), [0u8, 1, 2]).unwrap();
+
+        let result = ClassScanner::new().scan_directory(root).unwrap();
+        let paths: Vec<&Path> = result.classes.keys().map(PathBuf::as_path).collect();
+        assert_eq!(paths, vec![Path::new("@mod/addons/loose/config.cpp"), Path::new("@mod/addons/packed.pbo")]);
+
+        let packed = &result.classes[Path::new("@mod/addons/packed.pbo")];
+        assert_eq!(packed.iter().map(|class| class.name.as_str()).collect::<Vec<_>>(), vec!["CfgWeapons", "CfgPatches"]);
+        assert_eq!(packed[1].nested_classes[0].properties["version"].raw_value, "2");
+        let location = packed[1].location.as_ref().unwrap();
+        assert_eq!(location.file.as_deref(), Some(root.join("@mod/addons/packed.pbo/config.cpp").as_path()));
+        assert!(matches!(result.errors[Path::new("@mod/addons/broken.pbo")], Error::ParseError { .. }));
+        assert_eq!(result.prefixes.keys().collect::<Vec<_>>(), vec![Path::new("@mod/addons/packed.pbo")]);
+        assert_eq!(result.game_path(Path::new("@mod/addons/packed.pbo")).as_deref(), Some("x\\mod\\addons\\packed"));
+
+        // Only unpacked addons
+        let config = ScannerConfig::new().with_extensions(["cpp", "hpp"]);
+        let result = ClassScanner::new().with_scanner_config(config).scan_directory(root).unwrap();
+        assert_eq!(result.classes.keys().collect::<Vec<_>>(), vec![Path::new("@mod/addons/loose/config.cpp")]);
+    }
+
+    #[test]
+    fn test_scanner_config_filters() {
+        let temp_dir = fixture();
+        let root = temp_dir.path();
+        let names = |config: ScannerConfig| -> Vec<PathBuf> {
+            config.collect_files(root).unwrap().into_iter()
+                .map(|p| p.strip_prefix(root).unwrap().to_path_buf())
+                .collect()
+        };
+
+        assert_eq!(names(ScannerConfig::new().with_max_depth(Some(1))), vec![
+            PathBuf::from("addons/broken.cpp"),
+            PathBuf::from("addons/main.pbo"),
+        ]);
+        assert_eq!(names(ScannerConfig::new().with_extensions(["cpp"])).len(), 2);
+        assert_eq!(names(ScannerConfig::new().with_extensions(["pbo"])), vec![PathBuf::from("addons/main.pbo")]);
+        assert_eq!(names(ScannerConfig::new().with_ignore(["main", ".git"])), vec![
+            PathBuf::from(".hidden/config.cpp"),
+            PathBuf::from("addons/broken.cpp"),
+            PathBuf::from("addons/main.pbo"),
+        ]);
+    }
+
+    #[test]
+    fn test_scan_progress() {
+        let temp_dir = fixture();
+        let updates = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let sink = Arc::clone(&updates);
+        let config = ScannerConfig::new().on_progress(Box::new(move |progress| sink.lock().unwrap().push(progress)));
+
+        ClassScanner::new().with_scanner_config(config).scan_directory(temp_dir.path()).unwrap();
+
+        let mut updates = updates.lock().unwrap().clone();
+        assert_eq!(updates.len(), 4);
+        updates.sort_by_key(|progress| progress.files_completed);
+        assert!(updates.iter().all(|progress| progress.files_discovered == 4));
+        let mut paths: Vec<&PathBuf> = updates.iter().map(|progress| &progress.current_path).collect();
+        paths.sort();
+        paths.dedup();
+        assert_eq!(paths.len(), 4);
+
+        let last = updates.last().unwrap();
+        assert_eq!((last.files_completed, last.classes_found, last.errors), (4, 4, 1));
+    }
+
+    #[test]
+    fn test_scan_directory_with_progress() {
+        let temp_dir = fixture();
+        let events = std::sync::Mutex::new(Vec::new());
+        let scanner = ClassScanner::new().with_scanner_config(ScannerConfig::new().with_threads(Some(2)));
+        let result = scanner.scan_directory_with_progress(temp_dir.path(), |event| {
+            let path = |path: &Path| path.strip_prefix(temp_dir.path()).unwrap().to_string_lossy().replace('\\', "/");
+            events.lock().unwrap().push(match event {
+                ProgressEvent::FileStarted { path: p, index, total } => format!("started {} {}/{}", path(&p), index, total),
+                ProgressEvent::FileCompleted { path: p, class_count } => format!("completed {} {}", path(&p), class_count),
+                ProgressEvent::FileFailed { path: p, error } => {
+                    assert!(matches!(error, Error::ParseError { .. }), "{}", error);
+                    format!("failed {}", path(&p))
+                }
+            });
+        }).unwrap();
+
+        let mut events = events.into_inner().unwrap();
+        events.sort();
+        assert_eq!(events, vec![
+            "completed addons/main.pbo 1",
+            "completed addons/main/config.cpp 1",
+            "completed addons/main/sub/weapons.HPP 2",
+            "failed addons/broken.cpp",
+            "started addons/broken.cpp 0/4",
+            "started addons/main.pbo 3/4",
+            "started addons/main/config.cpp 1/4",
+            "started addons/main/sub/weapons.HPP 2/4",
+        ]);
+        assert_eq!(result.class_count(), 4);
+    }
+
+    #[test]
+    fn test_scanner_config_from_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("scanner.json");
+        fs::write(&path, r#"{ "extensions": ["cpp"], "threads": 2, "timeout": 1.5 }"#).unwrap();
+
+        let config = ScannerConfig::from_file(&path).unwrap();
+        assert_eq!(config.extensions, vec!["cpp"]);
+        assert_eq!(config.threads, Some(2));
+        assert_eq!(config.timeout, Some(Duration::from_millis(1500)));
+        assert_eq!(config.ignore, ScannerConfig::default().ignore);
+
+        let json = serde_json::to_string(&config).unwrap();
+        let restored: ScannerConfig = serde_json::from_str(&json).unwrap();
+        assert_eq!(format!("{:?}", restored), format!("{:?}", config));
+        assert!(ClassScanner::from_config_file(&path).is_ok());
+
+        fs::write(&path, r#"{ "thread": 2 }"#).unwrap();
+        assert!(matches!(ScannerConfig::from_file(&path), Err(Error::ConfigError(_))));
+        let other = temp_dir.path().join("scanner.yaml");
+        fs::write(&other, "threads: 2").unwrap();
+        assert!(matches!(ScannerConfig::from_file(&other), Err(Error::ConfigError(_))));
+        assert!(matches!(ScannerConfig::from_file(temp_dir.path().join("missing.json")), Err(Error::Io(_))));
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn test_scanner_config_example_toml() {
+        let config = ScannerConfig::from_file(Path::new(env!("CARGO_MANIFEST_DIR")).join("examples/scanner.toml")).unwrap();
+        assert_eq!(config.threads, Some(4));
+        assert_eq!(config.timeout, Some(Duration::from_secs(30)));
+    }
+
+    fn write_addons(root: &Path, count: usize) {
+        for i in 0..count {
+            let dir = root.join(format!("addon_{:03}", i % 20));
+            fs::create_dir_all(&dir).unwrap();
+            let mut content = format!("class CfgPatches {{ class Addon_{} {{ units[] = {{}}; }}; }};\nclass CfgWeapons {{\n", i);
+            for j in 0..50 {
+                content.push_str(&format!("    class Weapon_{}_{} {{ mass = {}; magazines[] = {{\"Mag_{}\"}}; }};\n", i, j, j, j));
+            }
+            if i % 50 == 7 {
+                content.push_str("    class Broken {\n");
+            }
+            content.push_str("};\n");
+            fs::write(dir.join(format!("config_{:03}.cpp", i)), content).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_scan_threads_deterministic() {
+        let temp_dir = TempDir::new().unwrap();
+        write_addons(temp_dir.path(), 40);
+
+        let scan = |threads| ClassScanner::new()
+            .with_scanner_config(ScannerConfig::new().with_threads(Some(threads)))
+            .scan_directory(temp_dir.path())
+            .unwrap();
+        let single = scan(1);
+        let parallel = scan(8);
+
+        assert_eq!(single.classes.len(), 39);
+        assert_eq!(single.classes, parallel.classes);
+        let errors = |result: &ScanResult| result.errors.iter()
+            .map(|(path, error)| (path.clone(), error.to_string()))
+            .collect::<Vec<_>>();
+        assert_eq!(errors(&single), errors(&parallel));
+        assert_eq!(errors(&single)[0].0, Path::new("addon_007/config_007.cpp"));
+    }
+
+    /// Compare scan times across thread counts on a 200-file tree. Run with
+    /// `cargo test --release -- --ignored --nocapture bench_scan_threads`.
+    #[test]
+    #[ignore]
+    fn bench_scan_threads() {
+        let temp_dir = TempDir::new().unwrap();
+        write_addons(temp_dir.path(), 200);
+
+        let mut baseline = None;
+        for threads in [1, 2, 4, 8] {
+            let start = std::time::Instant::now();
+            let result = ClassScanner::new()
+                .with_scanner_config(ScannerConfig::new().with_threads(Some(threads)))
+                .scan_directory(temp_dir.path())
+                .unwrap();
+            println!("{} thread(s): {:?}", threads, start.elapsed());
+            match &baseline {
+                None => baseline = Some(result.classes),
+                Some(classes) => assert_eq!(&result.classes, classes),
+            }
+        }
+    }
+
+    /// A small config and one large enough to take well over a 50 ms
+    /// timeout even in release builds.
+    fn write_timeout_fixture(root: &Path) {
+        fs::write(root.join("small.cpp"), "class Small { value = 1; };").unwrap();
+        let mut content = String::from("class CfgGenerated {\n");
+        for i in 0..100_000 {
+            content.push_str(&format!("    class Generated_{} {{ class Inner {{ value[] = {{{}, \"text\"}}; }}; }};\n", i, i));
+        }
+        content.push_str("};\n");
+        fs::write(root.join("generated.hpp"), content).unwrap();
+    }
+
+    fn scan_with_timeout(root: &Path) -> ScanResult {
+        ClassScanner::new()
+            .with_scanner_config(ScannerConfig::new().with_threads(Some(1)).with_timeout(Some(Duration::from_millis(50))))
+            .scan_directory(root)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_scan_timeout() {
+        let temp_dir = TempDir::new().unwrap();
+        write_timeout_fixture(temp_dir.path());
+
+        let result = scan_with_timeout(temp_dir.path());
+        assert_eq!(result.classes.keys().collect::<Vec<_>>(), vec![Path::new("small.cpp")]);
+        assert_eq!(result.errors.len(), 1);
+        assert!(matches!(&result.errors[Path::new("generated.hpp")], Error::Timeout(path) if path.ends_with("generated.hpp")));
+    }
+
+    /// How long a scan with a 50 ms timeout takes to give up on a file
+    /// that would parse for much longer. Run with
+    /// `cargo test --release -- --ignored --nocapture bench_scan_timeout`.
+    #[test]
+    #[ignore]
+    fn bench_scan_timeout() {
+        let temp_dir = TempDir::new().unwrap();
+        write_timeout_fixture(temp_dir.path());
+
+        let start = std::time::Instant::now();
+        let result = scan_with_timeout(temp_dir.path());
+        println!("Scan with a 50 ms timeout returned after {:?}", start.elapsed());
+        assert_eq!(result.errors.len(), 1);
+    }
+
+    #[test]
+    fn test_parse_prefix_file() {
+        assert_eq!(parse_prefix_file("x\\cba\\addons\\main\n").as_deref(), Some("x\\cba\\addons\\main"));
+        assert_eq!(parse_prefix_file("\\z\\ace\\addons\\common").as_deref(), Some("z\\ace\\addons\\common"));
+        assert_eq!(
+            parse_prefix_file("version=1.0\r\nprefix = x\\cba\\addons\\xeh\r\n").as_deref(),
+            Some("x\\cba\\addons\\xeh")
+        );
+        assert_eq!(parse_prefix_file("\n\n"), None);
+    }
+
+    #[test]
+    fn test_scan_with_addon_prefixes() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        let main = root.join("addons/main");
+        let other = root.join("addons/other");
+        fs::create_dir_all(&main).unwrap();
+        fs::create_dir_all(&other).unwrap();
+        fs::write(main.join(PREFIX_FILE), "x\\cba\\addons\\main\n").unwrap();
+        fs::write(main.join("script_macros.hpp"), "#define VERSION 3\n").unwrap();
+        fs::write(other.join(PREFIX_FILE), "prefix=x\\cba\\addons\\other\n").unwrap();
+        fs::write(other.join("config.cpp"), "#include \"\\x\\cba\\addons\\main\\script_macros.hpp\"\nclass Other { version = VERSION; };\n").unwrap();
+
+        let result = ClassScanner::new().scan_directory(root).unwrap();
+        assert!(result.errors.is_empty());
+        let config = Path::new("addons/other/config.cpp");
+        assert_eq!(result.classes[config][0].properties["version"].raw_value, "3");
+
+        assert_eq!(result.prefixes[Path::new("addons/main")], "x\\cba\\addons\\main");
+        assert_eq!(result.game_path(config).as_deref(), Some("x\\cba\\addons\\other\\config.cpp"));
+        assert_eq!(result.game_path(Path::new("loose.cpp")), None);
+
+        // The same include through a user-supplied include root
+        let p_drive = root.join("p");
+        fs::create_dir_all(p_drive.join("x/cba/addons/main")).unwrap();
+        fs::write(p_drive.join("x/cba/addons/main/script_macros.hpp"), "#define VERSION 4\n").unwrap();
+        let classes = ClassScanner::new().with_include_path(&p_drive).parse_file(other.join("config.cpp")).unwrap();
+        assert_eq!(classes[1].properties["version"].raw_value, "4");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_scanner_config_symlinks() {
+        let temp_dir = fixture();
+        let root = temp_dir.path();
+        std::os::unix::fs::symlink(root.join("addons/main/sub"), root.join("linked")).unwrap();
+        // A loop back to the root must not be walked forever
+        std::os::unix::fs::symlink(root, root.join("addons/main/sub/loop")).unwrap();
+
+        let count = |config: ScannerConfig| config.collect_files(root).unwrap().len();
+        assert_eq!(count(ScannerConfig::new()), 4);
+        assert_eq!(count(ScannerConfig::new().with_follow_symlinks(true)), 4);
+    }
+}
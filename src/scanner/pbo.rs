@@ -0,0 +1,369 @@
+use std::borrow::Cow;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use crate::error::{Error, SourceLocation};
+
+/// Packing method of the header entry holding the archive's properties.
+const PROPERTIES_METHOD: u32 = 0x5665_7273; // "Vers"
+/// Packing method of an LZSS-compressed entry.
+const COMPRESSED_METHOD: u32 = 0x4370_7273; // "Cprs"
+
+/// Extensions of the files a text config in an archive may include.
+pub const SOURCE_EXTENSIONS: &[&str] = &["cpp", "hpp", "h", "hh", "inc"];
+
+/// Whether `path` names a PBO archive. PBOs have no signature, so this
+/// goes by the extension.
+pub fn is_pbo_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("pbo"))
+}
+
+/// A file packed in a [`Pbo`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PboEntry {
+    /// Path inside the archive as stored, with `\` separators.
+    pub name: String,
+    pub compressed: bool,
+    /// Size once unpacked.
+    pub size: usize,
+    pub timestamp: u32,
+    offset: u64,
+    stored_size: usize,
+}
+
+impl PboEntry {
+    /// [`name`](Self::name) as a relative path.
+    pub fn path(&self) -> PathBuf {
+        self.name.split('\\').filter(|part| !part.is_empty()).collect()
+    }
+
+    fn file_name(&self) -> &str {
+        self.name.rsplit('\\').next().unwrap_or(&self.name)
+    }
+
+    fn dir(&self) -> &str {
+        self.name.rsplit_once('\\').map_or("", |(dir, _)| dir)
+    }
+
+    fn has_extension(&self, extensions: &[&str]) -> bool {
+        self.file_name().rsplit_once('.')
+            .is_some_and(|(_, ext)| extensions.iter().any(|e| e.eq_ignore_ascii_case(ext)))
+    }
+}
+
+enum Contents {
+    File(PathBuf),
+    Bytes(Vec<u8>),
+}
+
+/// A PBO archive, the packed form addons ship in.
+///
+/// Layout: a header of entries, each a NUL-terminated name followed by five
+/// little-endian words (packing method, unpacked size, reserved, timestamp,
+/// stored size). A leading entry with an empty name and the `Vers` method
+/// is followed by NUL-terminated key/value strings up to an empty key, and
+/// an entry with an empty name ends the header. The files' data follows in
+/// header order. A trailing checksum is not read.
+///
+/// Only the header is read up front; [`read`](Self::read) loads one file.
+pub struct Pbo {
+    /// Header properties, such as `prefix`, in archive order.
+    pub properties: Vec<(String, String)>,
+    pub entries: Vec<PboEntry>,
+    contents: Contents,
+    file_path: Option<PathBuf>,
+}
+
+impl Pbo {
+    /// Read the header of the archive at `path`.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let file = File::open(path)?;
+        let len = file.metadata()?.len();
+        let mut header = HeaderReader::new(BufReader::new(file), Some(path.to_path_buf()));
+        let (properties, entries) = header.read(len)?;
+        Ok(Self { properties, entries, contents: Contents::File(path.to_path_buf()), file_path: Some(path.to_path_buf()) })
+    }
+
+    /// An archive held in memory.
+    pub fn from_bytes(data: Vec<u8>) -> Result<Self, Error> {
+        let mut header = HeaderReader::new(&data[..], None);
+        let (properties, entries) = header.read(data.len() as u64)?;
+        Ok(Self { properties, entries, contents: Contents::Bytes(data), file_path: None })
+    }
+
+    /// Value of the header property `key`, matched ignoring case.
+    pub fn property(&self, key: &str) -> Option<&str> {
+        self.properties.iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(key))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// The addon prefix from the header, e.g. `x\cba\addons\main`.
+    pub fn prefix(&self) -> Option<&str> {
+        self.property("prefix")
+            .map(|prefix| prefix.trim_matches('\\'))
+            .filter(|prefix| !prefix.is_empty())
+    }
+
+    /// The entry stored as `name`, matched ignoring case and with either
+    /// separator.
+    pub fn entry(&self, name: &str) -> Option<&PboEntry> {
+        let name = name.replace('/', "\\");
+        self.entries.iter().find(|entry| entry.name.eq_ignore_ascii_case(&name))
+    }
+
+    /// The configs the game would load from this archive: every
+    /// `config.bin`, and every `config.cpp` without one beside it, in
+    /// archive order.
+    pub fn config_entries(&self) -> Vec<&PboEntry> {
+        let is_named = |entry: &PboEntry, name: &str| entry.file_name().eq_ignore_ascii_case(name);
+        self.entries.iter()
+            .filter(|entry| {
+                is_named(entry, "config.bin")
+                    || is_named(entry, "config.cpp") && !self.entries.iter()
+                        .any(|other| is_named(other, "config.bin") && other.dir().eq_ignore_ascii_case(entry.dir()))
+            })
+            .collect()
+    }
+
+    /// Entries with one of [`SOURCE_EXTENSIONS`].
+    pub fn source_entries(&self) -> impl Iterator<Item = &PboEntry> {
+        self.entries.iter().filter(|entry| entry.has_extension(SOURCE_EXTENSIONS))
+    }
+
+    /// The unpacked contents of `entry`.
+    pub fn read(&self, entry: &PboEntry) -> Result<Cow<'_, [u8]>, Error> {
+        let stored: Cow<'_, [u8]> = match &self.contents {
+            Contents::Bytes(data) => {
+                let start = entry.offset as usize;
+                Cow::Borrowed(data.get(start..start + entry.stored_size).ok_or_else(|| self.error(entry, "data out of range"))?)
+            }
+            Contents::File(path) => {
+                let mut file = File::open(path)?;
+                file.seek(SeekFrom::Start(entry.offset))?;
+                let mut data = vec![0; entry.stored_size];
+                file.read_exact(&mut data)?;
+                Cow::Owned(data)
+            }
+        };
+        if !entry.compressed {
+            return Ok(stored);
+        }
+        decompress(&stored, entry.size)
+            .map(Cow::Owned)
+            .ok_or_else(|| self.error(entry, "corrupt compressed data"))
+    }
+
+    fn error(&self, entry: &PboEntry, message: &str) -> Error {
+        Error::ParseError {
+            message: format!("Invalid PBO entry {}: {}", entry.name, message),
+            location: SourceLocation::new(self.file_path.clone(), 0, 0),
+        }
+    }
+}
+
+/// Header properties and entries of an archive.
+type Header = (Vec<(String, String)>, Vec<PboEntry>);
+
+/// Reads the header from the start of an archive, tracking the position for
+/// error messages and the offset the data starts at.
+struct HeaderReader<R> {
+    reader: R,
+    position: u64,
+    file_path: Option<PathBuf>,
+}
+
+impl<R: BufRead> HeaderReader<R> {
+    fn new(reader: R, file_path: Option<PathBuf>) -> Self {
+        Self { reader, position: 0, file_path }
+    }
+
+    /// Properties and entries of an archive of `len` bytes.
+    fn read(&mut self, len: u64) -> Result<Header, Error> {
+        let mut properties = Vec::new();
+        let mut entries = Vec::new();
+        loop {
+            let name = self.read_string()?;
+            let method = self.read_u32()?;
+            let size = self.read_u32()? as usize;
+            let _reserved = self.read_u32()?;
+            let timestamp = self.read_u32()?;
+            let stored_size = self.read_u32()? as usize;
+
+            if !name.is_empty() {
+                entries.push(PboEntry {
+                    name,
+                    compressed: method == COMPRESSED_METHOD,
+                    // Unpacked sizes are only filled in for compressed files
+                    size: if method == COMPRESSED_METHOD { size } else { stored_size },
+                    timestamp,
+                    offset: 0,
+                    stored_size,
+                });
+            } else if method == PROPERTIES_METHOD {
+                loop {
+                    let key = self.read_string()?;
+                    if key.is_empty() {
+                        break;
+                    }
+                    properties.push((key, self.read_string()?));
+                }
+            } else {
+                break;
+            }
+        }
+
+        let mut offset = self.position;
+        for entry in &mut entries {
+            entry.offset = offset;
+            offset += entry.stored_size as u64;
+            if offset > len {
+                return Err(self.error(&format!("data of {} runs past the end of the archive", entry.name)));
+            }
+        }
+        Ok((properties, entries))
+    }
+
+    fn read_u32(&mut self) -> Result<u32, Error> {
+        let mut bytes = [0; 4];
+        self.reader.read_exact(&mut bytes).map_err(|_| self.error("unexpected end of header"))?;
+        self.position += 4;
+        Ok(u32::from_le_bytes(bytes))
+    }
+
+    fn read_string(&mut self) -> Result<String, Error> {
+        let mut bytes = Vec::new();
+        self.reader.read_until(0, &mut bytes)?;
+        self.position += bytes.len() as u64;
+        if bytes.pop() != Some(0) {
+            return Err(self.error("unterminated string"));
+        }
+        Ok(String::from_utf8_lossy(&bytes).into_owned())
+    }
+
+    fn error(&self, message: &str) -> Error {
+        Error::ParseError {
+            message: format!("Invalid PBO header at byte {}: {}", self.position, message),
+            location: SourceLocation::new(self.file_path.clone(), 0, 0),
+        }
+    }
+}
+
+/// Unpack LZSS data to `size` bytes.
+///
+/// Each flag byte covers the next eight items, lowest bit first: a set bit
+/// is a literal byte, a clear one a two-byte back reference of a 12-bit
+/// distance and a length of 3 to 18. Positions before the start of the
+/// output read as spaces.
+fn decompress(input: &[u8], size: usize) -> Option<Vec<u8>> {
+    // A reference expands 2 bytes to at most 18
+    let mut output = Vec::with_capacity(size.min(input.len().saturating_mul(9)));
+    let mut bytes = input.iter().copied();
+    while output.len() < size {
+        let mut flags = bytes.next()?;
+        for _ in 0..8 {
+            if output.len() >= size {
+                break;
+            }
+            if flags & 1 != 0 {
+                output.push(bytes.next()?);
+            } else {
+                let low = bytes.next()? as usize;
+                let high = bytes.next()? as usize;
+                let distance = low | ((high & 0xf0) << 4);
+                let length = (high & 0x0f) + 3;
+                let start = output.len() as isize - distance as isize;
+                for position in start..start + length as isize {
+                    let byte = match usize::try_from(position) {
+                        Ok(position) => *output.get(position)?,
+                        Err(_) => b' ',
+                    };
+                    output.push(byte);
+                }
+            }
+            flags >>= 1;
+        }
+    }
+    output.truncate(size);
+    Some(output)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// An uncompressed archive with header `properties` holding `files`.
+    pub(crate) fn write_pbo(properties: &[(&str, &str)], files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut data = vec![0];
+        data.extend_from_slice(&PROPERTIES_METHOD.to_le_bytes());
+        data.extend_from_slice(&[0; 16]);
+        for (key, value) in properties {
+            data.extend_from_slice(format!("{}\0{}\0", key, value).as_bytes());
+        }
+        data.push(0);
+        for (name, contents) in files {
+            data.extend_from_slice(name.as_bytes());
+            data.push(0);
+            data.extend_from_slice(&[0; 12]);
+            data.extend_from_slice(&7u32.to_le_bytes());
+            data.extend_from_slice(&(contents.len() as u32).to_le_bytes());
+        }
+        data.extend_from_slice(&[0; 21]);
+        for (_, contents) in files {
+            data.extend_from_slice(contents);
+        }
+        // Checksum
+        data.push(0);
+        data.extend_from_slice(&[0; 20]);
+        data
+    }
+
+    #[test]
+    fn test_read_pbo() {
+        let data = write_pbo(&[("prefix", "\\x\\cba\\addons\\main"), ("version", "1")], &[
+            ("config.cpp", b"class A {};"),
+            ("sub\\config.cpp", b"class B {};"),
+            ("sub\\config.bin", b"\0raP"),
+            ("script_macros.hpp", b"#define X 1"),
+            ("data\\icon.paa", b"\xff\xfe"),
+        ]);
+        let pbo = Pbo::from_bytes(data).unwrap();
+
+        assert_eq!(pbo.prefix(), Some("x\\cba\\addons\\main"));
+        assert_eq!(pbo.property("VERSION"), Some("1"));
+        assert_eq!(pbo.entries.len(), 5);
+        assert_eq!(pbo.entries[1].path(), Path::new("sub/config.cpp"));
+        assert_eq!(pbo.entries[0].timestamp, 7);
+        let configs: Vec<&str> = pbo.config_entries().iter().map(|entry| entry.name.as_str()).collect();
+        assert_eq!(configs, vec!["config.cpp", "sub\\config.bin"]);
+        let sources: Vec<&str> = pbo.source_entries().map(|entry| entry.name.as_str()).collect();
+        assert_eq!(sources, vec!["config.cpp", "sub\\config.cpp", "script_macros.hpp"]);
+
+        let macros = pbo.entry("Script_Macros.hpp").unwrap();
+        assert_eq!(&pbo.read(macros).unwrap()[..], b"#define X 1");
+        assert_eq!(&pbo.read(pbo.entry("sub/config.cpp").unwrap()).unwrap()[..], b"class B {};");
+    }
+
+    #[test]
+    fn test_read_invalid_pbo() {
+        assert!(matches!(Pbo::from_bytes(vec![0, 1, 2]), Err(Error::ParseError { .. })));
+        let mut data = write_pbo(&[], &[("config.cpp", b"class A {};")]);
+        data.truncate(data.len() - 30);
+        assert!(matches!(Pbo::from_bytes(data), Err(Error::ParseError { .. })));
+    }
+
+    #[test]
+    fn test_decompress() {
+        // "abcabcabc x": three literals, a reference 3 back of length 6,
+        // then " x" as literals
+        let input = [0b0011_0111, b'a', b'b', b'c', 0x03, 0x03, b' ', b'x'];
+        assert_eq!(decompress(&input, 11).unwrap(), b"abcabcabc x");
+        // A reference before the start reads spaces
+        assert_eq!(decompress(&[0b10, 0x02, 0x00, b'!'], 4).unwrap(), b"   !");
+        assert_eq!(decompress(&[0b1, b'a'], 4), None);
+        assert_eq!(decompress(&[0b0, 0x00, 0x00], 3), None);
+    }
+}
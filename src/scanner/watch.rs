@@ -0,0 +1,126 @@
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+use std::thread::JoinHandle;
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use crate::ast::ClassNode;
+use crate::error::Error;
+use crate::ClassScanner;
+
+/// A watched file that changed, parsed again.
+#[derive(Debug)]
+pub struct WatchEvent {
+    pub path: PathBuf,
+    /// What [`ClassScanner::parse_file`] returned for the new content.
+    pub result: Result<Vec<ClassNode>, Error>,
+}
+
+/// Keeps a [`ClassScanner::watch`] running. Dropping it stops watching too.
+pub struct WatchHandle {
+    watcher: Option<RecommendedWatcher>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl WatchHandle {
+    /// Stop watching and wait for a callback in progress to return.
+    pub fn stop(mut self) {
+        self.shutdown();
+    }
+
+    fn shutdown(&mut self) {
+        // Dropping the watcher closes the channel, which ends the thread
+        drop(self.watcher.take());
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for WatchHandle {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+impl ClassScanner {
+    /// Watch `path`, a file or a directory, and call `callback` from a
+    /// background thread each time a file the scanner config accepts is
+    /// created or modified, with the file parsed again.
+    ///
+    /// Changes that arrive together are reported once per file.
+    pub fn watch<F>(&self, path: &Path, callback: F) -> Result<WatchHandle, Error>
+    where
+        F: Fn(WatchEvent) + Send + 'static,
+    {
+        let (sender, receiver) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(sender).map_err(|e| Error::WatchError(e.to_string()))?;
+        watcher.watch(path, RecursiveMode::Recursive)
+            .map_err(|e| Error::WatchError(format!("{}: {}", path.display(), e)))?;
+
+        let scanner = self.clone();
+        let thread = std::thread::spawn(move || {
+            while let Ok(event) = receiver.recv() {
+                for path in scanner.changed_files(event, &receiver) {
+                    let result = scanner.parse_file(&path);
+                    callback(WatchEvent { path, result });
+                }
+            }
+        });
+
+        Ok(WatchHandle { watcher: Some(watcher), thread: Some(thread) })
+    }
+
+    /// Files created or modified by `first` and any events already queued
+    /// behind it, each once, in the order they were first reported.
+    fn changed_files(&self, first: notify::Result<notify::Event>, queued: &Receiver<notify::Result<notify::Event>>) -> Vec<PathBuf> {
+        let mut paths: Vec<PathBuf> = Vec::new();
+        for event in std::iter::once(first).chain(queued.try_iter()) {
+            let Ok(event) = event else {
+                continue;
+            };
+            if !matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+                continue;
+            }
+            for path in event.paths {
+                if path.is_file() && self.scanner_config.matches_extension(&path) && !paths.contains(&path) {
+                    paths.push(path);
+                }
+            }
+        }
+        paths
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+    use std::time::Duration;
+    use tempfile::TempDir;
+    use crate::ClassScanner;
+
+    #[test]
+    fn test_watch_reparses_changed_file() {
+        let dir = TempDir::new().unwrap();
+        let config = dir.path().join("config.cpp");
+        std::fs::write(&config, "class A {};").unwrap();
+
+        let (sender, receiver) = mpsc::channel();
+        let handle = ClassScanner::new().watch(dir.path(), move |event| {
+            let names: Vec<String> = event.result.unwrap().iter().skip(1).map(|class| class.name.clone()).collect();
+            let _ = sender.send((event.path, names));
+        }).unwrap();
+        // Give the watcher time to register before writing
+        std::thread::sleep(Duration::from_millis(200));
+
+        std::fs::write(&config, "class A {}; class B {};").unwrap();
+        std::fs::write(dir.path().join("notes.txt"), "not a config").unwrap();
+        let (path, names) = receiver.recv_timeout(Duration::from_secs(5)).expect("no watch event");
+        assert_eq!(path.file_name().unwrap(), "config.cpp");
+        assert_eq!(names, vec!["A", "B"]);
+
+        handle.stop();
+        // Stopping dropped the callback, and with it the sender
+        while let Ok((path, _)) = receiver.recv_timeout(Duration::from_secs(1)) {
+            assert_eq!(path.file_name().unwrap(), "config.cpp");
+        }
+    }
+}
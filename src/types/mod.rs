@@ -1,7 +1,12 @@
+mod schema;
+
+pub use schema::{ClassSchema, PropertySchema, ValidationError, ValidationErrorKind};
+
+use serde::{Deserialize, Serialize};
 use crate::error::Error;
 use crate::ast::PropertyType;
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum TypeDefinition {
     String,
     Number,
@@ -98,6 +103,9 @@ impl TypeValidator {
                     Err(Error::TypeError(format!("Cannot convert '{}' to object", value)))
                 }
             }
+            PropertyType::Deleted => {
+                Err(Error::TypeError(format!("Cannot convert '{}' to a deleted property", value)))
+            }
         }
     }
 }
\ No newline at end of file
@@ -0,0 +1,335 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use super::{TypeDefinition, TypeValidator};
+use crate::ast::{ClassNode, PropertyNode, PropertyType};
+use crate::error::SourceLocation;
+
+/// Expected properties of a class, keyed by property name.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ClassSchema {
+    #[serde(default)]
+    pub properties: HashMap<String, PropertySchema>,
+    /// `regex_pattern`s compiled when the schema was built or loaded, by
+    /// property name. Patterns changed through `properties` afterwards are
+    /// compiled during validation instead.
+    #[serde(skip)]
+    patterns: HashMap<String, Regex>,
+}
+
+impl PartialEq for ClassSchema {
+    fn eq(&self, other: &Self) -> bool {
+        self.properties == other.properties
+    }
+}
+
+/// Constraints on a single property. For arrays, `min`, `max`,
+/// `allowed_values` and `regex_pattern` apply to each element.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PropertySchema {
+    #[serde(default)]
+    pub required: bool,
+    pub value_type: TypeDefinition,
+    #[serde(default)]
+    pub min: Option<f64>,
+    #[serde(default)]
+    pub max: Option<f64>,
+    #[serde(default)]
+    pub allowed_values: Option<Vec<String>>,
+    #[serde(default)]
+    pub regex_pattern: Option<String>,
+}
+
+impl PropertySchema {
+    pub fn new(value_type: TypeDefinition) -> Self {
+        Self {
+            required: false,
+            value_type,
+            min: None,
+            max: None,
+            allowed_values: None,
+            regex_pattern: None,
+        }
+    }
+
+    pub fn required(mut self) -> Self {
+        self.required = true;
+        self
+    }
+
+    pub fn with_range(mut self, min: Option<f64>, max: Option<f64>) -> Self {
+        self.min = min;
+        self.max = max;
+        self
+    }
+
+    pub fn with_allowed_values<I, S>(mut self, values: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.allowed_values = Some(values.into_iter().map(Into::into).collect());
+        self
+    }
+
+    pub fn with_pattern(mut self, pattern: impl Into<String>) -> Self {
+        self.regex_pattern = Some(pattern.into());
+        self
+    }
+}
+
+impl ClassSchema {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add `schema` for the property `name`, compiling its pattern. An
+    /// invalid pattern is reported by validation as
+    /// [`ValidationErrorKind::InvalidSchema`].
+    pub fn with_property(mut self, name: impl Into<String>, schema: PropertySchema) -> Self {
+        let name = name.into();
+        match schema.regex_pattern.as_deref().map(Regex::new) {
+            Some(Ok(regex)) => self.patterns.insert(name.clone(), regex),
+            _ => self.patterns.remove(&name),
+        };
+        self.properties.insert(name, schema);
+        self
+    }
+
+    /// Parse a schema, compiling its patterns so an invalid one is an error
+    /// here rather than during validation.
+    pub fn from_json(s: &str) -> Result<Self, serde_json::Error> {
+        let mut schema: Self = serde_json::from_str(s)?;
+        for (name, property) in &schema.properties {
+            if let Some(pattern) = &property.regex_pattern {
+                let regex = Regex::new(pattern)
+                    .map_err(|e| <serde_json::Error as serde::de::Error>::custom(format!("pattern of '{}': {}", name, e)))?;
+                schema.patterns.insert(name.clone(), regex);
+            }
+        }
+        Ok(schema)
+    }
+
+    /// The compiled `regex_pattern` of the property `name`, compiling it
+    /// now if it was not compiled with the schema or has changed since.
+    fn pattern(&self, name: &str) -> Option<Result<Cow<'_, Regex>, regex::Error>> {
+        let pattern = self.properties.get(name)?.regex_pattern.as_deref()?;
+        Some(match self.patterns.get(name) {
+            Some(regex) if regex.as_str() == pattern => Ok(Cow::Borrowed(regex)),
+            _ => Regex::new(pattern).map(Cow::Owned),
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ValidationErrorKind {
+    MissingProperty,
+    TypeMismatch,
+    OutOfRange,
+    NotAllowed,
+    PatternMismatch,
+    /// The schema itself is unusable, e.g. an invalid regex.
+    InvalidSchema,
+}
+
+/// A single schema violation.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ValidationError {
+    pub class_name: String,
+    pub property: String,
+    pub kind: ValidationErrorKind,
+    pub message: String,
+    pub location: Option<SourceLocation>,
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}: {}", self.class_name, self.property, self.message)?;
+        if let Some(location) = &self.location {
+            write!(f, " ({})", location)?;
+        }
+        Ok(())
+    }
+}
+
+impl TypeValidator {
+    /// Check `class` against `schema`, returning every violation found.
+    ///
+    /// Property names are matched case-insensitively; errors are ordered by
+    /// property name.
+    pub fn validate_class(class: &ClassNode, schema: &ClassSchema) -> Vec<ValidationError> {
+        let mut names: Vec<&String> = schema.properties.keys().collect();
+        names.sort();
+
+        let mut errors = Vec::new();
+        for name in names {
+            let property_schema = &schema.properties[name];
+            let error = |kind, message: String, property: Option<&PropertyNode>| ValidationError {
+                class_name: class.name.clone(),
+                property: name.clone(),
+                kind,
+                message,
+                location: property.and_then(|p| p.location.clone()).or_else(|| class.location.clone()),
+            };
+
+            let property = class.properties.values()
+                .find(|p| p.name.eq_ignore_ascii_case(name) && !p.is_deleted());
+            let Some(property) = property else {
+                if property_schema.required {
+                    errors.push(error(ValidationErrorKind::MissingProperty, "required property is missing".to_string(), None));
+                }
+                continue;
+            };
+
+            let (element_type, values) = match (&property_schema.value_type, property.value_type == PropertyType::Array) {
+                (TypeDefinition::Array(inner), true) => (inner.as_ref(), property.array_values.clone()),
+                (TypeDefinition::Array(_), false) => {
+                    errors.push(error(ValidationErrorKind::TypeMismatch, "expected an array".to_string(), Some(property)));
+                    continue;
+                }
+                (_, true) => {
+                    errors.push(error(ValidationErrorKind::TypeMismatch, "unexpected array".to_string(), Some(property)));
+                    continue;
+                }
+                (expected, false) => (expected, vec![property.raw_value.clone()]),
+            };
+
+            let pattern = match schema.pattern(name).transpose() {
+                Ok(pattern) => pattern,
+                Err(e) => {
+                    errors.push(error(ValidationErrorKind::InvalidSchema, format!("invalid pattern: {}", e), Some(property)));
+                    None
+                }
+            };
+
+            for value in &values {
+                if let Err(e) = Self::validate_value(value, element_type) {
+                    errors.push(error(ValidationErrorKind::TypeMismatch, e.to_string(), Some(property)));
+                    continue;
+                }
+
+                if let Ok(number) = value.parse::<f64>() {
+                    let below = property_schema.min.is_some_and(|min| number < min);
+                    let above = property_schema.max.is_some_and(|max| number > max);
+                    if below || above {
+                        errors.push(error(
+                            ValidationErrorKind::OutOfRange,
+                            format!("{} is outside {}..={}", value, bound(property_schema.min), bound(property_schema.max)),
+                            Some(property),
+                        ));
+                    }
+                }
+
+                if let Some(allowed) = &property_schema.allowed_values {
+                    if !allowed.iter().any(|a| a == value) {
+                        errors.push(error(ValidationErrorKind::NotAllowed, format!("'{}' is not an allowed value", value), Some(property)));
+                    }
+                }
+
+                if let Some(pattern) = &pattern {
+                    if !pattern.is_match(value) {
+                        errors.push(error(
+                            ValidationErrorKind::PatternMismatch,
+                            format!("'{}' does not match /{}/", value, pattern.as_str()),
+                            Some(property),
+                        ));
+                    }
+                }
+            }
+        }
+        errors
+    }
+}
+
+fn bound(value: Option<f64>) -> String {
+    value.map(|v| v.to_string()).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ClassScanner;
+
+    fn parse_class(input: &str) -> ClassNode {
+        ClassScanner::new().parse_string(input).unwrap().remove(1)
+    }
+
+    #[test]
+    fn test_validate_class_collects_all_errors() {
+        let class = parse_class(r#"
+            class Rifle {
+                mass = 250;
+                displayName = "rifle";
+                type = 3;
+                magazines[] = {"30Rnd_556", "bad mag", 5};
+            };
+        "#);
+        let schema = ClassSchema::new()
+            .with_property("scope", PropertySchema::new(TypeDefinition::Number).required())
+            .with_property("mass", PropertySchema::new(TypeDefinition::Number).with_range(Some(0.0), Some(100.0)))
+            .with_property("displayName", PropertySchema::new(TypeDefinition::String).with_pattern("^[A-Z]"))
+            .with_property("type", PropertySchema::new(TypeDefinition::Number).with_allowed_values(["1", "2"]))
+            .with_property("magazines", PropertySchema::new(TypeDefinition::Array(Box::new(TypeDefinition::String)))
+                .with_pattern(r"^\w+$"));
+
+        let errors = TypeValidator::validate_class(&class, &schema);
+        let kinds: Vec<(&str, &ValidationErrorKind)> = errors.iter().map(|e| (e.property.as_str(), &e.kind)).collect();
+        assert_eq!(kinds, vec![
+            ("displayName", &ValidationErrorKind::PatternMismatch),
+            ("magazines", &ValidationErrorKind::PatternMismatch),
+            ("mass", &ValidationErrorKind::OutOfRange),
+            ("scope", &ValidationErrorKind::MissingProperty),
+            ("type", &ValidationErrorKind::NotAllowed),
+        ]);
+        assert_eq!(errors[2].location.as_ref().unwrap().line, 3);
+        assert!(errors[2].to_string().starts_with("Rifle.mass: 250 is outside 0..=100"));
+
+        let valid = ClassSchema::new().with_property("MASS", PropertySchema::new(TypeDefinition::Number).required());
+        assert!(TypeValidator::validate_class(&class, &valid).is_empty());
+    }
+
+    #[test]
+    fn test_schema_from_json() {
+        let schema = ClassSchema::from_json(r#"{
+            "properties": {
+                "scope": { "required": true, "value_type": "Number", "min": 0, "max": 2 },
+                "author": { "value_type": "String", "allowed_values": ["Bohemia Interactive"] },
+                "weapons": { "value_type": { "Array": "String" }, "regex_pattern": "^[A-Za-z_]+$" }
+            }
+        }"#).unwrap();
+
+        assert_eq!(schema.properties["scope"], PropertySchema::new(TypeDefinition::Number)
+            .required()
+            .with_range(Some(0.0), Some(2.0)));
+        assert_eq!(schema.properties["weapons"].value_type, TypeDefinition::Array(Box::new(TypeDefinition::String)));
+
+        let class = parse_class(r#"class Car { scope = 3; weapons = "Horn"; };"#);
+        let errors = TypeValidator::validate_class(&class, &schema);
+        let kinds: Vec<&ValidationErrorKind> = errors.iter().map(|e| &e.kind).collect();
+        assert_eq!(kinds, vec![&ValidationErrorKind::OutOfRange, &ValidationErrorKind::TypeMismatch]);
+
+        let invalid = ClassSchema::from_json(r#"{ "properties": { "model": { "value_type": "String", "regex_pattern": "(" } } }"#);
+        assert!(invalid.unwrap_err().to_string().contains("pattern of 'model'"));
+    }
+
+    #[test]
+    fn test_schema_patterns() {
+        let class = parse_class(r#"class Car { model = "car.p3d"; };"#);
+        let kinds = |schema: &ClassSchema| -> Vec<ValidationErrorKind> {
+            TypeValidator::validate_class(&class, schema).into_iter().map(|e| e.kind).collect()
+        };
+
+        let mut schema = ClassSchema::new()
+            .with_property("model", PropertySchema::new(TypeDefinition::String).with_pattern(r"\.p3d$"));
+        assert!(kinds(&schema).is_empty());
+
+        // Editing a pattern after the schema was built still takes effect
+        schema.properties.get_mut("model").unwrap().regex_pattern = Some(r"\.paa$".to_string());
+        assert_eq!(kinds(&schema), vec![ValidationErrorKind::PatternMismatch]);
+
+        let invalid = ClassSchema::new().with_property("model", PropertySchema::new(TypeDefinition::String).with_pattern("("));
+        assert_eq!(kinds(&invalid), vec![ValidationErrorKind::InvalidSchema]);
+    }
+}
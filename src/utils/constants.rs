@@ -15,7 +15,23 @@ lazy_static! {
     ).unwrap();
 
     pub static ref DEFINE_PATTERN: Regex = Regex::new(
-        r"#define\s+(\w+)\s+(.+)"
+        r"#define\s+(\w+)(?:\s+(.*))?$"
+    ).unwrap();
+
+    pub static ref FUNCTION_DEFINE_PATTERN: Regex = Regex::new(
+        r"#define\s+(\w+)\(([^)]*)\)(?:\s*(.*))?$"
+    ).unwrap();
+
+    pub static ref UNDEF_PATTERN: Regex = Regex::new(
+        r"#undef\s+(\w+)"
+    ).unwrap();
+
+    pub static ref DEFINED_PATTERN: Regex = Regex::new(
+        r"\bdefined\s*(?:\(\s*(\w+)\s*\)|\s(\w+))"
+    ).unwrap();
+
+    pub static ref CONDITIONAL_PATTERN: Regex = Regex::new(
+        r"^#\s*(ifdef|ifndef|if|elif|else|endif)\b(.*)$"
     ).unwrap();
 }
 
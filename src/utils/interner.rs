@@ -0,0 +1,162 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, RwLock};
+
+/// Handle to a string in a [`StringInterner`]. Equal strings from one
+/// interner get equal handles, so comparing and hashing a handle is as
+/// cheap as for the `u32` it holds.
+///
+/// The handle does not know its interner, so it displays as its number,
+/// e.g. `#3`; [`StringInterner::resolve`] gives the text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct InternedStr(u32);
+
+impl InternedStr {
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+impl fmt::Display for InternedStr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
+/// Stores each distinct string once and hands out an [`InternedStr`] for
+/// it. Interning takes `&self`, so one interner can be shared through an
+/// `Arc` by every parse of a [`ClassScanner`](crate::ClassScanner)
+/// session, including parallel ones.
+#[derive(Debug, Default)]
+pub struct StringInterner {
+    inner: RwLock<Strings>,
+}
+
+#[derive(Debug, Default)]
+struct Strings {
+    ids: HashMap<Arc<str>, InternedStr>,
+    strings: Vec<Arc<str>>,
+}
+
+impl StringInterner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The handle for `text`, adding it if it is new.
+    pub fn intern(&self, text: &str) -> InternedStr {
+        if let Some(id) = self.get(text) {
+            return id;
+        }
+        let mut inner = self.inner.write().unwrap_or_else(|e| e.into_inner());
+        // Another thread may have added it since the read lock was released
+        if let Some(&id) = inner.ids.get(text) {
+            return id;
+        }
+        let id = InternedStr(u32::try_from(inner.strings.len()).expect("more than u32::MAX interned strings"));
+        let text: Arc<str> = Arc::from(text);
+        inner.strings.push(Arc::clone(&text));
+        inner.ids.insert(text, id);
+        id
+    }
+
+    /// The handle for `text` if it was interned.
+    pub fn get(&self, text: &str) -> Option<InternedStr> {
+        self.inner.read().unwrap_or_else(|e| e.into_inner()).ids.get(text).copied()
+    }
+
+    /// The text of `id`, or `None` for a handle from another interner.
+    pub fn resolve(&self, id: InternedStr) -> Option<Arc<str>> {
+        self.inner.read().unwrap_or_else(|e| e.into_inner()).strings.get(id.index()).cloned()
+    }
+
+    /// Number of distinct strings interned.
+    pub fn len(&self) -> usize {
+        self.inner.read().unwrap_or_else(|e| e.into_inner()).strings.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Bytes held for the interned strings: their text, the shared
+    /// allocation header of each and the lookup table entries.
+    pub fn heap_size(&self) -> usize {
+        let inner = self.inner.read().unwrap_or_else(|e| e.into_inner());
+        let entry = std::mem::size_of::<Arc<str>>() * 2 + std::mem::size_of::<InternedStr>();
+        inner.strings.iter()
+            .map(|text| text.len() + 2 * std::mem::size_of::<usize>() + entry)
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use crate::ClassScanner;
+
+    #[test]
+    fn test_intern_and_resolve() {
+        let interner = StringInterner::new();
+        let scope = interner.intern("scope");
+        let model = interner.intern("model");
+        assert_eq!(interner.intern("scope"), scope);
+        assert_ne!(scope, model);
+        assert_eq!(interner.get("model"), Some(model));
+        assert_eq!(interner.get("Scope"), None);
+        assert_eq!(interner.resolve(model).as_deref(), Some("model"));
+        assert_eq!(interner.resolve(InternedStr(7)), None);
+        assert_eq!(interner.len(), 2);
+        assert_eq!(model.to_string(), "#1");
+    }
+
+    /// Memory held by the property name keys of a 10 000 property parse,
+    /// as `String` keys against `InternedStr` keys plus the interner. Run
+    /// with `cargo test --release -- --ignored --nocapture bench_interned_property_keys`.
+    #[test]
+    #[ignore]
+    fn bench_interned_property_keys() {
+        const NAMES: [&str; 8] = [
+            "displayName", "scope", "model", "hiddenSelections",
+            "hiddenSelectionsTextures", "picture", "descriptionShort", "mass",
+        ];
+        let mut input = String::new();
+        for class in 0..10_000 / NAMES.len() {
+            input.push_str(&format!("class Item_{} {{\n", class));
+            for name in NAMES {
+                input.push_str(&format!("    {} = \"{}\";\n", name, class));
+            }
+            input.push_str("};\n");
+        }
+
+        let scanner = ClassScanner::new().with_string_interning(true);
+        let start = std::time::Instant::now();
+        let classes = scanner.parse_string(&input).unwrap();
+        println!("Parsed {} classes in {:?}", classes.len() - 1, start.elapsed());
+        let interner = scanner.string_interner().unwrap();
+
+        let string_keyed: Vec<HashMap<String, usize>> = classes.iter()
+            .map(|class| class.properties.keys().map(|name| (name.clone(), 0)).collect())
+            .collect();
+        let interned_keyed: Vec<HashMap<InternedStr, usize>> = classes.iter()
+            .map(|class| class.properties.keys().map(|name| (interner.intern(name), 0)).collect())
+            .collect();
+
+        let properties: usize = string_keyed.iter().map(HashMap::len).sum();
+        let string_bytes: usize = string_keyed.iter()
+            .flat_map(HashMap::keys)
+            .map(|name| std::mem::size_of::<String>() + name.capacity())
+            .sum();
+        let interned_bytes = properties * std::mem::size_of::<InternedStr>() + interner.heap_size();
+        println!(
+            "{} property keys: String {} bytes, InternedStr {} bytes ({} distinct names), {:.0}% saved",
+            properties,
+            string_bytes,
+            interned_bytes,
+            interner.len(),
+            100.0 * (1.0 - interned_bytes as f64 / string_bytes as f64),
+        );
+        assert!(interned_keyed.iter().map(HashMap::len).sum::<usize>() == properties);
+    }
+}
@@ -1,7 +1,11 @@
 mod constants;
 mod path_resolver;
 mod logging;
+mod serde_helpers;
+mod interner;
 
 pub use constants::*;
 pub use path_resolver::PathResolver;
-pub use logging::init_logging;
\ No newline at end of file
+pub use logging::init_logging;
+pub use interner::{StringInterner, InternedStr};
+pub use serde_helpers::{serialize_f64_finite, deserialize_f64_finite, optional_path, optional_secs};
@@ -1,9 +1,15 @@
+use std::fs;
 use std::path::{Path, PathBuf};
 use crate::error::Error;
 
 pub struct PathResolver {
     base_path: PathBuf,
     include_paths: Vec<PathBuf>,
+    /// Addon prefixes (normalized to forward slashes) and the directories
+    /// they map to.
+    prefixes: Vec<(String, PathBuf)>,
+    /// Files that exist only in memory, found before those on disk.
+    in_memory: Vec<PathBuf>,
 }
 
 impl PathResolver {
@@ -11,6 +17,8 @@ impl PathResolver {
         Self {
             base_path: base_path.as_ref().to_path_buf(),
             include_paths: Vec::new(),
+            prefixes: Vec::new(),
+            in_memory: Vec::new(),
         }
     }
 
@@ -18,31 +26,169 @@ impl PathResolver {
         self.include_paths.push(path.as_ref().to_path_buf());
     }
 
+    /// Map includes starting with an addon `prefix` such as
+    /// `x\cba\addons\main` onto `dir`, the unpacked addon folder.
+    pub fn add_prefix<P: AsRef<Path>>(&mut self, prefix: &str, dir: P) {
+        self.prefixes.push((normalize(prefix), dir.as_ref().to_path_buf()));
+    }
+
+    /// Resolve includes naming `path` to it although it is not on disk,
+    /// such as a file packed in a PBO.
+    pub fn add_in_memory_file<P: AsRef<Path>>(&mut self, path: P) {
+        self.in_memory.push(path.as_ref().to_path_buf());
+    }
+
+    /// Find the file an `#include` in `source_file` names. Tried in order:
+    /// the directory of `source_file`, addon prefixes, the include paths in
+    /// the order they were added, and the base path.
+    ///
+    /// Include paths are written for Windows, so backslashes separate
+    /// components, and a path that does not exist as written is looked up
+    /// again ignoring the case of each component.
     pub fn resolve_include(&self, include_path: &str, source_file: &Path) -> Result<PathBuf, Error> {
-        // First try relative to the current file
+        let normalized = normalize(include_path);
+        let mut tried = Vec::new();
+        let mut search = |dir: &Path, relative: &str| {
+            let candidate = dir.join(relative);
+            let in_memory = self.find_in_memory(&candidate);
+            if !tried.contains(&candidate) {
+                tried.push(candidate);
+            }
+            in_memory.or_else(|| find_path(dir, relative))
+        };
+
         let source_dir = source_file.parent().unwrap_or(Path::new(""));
-        let relative_path = source_dir.join(include_path);
-        if (relative_path.exists()) {
-            return Ok(relative_path);
+        if let Some(path) = search(source_dir, &normalized) {
+            return Ok(path);
+        }
+
+        for (prefix, dir) in &self.prefixes {
+            let head = normalized.get(..prefix.len());
+            let rest = normalized.get(prefix.len()..).and_then(|rest| rest.strip_prefix('/'));
+            if let (Some(head), Some(rest)) = (head, rest) {
+                if head.eq_ignore_ascii_case(prefix) {
+                    if let Some(path) = search(dir, rest) {
+                        return Ok(path);
+                    }
+                }
+            }
         }
 
-        // Try each include path
+        // Game paths (`\x\addon\file.hpp`) are looked up below each include path
         for include_dir in &self.include_paths {
-            let full_path = include_dir.join(include_path);
-            if (full_path.exists()) {
-                return Ok(full_path);
+            if let Some(path) = search(include_dir, &normalized) {
+                return Ok(path);
             }
         }
 
-        // Finally try relative to base path
-        let base_path = self.base_path.join(include_path);
-        if (base_path.exists()) {
-            return Ok(base_path);
+        if let Some(path) = search(&self.base_path, &normalized) {
+            return Ok(path);
         }
 
         Err(Error::IncludeError(
             include_path.to_string(),
             source_file.display().to_string(),
+            tried,
         ))
     }
-}
\ No newline at end of file
+
+    /// The in-memory file at `candidate`, ignoring case.
+    fn find_in_memory(&self, candidate: &Path) -> Option<PathBuf> {
+        let key = |path: &Path| -> Vec<String> {
+            path.components()
+                .map(|component| component.as_os_str().to_string_lossy().to_ascii_lowercase())
+                .collect()
+        };
+        let wanted = key(candidate);
+        self.in_memory.iter().find(|path| key(path) == wanted).cloned()
+    }
+}
+
+/// `\x\cba\addons\main\file.hpp` -> `x/cba/addons/main/file.hpp`
+fn normalize(path: &str) -> String {
+    path.replace('\\', "/").trim_start_matches('/').trim_end_matches('/').to_string()
+}
+
+/// `relative` (with `/` separators) below `dir`, matching each component
+/// that does not exist as written against the directory's entries
+/// ignoring case.
+fn find_path(dir: &Path, relative: &str) -> Option<PathBuf> {
+    let exact = dir.join(relative);
+    if exact.is_file() {
+        return Some(exact);
+    }
+
+    let mut path = dir.to_path_buf();
+    for component in relative.split('/').filter(|component| !component.is_empty() && *component != ".") {
+        let exact = path.join(component);
+        if component == ".." || exact.exists() {
+            path = exact;
+            continue;
+        }
+        let listed = if path.as_os_str().is_empty() { Path::new(".") } else { path.as_path() };
+        // The smallest of several names differing only by case, so the
+        // result does not depend on directory order
+        let name = fs::read_dir(listed).ok()?
+            .filter_map(|entry| entry.ok().map(|entry| entry.file_name()))
+            .filter(|name| name.to_str().is_some_and(|name| name.eq_ignore_ascii_case(component)))
+            .min()?;
+        path.push(name);
+    }
+    path.is_file().then_some(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn write(path: &Path) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, "").unwrap();
+    }
+
+    #[test]
+    fn test_resolve_ignores_case_and_separators() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        let header = root.join("game/a3/ui_f/hpp/defineDIKCodes.inc");
+        write(&header);
+
+        let mut resolver = PathResolver::new(root.join("missing"));
+        resolver.add_include_path(root.join("game"));
+        let found = resolver.resolve_include(r"\A3\UI_F\hpp\DefineDikCodes.INC", &root.join("config.cpp")).unwrap();
+        assert!(found.ends_with("a3/ui_f/hpp/defineDIKCodes.inc"));
+        assert_eq!(fs::canonicalize(found).unwrap(), fs::canonicalize(header).unwrap());
+
+        // Relative to the including file, with a parent component
+        write(&root.join("addon/Common.hpp"));
+        let found = resolver.resolve_include(r"..\addon\common.HPP", &root.join("sub/config.cpp")).unwrap();
+        assert!(found.ends_with("Common.hpp"));
+    }
+
+    #[test]
+    fn test_include_paths_in_order_and_error() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        write(&root.join("first/shared.hpp"));
+        write(&root.join("second/shared.hpp"));
+        write(&root.join("second/only_second.hpp"));
+
+        let mut resolver = PathResolver::new(root.join("base"));
+        resolver.add_include_path(root.join("first"));
+        resolver.add_include_path(root.join("second"));
+        let source = root.join("addon/config.cpp");
+        assert_eq!(resolver.resolve_include("Shared.hpp", &source).unwrap(), root.join("first/shared.hpp"));
+        assert_eq!(resolver.resolve_include("only_second.hpp", &source).unwrap(), root.join("second/only_second.hpp"));
+
+        match resolver.resolve_include(r"\x\missing.hpp", &source) {
+            Err(Error::IncludeError(include, from, tried)) => {
+                assert_eq!(include, r"\x\missing.hpp");
+                assert_eq!(from, source.display().to_string());
+                let tried_in = |dir: &str| root.join(dir).join("x/missing.hpp");
+                assert_eq!(tried, vec![tried_in("addon"), tried_in("first"), tried_in("second"), tried_in("base")]);
+            }
+            other => panic!("expected an include error, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,80 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// JSON has no NaN or infinity, so non-finite values are written as the
+/// strings `"NaN"`, `"inf"` and `"-inf"` instead of collapsing to `null`.
+pub fn serialize_f64_finite<S: Serializer>(value: &f64, serializer: S) -> Result<S::Ok, S::Error> {
+    if value.is_finite() {
+        serializer.serialize_f64(*value)
+    } else {
+        serializer.serialize_str(&value.to_string())
+    }
+}
+
+pub fn deserialize_f64_finite<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Repr {
+        Number(f64),
+        Text(String),
+    }
+
+    match Repr::deserialize(deserializer)? {
+        Repr::Number(n) => Ok(n),
+        Repr::Text(text) => text.parse().map_err(serde::de::Error::custom),
+    }
+}
+
+/// Paths are stored as (lossy) UTF-8 strings so non-UTF-8 file names do not
+/// make a whole tree fail to serialize.
+pub mod optional_path {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(path: &Option<PathBuf>, serializer: S) -> Result<S::Ok, S::Error> {
+        path.as_ref()
+            .map(|p| p.to_string_lossy().into_owned())
+            .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<PathBuf>, D::Error> {
+        Ok(Option::<String>::deserialize(deserializer)?.map(PathBuf::from))
+    }
+}
+
+/// Durations as fractional seconds, which reads better in config files
+/// than serde's `{ secs, nanos }`.
+pub mod optional_secs {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(duration: &Option<Duration>, serializer: S) -> Result<S::Ok, S::Error> {
+        duration.map(|d| d.as_secs_f64()).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Duration>, D::Error> {
+        Option::<f64>::deserialize(deserializer)?
+            .map(|secs| Duration::try_from_secs_f64(secs).map_err(serde::de::Error::custom))
+            .transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lexer::tokens::TokenType;
+
+    #[test]
+    fn test_non_finite_numbers_round_trip() {
+        for value in [1.5, f64::INFINITY, f64::NEG_INFINITY] {
+            let json = serde_json::to_string(&TokenType::NumberLiteral(value)).unwrap();
+            let restored: TokenType = serde_json::from_str(&json).unwrap();
+            assert_eq!(restored, TokenType::NumberLiteral(value));
+        }
+
+        let json = serde_json::to_string(&TokenType::NumberLiteral(f64::NAN)).unwrap();
+        assert_eq!(json, r#"{"NumberLiteral":"NaN"}"#);
+        match serde_json::from_str::<TokenType>(&json).unwrap() {
+            TokenType::NumberLiteral(n) => assert!(n.is_nan()),
+            other => panic!("unexpected token {:?}", other),
+        }
+    }
+}
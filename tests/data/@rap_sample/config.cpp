@@ -0,0 +1,38 @@
+// Plaintext source of the binarized config.bin next to it
+enum {
+    destructengine = 2,
+    destructdefault = 6
+};
+
+class CfgPatches {
+    class rap_sample {
+        units[] = {"Sample_Car"};
+        weapons[] = {};
+        requiredVersion = 2.1;
+        requiredAddons[] = {"A3_Data_F"};
+        author = "Sample";
+    };
+};
+
+class CfgVehicles {
+    class Car;
+    class Car_F: Car {
+        scope = 0;
+        maxSpeed = 120;
+        displayName = "Car";
+        destrType = destructengine;
+        class Turrets {
+            class MainTurret {
+                weapons[] = {"HMG_127"};
+                magazines[] = {"100Rnd_127x99_mag", "100Rnd_127x99_mag"};
+            };
+        };
+    };
+    class Sample_Car: Car_F {
+        scope = 2;
+        armor = 52.5;
+        hiddenSelectionsTextures[] += {"a3\data\car.paa"};
+        soundEngine[] = {"a3\sounds\engine", 1.5, 1, {0, 10}};
+        delete Turrets;
+    };
+};
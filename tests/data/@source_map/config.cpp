@@ -0,0 +1,5 @@
+#include "weapons.hpp"
+
+class CfgVehicles {
+    class Car {};
+};
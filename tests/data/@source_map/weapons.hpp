@@ -0,0 +1,6 @@
+class CfgWeapons {
+    class Rifle {
+        scope = 2
+        mass = 10;
+    };
+};
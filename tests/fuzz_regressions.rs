@@ -0,0 +1,32 @@
+//! Inputs that broke the tokenizer or parser once. Each must come back as
+//! `Ok` or `Err`, never as a panic.
+
+use class_scanner::{error::Error, lexer::Tokenizer, parser::Parser, ClassScanner};
+
+/// Tokenize and parse `input`, the pipeline the `fuzz_parser` target runs.
+fn parse(input: &str) -> Result<(), Error> {
+    let tokens = Tokenizer::new(input).tokenize()?;
+    Parser::new(tokens).parse()?;
+    Ok(())
+}
+
+#[test]
+fn test_backslash_at_end_of_input() {
+    for input in ["\\", "x = \\", "class A { x = \\", "class A { x[] = {\"a\", \\", "#define X \\"] {
+        assert!(Tokenizer::new(input).tokenize().is_ok(), "{:?}", input);
+        let _ = parse(input);
+        let _ = ClassScanner::new().parse_string(input);
+    }
+}
+
+#[test]
+fn test_hex_prefix_without_digits() {
+    for input in ["0x", "x = 0x;", "class A { x = 0x; };", "x[] = {1, 0x};"] {
+        match Tokenizer::new(input).tokenize() {
+            Err(Error::LexerError { message, .. }) => assert!(message.contains("0x"), "{}", message),
+            other => panic!("expected a lexer error for {:?}, got {:?}", input, other),
+        }
+        assert!(parse(input).is_err());
+    }
+    assert!(parse("x = 0x1F;").is_ok());
+}
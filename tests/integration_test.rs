@@ -2,8 +2,10 @@ use class_scanner::{
     error::Error,
     lexer::{Tokenizer, Preprocessor},
     parser::Parser,
-    ast::{inheritance_visitor::InheritanceVisitor, array_visitor::ArrayVisitor, ClassNode, AstVisitor},
+    ast::{inheritance_visitor::InheritanceVisitor, array_visitor::ArrayVisitor, ClassNode, AstVisitor, DocCommentVisitor},
     utils::init_logging,
+    query::Query,
+    ClassScanner,
 };
 use std::path::PathBuf;
 
@@ -100,17 +102,7 @@ fn test_parse_mirrorform() -> Result<(), Error> {
     array_visitor.visit_class(&mut ast)?;
 
     // Step 5: Find and validate classes
-    let find_class = |class_name: &str| -> Option<&ClassNode> {
-        for top_level in &ast.nested_classes {
-            if let Some(found) = top_level.nested_classes.iter().find(|c| c.name == class_name) {
-                return Some(found);
-            }
-        }
-        None
-    };
-
-    // Check TC_MIRROR in CfgPatches
-    let tc_mirror = find_class("TC_MIRROR").expect("TC_MIRROR class not found");
+    let tc_mirror = ast.get_path("CfgPatches/TC_MIRROR").expect("TC_MIRROR class not found");
     
     // Validate arrays
     assert!(tc_mirror.properties.values().any(|p| p.name == "units"));
@@ -118,15 +110,132 @@ fn test_parse_mirrorform() -> Result<(), Error> {
     assert!(tc_mirror.properties.values().any(|p| p.name == "requiredAddons"));
 
     // Check inheritance chain
-    let mirror_base = find_class("TC_B_Mirror_Base").expect("TC_B_Mirror_Base class not found");
+    let mirror_base = ast.find_class("TC_B_Mirror_Base").expect("TC_B_Mirror_Base class not found");
     assert_eq!(mirror_base.parent, Some("B_Soldier_base_F".to_string()));
 
-    let mirror_1 = find_class("TC_B_Mirror_1").expect("TC_B_Mirror_1 class not found");
+    let mirror_1 = ast.find_class("TC_B_Mirror_1").expect("TC_B_Mirror_1 class not found");
     assert_eq!(mirror_1.parent, Some("TC_B_Mirror_Base".to_string()));
 
     Ok(())
 }
 
+#[test]
+fn test_doc_comments_from_config() -> Result<(), Error> {
+    init_test_logging();
+    let data_dir = get_test_data_dir();
+    let config_path = data_dir.join("@tc_rhs_headband").join("config.cpp");
+
+    let mut preprocessor = Preprocessor::new(&data_dir);
+    let content = preprocessor.process_file(&config_path)?;
+    let tokens = Tokenizer::with_file_path(&content, &config_path)
+        .with_comments(true)
+        .tokenize()?;
+    let mut ast = Parser::new(tokens).with_comments(true).parse()?;
+
+    // The DeRap banner is the first thing before CfgPatches
+    let mut visitor = DocCommentVisitor::new();
+    ast.accept(&mut visitor)?;
+    let comments = visitor.into_comments();
+    let banner = &comments["CfgPatches"];
+    assert!(banner.starts_with("DeRap: config.bin\nProduced from mikero's Dos Tools"), "{}", banner);
+    assert_eq!(comments.len(), 1);
+
+    let cfg_weapons = ast.nested_classes.iter().find(|c| c.name == "CfgWeapons").unwrap();
+    assert_eq!(cfg_weapons.doc_comment, None);
+
+    Ok(())
+}
+
+#[test]
+fn test_mirrorform_scoped_inheritance() -> Result<(), Error> {
+    init_test_logging();
+    let data_dir = get_test_data_dir();
+    let config_path = data_dir.join("@tc_mirrorform").join("config.cpp");
+
+    let scanner = ClassScanner::new();
+    let classes = scanner.parse_file(&config_path)?;
+
+    // Each ItemInfo is resolved in its own scope; `UniformItem` is the
+    // declaration in CfgWeapons, not some other class of the same name
+    let item_info = scanner.process_inheritance(classes.clone(), "CfgWeapons/TC_U_Mirror_1/ItemInfo")?;
+    assert_eq!(item_info.parent, Some("UniformItem".to_string()));
+    assert_eq!(item_info.properties["uniformClass"].raw_value, "TC_B_Mirror_1");
+    assert_eq!(item_info.properties["mass"].raw_value, "40");
+
+    let base_info = scanner.process_inheritance(classes.clone(), "CfgWeapons/TC_U_Mirror_Base/ItemInfo")?;
+    assert_eq!(base_info.properties["uniformClass"].raw_value, "TC_B_Mirror_Base");
+
+    let uniform = scanner.process_inheritance(classes, "CfgWeapons/TC_U_Mirror_1")?;
+    assert_eq!(uniform.properties["author"].raw_value, "Tyen");
+    assert_eq!(uniform.properties["scope"].raw_value, "2");
+
+    Ok(())
+}
+
+/// Structural equality, ignoring source locations and spans.
+fn assert_same_tree(expected: &ClassNode, actual: &ClassNode) {
+    assert_eq!(expected.name, actual.name);
+    assert_eq!(expected.parent, actual.parent, "parent of {}", expected.name);
+    assert_eq!(expected.is_external, actual.is_external, "external {}", expected.name);
+    assert_eq!(expected.deleted_classes, actual.deleted_classes, "deletions in {}", expected.name);
+
+    let enums = |class: &ClassNode| class.enums.iter().map(|e| e.values.clone()).collect::<Vec<_>>();
+    assert_eq!(enums(expected), enums(actual), "enums in {}", expected.name);
+
+    let names = |class: &ClassNode| class.properties.keys().cloned().collect::<Vec<_>>();
+    assert_eq!(names(expected), names(actual), "properties of {}", expected.name);
+    for (name, property) in &expected.properties {
+        let other = &actual.properties[name];
+        assert_eq!(property.value_type, other.value_type, "{}.{}", expected.name, name);
+        assert_eq!(property.raw_value, other.raw_value, "{}.{}", expected.name, name);
+        assert_eq!(property.operation, other.operation, "{}.{}", expected.name, name);
+        assert_eq!(property.array_values, other.array_values, "{}.{}", expected.name, name);
+    }
+
+    assert_eq!(expected.nested_classes.len(), actual.nested_classes.len(), "classes in {}", expected.name);
+    for (expected, actual) in expected.nested_classes.iter().zip(&actual.nested_classes) {
+        assert_same_tree(expected, actual);
+    }
+}
+
+#[test]
+fn test_parse_binarized_config() -> Result<(), Error> {
+    init_test_logging();
+    let addon_dir = get_test_data_dir().join("@rap_sample");
+    let scanner = ClassScanner::new();
+
+    let text = scanner.parse_file(addon_dir.join("config.cpp"))?;
+    let binary = scanner.parse_file(addon_dir.join("config.bin"))?;
+    assert_eq!(text.len(), binary.len());
+    assert_same_tree(&text[0], &binary[0]);
+
+    let sample = binary[0].find_at_path("CfgVehicles.Sample_Car").expect("Sample_Car not found");
+    assert_eq!(sample.parent.as_deref(), Some("Car_F"));
+    assert_eq!(sample.file_path.as_deref(), Some(addon_dir.join("config.bin").as_path()));
+
+    // The binarized config is preferred over its source
+    let result = scanner.scan_directory(&addon_dir)?;
+    let files: Vec<&PathBuf> = result.classes.keys().collect();
+    assert_eq!(files, vec![&PathBuf::from("config.bin")]);
+
+    Ok(())
+}
+
+#[test]
+fn test_error_in_included_file() {
+    init_test_logging();
+    let addon_dir = get_test_data_dir().join("@source_map");
+
+    // `scope = 2` on line 3 of weapons.hpp lacks its semicolon
+    match ClassScanner::new().parse_file(addon_dir.join("config.cpp")) {
+        Err(Error::ParseError { location, .. }) => {
+            assert_eq!(location.file.as_deref().and_then(|file| file.file_name()), Some("weapons.hpp".as_ref()));
+            assert_eq!(location.line, 4);
+        }
+        other => panic!("expected a parse error, got {:?}", other),
+    }
+}
+
 #[test]
 fn test_config_file_errors() {
     init_test_logging();
@@ -323,6 +432,89 @@ fn test_parse_hidden_vest() -> Result<(), Error> {
     Ok(())
 }
 
+#[test]
+fn test_pretty_print_round_trip() {
+    init_test_logging();
+    let scanner = ClassScanner::new();
+    let mut pending = vec![get_test_data_dir()];
+    let mut checked = 0;
+    while let Some(dir) = pending.pop() {
+        for entry in std::fs::read_dir(&dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                pending.push(path);
+                continue;
+            }
+            if !matches!(path.extension().and_then(|e| e.to_str()), Some("cpp" | "hpp" | "bin")) {
+                continue;
+            }
+            // Fixtures with a deliberate parse error
+            if path.parent().unwrap().ends_with("@source_map") {
+                continue;
+            }
+
+            let classes = scanner.parse_file(&path).unwrap_or_else(|e| panic!("{}: {}", path.display(), e));
+            let printed = classes[0].to_config_string();
+            let reparsed = scanner.parse_string(&printed)
+                .unwrap_or_else(|e| panic!("{} printed:\n{}\n{}", path.display(), printed, e));
+            assert_eq!(reparsed[0], classes[0], "{} printed:\n{}", path.display(), printed);
+            checked += 1;
+        }
+    }
+    assert!(checked >= 15, "only {} fixtures checked", checked);
+}
+
+#[test]
+fn test_minify_file() -> Result<(), Error> {
+    init_test_logging();
+    for addon in ["@pca_misc", "@tc_mirrorform", "@tc_rhs_headband", "@em"] {
+        let config_path = get_test_data_dir().join(addon).join("config.cpp");
+        let preprocessed = Preprocessor::new(get_test_data_dir().join(addon)).process_file(&config_path)?;
+        let minified = ClassScanner::new().with_minify_line_length(Some(200)).minify_file(&config_path)?;
+        assert!(minified.len() < preprocessed.len());
+        assert!(minified.lines().all(|line| line.len() <= 200));
+
+        let scanner = ClassScanner::new();
+        let reparsed = scanner.parse_string(&minified)?;
+        assert_eq!(reparsed[0], scanner.parse_file(&config_path)?[0], "{} minified:\n{}", config_path.display(), minified);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_query_vests() -> Result<(), Error> {
+    init_test_logging();
+    let config_path = get_test_data_dir().join("@pca_misc").join("config.cpp");
+    let classes = ClassScanner::new().parse_file(&config_path)?;
+    let root = &classes[0];
+    let names = |found: Vec<&ClassNode>| found.iter().map(|c| c.name.clone()).collect::<Vec<_>>();
+
+    let vests = Query::new()
+        .under("CfgWeapons")
+        .max_depth(Some(0))
+        .derives_from("Vest_Camo_Base")
+        .where_property("scope", |v| v.raw_value == "2")
+        .run(root);
+    assert_eq!(names(vests), vec!["pca_vest_invisible", "pca_vest_invisible_kevlar", "pca_vest_invisible_plate"]);
+
+    // Found through the chain pca_vest_invisible -> Vest_Camo_Base -> ItemCore
+    let items = Query::new().under("CfgWeapons").derives_from("ItemCore").name_matches("pca_vest_*").run(root);
+    assert_eq!(items.len(), 3);
+
+    let heavy = Query::new()
+        .under("CfgWeapons")
+        .name_matches("ItemInfo")
+        .where_property("MASS", |v| v.raw_value.parse::<u32>().is_ok_and(|mass| mass >= 40))
+        .run(root);
+    assert!(!heavy.is_empty());
+    assert!(heavy.iter().all(|info| info.properties["mass"].raw_value != "20"));
+
+    let patches = Query::new().under("CfgPatches").where_array_contains("requiredAddons", "RHS_C_TROOPS").run(root);
+    assert_eq!(names(patches), vec!["pca_misc_pca_extra_contents"]);
+
+    Ok(())
+}
+
 #[test]
 fn test_parse_3den_config() -> Result<(), Error> {
     // init_test_logging();
@@ -0,0 +1,85 @@
+//! Invariants of array operations and inheritance, checked on generated input.
+
+use class_scanner::ast::ClassNodeBuilder;
+use class_scanner::operations::arrays::{ArrayOperation, ArrayProcessor};
+use class_scanner::operations::InheritanceResolver;
+use class_scanner::PropertyType;
+use proptest::prelude::*;
+
+/// Array elements, short and from a small alphabet so that values repeat
+/// and some are numbers ("1" and "1.0" count as the same element).
+fn items() -> impl Strategy<Value = Vec<String>> {
+    prop::collection::vec("[ab1.]{0,3}", 0..8)
+}
+
+fn process(base: &[String], values: &[String], operation: ArrayOperation) -> Vec<String> {
+    ArrayProcessor::process(base, values, operation)
+}
+
+proptest! {
+    #[test]
+    fn append_only_grows(base in items(), values in items()) {
+        let result = process(&base, &values, ArrayOperation::Append);
+        prop_assert!(result.len() >= base.len());
+        prop_assert_eq!(&result[..base.len()], &base[..]);
+    }
+
+    #[test]
+    fn append_keeps_every_item(base in items(), values in items()) {
+        let result = process(&base, &values, ArrayOperation::Append);
+        for item in &values {
+            prop_assert!(result.contains(item), "{:?} missing from {:?}", item, result);
+        }
+    }
+
+    #[test]
+    fn remove_undoes_append(base in items(), values in items()) {
+        // Items of `base` matching one of `values` are removed as well
+        let appended = process(&base, &values, ArrayOperation::Append);
+        prop_assert_eq!(
+            process(&appended, &values, ArrayOperation::Remove),
+            process(&base, &values, ArrayOperation::Remove)
+        );
+    }
+
+    #[test]
+    fn remove_leaves_none_of_the_values(base in items(), values in items()) {
+        let result = process(&base, &values, ArrayOperation::Remove);
+        for item in &result {
+            prop_assert!(!values.contains(item), "{:?} still in {:?}", item, result);
+        }
+        prop_assert!(result.len() <= base.len());
+    }
+
+    #[test]
+    fn replace_is_exact(base in items(), values in items()) {
+        prop_assert_eq!(process(&base, &values, ArrayOperation::Replace), values);
+    }
+
+    #[test]
+    fn class_without_parent_resolves_unchanged(
+        name in "[A-Za-z_][A-Za-z0-9_]{0,8}",
+        properties in prop::collection::vec(("[a-z]{1,6}", "[a-z0-9]{0,6}"), 0..8),
+        arrays in prop::collection::vec(("[A-Z]{1,6}", items()), 0..4),
+    ) {
+        let mut builder = ClassNodeBuilder::new(name.clone());
+        let mut seen = Vec::new();
+        for (property, value) in &properties {
+            if !seen.contains(property) {
+                builder = builder.property_raw(property.clone(), PropertyType::String, value.clone());
+                seen.push(property.clone());
+            }
+        }
+        for (property, values) in &arrays {
+            if !seen.contains(property) {
+                builder = builder.array_property(property.clone(), values.clone());
+                seen.push(property.clone());
+            }
+        }
+        let class = builder.build().unwrap();
+
+        let mut resolver = InheritanceResolver::new();
+        resolver.add_class(class.clone());
+        prop_assert_eq!(resolver.resolve_class(&name).unwrap(), class);
+    }
+}